@@ -14,6 +14,7 @@ main() {
 
         cargo test --target $TARGET
         cargo test --target $TARGET --release
+        cargo test --target $TARGET --features oscore
 
         pushd tools
         cargo check --target $TARGET --bins
@@ -321,13 +321,8 @@ fn on_new_packet<'a>(
                                 return Action::Nop;
                             };
 
-                            if !coap.get_code().is_request()
-                                || match coap.get_type() {
-                                    coap::Type::Confirmable | coap::Type::NonConfirmable => false,
-                                    _ => true,
-                                }
-                            {
-                                warning!("CoAP message is not a valid request; ignoring");
+                            if coap::server::is_ignored(&coap) {
+                                warning!("CoAP message is neither a request nor a ping; ignoring");
 
                                 return Action::Nop;
                             }
@@ -348,7 +343,7 @@ fn on_new_packet<'a>(
                                     udp.set_source(coap::PORT);
                                     udp.set_destination(src_port);
 
-                                    udp.coap(0, |resp| {
+                                    udp.coap(coap.get_token_length(), |resp| {
                                         on_coap_request(state, coap, resp, &mut change)
                                     })
                                 });
@@ -397,91 +392,70 @@ fn on_new_packet<'a>(
     Action::Nop
 }
 
+// CoAP resources
+static RESOURCES: &[coap::server::Resource<'static, Context>] = &[coap::server::Resource {
+    path: &["led"],
+    methods: &[coap::Method::Get, coap::Method::Put],
+    content_formats: &[coap::ContentFormat::ApplicationJson],
+    attributes: "",
+    handler: on_led_request,
+}];
+
+struct Context {
+    led: bool,
+    change: Option<bool>,
+}
+
 fn on_coap_request<'a>(
     state: &State,
     req: coap::Message<&[u8]>,
-    mut resp: coap::Message<&'a mut [u8], coap::Unset>,
+    resp: coap::Message<&'a mut [u8], coap::Unset>,
     change: &mut Option<bool>,
 ) -> coap::Message<&'a mut [u8]> {
-    let code = req.get_code();
-
-    resp.set_message_id(req.get_message_id());
-    resp.set_type(if req.get_type() == coap::Type::Confirmable {
-        coap::Type::Acknowledgement
-    } else {
-        coap::Type::NonConfirmable
-    });
-
-    if code == coap::Method::Get.into() {
-        info!("CoAP: GET request");
-
-        let mut opts = req.options();
-        while let Some(opt) = opts.next() {
-            if opt.number() == coap::OptionNumber::UriPath {
-                if opt.value() == b"led" && opts.next().is_none() {
-                    info!("CoAP: GET /led");
-
-                    let mut tmp = [0; 13];
-                    let payload =
-                        ujson::write(&Payload { led: state.led }, &mut tmp).expect("unreachable");
+    let mut ctx = Context {
+        led: state.led,
+        change: None,
+    };
 
-                    resp.set_code(coap::Response::Content);
-                    return resp.set_payload(payload.as_bytes());
-                } else {
-                    // fall-through: Not Found
-                    break;
-                }
-            } else {
-                error!("CoAP: Bad Option");
+    // NOTE `on_new_packet` doesn't pass the messages that the server ignores to this function
+    // so the server always responds
+    let resp = coap::server::Server::new(RESOURCES)
+        .handle(&mut ctx, &req, resp)
+        .expect("unreachable");
+    *change = ctx.change;
+    resp
+}
 
-                resp.set_code(coap::Response::BadOption);
-                return resp.no_payload();
-            }
-        }
-    } else if code == coap::Method::Put.into() {
-        info!("CoAP: PUT request");
+fn on_led_request<'a>(
+    ctx: &mut Context,
+    req: &coap::Message<&[u8]>,
+    mut resp: coap::Message<&'a mut [u8], coap::Unset>,
+) -> coap::Message<&'a mut [u8]> {
+    if req.get_code() == coap::Method::Get.into() {
+        info!("CoAP: GET /led");
 
-        let mut opts = req.options();
-        while let Some(opt) = opts.next() {
-            if opt.number() == coap::OptionNumber::UriPath {
-                if opt.value() == b"led" && opts.next().is_none() {
-                    info!("CoAP: PUT /led");
+        let mut tmp = [0; 13];
+        let payload = ujson::write(&Payload { led: ctx.led }, &mut tmp).expect("unreachable");
 
-                    if let Ok(payload) = ujson::from_bytes::<Payload>(req.payload()) {
-                        info!("CoAP: Changed");
+        resp.add_content_format(coap::ContentFormat::ApplicationJson)
+            .expect("unreachable");
+        resp.set_payload(payload.as_bytes())
+    } else {
+        info!("CoAP: PUT /led");
 
-                        *change = Some(payload.led);
+        if let Ok(payload) = ujson::from_bytes::<Payload>(req.payload()) {
+            info!("CoAP: Changed");
 
-                        resp.set_code(coap::Response::Changed);
-                        return resp.no_payload();
-                    } else {
-                        error!("CoAP: Bad Request");
+            ctx.change = Some(payload.led);
 
-                        resp.set_code(coap::Response::BadRequest);
-                        return resp.no_payload();
-                    }
-                } else {
-                    // fall-through: Not Found
-                    break;
-                }
-            } else {
-                error!("CoAP: Bad Option");
+            resp.no_payload()
+        } else {
+            error!("CoAP: Bad Request");
 
-                resp.set_code(coap::Response::BadOption);
-                return resp.no_payload();
-            }
+            resp.set_code(coap::Response::BadRequest);
+            resp.no_payload()
         }
-    } else {
-        info!("CoAP: Method Not Allowed");
-
-        resp.set_code(coap::Response::MethodNotAllowed);
-        return resp.no_payload();
     }
-
-    error!("CoAP: Not Found");
-
-    resp.set_code(coap::Response::NotFound);
-    resp.no_payload()
 }
 
 enum Action<'a> {
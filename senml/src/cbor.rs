@@ -0,0 +1,290 @@
+//! `application/senml+cbor` representation
+//!
+//! See RFC8428 Section 6 "CBOR Representation (application/senml+cbor)"
+
+use ucbor::{ser::Cursor, Bytes, Serialize};
+
+use crate::{Record, Value};
+
+// See RFC8428 Table 6 "CBOR Representation: Integers for Map Keys"
+const BN: i8 = -2;
+const BT: i8 = -3;
+const BU: i8 = -4;
+const N: i8 = 0;
+const U: i8 = 1;
+const V: i8 = 2;
+const VS: i8 = 3;
+const VB: i8 = 4;
+const T: i8 = 6;
+const VD: i8 = 8;
+
+/// Serializes the `pack` into the given `buffer`
+pub fn write<'a>(pack: &[Record<'_>], buffer: &'a mut [u8]) -> Result<&'a [u8], ()> {
+    ucbor::write(pack, buffer)
+}
+
+impl Serialize for Record<'_> {
+    fn serialize(&self, cursor: &mut Cursor<'_>) -> Result<(), ()> {
+        let len = [
+            self.base_name.is_some(),
+            self.base_time.is_some(),
+            self.base_unit.is_some(),
+            self.name.is_some(),
+            self.unit.is_some(),
+            self.value.is_some(),
+            self.time.is_some(),
+        ]
+        .iter()
+        .filter(|present| **present)
+        .count();
+
+        ucbor::ser::map_header(len as u64, cursor)?;
+
+        if let Some(bn) = self.base_name {
+            BN.serialize(cursor)?;
+            bn.serialize(cursor)?;
+        }
+
+        if let Some(bt) = self.base_time {
+            BT.serialize(cursor)?;
+            float(bt, cursor)?;
+        }
+
+        if let Some(bu) = self.base_unit {
+            BU.serialize(cursor)?;
+            bu.serialize(cursor)?;
+        }
+
+        if let Some(n) = self.name {
+            N.serialize(cursor)?;
+            n.serialize(cursor)?;
+        }
+
+        if let Some(u) = self.unit {
+            U.serialize(cursor)?;
+            u.serialize(cursor)?;
+        }
+
+        match self.value {
+            Some(Value::Float(v)) => {
+                V.serialize(cursor)?;
+                float(v, cursor)?;
+            }
+
+            Some(Value::Integer(v)) => {
+                V.serialize(cursor)?;
+                v.serialize(cursor)?;
+            }
+
+            Some(Value::String(vs)) => {
+                VS.serialize(cursor)?;
+                vs.serialize(cursor)?;
+            }
+
+            Some(Value::Bool(vb)) => {
+                VB.serialize(cursor)?;
+                vb.serialize(cursor)?;
+            }
+
+            Some(Value::Data(vd)) => {
+                VD.serialize(cursor)?;
+                Bytes(vd).serialize(cursor)?;
+            }
+
+            None => {}
+        }
+
+        if let Some(t) = self.time {
+            T.serialize(cursor)?;
+            float(t, cursor)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Serializes `v` as a half, single or double precision float, whichever is the shortest that
+// represents it exactly (RFC8949 Section 4.2.2 "Additional Deterministic Encoding Considerations")
+fn float(v: f64, cursor: &mut Cursor<'_>) -> Result<(), ()> {
+    // major type 7 (simple / float) with additional information 25, 26 and 27
+    const HALF: u8 = 0xf9;
+    const SINGLE: u8 = 0xfa;
+    const DOUBLE: u8 = 0xfb;
+
+    if v.is_nan() {
+        // canonical (quiet) NaN
+        cursor.push_byte(HALF)?;
+        return cursor.push(&[0x7e, 0x00]);
+    }
+
+    let single = v as f32;
+    if f64::from(single) != v {
+        cursor.push_byte(DOUBLE)?;
+        cursor.push(&v.to_bits().to_be_bytes())
+    } else if let Some(half) = half(single) {
+        cursor.push_byte(HALF)?;
+        cursor.push(&half.to_be_bytes())
+    } else {
+        cursor.push_byte(SINGLE)?;
+        cursor.push(&single.to_bits().to_be_bytes())
+    }
+}
+
+// Converts a non-NaN `f32` into the bits of an IEEE 754 half precision float, if that conversion
+// is lossless
+fn half(v: f32) -> Option<u16> {
+    let bits = v.to_bits();
+    let sign = (bits >> 16) as u16 & 0x8000;
+    let exponent = (bits >> 23) & 0xff;
+    let mantissa = bits & 0x7f_ffff;
+
+    if exponent == 0xff {
+        // infinity
+        return Some(sign | 0x7c00);
+    }
+
+    if exponent == 0 {
+        // zero; single precision subnormals are too small for half precision
+        return if mantissa == 0 { Some(sign) } else { None };
+    }
+
+    let e = exponent as i32 - 127;
+    if (-14..=15).contains(&e) {
+        // normal half; 13 bits of mantissa get dropped
+        if mantissa & 0x1fff == 0 {
+            Some(sign | ((e + 15) as u16) << 10 | (mantissa >> 13) as u16)
+        } else {
+            None
+        }
+    } else if (-24..-14).contains(&e) {
+        // subnormal half: `m * 2^-24`
+        let significand = 0x80_0000 | mantissa;
+        let shift = -e - 1;
+        if significand & ((1 << shift) - 1) == 0 {
+            Some(sign | (significand >> shift) as u16)
+        } else {
+            None
+        }
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Record, Value};
+
+    #[test]
+    fn values() {
+        let pack = [
+            Record {
+                base_name: Some("dev:"),
+                base_unit: Some("V"),
+                value: Some(Value::Integer(-2)),
+                ..Record::default()
+            },
+            Record {
+                name: Some("a"),
+                value: Some(Value::Float(1.5)),
+                time: Some(-5.),
+                ..Record::default()
+            },
+            Record {
+                value: Some(Value::Bool(true)),
+                ..Record::default()
+            },
+            Record {
+                value: Some(Value::String("x")),
+                ..Record::default()
+            },
+            Record {
+                value: Some(Value::Data(&[0xaa])),
+                ..Record::default()
+            },
+        ];
+
+        assert_eq!(
+            super::write(&pack, &mut [0; 64]).unwrap(),
+            &[
+                0x85, // array(5)
+                0xa3, // map(3)
+                0x21, 0x64, b'd', b'e', b'v', b':', // -2: "dev:"
+                0x23, 0x61, b'V', // -4: "V"
+                0x02, 0x21, // 2: -2
+                0xa3, // map(3)
+                0x00, 0x61, b'a', // 0: "a"
+                0x02, 0xf9, 0x3e, 0x00, // 2: 1.5 (half)
+                0x06, 0xf9, 0xc5, 0x00, // 6: -5.0 (half)
+                0xa1, 0x04, 0xf5, // {4: true}
+                0xa1, 0x03, 0x61, b'x', // {3: "x"}
+                0xa1, 0x08, 0x41, 0xaa, // {8: h'aa'}
+            ][..]
+        );
+    }
+
+    #[test]
+    fn floats() {
+        let value = |v| {
+            [Record {
+                value: Some(Value::Float(v)),
+                ..Record::default()
+            }]
+        };
+        let mut buf = [0; 16];
+
+        // half
+        assert_eq!(
+            super::write(&value(0.), &mut buf).unwrap(),
+            [0x81, 0xa1, 0x02, 0xf9, 0x00, 0x00]
+        );
+        assert_eq!(
+            super::write(&value(-0.), &mut buf).unwrap(),
+            [0x81, 0xa1, 0x02, 0xf9, 0x80, 0x00]
+        );
+        assert_eq!(
+            super::write(&value(65504.), &mut buf).unwrap(),
+            [0x81, 0xa1, 0x02, 0xf9, 0x7b, 0xff]
+        );
+        assert_eq!(
+            super::write(&value(5.960464477539063e-8), &mut buf).unwrap(),
+            [0x81, 0xa1, 0x02, 0xf9, 0x00, 0x01]
+        );
+        assert_eq!(
+            super::write(&value(f64::INFINITY), &mut buf).unwrap(),
+            [0x81, 0xa1, 0x02, 0xf9, 0x7c, 0x00]
+        );
+        assert_eq!(
+            super::write(&value(f64::NAN), &mut buf).unwrap(),
+            [0x81, 0xa1, 0x02, 0xf9, 0x7e, 0x00]
+        );
+
+        // single
+        assert_eq!(
+            super::write(&value(100000.), &mut buf).unwrap(),
+            [0x81, 0xa1, 0x02, 0xfa, 0x47, 0xc3, 0x50, 0x00]
+        );
+        assert_eq!(
+            super::write(&value(3.4028234663852886e38), &mut buf).unwrap(),
+            [0x81, 0xa1, 0x02, 0xfa, 0x7f, 0x7f, 0xff, 0xff]
+        );
+
+        // double
+        assert_eq!(
+            super::write(&value(1.1), &mut buf).unwrap(),
+            [0x81, 0xa1, 0x02, 0xfb, 0x3f, 0xf1, 0x99, 0x99, 0x99, 0x99, 0x99, 0x9a]
+        );
+        assert_eq!(
+            super::write(&value(1.0e300), &mut buf).unwrap(),
+            [0x81, 0xa1, 0x02, 0xfb, 0x7e, 0x37, 0xe4, 0x3c, 0x88, 0x00, 0x75, 0x9c]
+        );
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(super::write(&[], &mut [0; 1]).unwrap(), [0x80]);
+        assert_eq!(
+            super::write(&[Record::default()], &mut [0; 2]).unwrap(),
+            [0x81, 0xa0]
+        );
+    }
+}
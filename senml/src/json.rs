@@ -0,0 +1,195 @@
+//! `application/senml+json` representation
+//!
+//! See RFC8428 Section 5 "JSON Representation (application/senml+json)"
+
+use ujson::{ser::Cursor, Serialize};
+
+use crate::{Record, Value};
+
+/// Serializes the `pack` into the given `buffer`
+pub fn write<'a>(pack: &[Record<'_>], buffer: &'a mut [u8]) -> Result<&'a str, ()> {
+    ujson::write(pack, buffer)
+}
+
+impl Serialize for Record<'_> {
+    fn serialize(&self, cursor: &mut Cursor<'_>) -> Result<(), ()> {
+        let mut is_first = true;
+
+        cursor.push_byte(b'{')?;
+
+        if let Some(bn) = self.base_name {
+            label(cursor, &mut is_first, b"bn")?;
+            bn.serialize(cursor)?;
+        }
+
+        if let Some(bt) = self.base_time {
+            label(cursor, &mut is_first, b"bt")?;
+            bt.serialize(cursor)?;
+        }
+
+        if let Some(bu) = self.base_unit {
+            label(cursor, &mut is_first, b"bu")?;
+            bu.serialize(cursor)?;
+        }
+
+        if let Some(n) = self.name {
+            label(cursor, &mut is_first, b"n")?;
+            n.serialize(cursor)?;
+        }
+
+        if let Some(u) = self.unit {
+            label(cursor, &mut is_first, b"u")?;
+            u.serialize(cursor)?;
+        }
+
+        match self.value {
+            Some(Value::Float(v)) => {
+                label(cursor, &mut is_first, b"v")?;
+                v.serialize(cursor)?;
+            }
+
+            Some(Value::Integer(v)) => {
+                label(cursor, &mut is_first, b"v")?;
+                v.serialize(cursor)?;
+            }
+
+            Some(Value::String(vs)) => {
+                label(cursor, &mut is_first, b"vs")?;
+                vs.serialize(cursor)?;
+            }
+
+            Some(Value::Bool(vb)) => {
+                label(cursor, &mut is_first, b"vb")?;
+                vb.serialize(cursor)?;
+            }
+
+            Some(Value::Data(vd)) => {
+                label(cursor, &mut is_first, b"vd")?;
+                cursor.push_byte(b'"')?;
+                base64url(vd, cursor)?;
+                cursor.push_byte(b'"')?;
+            }
+
+            None => {}
+        }
+
+        if let Some(t) = self.time {
+            label(cursor, &mut is_first, b"t")?;
+            t.serialize(cursor)?;
+        }
+
+        cursor.push_byte(b'}')
+    }
+}
+
+fn label(cursor: &mut Cursor<'_>, is_first: &mut bool, label: &[u8]) -> Result<(), ()> {
+    if *is_first {
+        *is_first = false;
+    } else {
+        cursor.push_byte(b',')?;
+    }
+
+    ujson::ser::field_name(label, cursor)?;
+    cursor.push_byte(b':')
+}
+
+// "base64" URL-safe alphabet without padding; see RFC8428 Section 4.3 and RFC4648 Section 5
+fn base64url(data: &[u8], cursor: &mut Cursor<'_>) -> Result<(), ()> {
+    static ALPHABET: [u8; 64] =
+        *b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+    for chunk in data.chunks(3) {
+        let mut word = 0u32;
+        for (i, byte) in chunk.iter().enumerate() {
+            word |= u32::from(*byte) << (16 - 8 * i);
+        }
+
+        for i in 0..=chunk.len() {
+            cursor.push_byte(ALPHABET[(word >> (18 - 6 * i)) as usize & 0x3f])?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Record, Value};
+
+    #[test]
+    fn values() {
+        let pack = [
+            Record {
+                base_name: Some("urn:dev:ow:10e2073a01080063:"),
+                base_time: Some(1.320067464e9),
+                base_unit: Some("%RH"),
+                value: Some(Value::Integer(21)),
+                ..Record::default()
+            },
+            Record {
+                time: Some(-5.),
+                value: Some(Value::Float(20.5)),
+                ..Record::default()
+            },
+            Record {
+                name: Some("open"),
+                value: Some(Value::Bool(false)),
+                ..Record::default()
+            },
+            Record {
+                name: Some("label"),
+                value: Some(Value::String("kitchen")),
+                ..Record::default()
+            },
+        ];
+
+        assert_eq!(
+            super::write(&pack, &mut [0; 192]).unwrap(),
+            "[{\"bn\":\"urn:dev:ow:10e2073a01080063:\",\"bt\":1320067464,\"bu\":\"%RH\",\"v\":21},\
+             {\"v\":20.5,\"t\":-5},\
+             {\"n\":\"open\",\"vb\":false},\
+             {\"n\":\"label\",\"vs\":\"kitchen\"}]"
+        );
+    }
+
+    #[test]
+    fn data() {
+        let record = |vd| {
+            [Record {
+                value: Some(Value::Data(vd)),
+                ..Record::default()
+            }]
+        };
+
+        assert_eq!(
+            super::write(&record(b""), &mut [0; 32]).unwrap(),
+            "[{\"vd\":\"\"}]"
+        );
+        assert_eq!(
+            super::write(&record(b"f"), &mut [0; 32]).unwrap(),
+            "[{\"vd\":\"Zg\"}]"
+        );
+        assert_eq!(
+            super::write(&record(b"fo"), &mut [0; 32]).unwrap(),
+            "[{\"vd\":\"Zm8\"}]"
+        );
+        assert_eq!(
+            super::write(&record(b"foob"), &mut [0; 32]).unwrap(),
+            "[{\"vd\":\"Zm9vYg\"}]"
+        );
+        assert_eq!(
+            super::write(&record(&[0xfb, 0xff]), &mut [0; 32]).unwrap(),
+            "[{\"vd\":\"-_8\"}]"
+        );
+    }
+
+    #[test]
+    fn overflow() {
+        let pack = [Record {
+            name: Some("temperature"),
+            ..Record::default()
+        }];
+
+        assert!(super::write(&pack, &mut [0; 16]).is_err());
+    }
+}
@@ -0,0 +1,81 @@
+//! `SenML`: Sensor Measurement Lists for memory constrained devices
+//!
+//! A pack is a slice of `Record`s. It can be serialized into a fixed buffer as
+//! `application/senml+json`, through `ujson`, or as `application/senml+cbor`, through `ucbor`.
+//!
+//! ```
+//! use senml::{Record, Value};
+//!
+//! let pack = [
+//!     Record {
+//!         base_name: Some("urn:dev:ow:10e2073a01080063:"),
+//!         name: Some("voltage"),
+//!         unit: Some("V"),
+//!         value: Some(Value::Float(120.1)),
+//!         ..Record::default()
+//!     },
+//!     Record {
+//!         name: Some("current"),
+//!         unit: Some("A"),
+//!         value: Some(Value::Float(1.2)),
+//!         ..Record::default()
+//!     },
+//! ];
+//!
+//! let mut buf = [0; 128];
+//! assert_eq!(
+//!     senml::json::write(&pack, &mut buf).unwrap(),
+//!     r#"[{"bn":"urn:dev:ow:10e2073a01080063:","n":"voltage","u":"V","v":120.1},{"n":"current","u":"A","v":1.2}]"#,
+//! );
+//! ```
+//!
+//! # References
+//!
+//! - [RFC8428 Sensor Measurement Lists (SenML)][rfc8428]
+//!
+//! [rfc8428]: https://tools.ietf.org/html/rfc8428
+
+#![deny(missing_docs)]
+#![deny(rust_2018_compatibility)]
+#![deny(rust_2018_idioms)]
+#![deny(warnings)]
+#![no_std]
+
+pub mod cbor;
+pub mod json;
+
+/// A SenML record
+///
+/// Base fields (`base_*`) apply to this record and to all the records that follow it in the pack
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Record<'a> {
+    /// Base Name (`bn`): prepended to the name of this and the following records
+    pub base_name: Option<&'a str>,
+    /// Base Time (`bt`): added to the time of this and the following records
+    pub base_time: Option<f64>,
+    /// Base Unit (`bu`): unit of the following records that don't specify one
+    pub base_unit: Option<&'a str>,
+    /// Name (`n`)
+    pub name: Option<&'a str>,
+    /// Unit (`u`)
+    pub unit: Option<&'a str>,
+    /// Value (`v`, `vs`, `vb` or `vd`)
+    pub value: Option<Value<'a>>,
+    /// Time (`t`), in seconds; negative values are relative to the current time
+    pub time: Option<f64>,
+}
+
+/// The value of a SenML record
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Value<'a> {
+    /// Numeric value (`v`)
+    Float(f64),
+    /// Numeric value (`v`)
+    Integer(i64),
+    /// String value (`vs`)
+    String(&'a str),
+    /// Boolean value (`vb`)
+    Bool(bool),
+    /// Data value (`vd`)
+    Data(&'a [u8]),
+}
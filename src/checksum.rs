@@ -0,0 +1,270 @@
+//! Internet checksum
+//!
+//! The 16-bit one's complement of the one's complement sum used by IPv4, ICMP, IGMP, UDP and
+//! ICMPv6. The sum is accumulated 32 bits at a time in a 64-bit register and the carries are
+//! folded once, at the end.
+//!
+//! # References
+//!
+//! - [RFC 1071: Computing the Internet Checksum][rfc1071]
+//!
+//! [rfc1071]: https://tools.ietf.org/html/rfc1071
+//!
+//! - [RFC 1624: Computation of the Internet Checksum via Incremental Update][rfc1624]
+//!
+//! [rfc1624]: https://tools.ietf.org/html/rfc1624
+//!
+//! # Example
+//!
+//! ```
+//! use jnet::{checksum, ipv4};
+//!
+//! // IPv4 header; the Checksum field (bytes 10 and 11) is ignored
+//! let mut header = [
+//!     0x45, 0x00, 0x00, 0x73, 0x00, 0x00, 0x40, 0x00, 0x40, 0x11, 0x00, 0x00, 0xc0, 0xa8, 0x00,
+//!     0x01, 0xc0, 0xa8, 0x00, 0xc7,
+//! ];
+//! let cksum = checksum::compute(&header, 10);
+//! assert_eq!(cksum, 0xb861);
+//! header[10..12].copy_from_slice(&cksum.to_be_bytes());
+//! assert!(checksum::verify(&header));
+//!
+//! // decrement the TTL without recomputing the checksum
+//! let old = u16::from_be_bytes([header[8], header[9]]);
+//! header[8] -= 1;
+//! let new = u16::from_be_bytes([header[8], header[9]]);
+//! let cksum = checksum::update(cksum, old, new);
+//! assert_eq!(cksum, checksum::compute(&header, 10));
+//! ```
+
+use byteorder::{ByteOrder, NetworkEndian as NE};
+
+use crate::{ipv4, ipv6};
+
+/// One's complement sum accumulator
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Checksum {
+    sum: u64,
+}
+
+impl Checksum {
+    /// Creates an empty sum
+    pub const fn new() -> Self {
+        Checksum { sum: 0 }
+    }
+
+    /// Starts a sum with the IPv4 pseudo-header used by UDP and TCP
+    pub fn ipv4_pseudo_header(src: ipv4::Addr, dest: ipv4::Addr, protocol: u8, len: u16) -> Self {
+        let mut sum = Checksum::new();
+        sum.add_bytes(&src.0);
+        sum.add_bytes(&dest.0);
+        sum.add_u16(u16::from(protocol));
+        sum.add_u16(len);
+        sum
+    }
+
+    /// Starts a sum with the IPv6 pseudo-header used by UDP and ICMPv6
+    pub fn ipv6_pseudo_header(
+        src: ipv6::Addr,
+        dest: ipv6::Addr,
+        next_header: u8,
+        len: u32,
+    ) -> Self {
+        let mut sum = Checksum::new();
+        sum.add_bytes(&src.0);
+        sum.add_bytes(&dest.0);
+        sum.add_u32(len);
+        sum.add_u32(u32::from(next_header));
+        sum
+    }
+
+    /// Adds a 16-bit word to the sum
+    pub fn add_u16(&mut self, word: u16) {
+        self.sum += u64::from(word);
+    }
+
+    /// Adds a 32-bit word to the sum
+    pub fn add_u32(&mut self, word: u32) {
+        self.sum += u64::from(word);
+    }
+
+    /// Adds `bytes` to the sum
+    ///
+    /// `bytes` must start at an even offset of the checksummed data; if its length is odd it's
+    /// padded with a zero byte, so only the last chunk of the data can have an odd length
+    pub fn add_bytes(&mut self, bytes: &[u8]) {
+        let mut words = bytes.chunks_exact(4);
+        for word in &mut words {
+            self.sum += u64::from(NE::read_u32(word));
+        }
+
+        let rest = words.remainder();
+        let mut last = [0; 4];
+        last[..rest.len()].copy_from_slice(rest);
+        self.sum += u64::from(NE::read_u32(&last));
+    }
+
+    /// Adds a whole message to the sum, skipping its 2-byte Checksum field, which starts at byte
+    /// `field`
+    ///
+    /// `field` must be even
+    pub fn add_message(&mut self, bytes: &[u8], field: usize) {
+        debug_assert_eq!(field % 2, 0);
+
+        self.add_bytes(&bytes[..field]);
+        self.add_bytes(&bytes[field + 2..]);
+    }
+
+    /// Returns the folded one's complement sum
+    pub fn sum(&self) -> u16 {
+        let mut sum = self.sum;
+        while sum >> 16 != 0 {
+            sum = (sum & 0xffff) + (sum >> 16);
+        }
+
+        sum as u16
+    }
+
+    /// Returns the checksum: the one's complement of the sum
+    pub fn finish(&self) -> u16 {
+        !self.sum()
+    }
+}
+
+/// Computes the checksum of `bytes`, skipping the 2-byte Checksum field that starts at `field`
+pub fn compute(bytes: &[u8], field: usize) -> u16 {
+    let mut sum = Checksum::new();
+    sum.add_message(bytes, field);
+    sum.finish()
+}
+
+/// Verifies the checksum of `bytes`, which include the Checksum field
+pub fn verify(bytes: &[u8]) -> bool {
+    let mut sum = Checksum::new();
+    sum.add_bytes(bytes);
+    sum.sum() == 0xffff
+}
+
+/// Updates `checksum` after a 16-bit word of the data changes from `old` to `new` (RFC 1624)
+pub fn update(checksum: u16, old: u16, new: u16) -> u16 {
+    // HC' = ~(~HC + ~m + m') (Eqn. 3)
+    let mut sum = Checksum::new();
+    sum.add_u16(!checksum);
+    sum.add_u16(!old);
+    sum.add_u16(new);
+    sum.finish()
+}
+
+/// Updates `checksum` after some bytes of the data, e.g. an address, change from `old` to `new`
+///
+/// `old` and `new` must have the same length and start at an even offset of the data
+///
+/// # Panics
+///
+/// This function panics if `old` and `new` have different lengths
+pub fn update_bytes(checksum: u16, old: &[u8], new: &[u8]) -> u16 {
+    assert_eq!(old.len(), new.len());
+
+    let mut sum = Checksum::new();
+    sum.add_u16(!checksum);
+    for (old, new) in old.chunks(2).zip(new.chunks(2)) {
+        sum.add_u16(!word(old));
+        sum.add_u16(word(new));
+    }
+    sum.finish()
+}
+
+// reads a 16-bit word; a trailing byte is padded with zero
+fn word(bytes: &[u8]) -> u16 {
+    if bytes.len() == 1 {
+        u16::from(bytes[0]) << 8
+    } else {
+        NE::read_u16(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ipv4, ipv6};
+
+    use super::Checksum;
+
+    #[test]
+    fn sum() {
+        // RFC 1071 - Section 3. Numerical Examples
+        let mut sum = Checksum::new();
+        sum.add_bytes(&[0x00, 0x01, 0xf2, 0x03, 0xf4, 0xf5, 0xf6, 0xf7]);
+        assert_eq!(sum.sum(), 0xddf2);
+
+        // odd length
+        let mut sum = Checksum::new();
+        sum.add_bytes(&[0x00, 0x01, 0xf2]);
+        assert_eq!(sum.sum(), 0xf201);
+    }
+
+    #[test]
+    fn pseudo_headers() {
+        let src = ipv4::Addr([192, 168, 0, 33]);
+        let dest = ipv4::Addr([192, 168, 0, 1]);
+        let mut expected = Checksum::new();
+        expected.add_bytes(&[192, 168, 0, 33, 192, 168, 0, 1, 0, 17, 0, 22]);
+        assert_eq!(
+            Checksum::ipv4_pseudo_header(src, dest, 17, 22).sum(),
+            expected.sum()
+        );
+
+        let src = ipv6::Addr([0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        let dest = ipv6::Addr::ALL_NODES;
+        let mut expected = Checksum::new();
+        expected.add_bytes(&src.0);
+        expected.add_bytes(&dest.0);
+        expected.add_bytes(&[0, 0, 0, 8, 0, 0, 0, 58]);
+        assert_eq!(
+            Checksum::ipv6_pseudo_header(src, dest, 58, 8).sum(),
+            expected.sum()
+        );
+    }
+
+    #[test]
+    fn update() {
+        // RFC 1624 Section 3: the other words sum to 0xcd7a and a word changes from 0x5555 to
+        // 0x3285; the new checksum is 0x0000, not the 0xffff that Eqn. 2 produces
+        let mut data = [0xcd, 0x7a, 0x55, 0x55, 0x00, 0x00];
+        let cksum = super::compute(&data, 4);
+        assert_eq!(cksum, 0xdd2f);
+        data[2..4].copy_from_slice(&[0x32, 0x85]);
+        assert_eq!(super::update(cksum, 0x5555, 0x3285), 0x0000);
+        assert_eq!(super::compute(&data, 4), 0x0000);
+
+        // a word changes from 0x0000 to 0xffff, the two representations of zero
+        let mut data = [0x12, 0x34, 0x00, 0x00, 0x00, 0x00];
+        let cksum = super::compute(&data, 4);
+        data[2..4].copy_from_slice(&[0xff, 0xff]);
+        assert_eq!(
+            super::update(cksum, 0x0000, 0xffff),
+            super::compute(&data, 4)
+        );
+
+        // several words at once
+        let mut data = [0x12, 0x34, 0x00, 0x00, 0xab, 0xcd, 0x01, 0x02, 0x03];
+        let cksum = super::compute(&data, 2);
+        let old = [data[4], data[5], data[6], data[7]];
+        let new = [0xff, 0xff, 0x00, 0x00];
+        data[4..8].copy_from_slice(&new);
+        assert_eq!(
+            super::update_bytes(cksum, &old, &new),
+            super::compute(&data, 2)
+        );
+
+        let cksum = super::compute(&data, 2);
+        data[0] = 0x11;
+        assert_eq!(
+            super::update(cksum, 0x1234, 0x1134),
+            super::compute(&data, 2)
+        );
+
+        let cksum = super::compute(&data, 2);
+        data[2..4].copy_from_slice(&cksum.to_be_bytes());
+        assert!(super::verify(&data));
+    }
+}
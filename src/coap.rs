@@ -5,6 +5,34 @@
 //! - [RFC 7252: The Constrained Application Protocol (CoAP)][rfc]
 //!
 //! [rfc]: https://tools.ietf.org/html/rfc7252
+//!
+//! - [RFC 7641: Observing Resources in the Constrained Application Protocol (CoAP)][observe]
+//!
+//! [observe]: https://tools.ietf.org/html/rfc7641
+//!
+//! - [RFC 7959: Block-Wise Transfers in the Constrained Application Protocol (CoAP)][block]
+//!
+//! [block]: https://tools.ietf.org/html/rfc7959
+//!
+//! - [RFC 6690: Constrained RESTful Environments (CoRE) Link Format][link]
+//!
+//! [link]: https://tools.ietf.org/html/rfc6690
+//!
+//! - [RFC 8323: CoAP (Constrained Application Protocol) over TCP, TLS, and WebSockets][tcp]
+//!
+//! [tcp]: https://tools.ietf.org/html/rfc8323
+//!
+//! - [RFC 8613: Object Security for Constrained RESTful Environments (OSCORE)][oscore]
+//!
+//! [oscore]: https://tools.ietf.org/html/rfc8613
+//!
+//! - [RFC 7967: Constrained Application Protocol (CoAP) Option for No Server Response][no-response]
+//!
+//! [no-response]: https://tools.ietf.org/html/rfc7967
+//!
+//! - [RFC 9175: CoAP: Echo, Request-Tag, and Token Processing][echo]
+//!
+//! [echo]: https://tools.ietf.org/html/rfc9175
 
 use core::{fmt, marker::PhantomData, ops::Range, option::Option as CoreOption, str};
 
@@ -15,9 +43,30 @@ use owning_slice::Truncate;
 
 use crate::traits::{TryFrom, UncheckedIndex};
 
+#[macro_use]
+mod macros;
+
+pub mod block;
+pub mod client;
+pub mod echo;
+pub mod link;
+pub mod no_response;
+pub mod observe;
+#[cfg(feature = "oscore")]
+pub mod oscore;
+pub mod reliability;
+pub mod server;
+pub mod tcp;
+pub mod uri;
+
+pub use self::reliability::{DedupCache, Transmitter};
+
 /// CoAP default UDP port
 pub const PORT: u16 = 5683;
 
+/// Value of Max-Age when the option is absent, in seconds
+pub const DEFAULT_MAX_AGE: u32 = 60;
+
 /* Message format */
 const VER_T_TKL: usize = 0;
 mod tkl {
@@ -82,13 +131,40 @@ const LENGTH8: u8 = 13;
 const LENGTH16: u8 = 14;
 
 /* Transmission parameters */
-// const ACK_TIMEOUT: u16 = 2_000; // ms
-// const ACK_RANDOM_FACTOR: f32 = 1.5;
-// const MAX_RETRANSMIT: u8 = 4;
+/// Minimum time to wait for the acknowledgement of a Confirmable message, in milliseconds
+pub const ACK_TIMEOUT: u32 = 2_000;
+
+/// The initial timeout is randomly picked from the range `ACK_TIMEOUT..ACK_TIMEOUT *
+/// ACK_RANDOM_FACTOR`
+pub const ACK_RANDOM_FACTOR: f32 = 1.5;
+
+/// Number of retransmissions of a Confirmable message before giving up
+pub const MAX_RETRANSMIT: u8 = 4;
+
 // const NSTART: u8 = 1;
-// const DEFAULT_LEISURE: u16 = 5_000; // ms
+
+/// Time a server waits before answering a multicast request, in milliseconds
+pub const DEFAULT_LEISURE: u32 = 5_000;
+
 // const PROBING_RATE: u8 = 1; // byte / second
 
+/* Derived parameters */
+/// Maximum time from the first transmission of a Confirmable message to its last retransmission,
+/// in milliseconds
+pub const MAX_TRANSMIT_SPAN: u32 = 45_000;
+
+/// Maximum time from the first transmission of a Confirmable message to the time when the sender
+/// gives up on receiving an acknowledgement, in milliseconds
+pub const MAX_TRANSMIT_WAIT: u32 = 93_000;
+
+/// Time from the first transmission of a Confirmable message to the time when an acknowledgement
+/// is no longer expected, in milliseconds
+pub const EXCHANGE_LIFETIME: u32 = 247_000;
+
+/// Time from the first transmission of a Non-confirmable message to the time its Message ID can
+/// be safely reused, in milliseconds
+pub const NON_LIFETIME: u32 = 145_000;
+
 /// CoAP (version 1) message
 // NOTE Invariants
 // - Options are always valid. For example, this means that the reserved bit pattern (0b1111)
@@ -177,19 +253,7 @@ where
         u16(self.as_bytes().len()).unwrap()
     }
 
-    /// Returns an iterator over the options of this message
-    pub fn options(&self) -> Options<'_> {
-        let end = if self.marker != NO_PAYLOAD {
-            usize(self.marker)
-        } else {
-            self.as_slice().len()
-        };
-
-        Options {
-            number: 0,
-            ptr: unsafe { self.as_slice().r(usize(self.options_start())..end) },
-        }
-    }
+    option_getters!();
 
     /* Private */
     fn as_slice(&self) -> &[u8] {
@@ -294,82 +358,6 @@ where
             return Err(bytes);
         }
 
-        // Scans the slice for options
-        //
-        // Returns the highest option number and the index of the PAYLOAD_MARKER
-        fn scan(bytes: &[u8]) -> Result<(u16, CoreOption<u16>), ()> {
-            let len = bytes.len();
-            let mut cursor = 0;
-            let mut number = 0;
-
-            let marker = loop {
-                let head = *match bytes.as_slice().get(usize(cursor)) {
-                    Some(b) => b,
-                    // end of packet -- no payload marker was found
-                    None => break None,
-                };
-
-                if head == PAYLOAD_MARKER {
-                    // end of options
-                    break Some(cursor);
-                }
-                cursor += 1;
-
-                let delta4 = get!(head, delta);
-                let len4 = get!(head, length);
-
-                if delta4 == DELTA8 {
-                    let byte = *bytes.as_slice().get(usize(cursor)).ok_or(())?;
-                    cursor += 1;
-
-                    number += u16(byte) + OFFSET8;
-                } else if delta4 == DELTA16 {
-                    if len < usize(cursor) + 1 {
-                        return Err(());
-                    }
-
-                    let halfword = unsafe {
-                        NE::read_u16(
-                            &*(bytes.as_slice().as_ptr().add(usize(cursor)) as *const [u8; 2]),
-                        )
-                    };
-                    cursor += 2;
-
-                    number += halfword + OFFSET16;
-                } else if delta4 == RESERVED {
-                    return Err(());
-                } else {
-                    number += u16(delta4);
-                }
-
-                if len4 == LENGTH8 {
-                    let byte = *bytes.as_slice().get(usize(cursor)).ok_or(())?;
-                    cursor += 1;
-
-                    cursor += u16(byte) + OFFSET8;
-                } else if len4 == LENGTH16 {
-                    if len < usize(cursor) + 1 {
-                        return Err(());
-                    }
-
-                    let halfword = unsafe {
-                        NE::read_u16(
-                            &*(bytes.as_slice().as_ptr().add(usize(cursor)) as *const [u8; 2]),
-                        )
-                    };
-                    cursor += 2;
-
-                    cursor += halfword + OFFSET16;
-                } else if len4 == RESERVED {
-                    return Err(());
-                } else {
-                    cursor += u16(len4);
-                }
-            };
-
-            Ok((number, marker))
-        }
-
         if let Ok((number, marker)) = unsafe { scan(bytes.as_slice().rf(usize(opts_start)..)) } {
             Ok(Message {
                 _payload: PhantomData,
@@ -442,83 +430,7 @@ where
         }
     }
 
-    /// Adds an option to this message
-    ///
-    /// *HEADS UP* This method will cause the first bytes of the payload to be lost
-    ///
-    /// # Panics
-    ///
-    /// This method panics
-    ///
-    /// - if `number` is smaller than the highest option number already contained in the message
-    /// - if there's no space in the message to add the option
-    pub fn add_option(&mut self, number: OptionNumber, value: &[u8]) {
-        /// Number of bytes required to encode `x`
-        fn nbytes(x: u16) -> u16 {
-            if x < OFFSET8 {
-                0 // 0.5 actually; this fits in a nibble
-            } else if x < OFFSET16 {
-                1
-            } else {
-                2
-            }
-        }
-
-        // we can only add options that have an equal or a higher option number
-        let nr: u16 = number.into();
-        let delta = nr.checked_sub(self.number).unwrap();
-
-        let len = u16(value.len()).unwrap();
-        let sz = 1 + nbytes(delta) + nbytes(len) + len;
-
-        let start = usize(self.marker);
-        let mut cursor = start + 1;
-
-        // update the cached highest number
-        self.number = nr;
-
-        // move the payload marker
-        self.marker += sz;
-        let end = usize(self.marker);
-
-        // fill in the delta
-        if delta < OFFSET8 {
-            set!(self.as_mut_slice()[start], delta, u8(delta).unwrap());
-        } else if delta < OFFSET16 {
-            set!(self.as_mut_slice()[start], delta, DELTA8);
-            self.as_mut_slice()[cursor] = u8(delta - OFFSET8).unwrap();
-            cursor += 1;
-        } else {
-            set!(self.as_mut_slice()[start], delta, DELTA16);
-            NE::write_u16(
-                &mut self.as_mut_slice()[cursor..cursor + 2],
-                delta - OFFSET16,
-            );
-            cursor += 2;
-        }
-
-        // fill in the length
-        if len < OFFSET8 {
-            set!(self.as_mut_slice()[start], length, u8(len).unwrap());
-        } else if len < OFFSET16 {
-            set!(self.as_mut_slice()[start], length, LENGTH8);
-            self.as_mut_slice()[cursor] = u8(len - OFFSET8).unwrap();
-            cursor += 1;
-        } else {
-            set!(self.as_mut_slice()[start], length, LENGTH16);
-            NE::write_u16(&mut self.as_mut_slice()[cursor..cursor + 2], len - OFFSET16);
-            cursor += 2;
-        }
-
-        // fill in the value
-        self.as_mut_slice()[cursor..end].copy_from_slice(value);
-    }
-
-    /// Removes all the options this message has
-    pub fn clear_options(&mut self) {
-        self.number = 0;
-        self.marker = u16(self.options_start());
-    }
+    option_setters!();
 }
 
 impl<B> Message<B, Unset>
@@ -553,6 +465,63 @@ where
         }
     }
 
+    /// Fills the payload in place and adjusts the length of the CoAP message
+    ///
+    /// `f` receives the free space after the payload marker (see `payload_capacity`) and must
+    /// return the length of the payload it wrote
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the returned length is larger than the space handed to `f`
+    pub fn set_payload_with<F>(mut self, f: F) -> Message<B>
+    where
+        F: FnOnce(&mut [u8]) -> usize,
+    {
+        let start = usize(self.marker) + 1;
+        let len = if start <= self.buffer.as_slice().len() {
+            let free = &mut self.buffer.as_mut_slice()[start..];
+            let len = f(free);
+            assert!(len <= free.len());
+            len
+        } else {
+            0
+        };
+
+        if len == 0 {
+            return self.no_payload();
+        }
+
+        self.buffer.as_mut_slice()[start - 1] = PAYLOAD_MARKER;
+        self.buffer.truncate(u16(start + len).unwrap());
+
+        Message {
+            _payload: PhantomData,
+            buffer: self.buffer,
+            marker: self.marker,
+            number: self.number,
+        }
+    }
+
+    /// Like `set_payload_with` but `f` can fail
+    ///
+    /// If `f` returns an error the message is handed back without a payload, so that, for example,
+    /// its code can be changed to report the error
+    pub fn try_set_payload_with<F, E>(mut self, f: F) -> Result<Message<B>, (Self, E)>
+    where
+        F: FnOnce(&mut [u8]) -> Result<usize, E>,
+    {
+        let start = usize(self.marker) + 1;
+        let res = match self.buffer.as_mut_slice().get_mut(start..) {
+            Some(free) => f(free),
+            None => f(&mut []),
+        };
+
+        match res {
+            Ok(len) => Ok(self.set_payload_with(|_| len)),
+            Err(e) => Err((self, e)),
+        }
+    }
+
     /// Finishing constructing this message by leaving the payload empty and truncating the message
     pub fn no_payload(mut self) -> Message<B> {
         let len = self.marker;
@@ -743,6 +712,194 @@ impl<'a> Iterator for Options<'a> {
     }
 }
 
+// Scans the slice for options
+//
+// Returns the highest option number and the index of the PAYLOAD_MARKER
+fn scan(bytes: &[u8]) -> Result<(u16, CoreOption<u16>), ()> {
+    let len = bytes.len();
+    let mut cursor = 0;
+    let mut number = 0;
+
+    let marker = loop {
+        let head = *match bytes.as_slice().get(usize(cursor)) {
+            Some(b) => b,
+            // end of packet -- no payload marker was found
+            None => {
+                // the value of the last option must not be truncated
+                if usize(cursor) > len {
+                    return Err(());
+                }
+
+                break None;
+            }
+        };
+
+        if head == PAYLOAD_MARKER {
+            // end of options
+            break Some(cursor);
+        }
+        cursor += 1;
+
+        let delta4 = get!(head, delta);
+        let len4 = get!(head, length);
+
+        if delta4 == DELTA8 {
+            let byte = *bytes.as_slice().get(usize(cursor)).ok_or(())?;
+            cursor += 1;
+
+            number += u16(byte) + OFFSET8;
+        } else if delta4 == DELTA16 {
+            if len < usize(cursor) + 2 {
+                return Err(());
+            }
+
+            let halfword = unsafe {
+                NE::read_u16(&*(bytes.as_slice().as_ptr().add(usize(cursor)) as *const [u8; 2]))
+            };
+            cursor += 2;
+
+            number += halfword + OFFSET16;
+        } else if delta4 == RESERVED {
+            return Err(());
+        } else {
+            number += u16(delta4);
+        }
+
+        if len4 == LENGTH8 {
+            let byte = *bytes.as_slice().get(usize(cursor)).ok_or(())?;
+            cursor += 1;
+
+            cursor += u16(byte) + OFFSET8;
+        } else if len4 == LENGTH16 {
+            if len < usize(cursor) + 2 {
+                return Err(());
+            }
+
+            let halfword = unsafe {
+                NE::read_u16(&*(bytes.as_slice().as_ptr().add(usize(cursor)) as *const [u8; 2]))
+            };
+            cursor += 2;
+
+            cursor += halfword + OFFSET16;
+        } else if len4 == RESERVED {
+            return Err(());
+        } else {
+            cursor += u16(len4);
+        }
+    };
+
+    Ok((number, marker))
+}
+
+// Encodes `x` as an uint option value using the minimal number of bytes (RFC 7252 Section 3.2)
+fn encode_uint(x: u32, buf: &mut [u8; 4]) -> &[u8] {
+    NE::write_u32(buf, x);
+    let zeros = x.leading_zeros() as usize / 8;
+    &buf[zeros..]
+}
+
+// Decodes an uint option value; returns `None` if the value is longer than 4 bytes
+fn decode_uint(bytes: &[u8]) -> CoreOption<u32> {
+    if bytes.len() > 4 {
+        None
+    } else {
+        Some(bytes.iter().fold(0, |x, byte| x << 8 | u32::from(*byte)))
+    }
+}
+
+// Position of an option within the message
+#[derive(Clone, Copy)]
+struct Position {
+    number: u16,
+    // start of the option header
+    start: usize,
+    // start of the option value
+    value: usize,
+    // end of the option value
+    end: usize,
+}
+
+impl Position {
+    fn len(&self) -> u16 {
+        u16(self.end - self.value).unwrap()
+    }
+}
+
+// Encodes an option header; returns the used part of `buf`
+fn encode_header(delta: u16, len: u16, buf: &mut [u8; 5]) -> &[u8] {
+    let mut cursor = 1;
+
+    let delta4 = if delta < OFFSET8 {
+        u8(delta).unwrap()
+    } else if delta < OFFSET16 {
+        buf[cursor] = u8(delta - OFFSET8).unwrap();
+        cursor += 1;
+        DELTA8
+    } else {
+        NE::write_u16(&mut buf[cursor..cursor + 2], delta - OFFSET16);
+        cursor += 2;
+        DELTA16
+    };
+
+    let len4 = if len < OFFSET8 {
+        u8(len).unwrap()
+    } else if len < OFFSET16 {
+        buf[cursor] = u8(len - OFFSET8).unwrap();
+        cursor += 1;
+        LENGTH8
+    } else {
+        NE::write_u16(&mut buf[cursor..cursor + 2], len - OFFSET16);
+        cursor += 2;
+        LENGTH16
+    };
+
+    buf[0] = 0;
+    set!(buf[0], delta, delta4);
+    set!(buf[0], length, len4);
+
+    &buf[..cursor]
+}
+
+/// Iterator over the values of all the options with the same number
+pub struct Values<'a> {
+    number: OptionNumber,
+    options: Options<'a>,
+}
+
+impl<'a> Iterator for Values<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> CoreOption<&'a [u8]> {
+        let number = self.number;
+
+        self.options
+            .find(|opt| opt.number() == number)
+            .map(|opt| opt.value())
+    }
+}
+
+/// Iterator over the values of all the options with the same number, as strings
+///
+/// Values that are not valid UTF-8 are yielded as errors
+pub struct Strings<'a>(Values<'a>);
+
+impl<'a> Iterator for Strings<'a> {
+    type Item = Result<&'a str, str::Utf8Error>;
+
+    fn next(&mut self) -> CoreOption<Self::Item> {
+        self.0.next().map(str::from_utf8)
+    }
+}
+
+/// CoAP error
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Error {
+    /// The length of the option value is outside the range allowed for the option number
+    OptionLength,
+    /// There's no space left in the buffer
+    BufferTooSmall,
+}
+
 /// CoAP Type
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Type {
@@ -856,6 +1013,8 @@ code!(
         Put = (0, 3),
         /// DELETE
         Delete = (0, 4),
+        /// FETCH (RFC 8132)
+        Fetch = (0, 5),
     }
 );
 
@@ -874,6 +1033,8 @@ code!(
         Changed = (2, 4),
         /// Content
         Content = (2, 5),
+        /// Continue
+        Continue = (2, 31),
 
         // Client error
         /// Bad Request
@@ -890,6 +1051,8 @@ code!(
         MethodNotAllowed = (4, 5),
         /// Not Acceptable
         NotAcceptable = (4, 6),
+        /// Request Entity Incomplete
+        RequestEntityIncomplete = (4, 8),
         /// Precondition Failed
         PreconditionFailed = (4, 12),
         /// Request Entity Too Large
@@ -928,10 +1091,14 @@ full_range!(
         ETag = 4,
         /// If-None-Patch
         IfNoneMatch = 5,
+        /// Observe
+        Observe = 6,
         /// Uri-Port
         UriPort = 7,
         /// Location-Path
         LocationPath = 8,
+        /// OSCORE (RFC 8613)
+        Oscore = 9,
         /// Uri-Path
         UriPath = 11,
         /// Content-Format
@@ -944,6 +1111,12 @@ full_range!(
         Accept = 17,
         /// Location-Query
         LocationQuery = 20,
+        /// Block2
+        Block2 = 23,
+        /// Block1
+        Block1 = 27,
+        /// Size2
+        Size2 = 28,
         /// Proxy-Uri
         ProxyUri = 35,
         /// Proxy-Scheme
@@ -958,6 +1131,12 @@ full_range!(
         Reserved3 = 136,
         /// Reserved
         Reserved4 = 140,
+        /// Echo (RFC 9175)
+        Echo = 252,
+        /// No-Response (RFC 7967)
+        NoResponse = 258,
+        /// Request-Tag (RFC 9175)
+        RequestTag = 292,
     }
 );
 
@@ -968,6 +1147,39 @@ impl OptionNumber {
         u16::from(*self) % 2 == 1
     }
 
+    /// Returns the minimum and maximum length of the value of this option
+    ///
+    /// Returns `None` for unknown options
+    fn length_limits(&self) -> CoreOption<(u16, u16)> {
+        // RFC 7252 - Section 5.10. Table 4
+        Some(match *self {
+            OptionNumber::IfMatch => (0, 8),
+            OptionNumber::UriHost => (1, 255),
+            OptionNumber::ETag => (1, 8),
+            OptionNumber::IfNoneMatch => (0, 0),
+            OptionNumber::Observe => (0, 3),
+            OptionNumber::UriPort => (0, 2),
+            OptionNumber::LocationPath => (0, 255),
+            OptionNumber::Oscore => (0, 255),
+            OptionNumber::UriPath => (0, 255),
+            OptionNumber::ContentFormat => (0, 2),
+            OptionNumber::MaxAge => (0, 4),
+            OptionNumber::UriQuery => (0, 255),
+            OptionNumber::Accept => (0, 2),
+            OptionNumber::LocationQuery => (0, 255),
+            OptionNumber::Block2 => (0, 3),
+            OptionNumber::Block1 => (0, 3),
+            OptionNumber::Size2 => (0, 4),
+            OptionNumber::ProxyUri => (1, 1034),
+            OptionNumber::ProxyScheme => (1, 255),
+            OptionNumber::Size1 => (0, 4),
+            OptionNumber::Echo => (1, 40),
+            OptionNumber::NoResponse => (0, 1),
+            OptionNumber::RequestTag => (0, 8),
+            _ => return None,
+        })
+    }
+
     /// Is this an elective option?
     pub fn is_elective(&self) -> bool {
         // even option numbers are elective
@@ -983,6 +1195,7 @@ impl OptionNumber {
 full_range!(
     u16,
     /// CoAP Content-Formats
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
     pub enum ContentFormat {
         /// text/plain; charset=utf-8
         TextPlain = 0,
@@ -996,6 +1209,14 @@ full_range!(
         ApplicationExi = 47,
         /// application/json
         ApplicationJson = 50,
+        /// application/cbor
+        ApplicationCbor = 60,
+        /// application/senml+json
+        ApplicationSenmlJson = 110,
+        /// application/senml+cbor
+        ApplicationSenmlCbor = 112,
+        /// application/vnd.oma.lwm2m+tlv
+        ApplicationVndOmaLwm2mTlv = 11542,
     }
 );
 
@@ -1037,6 +1258,26 @@ mod tests {
         assert_eq!(usize(m.len()), SZ);
     }
 
+    #[test]
+    fn try_set_payload_with() {
+        let mut chunk = [0; 16];
+
+        let mut coap = coap::Message::new(&mut chunk[..], 0);
+        coap.add_content_format(coap::ContentFormat::TextPlain)
+            .unwrap();
+        let (mut coap, ()) = coap.try_set_payload_with(|_| Err(())).unwrap_err();
+        assert!(coap.remove_option(coap::OptionNumber::ContentFormat));
+
+        let m = coap
+            .try_set_payload_with(|buf| {
+                buf[..2].copy_from_slice(b"ok");
+                Ok::<_, ()>(2)
+            })
+            .unwrap();
+        assert!(m.options().next().is_none());
+        assert_eq!(m.payload(), b"ok");
+    }
+
     #[test]
     fn options() {
         // NOTE start with randomized array to make sure we set *everything* correctly
@@ -1045,7 +1286,8 @@ mod tests {
 
         let mut coap = coap::Message::new(&mut buf[..], rand::thread_rng().gen::<u8>() % 9);
 
-        coap.add_option(coap::OptionNumber::UriHost, URI_HOST);
+        coap.add_option(coap::OptionNumber::UriHost, URI_HOST)
+            .unwrap();
 
         {
             let host = coap.options().next().unwrap();
@@ -1054,7 +1296,8 @@ mod tests {
             assert_eq!(host.value(), URI_HOST);
         }
 
-        coap.add_option(coap::OptionNumber::UriPort, URI_PORT);
+        coap.add_option(coap::OptionNumber::UriPort, URI_PORT)
+            .unwrap();
 
         {
             let host = coap.options().nth(0).unwrap();
@@ -1072,6 +1315,137 @@ mod tests {
         assert!(coap.options().next().is_none());
     }
 
+    #[test]
+    fn out_of_order_options() {
+        // (number, value); the Proxy-Uri option forces a 2-byte delta
+        let options: &[(coap::OptionNumber, &[u8])] = &[
+            (coap::OptionNumber::UriHost, URI_HOST),
+            (coap::OptionNumber::ETag, &[1]),
+            (coap::OptionNumber::UriPort, URI_PORT),
+            (coap::OptionNumber::UriPath, b"a"),
+            (coap::OptionNumber::UriPath, b"b"),
+            (coap::OptionNumber::ContentFormat, &[]),
+            (coap::OptionNumber::ProxyUri, &[0; 20]),
+            (coap::OptionNumber::Reserved4, &[]),
+        ];
+
+        let mut expected = [0; 128];
+        let mut m = coap::Message::new(&mut expected[..], 0);
+        for (number, value) in options {
+            m.add_option(*number, value).unwrap();
+        }
+        let expected = m.no_payload();
+
+        // insert in reverse order
+        let mut buf = [0; 128];
+        let mut m = coap::Message::new(&mut buf[..], 0);
+        for (number, value) in options.iter().rev() {
+            m.add_option(*number, value).unwrap();
+        }
+        // same number options keep their insertion order
+        assert!(m.remove_option(coap::OptionNumber::UriPath));
+        m.add_option(coap::OptionNumber::UriPath, b"b").unwrap();
+        assert_eq!(m.no_payload().as_bytes(), expected.as_bytes());
+
+        // removal
+        let mut buf = [0; 128];
+        let mut m = coap::Message::new(&mut buf[..], 0);
+        for (number, value) in options {
+            m.add_option(*number, value).unwrap();
+        }
+        assert_eq!(m.remove_options(coap::OptionNumber::UriPath), 2);
+        assert!(!m.remove_option(coap::OptionNumber::UriPath));
+        assert!(m.remove_option(coap::OptionNumber::UriHost));
+        assert!(m.remove_option(coap::OptionNumber::Reserved4));
+        let m = m.no_payload();
+        let m = coap::Message::parse(m.as_bytes()).unwrap();
+        assert!(m.options().map(|opt| opt.number()).eq([
+            coap::OptionNumber::ETag,
+            coap::OptionNumber::UriPort,
+            coap::OptionNumber::ContentFormat,
+            coap::OptionNumber::ProxyUri,
+        ]
+        .iter()
+        .cloned()));
+
+        // no space left; the message is left unchanged
+        let mut buf = [0; 8];
+        let mut m = coap::Message::new(&mut buf[..], 0);
+        m.add_option(coap::OptionNumber::UriPath, b"ab").unwrap();
+        assert_eq!(
+            m.add_option(coap::OptionNumber::UriHost, b"a"),
+            Err(coap::Error::BufferTooSmall)
+        );
+        assert_eq!(
+            m.add_option(coap::OptionNumber::UriPath, b"a"),
+            Err(coap::Error::BufferTooSmall)
+        );
+        assert_eq!(
+            m.no_payload().as_bytes(),
+            &[0x40, 0, 0, 0, 0xb2, b'a', b'b']
+        );
+    }
+
+    #[test]
+    fn typed_options() {
+        let mut buf = [0; 128];
+        let mut coap = coap::Message::new(&mut buf[..], 0);
+
+        coap.add_etag(&[1, 2]).unwrap();
+        coap.add_etag(&[3]).unwrap();
+        coap.add_uri_port(5683).unwrap();
+        coap.add_uri_path("sensors").unwrap();
+        coap.add_uri_path("temp").unwrap();
+        coap.add_content_format(coap::ContentFormat::TextPlain)
+            .unwrap();
+        coap.add_max_age(0x1_0000).unwrap();
+        coap.add_uri_query("unit=C").unwrap();
+        coap.add_accept(coap::ContentFormat::ApplicationJson)
+            .unwrap();
+
+        // invalid lengths
+        assert_eq!(coap.add_etag(&[]), Err(coap::Error::OptionLength));
+        assert_eq!(
+            coap.add_uint_option(coap::OptionNumber::UriPort, 0x1_0000),
+            Err(coap::Error::OptionLength)
+        );
+
+        let m = coap.no_payload();
+        let m = coap::Message::parse(m.as_bytes()).unwrap();
+
+        assert!(m.etags().eq([&[1, 2][..], &[3][..]].iter().cloned()));
+        assert_eq!(m.uri_port(), Some(5683));
+        assert!(m.uri_path().eq(["sensors", "temp"].iter().cloned().map(Ok)));
+        assert_eq!(m.content_format(), Some(coap::ContentFormat::TextPlain));
+        assert_eq!(m.max_age(), Some(0x1_0000));
+        assert!(m.uri_query().eq(["unit=C"].iter().cloned().map(Ok)));
+        assert_eq!(m.accept(), Some(coap::ContentFormat::ApplicationJson));
+
+        // minimal encoding
+        let cf = m
+            .options()
+            .find(|opt| opt.number() == coap::OptionNumber::ContentFormat)
+            .unwrap();
+        assert_eq!(cf.value(), &[]);
+        let max_age = m
+            .options()
+            .find(|opt| opt.number() == coap::OptionNumber::MaxAge)
+            .unwrap();
+        assert_eq!(max_age.value(), &[1, 0, 0]);
+
+        // Uri-Path values that are not valid UTF-8
+        let mut buf = [0; 32];
+        let mut coap = coap::Message::new(&mut buf[..], 0);
+        coap.add_uri_path("led").unwrap();
+        coap.add_option(coap::OptionNumber::UriPath, &[0xff])
+            .unwrap();
+        let m = coap.no_payload();
+        let mut path = m.uri_path();
+        assert_eq!(path.next(), Some(Ok("led")));
+        assert!(path.next().unwrap().is_err());
+        assert!(path.next().is_none());
+    }
+
     #[test]
     fn parse() {
         const TYPE: coap::Type = coap::Type::Confirmable;
@@ -1107,7 +1481,8 @@ mod tests {
             assert_eq!(m.token(), token);
         }
 
-        coap.add_option(coap::OptionNumber::UriHost, URI_HOST);
+        coap.add_option(coap::OptionNumber::UriHost, URI_HOST)
+            .unwrap();
 
         // one option
         {
@@ -1126,7 +1501,8 @@ mod tests {
             assert_eq!(host.value(), URI_HOST);
         }
 
-        coap.add_option(coap::OptionNumber::UriPort, URI_PORT);
+        coap.add_option(coap::OptionNumber::UriPort, URI_PORT)
+            .unwrap();
 
         // two options
         {
@@ -1148,5 +1524,12 @@ mod tests {
             assert_eq!(port.number(), coap::OptionNumber::UriPort);
             assert_eq!(port.value(), URI_PORT);
         }
+
+        // truncated option value
+        let len = coap.as_bytes().len();
+        assert!(coap::Message::parse(&coap.as_bytes()[..len - 1]).is_err());
+
+        // truncated extended option length
+        assert!(coap::Message::parse(&[0x40, 0x01, 0x00, 0x00, 0x3e, 0x00][..]).is_err());
     }
 }
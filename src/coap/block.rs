@@ -0,0 +1,541 @@
+//! Block-wise transfers (RFC 7959)
+//!
+//! - Servers use `serve` to pick the slice of a large representation that answers a request
+//!   carrying a Block2 option.
+//! - Clients use `Reassembler` to put together a representation transferred in Block2 responses,
+//!   and `Uploader` to split a large request payload into Block1 requests.
+//!
+//! # Example
+//!
+//! ```
+//! use jnet::coap::{self, block};
+//!
+//! static RESOURCE: &[u8] = &[0; 100];
+//!
+//! // server side: answer a request for the second 32-byte block
+//! let request = block::Block::new(1, false, 1);
+//! let (block, chunk) = block::serve(RESOURCE, Some(request), 6).unwrap();
+//! assert_eq!(block.num(), 1);
+//! assert!(block.more());
+//! assert_eq!(chunk.len(), 32);
+//!
+//! // client side
+//! let mut buf = [0; 128];
+//! let mut reassembler = block::Reassembler::new(&mut buf[..], 1);
+//! assert_eq!(reassembler.request().num(), 0);
+//! ```
+
+use core::{cmp, ops::Range};
+
+use as_slice::{AsMutSlice, AsSlice};
+
+use crate::coap::{encode_uint, Message, Response};
+
+// Block option value
+mod szx {
+    pub const MASK: u32 = (1 << SIZE) - 1;
+    pub const OFFSET: u32 = 0;
+    pub const SIZE: u32 = 3;
+}
+
+mod m {
+    pub const MASK: u32 = (1 << SIZE) - 1;
+    pub const OFFSET: u32 = super::szx::OFFSET + super::szx::SIZE;
+    pub const SIZE: u32 = 1;
+}
+
+mod num {
+    pub const OFFSET: u32 = super::m::OFFSET + super::m::SIZE;
+}
+
+/// The largest valid SZX value (1024-byte blocks)
+// NOTE 7 is reserved (and used by BERT, RFC 8323)
+pub const MAX_SZX: u8 = 6;
+
+/// The largest block number
+pub const MAX_NUM: u32 = (1 << 20) - 1;
+
+/// Value of a Block1 or Block2 option
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Block {
+    num: u32,
+    more: bool,
+    szx: u8,
+}
+
+impl Block {
+    /// Creates a new block descriptor
+    ///
+    /// # Panics
+    ///
+    /// This constructor panics if `num` is greater than `MAX_NUM` or `szx` is greater than
+    /// `MAX_SZX`
+    pub fn new(num: u32, more: bool, szx: u8) -> Self {
+        assert!(num <= MAX_NUM && szx <= MAX_SZX);
+
+        Block { num, more, szx }
+    }
+
+    /// Creates a new block descriptor
+    ///
+    /// Returns `None` if `num` is greater than `MAX_NUM` or `szx` is greater than `MAX_SZX`
+    pub fn try_new(num: u32, more: bool, szx: u8) -> Option<Self> {
+        if num <= MAX_NUM && szx <= MAX_SZX {
+            Some(Block { num, more, szx })
+        } else {
+            None
+        }
+    }
+
+    /// Decodes the value of a Block option
+    ///
+    /// Returns `None` if the value is longer than 3 bytes or if it uses the reserved SZX value
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() > 3 {
+            return None;
+        }
+
+        let x = super::decode_uint(bytes)?;
+        let szx = get!(x, szx) as u8;
+
+        if szx > MAX_SZX {
+            None
+        } else {
+            Some(Block {
+                num: x >> num::OFFSET,
+                more: get!(x, m) == 1,
+                szx,
+            })
+        }
+    }
+
+    /// Encodes this block descriptor using the minimal number of bytes
+    pub fn encode<'b>(&self, buf: &'b mut [u8; 4]) -> &'b [u8] {
+        let mut x = self.num << num::OFFSET;
+        set!(x, m, u32::from(self.more));
+        set!(x, szx, u32::from(self.szx));
+
+        encode_uint(x, buf)
+    }
+
+    /// Returns the block number (NUM)
+    pub fn num(&self) -> u32 {
+        self.num
+    }
+
+    /// Returns the 'More' flag (M)
+    pub fn more(&self) -> bool {
+        self.more
+    }
+
+    /// Returns the size exponent (SZX)
+    pub fn szx(&self) -> u8 {
+        self.szx
+    }
+
+    /// Returns the block size in bytes
+    pub fn size(&self) -> u16 {
+        size(self.szx)
+    }
+
+    /// Returns the offset, in bytes, of this block within the representation
+    pub fn offset(&self) -> u32 {
+        self.num * u32::from(self.size())
+    }
+}
+
+/// Error returned by `Reassembler` and `Uploader`
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Error {
+    /// The response doesn't carry the expected block
+    UnexpectedBlock,
+    /// The response is an error response
+    Response,
+    /// The reassembly buffer is too small
+    BufferTooSmall,
+}
+
+/// Returns the block of `representation` that answers a request with the given Block2 option
+///
+/// `szx` is the size exponent preferred by the server; if the client requested larger blocks the
+/// server's preference wins. A request without a Block2 option is answered with the first block.
+///
+/// Returns the Block2 option to include in the response and the payload of the response, or
+/// `None` if the requested block lies beyond the end of the representation or can't be expressed
+/// with the server's block size (the server should answer with 4.02 Bad Option)
+pub fn serve(representation: &[u8], request: Option<Block>, szx: u8) -> Option<(Block, &[u8])> {
+    let (block, range) = select(representation.len(), request, szx)?;
+
+    Some((block, &representation[range]))
+}
+
+/// Like `serve` but for a representation of length `len` that's not stored in memory
+///
+/// Returns the Block2 option to include in the response and the range of the representation
+/// that must be sent as the payload of the response
+pub fn select(len: usize, request: Option<Block>, szx: u8) -> Option<(Block, Range<usize>)> {
+    let szx = cmp::min(szx, MAX_SZX);
+    let block = match request {
+        None => Block::new(0, false, szx),
+        Some(request) if request.szx <= szx => request,
+        // RFC 7959 - Section 2.4. the server uses a smaller block size; the client's request still
+        // determines the offset
+        // NOTE the block number may not fit in 20 bits at the smaller block size
+        Some(request) => Block::try_new(request.offset() / u32::from(size(szx)), false, szx)?,
+    };
+
+    let start = block.offset() as usize;
+    if start > len || (start == len && start != 0) {
+        return None;
+    }
+
+    let end = cmp::min(start + usize::from(block.size()), len);
+
+    Some((Block::new(block.num, end < len, block.szx), start..end))
+}
+
+/// Reassembles a representation transferred in Block2 responses
+pub struct Reassembler<B>
+where
+    B: AsMutSlice<Element = u8>,
+{
+    buffer: B,
+    len: usize,
+    num: u32,
+    szx: u8,
+    done: bool,
+}
+
+impl<B> Reassembler<B>
+where
+    B: AsMutSlice<Element = u8>,
+{
+    /// Creates a new reassembler that will store the representation in `buffer`
+    ///
+    /// `szx` is the block size that will be requested; the server may answer with smaller blocks
+    pub fn new(buffer: B, szx: u8) -> Self {
+        Reassembler {
+            buffer,
+            len: 0,
+            num: 0,
+            szx: cmp::min(szx, MAX_SZX),
+            done: false,
+        }
+    }
+
+    /// Returns the Block2 option that the next request must carry
+    pub fn request(&self) -> Block {
+        Block::new(self.num, false, self.szx)
+    }
+
+    /// Processes a response
+    ///
+    /// Returns `true` if the whole representation has been received. A response without a Block2
+    /// option is treated as a complete representation.
+    pub fn response<C>(&mut self, response: &Message<C>) -> Result<bool, Error>
+    where
+        C: AsSlice<Element = u8>,
+    {
+        if self.done {
+            return Ok(true);
+        }
+
+        if !response.get_code().is_response() || response.get_code().class() != 2 {
+            return Err(Error::Response);
+        }
+
+        let payload = response.payload();
+        let block = match response.get_block2() {
+            Some(block) => block,
+            None if self.len == 0 => {
+                self.copy(payload)?;
+                self.done = true;
+                return Ok(true);
+            }
+            None => return Err(Error::UnexpectedBlock),
+        };
+
+        // NOTE comparing offsets handles the case where the server picked a smaller block size
+        if block.offset() as usize != self.len
+            || block.szx > self.szx
+            || (block.more && payload.len() != usize::from(block.size()))
+            || payload.len() > usize::from(block.size())
+        {
+            return Err(Error::UnexpectedBlock);
+        }
+
+        self.copy(payload)?;
+
+        if block.more {
+            self.szx = block.szx;
+            self.num = block.num + 1;
+
+            Ok(false)
+        } else {
+            self.done = true;
+
+            Ok(true)
+        }
+    }
+
+    /// Returns the part of the representation that has been received so far
+    pub fn payload(&self) -> &[u8] {
+        &self.buffer.as_slice()[..self.len]
+    }
+
+    /// Returns `true` if the whole representation has been received
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Frees the reassembly buffer
+    pub fn free(self) -> B {
+        self.buffer
+    }
+
+    fn copy(&mut self, payload: &[u8]) -> Result<(), Error> {
+        let start = self.len;
+        let end = start + payload.len();
+
+        self.buffer
+            .as_mut_slice()
+            .get_mut(start..end)
+            .ok_or(Error::BufferTooSmall)?
+            .copy_from_slice(payload);
+        self.len = end;
+
+        Ok(())
+    }
+}
+
+/// Splits a request payload into Block1 requests
+pub struct Uploader<'a> {
+    data: &'a [u8],
+    // offset of the next block
+    offset: usize,
+    szx: u8,
+    done: bool,
+}
+
+impl<'a> Uploader<'a> {
+    /// Creates a new uploader that will send `data` in blocks of size `szx`
+    pub fn new(data: &'a [u8], szx: u8) -> Self {
+        Uploader {
+            data,
+            offset: 0,
+            szx: cmp::min(szx, MAX_SZX),
+            done: false,
+        }
+    }
+
+    /// Returns the Block1 option and the payload of the next request, or `None` if the upload is
+    /// complete
+    pub fn block(&self) -> Option<(Block, &'a [u8])> {
+        if self.done {
+            return None;
+        }
+
+        let size = usize::from(size(self.szx));
+        let end = cmp::min(self.offset + size, self.data.len());
+        let block = Block::new((self.offset / size) as u32, end < self.data.len(), self.szx);
+
+        Some((block, &self.data[self.offset..end]))
+    }
+
+    /// Processes the response to the request built from the latest `block`
+    ///
+    /// Returns `true` if the upload is complete. The server can ask for smaller blocks in its
+    /// response; subsequent blocks will use that size.
+    pub fn response<C>(&mut self, response: &Message<C>) -> Result<bool, Error>
+    where
+        C: AsSlice<Element = u8>,
+    {
+        let (sent, chunk) = match self.block() {
+            Some(block) => block,
+            None => return Ok(true),
+        };
+
+        let code = response.get_code();
+        if !code.is_response() || code.class() != 2 {
+            return Err(Error::Response);
+        }
+
+        let is_continue = code == Response::Continue.into();
+        let acked = match response.get_block1() {
+            Some(acked) => acked,
+            // the server may process the whole request at once
+            None if !is_continue => {
+                self.done = true;
+                return Ok(true);
+            }
+            None => return Err(Error::UnexpectedBlock),
+        };
+
+        if acked.num != sent.num || acked.szx > sent.szx || is_continue != sent.more {
+            return Err(Error::UnexpectedBlock);
+        }
+
+        self.offset += chunk.len();
+        self.szx = acked.szx;
+        if !sent.more {
+            self.done = true;
+        }
+
+        Ok(self.done)
+    }
+}
+
+fn size(szx: u8) -> u16 {
+    1 << (szx + 4)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::coap::{self, Response};
+
+    use super::{Block, Error, Reassembler, Uploader};
+
+    #[test]
+    fn encode_decode() {
+        let mut buf = [0; 4];
+
+        let block = Block::new(0, false, 0);
+        assert_eq!(block.encode(&mut buf), &[]);
+        assert_eq!(Block::decode(&[]), Some(block));
+
+        let block = Block::new(1, true, 6);
+        assert_eq!(block.encode(&mut buf), &[0x1e]);
+        assert_eq!(Block::decode(&[0x1e]), Some(block));
+        assert_eq!(block.offset(), 1024);
+
+        let block = Block::new(super::MAX_NUM, false, 2);
+        assert_eq!(block.encode(&mut buf), &[0xff, 0xff, 0xf2]);
+        assert_eq!(Block::decode(&[0xff, 0xff, 0xf2]), Some(block));
+
+        // reserved SZX
+        assert_eq!(Block::decode(&[0x07]), None);
+        // too long
+        assert_eq!(Block::decode(&[0, 0, 0, 0]), None);
+    }
+
+    #[test]
+    fn serve() {
+        let repr = [0; 100];
+
+        let (block, chunk) = super::serve(&repr, None, 1).unwrap();
+        assert_eq!(block, Block::new(0, true, 1));
+        assert_eq!(chunk.len(), 32);
+
+        let (block, chunk) = super::serve(&repr, Some(Block::new(3, false, 1)), 6).unwrap();
+        assert_eq!(block, Block::new(3, false, 1));
+        assert_eq!(chunk.len(), 4);
+
+        // the client asks for larger blocks than the server wants to use
+        let (block, chunk) = super::serve(&repr, Some(Block::new(1, false, 2)), 1).unwrap();
+        assert_eq!(block, Block::new(2, true, 1));
+        assert_eq!(chunk.len(), 32);
+
+        assert!(super::serve(&repr, Some(Block::new(4, false, 1)), 6).is_none());
+
+        // the block number overflows when rescaled to a smaller block size
+        let request = Block::decode(&[0xff, 0xff, 0xf6]).unwrap();
+        assert_eq!((request.num(), request.szx()), (super::MAX_NUM, 6));
+        assert!(super::select(usize::MAX, Some(request), 2).is_none());
+        assert!(super::serve(&repr, Some(request), 2).is_none());
+    }
+
+    fn response<'a>(
+        buf: &'a mut [u8],
+        code: Response,
+        block1: Option<Block>,
+        block2: Option<Block>,
+        payload: &[u8],
+    ) -> coap::Message<&'a mut [u8]> {
+        let mut m = coap::Message::new(buf, 0);
+        m.set_type(coap::Type::Acknowledgement);
+        m.set_code(code);
+        if let Some(block) = block2 {
+            m.add_block2(block).unwrap();
+        }
+        if let Some(block) = block1 {
+            m.add_block1(block).unwrap();
+        }
+        m.set_payload(payload)
+    }
+
+    #[test]
+    fn reassembler() {
+        let mut repr = [0; 100];
+        for (i, byte) in repr.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+
+        let mut buf = [0; 128];
+        // request 64-byte blocks; the server only sends 32-byte blocks
+        let mut reassembler = Reassembler::new(&mut buf[..], 2);
+
+        let mut done = false;
+        let mut requests = 0;
+        while !done {
+            let request = reassembler.request();
+            let (block, chunk) = super::serve(&repr, Some(request), 1).unwrap();
+
+            let mut m = [0; 64];
+            let m = response(&mut m, Response::Content, None, Some(block), chunk);
+            done = reassembler.response(&m).unwrap();
+            requests += 1;
+        }
+
+        assert_eq!(requests, 4);
+        assert_eq!(reassembler.payload(), &repr[..]);
+
+        // out of order block
+        let mut buf = [0; 128];
+        let mut reassembler = Reassembler::new(&mut buf[..], 1);
+        let mut m = [0; 64];
+        let m = response(
+            &mut m,
+            Response::Content,
+            None,
+            Some(Block::new(1, true, 1)),
+            &[0; 32],
+        );
+        assert_eq!(reassembler.response(&m), Err(Error::UnexpectedBlock));
+    }
+
+    #[test]
+    fn uploader() {
+        let data = [0; 40];
+        let mut uploader = Uploader::new(&data, 1);
+
+        let (block, chunk) = uploader.block().unwrap();
+        assert_eq!(block, Block::new(0, true, 1));
+        assert_eq!(chunk.len(), 32);
+
+        // the server asks for 16-byte blocks
+        let mut m = [0; 16];
+        let m = response(
+            &mut m,
+            Response::Continue,
+            Some(Block::new(0, true, 0)),
+            None,
+            &[],
+        );
+        assert_eq!(uploader.response(&m), Ok(false));
+
+        let (block, chunk) = uploader.block().unwrap();
+        assert_eq!(block, Block::new(2, false, 0));
+        assert_eq!(chunk.len(), 8);
+
+        let mut m = [0; 16];
+        let m = response(
+            &mut m,
+            Response::Changed,
+            Some(Block::new(2, false, 0)),
+            None,
+            &[],
+        );
+        assert_eq!(uploader.response(&m), Ok(true));
+        assert!(uploader.block().is_none());
+    }
+}
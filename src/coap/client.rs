@@ -0,0 +1,495 @@
+//! CoAP client (RFC 7252 Section 5.3 "Request/Response Matching")
+//!
+//! `Client` allocates Message IDs and tokens, builds requests and matches incoming messages
+//! against the outstanding requests. A response can be piggybacked on the Acknowledgement of a
+//! Confirmable request, or be sent separately: an empty Acknowledgement followed, later, by a
+//! Confirmable (or Non-confirmable) response. Confirmable separate responses must be
+//! acknowledged by the client (see `ack`).
+//!
+//! The client doesn't retransmit requests; pair it with a `reliability::Transmitter` for that,
+//! and cancel the retransmissions when the request is acknowledged or answered.
+//!
+//! # Example
+//!
+//! ```
+//! use jnet::coap::{
+//!     self,
+//!     client::{Client, Event},
+//! };
+//!
+//! // tokens must come from a source of randomness, e.g. a hardware RNG; this xorshift generator
+//! // is only for illustration
+//! let mut x = 0x1234_5678_u32;
+//! let mut client = Client::<_, 2>::new(0x2a, move || {
+//!     x ^= x << 13;
+//!     x ^= x >> 17;
+//!     x ^= x << 5;
+//!     x
+//! });
+//!
+//! let mut buf = [0; 64];
+//! let (req, request) = client
+//!     .request(&mut buf[..], coap::Method::Get, true, "/sensors/temp", "")
+//!     .unwrap();
+//! let req = req.no_payload();
+//! assert!(req.uri_path().eq(["sensors", "temp"].iter().cloned().map(Ok)));
+//!
+//! // the server answers with a piggybacked response
+//! let mut buf = [0; 64];
+//! let mut resp = coap::Message::new(&mut buf[..], req.get_token_length());
+//! resp.set_type(coap::Type::Acknowledgement);
+//! resp.set_code(coap::Response::Content);
+//! resp.set_message_id(req.get_message_id());
+//! resp.token_mut().copy_from_slice(req.token());
+//! let resp = resp.set_payload(b"21");
+//!
+//! match client.received(&resp) {
+//!     Some(Event::Response(r, response)) => {
+//!         assert_eq!(r, request);
+//!         assert_eq!(response.status(), Some(coap::Response::Content));
+//!         assert!(response.piggybacked);
+//!     }
+//!     _ => unreachable!(),
+//! }
+//! assert!(client.is_empty());
+//! ```
+
+use as_slice::{AsMutSlice, AsSlice};
+use owning_slice::Truncate;
+
+use crate::{
+    coap::{self, Code, Message, Method, Type, Unset},
+    traits::TryFrom,
+};
+
+/// Length of the tokens allocated by the client
+pub const TOKEN_LENGTH: u8 = 4;
+
+/// Handle to an outstanding request
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Request {
+    message_id: u16,
+    token: [u8; TOKEN_LENGTH as usize],
+}
+
+impl Request {
+    /// Returns the Message ID of the request
+    pub fn message_id(&self) -> u16 {
+        self.message_id
+    }
+
+    /// Returns the token of the request
+    pub fn token(&self) -> &[u8] {
+        &self.token
+    }
+}
+
+/// A response that matched an outstanding request
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Response {
+    /// The response code
+    pub code: Code,
+    /// `true` if the response was piggybacked on the Acknowledgement of the request
+    pub piggybacked: bool,
+    /// `true` if this is a Confirmable separate response; it must be acknowledged (see `ack`)
+    pub confirmable: bool,
+}
+
+impl Response {
+    /// Returns the response code as a `coap::Response`, or `None` if the code is not known
+    pub fn status(&self) -> Option<coap::Response> {
+        coap::Response::try_from(self.code).ok()
+    }
+
+    /// Is this a success (2.xx) response?
+    pub fn is_success(&self) -> bool {
+        self.code.class() == 2
+    }
+}
+
+/// Events reported by a `Client`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event {
+    /// The Confirmable request was acknowledged with an empty message; the response will be sent
+    /// separately
+    Acknowledged(Request),
+    /// The request was rejected with a Reset message
+    Reset(Request),
+    /// The response to the request; the request is no longer outstanding
+    Response(Request, Response),
+}
+
+/// Error returned by `Client::request`
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Error {
+    /// There are already `N` outstanding requests
+    TooManyRequests,
+    /// The request could not be built
+    Message(coap::Error),
+}
+
+#[derive(Clone, Copy)]
+struct Pending {
+    request: Request,
+    confirmable: bool,
+    // an empty Acknowledgement was received
+    acknowledged: bool,
+}
+
+/// CoAP client that tracks up to `N` outstanding requests
+///
+/// `G` generates the tokens of the requests
+pub struct Client<G, const N: usize> {
+    message_id: u16,
+    tokens: G,
+    pending: [Option<Pending>; N],
+}
+
+impl<G, const N: usize> Client<G, N> {
+    /// Creates a client with no outstanding requests
+    ///
+    /// `message_id` is the Message ID of the first request; it should be random (RFC 7252 Section
+    /// 4.4) and is incremented for each request. `tokens` is called to get the token of each
+    /// request; it must return random values so tokens can't be guessed by an attacker that
+    /// wants to spoof responses (RFC 7252 Section 5.3.1).
+    pub const fn new(message_id: u16, tokens: G) -> Self {
+        Client {
+            message_id,
+            tokens,
+            pending: [None; N],
+        }
+    }
+
+    /// Builds a request and starts tracking it
+    ///
+    /// `path` is split on `/` into Uri-Path options and `query` is split on `&` into Uri-Query
+    /// options; empty segments are skipped. The request is returned without a payload so more
+    /// options (e.g. Content-Format) can be added before sending it.
+    pub fn request<B>(
+        &mut self,
+        buffer: B,
+        method: Method,
+        confirmable: bool,
+        path: &str,
+        query: &str,
+    ) -> Result<(Message<B, Unset>, Request), Error>
+    where
+        B: AsMutSlice<Element = u8>,
+        G: FnMut() -> u32,
+    {
+        let slot = self
+            .pending
+            .iter()
+            .position(|slot| slot.is_none())
+            .ok_or(Error::TooManyRequests)?;
+
+        let request = Request {
+            message_id: self.message_id,
+            token: (self.tokens)().to_be_bytes(),
+        };
+
+        let mut m = Message::new(buffer, TOKEN_LENGTH);
+        m.set_type(if confirmable {
+            Type::Confirmable
+        } else {
+            Type::NonConfirmable
+        });
+        m.set_code(method);
+        m.set_message_id(request.message_id);
+        m.token_mut().copy_from_slice(&request.token);
+
+        for segment in path.split('/').filter(|s| !s.is_empty()) {
+            m.add_uri_path(segment).map_err(Error::Message)?;
+        }
+
+        for argument in query.split('&').filter(|s| !s.is_empty()) {
+            m.add_uri_query(argument).map_err(Error::Message)?;
+        }
+
+        self.pending[slot] = Some(Pending {
+            request,
+            confirmable,
+            acknowledged: false,
+        });
+        self.message_id = self.message_id.wrapping_add(1);
+
+        Ok((m, request))
+    }
+
+    /// Processes an incoming message
+    ///
+    /// Returns `None` if the message doesn't match any of the outstanding requests. RFC 7252
+    /// requires rejecting unmatched Confirmable responses with a Reset message.
+    pub fn received<B>(&mut self, m: &Message<B>) -> Option<Event>
+    where
+        B: AsSlice<Element = u8>,
+    {
+        let code = m.get_code();
+
+        match m.get_type() {
+            // matched by Message ID
+            ty @ Type::Acknowledgement | ty @ Type::Reset => {
+                let message_id = m.get_message_id();
+                let slot = self.pending.iter_mut().find(|slot| {
+                    slot.map(|p| {
+                        p.confirmable && !p.acknowledged && p.request.message_id == message_id
+                    })
+                    .unwrap_or(false)
+                })?;
+                let pending = slot.as_mut().expect("unreachable");
+                let request = pending.request;
+
+                if ty == Type::Reset {
+                    *slot = None;
+                    Some(Event::Reset(request))
+                } else if code == Code::EMPTY {
+                    pending.acknowledged = true;
+                    Some(Event::Acknowledged(request))
+                } else if code.is_response() && m.token() == request.token() {
+                    *slot = None;
+                    Some(Event::Response(
+                        request,
+                        Response {
+                            code,
+                            piggybacked: true,
+                            confirmable: false,
+                        },
+                    ))
+                } else {
+                    None
+                }
+            }
+
+            // separate response (or response to a Non-confirmable request); matched by token
+            ty => {
+                if !code.is_response() {
+                    return None;
+                }
+
+                let token = m.token();
+                let slot = self
+                    .pending
+                    .iter_mut()
+                    .find(|slot| slot.map(|p| p.request.token() == token).unwrap_or(false))?;
+                let request = slot.take().expect("unreachable").request;
+
+                Some(Event::Response(
+                    request,
+                    Response {
+                        code,
+                        piggybacked: false,
+                        confirmable: ty == Type::Confirmable,
+                    },
+                ))
+            }
+        }
+    }
+
+    /// Stops tracking the given request, e.g. because its retransmissions timed out
+    ///
+    /// Returns `true` if the request was outstanding
+    pub fn cancel(&mut self, request: &Request) -> bool {
+        if let Some(slot) = self
+            .pending
+            .iter_mut()
+            .find(|slot| slot.map(|p| p.request == *request).unwrap_or(false))
+        {
+            *slot = None;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Checks if the given request is still waiting for a response
+    pub fn is_pending(&self, request: &Request) -> bool {
+        self.iter().any(|p| p.request == *request)
+    }
+
+    /// Returns the number of outstanding requests
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    /// Returns `true` if there are no outstanding requests
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /* Private */
+    fn iter(&self) -> impl Iterator<Item = &Pending> {
+        self.pending.iter().filter_map(|slot| slot.as_ref())
+    }
+}
+
+/// Builds the empty Acknowledgement of a Confirmable (separate) response
+///
+/// # Panics
+///
+/// This function panics if `buffer` is smaller than the CoAP header
+pub fn ack<B, R>(buffer: B, response: &Message<R>) -> Message<B>
+where
+    B: AsMutSlice<Element = u8> + Truncate<u16>,
+    R: AsSlice<Element = u8>,
+{
+    let mut m = Message::new(buffer, 0);
+    m.set_type(Type::Acknowledgement);
+    m.set_code(Code::EMPTY);
+    m.set_message_id(response.get_message_id());
+    m.no_payload()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::coap::{self, Code, Message, Type};
+
+    use super::{Client, Error, Event, Response};
+
+    fn response<'a>(
+        buf: &'a mut [u8],
+        ty: Type,
+        code: Code,
+        message_id: u16,
+        token: &[u8],
+    ) -> Message<&'a mut [u8]> {
+        let mut m = Message::new(buf, token.len() as u8);
+        m.set_type(ty);
+        m.set_code(code);
+        m.set_message_id(message_id);
+        m.token_mut().copy_from_slice(token);
+        m.no_payload()
+    }
+
+    #[test]
+    fn request() {
+        let mut tokens = [0x0102_0304, 0xdead_beef].iter().cloned();
+        let mut client = Client::<_, 1>::new(0x0304, move || tokens.next().unwrap());
+
+        let mut buf = [0; 64];
+        let (m, request) = client
+            .request(&mut buf[..], coap::Method::Post, false, "a/b/", "x=1&y")
+            .unwrap();
+        let m = m.no_payload();
+
+        assert_eq!(m.get_type(), Type::NonConfirmable);
+        assert_eq!(m.get_code(), coap::Method::Post.into());
+        assert_eq!(m.get_message_id(), 0x0304);
+        assert_eq!(m.token(), [1, 2, 3, 4]);
+        assert_eq!(request.message_id(), 0x0304);
+        assert_eq!(request.token(), [1, 2, 3, 4]);
+        assert!(m.uri_path().eq(["a", "b"].iter().cloned().map(Ok)));
+        assert!(m.uri_query().eq(["x=1", "y"].iter().cloned().map(Ok)));
+
+        // full
+        let mut buf = [0; 64];
+        assert_eq!(
+            client
+                .request(&mut buf[..], coap::Method::Get, true, "", "")
+                .err(),
+            Some(Error::TooManyRequests)
+        );
+
+        // the response to the Non-confirmable request
+        let mut buf = [0; 16];
+        let resp = response(
+            &mut buf,
+            Type::NonConfirmable,
+            coap::Response::Changed.into(),
+            0xbeef,
+            &[1, 2, 3, 4],
+        );
+        assert_eq!(
+            client.received(&resp),
+            Some(Event::Response(
+                request,
+                Response {
+                    code: coap::Response::Changed.into(),
+                    piggybacked: false,
+                    confirmable: false,
+                }
+            ))
+        );
+        assert!(client.is_empty());
+
+        // new Message ID and token; the token doesn't depend on the Message ID
+        let mut buf = [0; 64];
+        let (m, _) = client
+            .request(&mut buf[..], coap::Method::Get, true, "", "")
+            .unwrap();
+        assert_eq!(m.get_message_id(), 0x0305);
+        assert_eq!(m.token(), [0xde, 0xad, 0xbe, 0xef]);
+    }
+
+    #[test]
+    fn separate() {
+        let mut client = Client::<_, 2>::new(0, || 0);
+
+        let mut buf = [0; 64];
+        let (_, request) = client
+            .request(&mut buf[..], coap::Method::Get, true, "temp", "")
+            .unwrap();
+
+        // empty ACK
+        let mut buf = [0; 16];
+        let ack = response(&mut buf, Type::Acknowledgement, Code::EMPTY, 0, &[]);
+        assert_eq!(client.received(&ack), Some(Event::Acknowledged(request)));
+        assert!(client.is_pending(&request));
+
+        // duplicated ACK
+        assert_eq!(client.received(&ack), None);
+
+        // response with an unknown token
+        let mut buf = [0; 16];
+        let resp = response(
+            &mut buf,
+            Type::Confirmable,
+            coap::Response::Content.into(),
+            0x1000,
+            &[0, 0, 0, 1],
+        );
+        assert_eq!(client.received(&resp), None);
+
+        // the separate response
+        let mut buf = [0; 16];
+        let resp = response(
+            &mut buf,
+            Type::Confirmable,
+            coap::Response::NotFound.into(),
+            0x1000,
+            &[0, 0, 0, 0],
+        );
+        match client.received(&resp) {
+            Some(Event::Response(r, response)) => {
+                assert_eq!(r, request);
+                assert_eq!(response.status(), Some(coap::Response::NotFound));
+                assert!(!response.is_success());
+                assert!(!response.piggybacked);
+                assert!(response.confirmable);
+            }
+            e => panic!("{:?}", e),
+        }
+        assert!(client.is_empty());
+
+        let mut buf = [0; 4];
+        let ack = super::ack(&mut buf[..], &resp);
+        assert_eq!(ack.as_bytes(), [0x60, 0x00, 0x10, 0x00]);
+    }
+
+    #[test]
+    fn reset() {
+        let mut client = Client::<_, 2>::new(7, || 0);
+
+        let mut buf = [0; 64];
+        let (_, request) = client
+            .request(&mut buf[..], coap::Method::Get, true, "", "")
+            .unwrap();
+
+        let mut buf = [0; 16];
+        let rst = response(&mut buf, Type::Reset, Code::EMPTY, 8, &[]);
+        assert_eq!(client.received(&rst), None);
+
+        let rst = response(&mut buf, Type::Reset, Code::EMPTY, 7, &[]);
+        assert_eq!(client.received(&rst), Some(Event::Reset(request)));
+        assert!(client.is_empty());
+    }
+}
@@ -0,0 +1,235 @@
+//! Echo and Request-Tag options (RFC 9175)
+//!
+//! The Echo option lets a server verify the freshness of a request: the server answers an
+//! unverified request with 4.01 (Unauthorized) and an Echo option, and the client repeats the
+//! request including the same Echo value. `Freshness` implements the server side using the
+//! "time-based" method of RFC 9175 Appendix A: the server issues a single random Echo value and
+//! accepts it for a limited amount of time.
+//!
+//! The Request-Tag option only needs the accessors on `Message` (`request_tags` and
+//! `add_request_tag`); it lets a client tell apart concurrent block-wise operations on the same
+//! resource.
+//!
+//! Like in the `reliability` module the caller must pass the current time (`now`), *in
+//! milliseconds*, to the methods that need it. Timestamps are allowed to wrap around.
+//!
+//! # Example
+//!
+//! ```
+//! use jnet::coap::{self, echo::Freshness};
+//!
+//! // `value` should come from a source of randomness; requests are fresh for 10 seconds
+//! let freshness = Freshness::new([1, 2, 3, 4, 5, 6, 7, 8], 0, 10_000);
+//!
+//! // an actuation request without an Echo option
+//! let mut buf = [0; 32];
+//! let mut req = coap::Message::new(&mut buf[..], 0);
+//! req.set_code(coap::Method::Put);
+//! req.add_uri_path("lock").unwrap();
+//! let req = req.set_payload(b"open");
+//!
+//! let mut buf = [0; 32];
+//! let resp = coap::Message::new(&mut buf[..], 0);
+//! let challenge = match freshness.verify(&req, resp, 1_000) {
+//!     Ok(_) => unreachable!(),
+//!     Err(challenge) => challenge.unwrap(),
+//! };
+//! assert_eq!(challenge.get_code(), coap::Response::Unauthorized.into());
+//!
+//! // the client repeats the request with the Echo value
+//! let mut buf = [0; 32];
+//! let mut req = coap::Message::new(&mut buf[..], 0);
+//! req.set_code(coap::Method::Put);
+//! req.add_uri_path("lock").unwrap();
+//! req.add_echo(challenge.echo().unwrap()).unwrap();
+//! let req = req.set_payload(b"open");
+//!
+//! let mut buf = [0; 32];
+//! let resp = coap::Message::new(&mut buf[..], 0);
+//! assert!(freshness.verify(&req, resp, 2_000).is_ok());
+//! ```
+
+use as_slice::{AsMutSlice, AsSlice};
+use owning_slice::Truncate;
+
+use crate::coap::{Error, Message, Response, Unset};
+
+/// Length of the Echo values issued by `Freshness`
+pub const ECHO_LENGTH: usize = 8;
+
+/// Server-side freshness verification using the Echo option
+pub struct Freshness {
+    value: [u8; ECHO_LENGTH],
+    issued: u32,
+    lifetime: u32,
+}
+
+impl Freshness {
+    /// Creates a new verifier that issues the Echo `value` at time `now`
+    ///
+    /// Requests that carry `value` are considered fresh for `lifetime` milliseconds after `now`.
+    /// `value` must be unpredictable.
+    pub const fn new(value: [u8; ECHO_LENGTH], now: u32, lifetime: u32) -> Self {
+        Freshness {
+            value,
+            issued: now,
+            lifetime,
+        }
+    }
+
+    /// Returns the Echo value that's currently being issued
+    pub fn value(&self) -> &[u8] {
+        &self.value
+    }
+
+    /// Starts issuing a new Echo `value`; the previous value is no longer accepted
+    ///
+    /// The value should be refreshed once it expires; `is_expired` reports when that happens
+    pub fn refresh(&mut self, value: [u8; ECHO_LENGTH], now: u32) {
+        self.value = value;
+        self.issued = now;
+    }
+
+    /// Checks if the current Echo value has expired
+    pub fn is_expired(&self, now: u32) -> bool {
+        now.wrapping_sub(self.issued) > self.lifetime
+    }
+
+    /// Checks if the request carries a valid (current and not expired) Echo value
+    pub fn is_fresh<B, P>(&self, req: &Message<B, P>, now: u32) -> bool
+    where
+        B: AsSlice<Element = u8>,
+    {
+        req.echo() == Some(&self.value[..]) && !self.is_expired(now)
+    }
+
+    /// Turns `resp` into a 4.01 (Unauthorized) response that carries the current Echo value
+    ///
+    /// `resp` must already have its header and token filled
+    ///
+    /// Returns an error if `resp` has no space for the Echo option (`ECHO_LENGTH + 2` bytes)
+    pub fn challenge<B>(&self, mut resp: Message<B, Unset>) -> Result<Message<B>, Error>
+    where
+        B: AsSlice<Element = u8> + AsMutSlice<Element = u8> + Truncate<u16>,
+    {
+        resp.clear_options();
+        resp.set_code(Response::Unauthorized);
+        resp.add_echo(&self.value)?;
+        Ok(resp.no_payload())
+    }
+
+    /// Verifies the freshness of a request
+    ///
+    /// Returns `resp` untouched if `req` is fresh; otherwise it returns the result of turning
+    /// `resp` into the 4.01 (Unauthorized) challenge that must be sent to the client (see
+    /// `challenge`)
+    pub fn verify<R, B>(
+        &self,
+        req: &Message<R>,
+        resp: Message<B, Unset>,
+        now: u32,
+    ) -> Result<Message<B, Unset>, Result<Message<B>, Error>>
+    where
+        R: AsSlice<Element = u8>,
+        B: AsSlice<Element = u8> + AsMutSlice<Element = u8> + Truncate<u16>,
+    {
+        if self.is_fresh(req, now) {
+            Ok(resp)
+        } else {
+            Err(self.challenge(resp))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::coap::{self, Message, OptionNumber};
+
+    use super::Freshness;
+
+    const VALUE: [u8; 8] = [0xde, 0xad, 0xbe, 0xef, 0, 1, 2, 3];
+
+    #[test]
+    fn challenge() {
+        let freshness = Freshness::new(VALUE, 0, 1_000);
+
+        let mut buf = [0; 16];
+        let mut resp = Message::new(&mut buf[..], 1);
+        resp.set_type(coap::Type::Acknowledgement);
+        resp.set_message_id(0x1234);
+        resp.token_mut()[0] = 0x42;
+        // the challenge drops the options added by the caller
+        resp.add_content_format(coap::ContentFormat::TextPlain)
+            .unwrap();
+        let resp = freshness.challenge(resp).unwrap();
+
+        // header + token
+        assert_eq!(&resp.as_bytes()[..5], &[0x61, 0x81, 0x12, 0x34, 0x42]);
+        // Echo option: delta = 13 + 239 = 252, length = 8
+        assert_eq!(&resp.as_bytes()[5..7], &[0xd8, 239]);
+        assert_eq!(&resp.as_bytes()[7..], &VALUE);
+        assert_eq!(resp.echo(), Some(&VALUE[..]));
+
+        // no space for the Echo option
+        let mut buf = [0; 8];
+        let resp = Message::new(&mut buf[..], 1);
+        assert_eq!(
+            freshness.challenge(resp).err(),
+            Some(coap::Error::BufferTooSmall)
+        );
+
+        let mut buf = [0; 8];
+        let req = Message::new(&mut buf[..], 0).no_payload();
+        let mut buf = [0; 8];
+        let resp = Message::new(&mut buf[..], 1);
+        match freshness.verify(&req, resp, 0) {
+            Err(Err(e)) => assert_eq!(e, coap::Error::BufferTooSmall),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn freshness() {
+        let mut freshness = Freshness::new(VALUE, u32::MAX - 100, 1_000);
+
+        let mut buf = [0; 32];
+        let mut req = Message::new(&mut buf[..], 0);
+        req.set_code(coap::Method::Post);
+        req.add_echo(&VALUE).unwrap();
+        let req = req.no_payload();
+
+        // timestamps wrap around
+        assert!(freshness.is_fresh(&req, 899));
+        assert!(!freshness.is_fresh(&req, 900));
+
+        freshness.refresh([0; 8], 1_000);
+        assert!(!freshness.is_fresh(&req, 1_000));
+
+        let mut buf = [0; 32];
+        let req = Message::new(&mut buf[..], 0).no_payload();
+        assert!(!freshness.is_fresh(&req, 1_000));
+    }
+
+    #[test]
+    fn options() {
+        let mut buf = [0; 64];
+        let mut m = Message::new(&mut buf[..], 0);
+        assert_eq!(m.add_echo(&[]), Err(coap::Error::OptionLength));
+        assert_eq!(m.add_echo(&[0; 41]), Err(coap::Error::OptionLength));
+        assert_eq!(m.add_request_tag(&[0; 9]), Err(coap::Error::OptionLength));
+        m.add_request_tag(&[1]).unwrap();
+        m.add_request_tag(&[]).unwrap();
+        m.add_echo(&[7; 4]).unwrap();
+        let m = m.no_payload();
+
+        assert_eq!(m.echo(), Some(&[7; 4][..]));
+        assert!(m.request_tags().eq([&[1][..], &[][..]].iter().cloned()));
+        assert!(m.options().map(|opt| opt.number()).eq([
+            OptionNumber::Echo,
+            OptionNumber::RequestTag,
+            OptionNumber::RequestTag
+        ]
+        .iter()
+        .cloned()));
+    }
+}
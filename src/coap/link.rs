@@ -0,0 +1,509 @@
+//! CoRE Link Format (RFC 6690)
+//!
+//! `Writer` serializes link-format documents into any `fmt::Write` sink, e.g. a `Cursor` over a
+//! byte buffer. `parse` iterates over the links of a document without copying it.
+//!
+//! # Example
+//!
+//! ```
+//! use jnet::coap::link::{self, Cursor, Writer};
+//!
+//! let mut buf = [0; 128];
+//! let mut writer = Writer::new(Cursor::new(&mut buf));
+//! writer
+//!     .link("/sensors/temp")
+//!     .unwrap()
+//!     .attribute("rt", "temperature")
+//!     .unwrap()
+//!     .attribute("if", "sensor")
+//!     .unwrap()
+//!     .token("ct", 50)
+//!     .unwrap();
+//! writer.link("/sensors/light").unwrap().flag("obs").unwrap();
+//!
+//! let document = writer.into_inner().finish();
+//! assert_eq!(
+//!     document,
+//!     "</sensors/temp>;rt=\"temperature\";if=\"sensor\";ct=50,</sensors/light>;obs"
+//! );
+//!
+//! let mut links = link::parse(document);
+//! let temp = links.next().unwrap().unwrap();
+//! assert_eq!(temp.target(), "/sensors/temp");
+//! assert_eq!(temp.get("ct"), Some("50"));
+//! assert!(temp.matches("rt", "temp*"));
+//!
+//! let light = links.next().unwrap().unwrap();
+//! assert_eq!(light.get("obs"), Some(""));
+//! assert!(links.next().is_none());
+//! ```
+
+use core::{
+    fmt::{self, Write},
+    str,
+};
+
+/// Serializes links into a link-format document
+pub struct Writer<W>
+where
+    W: Write,
+{
+    inner: W,
+    empty: bool,
+}
+
+impl<W> Writer<W>
+where
+    W: Write,
+{
+    /// Creates a writer that emits the document into `inner`
+    pub fn new(inner: W) -> Self {
+        Writer { inner, empty: true }
+    }
+
+    /// Starts a new link to `target`
+    ///
+    /// The returned `LinkWriter` appends attributes to the link
+    pub fn link<T>(&mut self, target: T) -> Result<LinkWriter<'_, W>, fmt::Error>
+    where
+        T: fmt::Display,
+    {
+        if !self.empty {
+            self.inner.write_char(',')?;
+        }
+        self.empty = false;
+
+        write!(self.inner, "<{}>", target)?;
+
+        Ok(LinkWriter {
+            inner: &mut self.inner,
+        })
+    }
+
+    /// Returns the underlying sink
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+/// Appends attributes to a link
+pub struct LinkWriter<'w, W>
+where
+    W: Write,
+{
+    inner: &'w mut W,
+}
+
+impl<'w, W> LinkWriter<'w, W>
+where
+    W: Write,
+{
+    /// Appends an attribute whose value is a quoted string, e.g. `;rt="temperature"`
+    ///
+    /// Double quotes and backslashes in `value` are escaped
+    pub fn attribute<V>(self, name: &str, value: V) -> Result<Self, fmt::Error>
+    where
+        V: fmt::Display,
+    {
+        write!(self.inner, ";{}=\"", name)?;
+        write!(Escape(&mut *self.inner), "{}", value)?;
+        self.inner.write_char('"')?;
+        Ok(self)
+    }
+
+    /// Appends an attribute whose value is written as is, e.g. `;ct=50`
+    ///
+    /// `value` must be a valid token (see `ptoken` in RFC 6690); use `attribute` otherwise
+    pub fn token<V>(self, name: &str, value: V) -> Result<Self, fmt::Error>
+    where
+        V: fmt::Display,
+    {
+        write!(self.inner, ";{}={}", name, value)?;
+        Ok(self)
+    }
+
+    /// Appends an attribute that has no value, e.g. `;obs`
+    pub fn flag(self, name: &str) -> Result<Self, fmt::Error> {
+        write!(self.inner, ";{}", name)?;
+        Ok(self)
+    }
+
+    /// Appends already serialized attributes, e.g. `rt="temperature";if="sensor"`
+    ///
+    /// Does nothing if `attributes` is empty
+    pub fn raw(self, attributes: &str) -> Result<Self, fmt::Error> {
+        if !attributes.is_empty() {
+            write!(self.inner, ";{}", attributes)?;
+        }
+        Ok(self)
+    }
+}
+
+// escapes characters that can't appear in a quoted-string as is
+struct Escape<'w, W>(&'w mut W)
+where
+    W: Write;
+
+impl<W> Write for Escape<'_, W>
+where
+    W: Write,
+{
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for part in s.split_inclusive(['"', '\\']) {
+            match part.chars().last() {
+                Some(c @ '"') | Some(c @ '\\') => {
+                    self.0.write_str(&part[..part.len() - 1])?;
+                    self.0.write_char('\\')?;
+                    self.0.write_char(c)?;
+                }
+                _ => self.0.write_str(part)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A `fmt::Write` sink backed by a byte buffer
+pub struct Cursor<'a> {
+    buffer: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> Cursor<'a> {
+    /// Creates a cursor that writes into `buffer`
+    pub fn new(buffer: &'a mut [u8]) -> Self {
+        Cursor { buffer, len: 0 }
+    }
+
+    /// Returns the number of bytes written so far
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if nothing has been written yet
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the written part of the buffer
+    pub fn finish(self) -> &'a str {
+        let len = self.len;
+        // NOTE(unsafe) only `str`ings have been written into the buffer
+        unsafe { str::from_utf8_unchecked(&self.buffer[..len]) }
+    }
+}
+
+impl Write for Cursor<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        self.buffer
+            .get_mut(self.len..end)
+            .ok_or(fmt::Error)?
+            .copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+/// Parses a link-format document
+///
+/// The returned iterator yields an error, and then stops, if the document is malformed
+pub fn parse(document: &str) -> Links<'_> {
+    Links { rest: document }
+}
+
+/// Iterator over the links of a link-format document
+pub struct Links<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Iterator for Links<'a> {
+    type Item = Result<Link<'a>, ()>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let s = self.rest.trim_start();
+        if s.is_empty() {
+            return None;
+        }
+
+        let (link, rest) = split(s, ',');
+        self.rest = rest.unwrap_or("");
+
+        let link = link.trim_end();
+        let end = match link.find('>') {
+            Some(end) if link.starts_with('<') => end,
+            _ => {
+                self.rest = "";
+                return Some(Err(()));
+            }
+        };
+
+        let params = link[end + 1..].trim_start();
+        if !params.is_empty() && !params.starts_with(';') {
+            self.rest = "";
+            return Some(Err(()));
+        }
+
+        Some(Ok(Link {
+            target: &link[1..end],
+            params,
+        }))
+    }
+}
+
+/// A link of a link-format document
+#[derive(Clone, Copy, Debug)]
+pub struct Link<'a> {
+    target: &'a str,
+    params: &'a str,
+}
+
+impl<'a> Link<'a> {
+    /// Returns the target URI of this link (without the angle brackets)
+    pub fn target(&self) -> &'a str {
+        self.target
+    }
+
+    /// Returns an iterator over the attributes of this link
+    pub fn attributes(&self) -> Attributes<'a> {
+        Attributes { rest: self.params }
+    }
+
+    /// Returns the value of the first attribute named `name`
+    ///
+    /// Returns `Some("")` for attributes that have no value
+    pub fn get(&self, name: &str) -> Option<&'a str> {
+        self.attributes()
+            .find(|attr| attr.name() == name)
+            .map(|attr| attr.value().unwrap_or(""))
+    }
+
+    /// Checks if this link matches the filter `name=pattern` (RFC 6690 Section 4.1)
+    ///
+    /// A trailing `*` in `pattern` matches any suffix. Values made of space-separated items
+    /// (e.g. `rt="light temperature"`) match if any of the items matches. The `href` name
+    /// matches against the target of the link.
+    pub fn matches(&self, name: &str, pattern: &str) -> bool {
+        let matches = |value: &str| {
+            if let Some(prefix) = pattern.strip_suffix('*') {
+                value.starts_with(prefix)
+            } else {
+                value == pattern
+            }
+        };
+
+        if name == "href" {
+            return matches(self.target);
+        }
+
+        self.attributes()
+            .filter(|attr| attr.name() == name)
+            .any(|attr| {
+                let value = attr.value().unwrap_or("");
+                matches(value) || value.split(' ').any(matches)
+            })
+    }
+}
+
+/// Iterator over the attributes of a `Link`
+pub struct Attributes<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Iterator for Attributes<'a> {
+    type Item = Attribute<'a>;
+
+    fn next(&mut self) -> Option<Attribute<'a>> {
+        loop {
+            let s = self
+                .rest
+                .trim_start_matches(|c: char| c == ';' || c.is_whitespace());
+            if s.is_empty() {
+                self.rest = s;
+                return None;
+            }
+
+            let (param, rest) = split(s, ';');
+            self.rest = rest.unwrap_or("");
+
+            let param = param.trim_end();
+            if param.is_empty() {
+                continue;
+            }
+
+            let (name, value) = match param.find('=') {
+                Some(i) => {
+                    let value = param[i + 1..].trim();
+                    let value =
+                        if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+                            &value[1..value.len() - 1]
+                        } else {
+                            value
+                        };
+
+                    (param[..i].trim_end(), Some(value))
+                }
+                None => (param, None),
+            };
+
+            return Some(Attribute { name, value });
+        }
+    }
+}
+
+/// An attribute (`link-param`) of a `Link`
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Attribute<'a> {
+    name: &'a str,
+    value: Option<&'a str>,
+}
+
+impl<'a> Attribute<'a> {
+    /// Returns the name of this attribute
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// Returns the value of this attribute, without the surrounding double quotes
+    ///
+    /// NOTE escape sequences (`\"`) are left as they are
+    pub fn value(&self) -> Option<&'a str> {
+        self.value
+    }
+}
+
+/// Splits `s` at the first `separator` that's not part of a quoted string or a URI reference
+fn split(s: &str, separator: char) -> (&str, Option<&str>) {
+    let mut quoted = false;
+    let mut angled = false;
+    let mut escaped = false;
+
+    for (i, c) in s.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if quoted {
+            match c {
+                '\\' => escaped = true,
+                '"' => quoted = false,
+                _ => {}
+            }
+        } else if angled {
+            angled = c != '>';
+        } else if c == separator {
+            return (&s[..i], Some(&s[i + 1..]));
+        } else {
+            match c {
+                '"' => quoted = true,
+                '<' => angled = true,
+                _ => {}
+            }
+        }
+    }
+
+    (s, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use core::fmt::Write;
+
+    use super::{Attribute, Cursor, Writer};
+
+    #[test]
+    fn write() {
+        let mut buf = [0; 64];
+        let mut writer = Writer::new(Cursor::new(&mut buf));
+        writer
+            .link("/a")
+            .unwrap()
+            .attribute("title", "say \"hi\" \\o/")
+            .unwrap()
+            .raw("")
+            .unwrap();
+        writer.link("/b").unwrap().raw("if=\"x\"").unwrap();
+        assert_eq!(
+            writer.into_inner().finish(),
+            r#"</a>;title="say \"hi\" \\o/",</b>;if="x""#
+        );
+
+        // out of space
+        let mut buf = [0; 4];
+        let mut cursor = Cursor::new(&mut buf);
+        assert!(cursor.write_str("</a>").is_ok());
+        assert!(cursor.write_str(",").is_err());
+        assert_eq!(cursor.finish(), "</a>");
+    }
+
+    #[test]
+    fn parse() {
+        let document = "</sensors/temp>;rt=\"temperature-c light\";if=\"sensor\";ct=41,\r\n\
+                        </a,b>; title=\"x;y, z\" ;obs, </>";
+
+        let mut links = super::parse(document);
+
+        let link = links.next().unwrap().unwrap();
+        assert_eq!(link.target(), "/sensors/temp");
+        let mut attributes = link.attributes();
+        assert_eq!(
+            attributes.next(),
+            Some(Attribute {
+                name: "rt",
+                value: Some("temperature-c light")
+            })
+        );
+        assert_eq!(
+            attributes.next(),
+            Some(Attribute {
+                name: "if",
+                value: Some("sensor")
+            })
+        );
+        assert_eq!(
+            attributes.next(),
+            Some(Attribute {
+                name: "ct",
+                value: Some("41")
+            })
+        );
+        assert_eq!(attributes.next(), None);
+
+        let link = links.next().unwrap().unwrap();
+        assert_eq!(link.target(), "/a,b");
+        assert_eq!(link.get("title"), Some("x;y, z"));
+        assert_eq!(link.get("obs"), Some(""));
+        assert_eq!(link.get("rt"), None);
+
+        let link = links.next().unwrap().unwrap();
+        assert_eq!(link.target(), "/");
+        assert_eq!(link.attributes().count(), 0);
+
+        assert!(links.next().is_none());
+
+        // malformed
+        let mut links = super::parse("</a>,/b>,</c>");
+        assert!(links.next().unwrap().is_ok());
+        assert!(links.next().unwrap().is_err());
+        assert!(links.next().is_none());
+
+        assert!(super::parse("</a>x").next().unwrap().is_err());
+    }
+
+    #[test]
+    fn matches() {
+        let link = super::parse("</sensors/temp>;rt=\"temperature-c light\";ct=41")
+            .next()
+            .unwrap()
+            .unwrap();
+
+        assert!(link.matches("rt", "light"));
+        assert!(link.matches("rt", "temperature-c"));
+        assert!(link.matches("rt", "temp*"));
+        assert!(link.matches("rt", "*"));
+        assert!(!link.matches("rt", "temperature"));
+        assert!(link.matches("ct", "41"));
+        assert!(!link.matches("if", "*"));
+        assert!(link.matches("href", "/sensors/*"));
+        assert!(!link.matches("href", "/sensors"));
+    }
+}
@@ -0,0 +1,442 @@
+//! Macros that implement the option API shared by the UDP and TCP messages
+//!
+//! The call site must have `Message` like fields (`buffer`, `marker` and `number`), the
+//! `as_slice`, `as_mut_slice` and `options_start` methods, and the items used by the expanded
+//! code (`CoreOption`, `OptionNumber`, `usize`, etc.) in scope.
+
+// Option getters; expands inside an `impl<B, P> Message<B, P> where B: AsSlice<Element = u8>`
+macro_rules! option_getters {
+    () => {
+        /// Returns an iterator over the options of this message
+        pub fn options(&self) -> Options<'_> {
+            let end = if self.marker != NO_PAYLOAD {
+                usize(self.marker)
+            } else {
+                self.as_slice().len()
+            };
+
+            Options {
+                number: 0,
+                ptr: unsafe { self.as_slice().r(usize(self.options_start())..end) },
+            }
+        }
+
+        /// Returns the value of the Observe option, if present
+        pub fn get_observe(&self) -> CoreOption<u32> {
+            self.options()
+                .find(|opt| opt.number() == OptionNumber::Observe)
+                .and_then(|opt| decode_uint(opt.value()))
+        }
+
+        /// Returns the value of the Block1 option, if present and valid
+        pub fn get_block1(&self) -> CoreOption<block::Block> {
+            self.options()
+                .find(|opt| opt.number() == OptionNumber::Block1)
+                .and_then(|opt| block::Block::decode(opt.value()))
+        }
+
+        /// Returns the value of the Block2 option, if present and valid
+        pub fn get_block2(&self) -> CoreOption<block::Block> {
+            self.options()
+                .find(|opt| opt.number() == OptionNumber::Block2)
+                .and_then(|opt| block::Block::decode(opt.value()))
+        }
+
+        /// Returns the value of the Size2 option, if present
+        pub fn get_size2(&self) -> CoreOption<u32> {
+            self.options()
+                .find(|opt| opt.number() == OptionNumber::Size2)
+                .and_then(|opt| decode_uint(opt.value()))
+        }
+
+        /// Returns the value of the Content-Format option, if present
+        pub fn content_format(&self) -> CoreOption<ContentFormat> {
+            self.uint_option(OptionNumber::ContentFormat)
+                .map(|x| ContentFormat::from(x as u16))
+        }
+
+        /// Returns the value of the Max-Age option (in seconds), if present
+        ///
+        /// If the option is absent the default value, `DEFAULT_MAX_AGE`, applies
+        pub fn max_age(&self) -> CoreOption<u32> {
+            self.uint_option(OptionNumber::MaxAge)
+        }
+
+        /// Returns the value of the Uri-Port option, if present
+        pub fn uri_port(&self) -> CoreOption<u16> {
+            self.uint_option(OptionNumber::UriPort).map(|x| x as u16)
+        }
+
+        /// Returns an iterator over the segments of the path of the request URI (Uri-Path options)
+        pub fn uri_path(&self) -> Strings<'_> {
+            Strings(self.values(OptionNumber::UriPath))
+        }
+
+        /// Returns an iterator over the arguments of the query of the request URI (Uri-Query options)
+        pub fn uri_query(&self) -> Strings<'_> {
+            Strings(self.values(OptionNumber::UriQuery))
+        }
+
+        /// Returns an iterator over the values of the ETag options
+        pub fn etags(&self) -> Values<'_> {
+            self.values(OptionNumber::ETag)
+        }
+
+        /// Returns an iterator over the segments of the Location-Path options
+        pub fn location_path(&self) -> Strings<'_> {
+            Strings(self.values(OptionNumber::LocationPath))
+        }
+
+        /// Returns the value of the Accept option, if present
+        pub fn accept(&self) -> CoreOption<ContentFormat> {
+            self.uint_option(OptionNumber::Accept)
+                .map(|x| ContentFormat::from(x as u16))
+        }
+
+        /// Returns the value of the No-Response option, if present
+        pub fn no_response(&self) -> CoreOption<no_response::NoResponse> {
+            self.uint_option(OptionNumber::NoResponse)
+                .map(|x| no_response::NoResponse::from_bits(x as u8))
+        }
+
+        /// Returns the value of the Echo option, if present
+        pub fn echo(&self) -> CoreOption<&[u8]> {
+            self.values(OptionNumber::Echo).next()
+        }
+
+        /// Returns an iterator over the values of the Request-Tag options
+        pub fn request_tags(&self) -> Values<'_> {
+            self.values(OptionNumber::RequestTag)
+        }
+
+        // Returns the position of each option within the buffer
+        fn positions(&self) -> impl Iterator<Item = Position> + '_ {
+            let base = self.as_slice().as_ptr() as usize;
+            let mut end = usize(self.options_start());
+
+            self.options().map(move |opt| {
+                let start = end;
+                let value = opt.value.as_ptr() as usize - base;
+                end = value + opt.value.len();
+
+                Position {
+                    number: opt.number,
+                    start,
+                    value,
+                    end,
+                }
+            })
+        }
+
+        fn values(&self, number: OptionNumber) -> Values<'_> {
+            Values {
+                number,
+                options: self.options(),
+            }
+        }
+
+        // Returns the value of the first `number` option; `None` if the option is absent or if its
+        // value is longer than allowed
+        fn uint_option(&self, number: OptionNumber) -> CoreOption<u32> {
+            let value = self.values(number).next()?;
+
+            if number
+                .length_limits()
+                .map(|(_, max)| value.len() > usize(max))
+                .unwrap_or(false)
+            {
+                None
+            } else {
+                decode_uint(value)
+            }
+        }
+    };
+}
+
+// Option setters; expands inside an `impl<B> Message<B, Unset> where B: AsMutSlice<Element = u8>`
+macro_rules! option_setters {
+    () => {
+        /// Adds an option to this message
+        ///
+        /// Options can be added in any order; the option will be inserted after all the options that
+        /// have an equal or lower number.
+        ///
+        /// *HEADS UP* This method will cause the first bytes of the payload to be lost
+        ///
+        /// Returns an error, and leaves the message unchanged, if there's no space in the message to
+        /// add the option
+        pub fn add_option(&mut self, number: OptionNumber, value: &[u8]) -> Result<(), Error> {
+            let nr: u16 = number.into();
+            let len = u16(value.len()).map_err(|_| Error::BufferTooSmall)?;
+
+            // the option goes before the first option that has a higher number
+            let mut prev = 0;
+            let mut next = None;
+            for pos in self.positions() {
+                if pos.number > nr {
+                    next = Some(pos);
+                    break;
+                }
+
+                prev = pos.number;
+            }
+
+            let mut buf = [0; 5];
+            let header = encode_header(nr - prev, len, &mut buf);
+            let marker = usize(self.marker);
+            let capacity = self.as_slice().len();
+
+            if let Some(next) = next {
+                // the delta of the next option changes so its header needs to be re-encoded
+                let mut buf = [0; 5];
+                let next_header = encode_header(next.number - nr, next.len(), &mut buf);
+
+                let start = next.start;
+                let new_end = start + header.len() + value.len() + next_header.len();
+                let new_marker = marker + new_end - next.value;
+                if new_marker > capacity {
+                    return Err(Error::BufferTooSmall);
+                }
+
+                let bytes = self.as_mut_slice();
+                bytes.copy_within(next.value..marker, new_end);
+
+                let mut cursor = start;
+                for chunk in &[header, value, next_header] {
+                    bytes[cursor..cursor + chunk.len()].copy_from_slice(chunk);
+                    cursor += chunk.len();
+                }
+
+                self.marker = u16(new_marker).unwrap();
+            } else {
+                let end = marker + header.len() + value.len();
+                if end > capacity {
+                    return Err(Error::BufferTooSmall);
+                }
+
+                let bytes = self.as_mut_slice();
+                bytes[marker..marker + header.len()].copy_from_slice(header);
+                bytes[marker + header.len()..end].copy_from_slice(value);
+
+                // update the cached highest number
+                self.number = nr;
+                self.marker = u16(end).unwrap();
+            }
+
+            Ok(())
+        }
+
+        /// Removes the first option with the given `number`
+        ///
+        /// Returns `true` if an option was removed
+        pub fn remove_option(&mut self, number: OptionNumber) -> bool {
+            let nr: u16 = number.into();
+
+            let mut prev = 0;
+            let mut target = None;
+            let mut next = None;
+            for pos in self.positions() {
+                if target.is_some() {
+                    next = Some(pos);
+                    break;
+                } else if pos.number == nr {
+                    target = Some(pos);
+                } else if pos.number > nr {
+                    break;
+                } else {
+                    prev = pos.number;
+                }
+            }
+
+            let target = if let Some(target) = target {
+                target
+            } else {
+                return false;
+            };
+
+            if let Some(next) = next {
+                // the delta of the next option grows; this never needs more space than what the
+                // removed option used
+                let mut buf = [0; 5];
+                let next_header = encode_header(next.number - prev, next.len(), &mut buf);
+
+                let marker = usize(self.marker);
+                let start = target.start;
+                let new_value = start + next_header.len();
+
+                let bytes = self.as_mut_slice();
+                bytes.copy_within(next.value..marker, new_value);
+                bytes[start..new_value].copy_from_slice(next_header);
+
+                self.marker = u16(marker - (next.value - new_value)).unwrap();
+            } else {
+                // this was the last option
+                self.number = prev;
+                self.marker = u16(target.start).unwrap();
+            }
+
+            true
+        }
+
+        /// Removes all the options with the given `number`
+        ///
+        /// Returns the number of removed options
+        pub fn remove_options(&mut self, number: OptionNumber) -> usize {
+            let mut n = 0;
+            while self.remove_option(number) {
+                n += 1;
+            }
+            n
+        }
+
+        /// Adds an option whose value is an unsigned integer
+        ///
+        /// The value is encoded using the minimal number of bytes. Returns an error if the encoded
+        /// value is too long for the option `number` (see Table 4 of RFC 7252) or if there's no space
+        /// in the message to add the option
+        pub fn add_uint_option(&mut self, number: OptionNumber, value: u32) -> Result<(), Error> {
+            let mut buf = [0; 4];
+            let value = encode_uint(value, &mut buf);
+            self.add_checked_option(number, value)
+        }
+
+        /// Adds a Content-Format option to this message
+        ///
+        /// Returns an error if there's no space in the message to add the option
+        pub fn add_content_format(&mut self, format: ContentFormat) -> Result<(), Error> {
+            self.add_uint_option(OptionNumber::ContentFormat, u32::from(u16::from(format)))
+        }
+
+        /// Adds a Max-Age option (in seconds) to this message
+        ///
+        /// Returns an error if there's no space in the message to add the option
+        pub fn add_max_age(&mut self, seconds: u32) -> Result<(), Error> {
+            self.add_uint_option(OptionNumber::MaxAge, seconds)
+        }
+
+        /// Adds an Uri-Port option to this message
+        ///
+        /// Returns an error if there's no space in the message to add the option
+        pub fn add_uri_port(&mut self, port: u16) -> Result<(), Error> {
+            self.add_uint_option(OptionNumber::UriPort, u32::from(port))
+        }
+
+        /// Adds an Uri-Path option (one segment of the path) to this message
+        ///
+        /// Returns an error if `segment` is longer than 255 bytes, or if there's no space in the
+        /// message to add the option
+        pub fn add_uri_path(&mut self, segment: &str) -> Result<(), Error> {
+            self.add_checked_option(OptionNumber::UriPath, segment.as_bytes())
+        }
+
+        /// Adds an Uri-Query option (one argument of the query) to this message
+        ///
+        /// Returns an error if `argument` is longer than 255 bytes, or if there's no space in the
+        /// message to add the option
+        pub fn add_uri_query(&mut self, argument: &str) -> Result<(), Error> {
+            self.add_checked_option(OptionNumber::UriQuery, argument.as_bytes())
+        }
+
+        /// Adds an ETag option to this message
+        ///
+        /// Returns an error if `etag` is empty or longer than 8 bytes, or if there's no space in the
+        /// message to add the option
+        pub fn add_etag(&mut self, etag: &[u8]) -> Result<(), Error> {
+            self.add_checked_option(OptionNumber::ETag, etag)
+        }
+
+        /// Adds an Accept option to this message
+        ///
+        /// Returns an error if there's no space in the message to add the option
+        pub fn add_accept(&mut self, format: ContentFormat) -> Result<(), Error> {
+            self.add_uint_option(OptionNumber::Accept, u32::from(u16::from(format)))
+        }
+
+        /// Adds an Observe option to this message
+        ///
+        /// Only the lower 24 bits of `value` are used. The value is encoded using the minimal number
+        /// of bytes.
+        ///
+        /// Returns an error if there's no space in the message to add the option
+        pub fn add_observe(&mut self, value: u32) -> Result<(), Error> {
+            let mut buf = [0; 4];
+            let value = encode_uint(value & observe::SEQUENCE_MASK, &mut buf);
+            self.add_option(OptionNumber::Observe, value)
+        }
+
+        /// Adds a Block1 option to this message
+        ///
+        /// Returns an error if there's no space in the message to add the option
+        pub fn add_block1(&mut self, block: block::Block) -> Result<(), Error> {
+            let mut buf = [0; 4];
+            let value = block.encode(&mut buf);
+            self.add_option(OptionNumber::Block1, value)
+        }
+
+        /// Adds a Block2 option to this message
+        ///
+        /// Returns an error if there's no space in the message to add the option
+        pub fn add_block2(&mut self, block: block::Block) -> Result<(), Error> {
+            let mut buf = [0; 4];
+            let value = block.encode(&mut buf);
+            self.add_option(OptionNumber::Block2, value)
+        }
+
+        /// Adds a Size2 option to this message
+        ///
+        /// Returns an error if there's no space in the message to add the option
+        pub fn add_size2(&mut self, size: u32) -> Result<(), Error> {
+            let mut buf = [0; 4];
+            let value = encode_uint(size, &mut buf);
+            self.add_option(OptionNumber::Size2, value)
+        }
+
+        /// Adds a No-Response option to this message
+        ///
+        /// Returns an error if there's no space in the message to add the option
+        pub fn add_no_response(&mut self, value: no_response::NoResponse) -> Result<(), Error> {
+            self.add_uint_option(OptionNumber::NoResponse, u32::from(value.bits()))
+        }
+
+        /// Adds an Echo option to this message
+        ///
+        /// Returns an error if `value` is empty or longer than 40 bytes, or if there's no space in
+        /// the message to add the option
+        pub fn add_echo(&mut self, value: &[u8]) -> Result<(), Error> {
+            self.add_checked_option(OptionNumber::Echo, value)
+        }
+
+        /// Adds a Request-Tag option to this message
+        ///
+        /// Returns an error if `tag` is longer than 8 bytes, or if there's no space in the message
+        /// to add the option
+        pub fn add_request_tag(&mut self, tag: &[u8]) -> Result<(), Error> {
+            self.add_checked_option(OptionNumber::RequestTag, tag)
+        }
+
+        // Like `add_option` but checks the length of `value` against Table 4 of RFC 7252
+        fn add_checked_option(&mut self, number: OptionNumber, value: &[u8]) -> Result<(), Error> {
+            if let Some((min, max)) = number.length_limits() {
+                if value.len() < usize(min) || value.len() > usize(max) {
+                    return Err(Error::OptionLength);
+                }
+            }
+
+            self.add_option(number, value)
+        }
+
+        /// Returns the size of the largest payload that fits in the remaining space
+        pub fn payload_capacity(&self) -> usize {
+            self.buffer
+                .as_slice()
+                .len()
+                .saturating_sub(usize(self.marker) + 1)
+        }
+
+        /// Removes all the options this message has
+        pub fn clear_options(&mut self) {
+            self.number = 0;
+            self.marker = u16(self.options_start());
+        }
+    };
+}
@@ -0,0 +1,168 @@
+//! No-Response option (RFC 7967)
+//!
+//! A client uses the No-Response option to tell the server which classes of responses it's not
+//! interested in. This is useful with multicast requests, e.g. actuator commands sent to a group,
+//! where the responses of all the group members would flood the network.
+//!
+//! The server should check `suppress` before sending a response. An absent option, or a value of
+//! `0`, means that the client is interested in all the responses.
+//!
+//! # Example
+//!
+//! ```
+//! use jnet::coap::{self, no_response::{self, NoResponse}};
+//!
+//! // the client is only interested in errors
+//! let mut buf = [0; 32];
+//! let mut req = coap::Message::new(&mut buf[..], 0);
+//! req.set_type(coap::Type::NonConfirmable);
+//! req.set_code(coap::Method::Put);
+//! req.add_uri_path("led").unwrap();
+//! req.add_no_response(NoResponse::SUCCESS).unwrap();
+//! let req = req.set_payload(b"on");
+//!
+//! assert!(no_response::suppress(&req, coap::Response::Changed.into()));
+//! assert!(!no_response::suppress(&req, coap::Response::BadRequest.into()));
+//! ```
+
+use core::ops;
+
+use as_slice::AsSlice;
+
+use crate::coap::{Code, Message};
+
+// the bit of each response class; see RFC 7967 Section 2.1 Table 2
+const SUCCESS: u8 = 1 << 1;
+const CLIENT_ERROR: u8 = 1 << 3;
+const SERVER_ERROR: u8 = 1 << 4;
+
+/// The value of a No-Response option: the set of response classes the client is not interested in
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct NoResponse(u8);
+
+impl NoResponse {
+    /// Suppress 2.xx (Success) responses
+    pub const SUCCESS: Self = NoResponse(SUCCESS);
+
+    /// Suppress 4.xx (Client Error) responses
+    pub const CLIENT_ERROR: Self = NoResponse(CLIENT_ERROR);
+
+    /// Suppress 5.xx (Server Error) responses
+    pub const SERVER_ERROR: Self = NoResponse(SERVER_ERROR);
+
+    /// Suppress all responses
+    pub const ALL: Self = NoResponse(SUCCESS | CLIENT_ERROR | SERVER_ERROR);
+
+    /// Interested in all the responses; same as omitting the option
+    pub const NONE: Self = NoResponse(0);
+
+    /// Builds a `NoResponse` value from its encoded form; unassigned bits are ignored
+    pub const fn from_bits(bits: u8) -> Self {
+        NoResponse(bits & Self::ALL.0)
+    }
+
+    /// Returns the encoded form of this value
+    pub const fn bits(&self) -> u8 {
+        self.0
+    }
+
+    /// Checks if all the classes in `other` are also in `self`
+    pub const fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Checks if a response with the given `code` should be suppressed
+    ///
+    /// Only responses are ever suppressed; empty messages and requests are not
+    pub fn suppresses(&self, code: Code) -> bool {
+        let bit = match code.class() {
+            2 => SUCCESS,
+            4 => CLIENT_ERROR,
+            5 => SERVER_ERROR,
+            _ => return false,
+        };
+
+        self.0 & bit != 0
+    }
+}
+
+impl ops::BitOr for NoResponse {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        NoResponse(self.0 | rhs.0)
+    }
+}
+
+impl ops::BitOrAssign for NoResponse {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0
+    }
+}
+
+/// Checks if the response to `req` with the given `code` should be suppressed according to the
+/// No-Response option of the request
+pub fn suppress<B, P>(req: &Message<B, P>, code: Code) -> bool
+where
+    B: AsSlice<Element = u8>,
+{
+    req.no_response()
+        .map(|no_response| no_response.suppresses(code))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::coap::{self, Code, Message, OptionNumber};
+
+    use super::NoResponse;
+
+    #[test]
+    fn classes() {
+        let value = NoResponse::SUCCESS | NoResponse::SERVER_ERROR;
+        assert_eq!(value.bits(), 0x12);
+        assert!(value.contains(NoResponse::SUCCESS));
+        assert!(!value.contains(NoResponse::ALL));
+
+        assert!(value.suppresses(coap::Response::Content.into()));
+        assert!(!value.suppresses(coap::Response::NotFound.into()));
+        assert!(value.suppresses(coap::Response::InternalServerError.into()));
+        assert!(!value.suppresses(Code::EMPTY));
+        assert!(!value.suppresses(coap::Method::Get.into()));
+
+        assert!(!NoResponse::NONE.suppresses(coap::Response::Content.into()));
+        assert_eq!(NoResponse::from_bits(0xff), NoResponse::ALL);
+    }
+
+    #[test]
+    fn option() {
+        let mut buf = [0; 16];
+        let mut m = Message::new(&mut buf[..], 0);
+        m.set_code(coap::Method::Put);
+        m.add_no_response(NoResponse::CLIENT_ERROR).unwrap();
+        let m = m.no_payload();
+
+        assert_eq!(m.no_response(), Some(NoResponse::CLIENT_ERROR));
+        {
+            let opt = m.options().next().unwrap();
+            assert_eq!(opt.number(), OptionNumber::NoResponse);
+            assert_eq!(opt.value(), &[8]);
+        }
+        assert!(super::suppress(&m, coap::Response::Forbidden.into()));
+        assert!(!super::suppress(&m, coap::Response::Changed.into()));
+
+        // the value `0` is encoded as an empty option
+        let mut buf = [0; 16];
+        let mut m = Message::new(&mut buf[..], 0);
+        m.add_no_response(NoResponse::NONE).unwrap();
+        let m = m.no_payload();
+        assert_eq!(m.options().next().unwrap().value(), &[]);
+        assert_eq!(m.no_response(), Some(NoResponse::NONE));
+
+        // no option
+        let mut buf = [0; 16];
+        let m = Message::new(&mut buf[..], 0).no_payload();
+        assert_eq!(m.no_response(), None);
+        assert!(!super::suppress(&m, coap::Response::Changed.into()));
+    }
+}
@@ -0,0 +1,305 @@
+//! Observing resources (RFC 7641)
+//!
+//! `ObserverList` keeps track of the clients that are observing a single resource. When the state
+//! of the resource changes call `ObserverList::changed` and then build one notification per
+//! observer using `ObserverList::notification`.
+//!
+//! # Example
+//!
+//! ```
+//! use jnet::coap::{self, observe::ObserverList};
+//!
+//! // observers are identified by their (endpoint, token) pair; here the endpoint is a port number
+//! let mut observers = ObserverList::<u16, 2>::new();
+//!
+//! // a GET request with `Observe: 0` registers the client
+//! observers.register(1234, &[0xab, 0xcd]).unwrap();
+//!
+//! // the resource changed
+//! let seq = observers.changed();
+//!
+//! for observer in observers.iter() {
+//!     let mut buf = [0; 32];
+//!     let mut m = observers
+//!         .notification(observer, &mut buf[..], coap::Type::NonConfirmable, 1)
+//!         .unwrap();
+//!     m.add_content_format(coap::ContentFormat::TextPlain).unwrap();
+//!     let m = m.set_payload(b"22.5");
+//!
+//!     assert_eq!(m.token(), &[0xab, 0xcd]);
+//!     assert_eq!(m.get_observe(), Some(seq));
+//! }
+//! ```
+
+use as_slice::AsMutSlice;
+
+use crate::coap::{Error, Message, Response, Type, Unset};
+
+/// Value of the Observe option that registers an observer
+pub const REGISTER: u32 = 0;
+
+/// Value of the Observe option that deregisters an observer
+pub const DEREGISTER: u32 = 1;
+
+/// Observe sequence numbers are 24-bit long
+pub const SEQUENCE_MASK: u32 = (1 << 24) - 1;
+
+// RFC 7641 - Section 3.4
+const HALF: u32 = 1 << 23;
+
+/// Returns the sequence number that follows `seq`
+pub fn next(seq: u32) -> u32 {
+    seq.wrapping_add(1) & SEQUENCE_MASK
+}
+
+/// Checks if a notification with sequence number `new` is newer than one with sequence number
+/// `old`
+///
+/// NOTE this doesn't implement the "more than 128 seconds apart" rule of RFC 7641 Section 3.4;
+/// the caller should accept the notification if that much time has elapsed since the last one
+pub fn is_newer(old: u32, new: u32) -> bool {
+    let (old, new) = (old & SEQUENCE_MASK, new & SEQUENCE_MASK);
+
+    (old < new && new - old < HALF) || (old > new && old - new > HALF)
+}
+
+/// A client that observes a resource
+#[derive(Clone, Copy, Debug)]
+pub struct Observer<E>
+where
+    E: Copy,
+{
+    endpoint: E,
+    token: [u8; 8],
+    token_length: u8,
+}
+
+impl<E> Observer<E>
+where
+    E: Copy,
+{
+    /// Returns the endpoint of the observer
+    pub fn endpoint(&self) -> E {
+        self.endpoint
+    }
+
+    /// Returns the token that the observer used in its registration
+    pub fn token(&self) -> &[u8] {
+        &self.token[..usize::from(self.token_length)]
+    }
+}
+
+/// The list of clients observing a resource
+///
+/// The list can hold up to `N` observers. `E` is the type used to identify the endpoint of an
+/// observer, e.g. an (IP address, port) pair.
+pub struct ObserverList<E, const N: usize>
+where
+    E: Copy,
+{
+    observers: [Option<Observer<E>>; N],
+    sequence: u32,
+}
+
+impl<E, const N: usize> ObserverList<E, N>
+where
+    E: Copy + PartialEq,
+{
+    /// Creates an empty list
+    pub const fn new() -> Self {
+        ObserverList {
+            observers: [None; N],
+            sequence: 0,
+        }
+    }
+
+    /// Registers an observer
+    ///
+    /// If the observer is already registered this updates nothing; RFC 7641 treats it as a
+    /// re-registration. Returns an error if the list is full or if `token` is longer than 8 bytes.
+    pub fn register(&mut self, endpoint: E, token: &[u8]) -> Result<(), ()> {
+        if token.len() > 8 {
+            return Err(());
+        }
+
+        if self.position(endpoint, token).is_some() {
+            return Ok(());
+        }
+
+        let slot = self
+            .observers
+            .iter_mut()
+            .find(|slot| slot.is_none())
+            .ok_or(())?;
+
+        let mut observer = Observer {
+            endpoint,
+            token: [0; 8],
+            token_length: token.len() as u8,
+        };
+        observer.token[..token.len()].copy_from_slice(token);
+        *slot = Some(observer);
+
+        Ok(())
+    }
+
+    /// Deregisters an observer
+    ///
+    /// Returns `true` if the observer was registered
+    pub fn deregister(&mut self, endpoint: E, token: &[u8]) -> bool {
+        if let Some(i) = self.position(endpoint, token) {
+            self.observers[i] = None;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Deregisters all the observers that use the given `endpoint`
+    ///
+    /// Use this when the endpoint answers a notification with a Reset message
+    pub fn deregister_endpoint(&mut self, endpoint: E) {
+        for slot in self.observers.iter_mut() {
+            if slot.map(|o| o.endpoint == endpoint).unwrap_or(false) {
+                *slot = None;
+            }
+        }
+    }
+
+    /// Returns the number of registered observers
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    /// Returns `true` if there are no registered observers
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns an iterator over the registered observers
+    pub fn iter(&self) -> impl Iterator<Item = &Observer<E>> {
+        self.observers.iter().filter_map(|slot| slot.as_ref())
+    }
+
+    /// Returns the sequence number of the latest notification
+    pub fn sequence(&self) -> u32 {
+        self.sequence
+    }
+
+    /// Signals that the state of the resource changed
+    ///
+    /// Returns the sequence number that the next notifications will carry
+    pub fn changed(&mut self) -> u32 {
+        self.sequence = next(self.sequence);
+        self.sequence
+    }
+
+    /// Builds a notification for the given `observer`
+    ///
+    /// This sets the header (`ty`, Code = 2.05 Content and `message_id`), the token and the
+    /// Observe option. The caller must add any other option and the payload.
+    ///
+    /// Returns an error if `buffer` is too small to hold the Observe option
+    ///
+    /// # Panics
+    ///
+    /// This method panics if `buffer` is too small to hold the header and the token
+    pub fn notification<B>(
+        &self,
+        observer: &Observer<E>,
+        buffer: B,
+        ty: Type,
+        message_id: u16,
+    ) -> Result<Message<B, Unset>, Error>
+    where
+        B: AsMutSlice<Element = u8>,
+    {
+        let mut m = Message::new(buffer, observer.token_length);
+        m.set_type(ty);
+        m.set_code(Response::Content);
+        m.set_message_id(message_id);
+        m.token_mut().copy_from_slice(observer.token());
+        m.add_observe(self.sequence)?;
+        Ok(m)
+    }
+
+    fn position(&self, endpoint: E, token: &[u8]) -> Option<usize> {
+        self.observers.iter().position(|slot| {
+            slot.map(|o| o.endpoint == endpoint && o.token() == token)
+                .unwrap_or(false)
+        })
+    }
+}
+
+impl<E, const N: usize> Default for ObserverList<E, N>
+where
+    E: Copy + PartialEq,
+{
+    fn default() -> Self {
+        ObserverList::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::coap;
+
+    use super::ObserverList;
+
+    #[test]
+    fn sequence() {
+        assert_eq!(super::next(super::SEQUENCE_MASK), 0);
+
+        assert!(super::is_newer(1, 2));
+        assert!(!super::is_newer(2, 1));
+        assert!(!super::is_newer(2, 2));
+        // wrap around
+        assert!(super::is_newer(super::SEQUENCE_MASK, 0));
+        assert!(!super::is_newer(0, super::SEQUENCE_MASK));
+    }
+
+    #[test]
+    fn observers() {
+        let mut observers = ObserverList::<u16, 2>::new();
+
+        assert!(observers.register(1, &[1]).is_ok());
+        // re-registration
+        assert!(observers.register(1, &[1]).is_ok());
+        assert!(observers.register(1, &[2]).is_ok());
+        assert_eq!(observers.len(), 2);
+        // full
+        assert!(observers.register(2, &[1]).is_err());
+
+        assert!(observers.deregister(1, &[1]));
+        assert!(!observers.deregister(1, &[1]));
+        assert_eq!(observers.len(), 1);
+
+        observers.deregister_endpoint(1);
+        assert!(observers.is_empty());
+    }
+
+    #[test]
+    fn notification() {
+        let mut observers = ObserverList::<u16, 1>::new();
+        observers.register(1, &[0xab; 8]).unwrap();
+
+        for expected in 1..=300 {
+            assert_eq!(observers.changed(), expected);
+        }
+
+        let observer = observers.iter().next().unwrap();
+        let mut buf = [0; 32];
+        let m = observers
+            .notification(observer, &mut buf[..], coap::Type::Confirmable, 42)
+            .unwrap()
+            .no_payload();
+
+        let m = coap::Message::parse(m.as_bytes()).unwrap();
+        assert_eq!(m.get_type(), coap::Type::Confirmable);
+        assert_eq!(m.get_message_id(), 42);
+        assert_eq!(m.token(), &[0xab; 8]);
+        assert_eq!(m.get_observe(), Some(300));
+        // minimal encoding
+        assert_eq!(m.options().next().unwrap().value(), &[1, 44]);
+    }
+}
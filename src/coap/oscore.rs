@@ -0,0 +1,1186 @@
+//! OSCORE: Object Security for Constrained RESTful Environments (RFC 8613)
+//!
+//! A security `Context` transforms a plain CoAP message into an OSCORE protected message and
+//! back. The request method / response code, the Class E options and the payload are encrypted
+//! (AES-CCM-16-64-128, COSE_Encrypt0) into the payload of the protected message; only the Class U
+//! options (Uri-Host, Uri-Port, Proxy-Scheme and the outer copy of Observe) and the OSCORE option
+//! remain visible to proxies. The outer code is POST / 2.04 (Changed), or FETCH / 2.05 (Content)
+//! for Observe messages.
+//!
+//! (Un)protecting a request returns an `Exchange` that must be used to (un)protect the matching
+//! response.
+//!
+//! This module is only available when the `oscore` feature is enabled.
+//!
+//! # Example
+//!
+//! ```
+//! use jnet::coap::{self, oscore::Context};
+//!
+//! let secret = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+//! let mut client = Context::new(&secret, &[], &[], &[1], None).unwrap();
+//! let mut server = Context::new(&secret, &[], &[1], &[], None).unwrap();
+//!
+//! // client: GET /temperature
+//! let mut buf = [0; 64];
+//! let mut req = coap::Message::new(&mut buf[..], 0);
+//! req.set_code(coap::Method::Get);
+//! req.add_uri_path("temperature").unwrap();
+//! let req = req.no_payload();
+//!
+//! let mut buf = [0; 64];
+//! let (mut req, exchange) = client
+//!     .protect_request(&req, coap::Message::new(&mut buf[..], 0))
+//!     .unwrap();
+//! assert_eq!(req.get_code(), coap::Method::Post.into());
+//! assert_eq!(req.uri_path().count(), 0);
+//!
+//! // server
+//! let mut buf = [0; 64];
+//! let (req, server_exchange) = server
+//!     .unprotect_request(&mut req, coap::Message::new(&mut buf[..], 0))
+//!     .unwrap();
+//! assert_eq!(req.get_code(), coap::Method::Get.into());
+//! assert!(req.uri_path().eq(["temperature"].iter().cloned().map(Ok)));
+//!
+//! let mut buf = [0; 64];
+//! let mut resp = coap::Message::new(&mut buf[..], 0);
+//! resp.set_code(coap::Response::Content);
+//! let resp = resp.set_payload(b"21");
+//!
+//! let mut buf = [0; 64];
+//! let mut resp = server
+//!     .protect_response(&resp, &server_exchange, coap::Message::new(&mut buf[..], 0))
+//!     .unwrap();
+//! assert_eq!(resp.get_code(), coap::Response::Changed.into());
+//!
+//! // client
+//! let mut buf = [0; 64];
+//! let resp = client
+//!     .unprotect_response(&mut resp, &exchange, coap::Message::new(&mut buf[..], 0))
+//!     .unwrap();
+//! assert_eq!(resp.get_code(), coap::Response::Content.into());
+//! assert_eq!(resp.payload(), b"21");
+//! ```
+//!
+//! NOTE The Proxy-Uri option is not supported; it must be decomposed into the Proxy-Scheme and
+//! Uri-* options before protecting the request.
+//!
+//! NOTE The Sender Sequence Number and the Replay Window are kept in memory. Persisting them
+//! across reboots (RFC 8613 Appendix B.1) is left to the application (see `sequence_number`).
+
+use aes::Aes128;
+use as_slice::{AsMutSlice, AsSlice};
+use cast::{u16, u8, usize};
+use ccm::{
+    aead::{generic_array::GenericArray, AeadInPlace, KeyInit},
+    consts::{U13, U8},
+    Ccm,
+};
+use hkdf::Hkdf;
+use owning_slice::Truncate;
+use sha2::Sha256;
+
+use crate::coap::{
+    encode_header, scan, Code, Message, Method, OptionNumber, Options, Response, Unset,
+    PAYLOAD_MARKER,
+};
+
+/// COSE algorithm identifier of AES-CCM-16-64-128, the AEAD algorithm used by this module
+pub const AES_CCM_16_64_128: u8 = 10;
+
+/// Maximum length of a Sender / Recipient ID (nonce length - 6)
+pub const MAX_ID_LEN: usize = NONCE_LEN - 6;
+
+/// Maximum length of the ID Context supported by this implementation
+pub const MAX_ID_CONTEXT_LEN: usize = 16;
+
+/// Largest Sender Sequence Number (2^40 - 1)
+pub const MAX_SEQUENCE_NUMBER: u64 = (1 << 40) - 1;
+
+/// Size, in sequence numbers, of the Replay Window
+pub const REPLAY_WINDOW_SIZE: u64 = 32;
+
+const KEY_LEN: usize = 16;
+const NONCE_LEN: usize = 13;
+const TAG_LEN: usize = 8;
+const MAX_PIV_LEN: usize = 5;
+
+// OSCORE option value (RFC 8613 Section 6.1)
+mod flags {
+    // length of the Partial IV
+    pub const N: u8 = 0b111;
+    // Key ID (kid) flag
+    pub const K: u8 = 1 << 3;
+    // Key ID Context (kid context) flag
+    pub const H: u8 = 1 << 4;
+    pub const RESERVED: u8 = 0b1110_0000;
+}
+
+type AesCcm = Ccm<Aes128, U8, U13>;
+
+/// OSCORE Security Context (Common, Sender and Recipient Contexts)
+pub struct Context {
+    sender_id: Bytes<MAX_ID_LEN>,
+    recipient_id: Bytes<MAX_ID_LEN>,
+    id_context: Option<Bytes<MAX_ID_CONTEXT_LEN>>,
+    sender_key: [u8; KEY_LEN],
+    recipient_key: [u8; KEY_LEN],
+    common_iv: [u8; NONCE_LEN],
+    sequence_number: u64,
+    replay: ReplayWindow,
+}
+
+/// The request identifiers (`request_kid` and `request_piv`) that bind a response to its request
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Exchange {
+    kid: Bytes<MAX_ID_LEN>,
+    piv: Bytes<MAX_PIV_LEN>,
+}
+
+/// OSCORE error
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Error {
+    /// The message doesn't carry an OSCORE option
+    NotProtected,
+    /// The OSCORE option, or the decrypted message, is malformed
+    Malformed,
+    /// The message was not protected with this security context (kid / kid context mismatch)
+    UnknownContext,
+    /// The Partial IV has already been received or is too old
+    Replay,
+    /// Decryption failed; the message was tampered with or the keys don't match
+    Decryption,
+    /// The Sender Sequence Number space is exhausted; a new security context must be established
+    SequenceNumberExhausted,
+    /// The message contains a Proxy-Uri option
+    ProxyUri,
+    /// There's no space left in the output buffer
+    BufferTooSmall,
+}
+
+impl Error {
+    /// Returns the (unprotected) error response a server should send back, if any
+    ///
+    /// See RFC 8613 Section 8.2
+    pub fn response(&self) -> Option<Response> {
+        match *self {
+            Error::Malformed => Some(Response::BadOption),
+            Error::UnknownContext | Error::Replay => Some(Response::Unauthorized),
+            Error::Decryption => Some(Response::BadRequest),
+            _ => None,
+        }
+    }
+}
+
+impl Context {
+    /// Derives a security context from the Master Secret and Master Salt (RFC 8613 Section 3.2)
+    ///
+    /// Returns an error if either ID is longer than `MAX_ID_LEN` or if the ID Context is longer
+    /// than `MAX_ID_CONTEXT_LEN`
+    pub fn new(
+        master_secret: &[u8],
+        master_salt: &[u8],
+        sender_id: &[u8],
+        recipient_id: &[u8],
+        id_context: Option<&[u8]>,
+    ) -> Result<Self, ()> {
+        let sender_id = Bytes::new(sender_id)?;
+        let recipient_id = Bytes::new(recipient_id)?;
+        let id_context = match id_context {
+            Some(id_context) => Some(Bytes::new(id_context)?),
+            None => None,
+        };
+
+        let hkdf = Hkdf::<Sha256>::new(Some(master_salt), master_secret);
+        let id_ctx = id_context.as_ref().map(|ctx| ctx.as_slice());
+
+        let mut sender_key = [0; KEY_LEN];
+        derive(&hkdf, sender_id.as_slice(), id_ctx, "Key", &mut sender_key);
+
+        let mut recipient_key = [0; KEY_LEN];
+        derive(
+            &hkdf,
+            recipient_id.as_slice(),
+            id_ctx,
+            "Key",
+            &mut recipient_key,
+        );
+
+        let mut common_iv = [0; NONCE_LEN];
+        derive(&hkdf, &[], id_ctx, "IV", &mut common_iv);
+
+        Ok(Context {
+            sender_id,
+            recipient_id,
+            id_context,
+            sender_key,
+            recipient_key,
+            common_iv,
+            sequence_number: 0,
+            replay: ReplayWindow::new(),
+        })
+    }
+
+    /// Returns the Sender ID
+    pub fn sender_id(&self) -> &[u8] {
+        self.sender_id.as_slice()
+    }
+
+    /// Returns the Recipient ID
+    pub fn recipient_id(&self) -> &[u8] {
+        self.recipient_id.as_slice()
+    }
+
+    /// Returns the Sender Sequence Number that will be used by the next protected message
+    pub fn sequence_number(&self) -> u64 {
+        self.sequence_number
+    }
+
+    /// Sets the Sender Sequence Number, e.g. after restoring the context from persistent storage
+    ///
+    /// # Panics
+    ///
+    /// This method panics if `seq` is larger than `MAX_SEQUENCE_NUMBER + 1`
+    pub fn set_sequence_number(&mut self, seq: u64) {
+        assert!(seq <= MAX_SEQUENCE_NUMBER + 1);
+
+        self.sequence_number = seq;
+    }
+
+    /// Protects the request `req`; the protected request is written into `out`
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the token length of `out` doesn't match the token length of `req`
+    pub fn protect_request<I, O>(
+        &mut self,
+        req: &Message<I>,
+        out: Message<O, Unset>,
+    ) -> Result<(Message<O>, Exchange), Error>
+    where
+        I: AsSlice<Element = u8>,
+        O: AsMutSlice<Element = u8> + Truncate<u16>,
+    {
+        let piv = self.next_piv()?;
+        let exchange = Exchange {
+            kid: self.sender_id,
+            piv,
+        };
+
+        let mut option = Bytes::<{ 3 + MAX_PIV_LEN + MAX_ID_CONTEXT_LEN + MAX_ID_LEN }>::empty();
+        let mut flags = piv.len | flags::K;
+        if self.id_context.is_some() {
+            flags |= flags::H;
+        }
+        option.push(&[flags]);
+        option.push(piv.as_slice());
+        if let Some(id_context) = self.id_context.as_ref() {
+            option.push(&[id_context.len]);
+            option.push(id_context.as_slice());
+        }
+        option.push(self.sender_id.as_slice());
+
+        let code = if req.get_observe().is_some() {
+            Method::Fetch
+        } else {
+            Method::Post
+        };
+
+        let m = seal(
+            &self.sender_key,
+            req,
+            out,
+            code.into(),
+            option.as_slice(),
+            &self.nonce(self.sender_id.as_slice(), piv.as_slice()),
+            &exchange,
+        )?;
+
+        Ok((m, exchange))
+    }
+
+    /// Verifies and decrypts the protected request `req`; the plain request is written into `out`
+    ///
+    /// The payload of `req` is decrypted in place.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the token length of `out` doesn't match the token length of `req`
+    pub fn unprotect_request<B, O>(
+        &mut self,
+        req: &mut Message<B>,
+        out: Message<O, Unset>,
+    ) -> Result<(Message<O>, Exchange), Error>
+    where
+        B: AsMutSlice<Element = u8>,
+        O: AsMutSlice<Element = u8> + Truncate<u16>,
+    {
+        let (exchange, seq) = {
+            let option = OscoreOption::find(req)?;
+
+            let piv = option.piv.ok_or(Error::Malformed)?;
+            let kid = option.kid.ok_or(Error::Malformed)?;
+
+            if kid != self.recipient_id.as_slice() {
+                return Err(Error::UnknownContext);
+            }
+
+            if let Some(kid_context) = option.kid_context {
+                if Some(kid_context) != self.id_context.as_ref().map(|ctx| ctx.as_slice()) {
+                    return Err(Error::UnknownContext);
+                }
+            }
+
+            let seq = decode_piv(piv);
+            if !self.replay.is_fresh(seq) {
+                return Err(Error::Replay);
+            }
+
+            (
+                Exchange {
+                    kid: self.recipient_id,
+                    piv: Bytes::new(piv).map_err(|_| Error::Malformed)?,
+                },
+                seq,
+            )
+        };
+
+        let nonce = self.nonce(exchange.kid.as_slice(), exchange.piv.as_slice());
+        let m = open(&self.recipient_key, req, out, &nonce, &exchange)?;
+        self.replay.update(seq);
+
+        Ok((m, exchange))
+    }
+
+    /// Protects the response `resp` to the request identified by `exchange`; the protected
+    /// response is written into `out`
+    ///
+    /// Observe notifications are protected with a fresh Partial IV; other responses reuse the
+    /// nonce of the request.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the token length of `out` doesn't match the token length of `resp`
+    pub fn protect_response<I, O>(
+        &mut self,
+        resp: &Message<I>,
+        exchange: &Exchange,
+        out: Message<O, Unset>,
+    ) -> Result<Message<O>, Error>
+    where
+        I: AsSlice<Element = u8>,
+        O: AsMutSlice<Element = u8> + Truncate<u16>,
+    {
+        let fresh = resp.get_observe().is_some();
+        self.protect_response_(resp, exchange, out, fresh)
+    }
+
+    /// Verifies and decrypts the protected response `resp` to the request identified by
+    /// `exchange`; the plain response is written into `out`
+    ///
+    /// The payload of `resp` is decrypted in place.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the token length of `out` doesn't match the token length of `resp`
+    pub fn unprotect_response<B, O>(
+        &mut self,
+        resp: &mut Message<B>,
+        exchange: &Exchange,
+        out: Message<O, Unset>,
+    ) -> Result<Message<O>, Error>
+    where
+        B: AsMutSlice<Element = u8>,
+        O: AsMutSlice<Element = u8> + Truncate<u16>,
+    {
+        let (nonce, seq) = {
+            let option = OscoreOption::find(resp)?;
+
+            if let Some(piv) = option.piv {
+                let seq = decode_piv(piv);
+                if !self.replay.is_fresh(seq) {
+                    return Err(Error::Replay);
+                }
+
+                (self.nonce(self.recipient_id.as_slice(), piv), Some(seq))
+            } else {
+                (
+                    self.nonce(exchange.kid.as_slice(), exchange.piv.as_slice()),
+                    None,
+                )
+            }
+        };
+
+        let m = open(&self.recipient_key, resp, out, &nonce, exchange)?;
+        if let Some(seq) = seq {
+            self.replay.update(seq);
+        }
+
+        Ok(m)
+    }
+
+    /* Private */
+    fn protect_response_<I, O>(
+        &mut self,
+        resp: &Message<I>,
+        exchange: &Exchange,
+        out: Message<O, Unset>,
+        fresh: bool,
+    ) -> Result<Message<O>, Error>
+    where
+        I: AsSlice<Element = u8>,
+        O: AsMutSlice<Element = u8> + Truncate<u16>,
+    {
+        let mut option = Bytes::<{ 1 + MAX_PIV_LEN }>::empty();
+        let nonce = if fresh {
+            let piv = self.next_piv()?;
+            option.push(&[piv.len]);
+            option.push(piv.as_slice());
+
+            self.nonce(self.sender_id.as_slice(), piv.as_slice())
+        } else {
+            self.nonce(exchange.kid.as_slice(), exchange.piv.as_slice())
+        };
+
+        let code = if resp.get_observe().is_some() {
+            Response::Content
+        } else {
+            Response::Changed
+        };
+
+        seal(
+            &self.sender_key,
+            resp,
+            out,
+            code.into(),
+            option.as_slice(),
+            &nonce,
+            exchange,
+        )
+    }
+
+    fn next_piv(&mut self) -> Result<Bytes<MAX_PIV_LEN>, Error> {
+        let seq = self.sequence_number;
+        if seq > MAX_SEQUENCE_NUMBER {
+            return Err(Error::SequenceNumberExhausted);
+        }
+        self.sequence_number += 1;
+
+        // minimal big endian encoding; 0 is encoded as a single byte
+        let bytes = seq.to_be_bytes();
+        let zeros = (seq.leading_zeros() as usize / 8).min(7);
+        Ok(Bytes::new(&bytes[zeros..]).expect("unreachable"))
+    }
+
+    // RFC 8613 Section 5.2
+    fn nonce(&self, id_piv: &[u8], piv: &[u8]) -> [u8; NONCE_LEN] {
+        let mut nonce = [0; NONCE_LEN];
+        let id_end = NONCE_LEN - MAX_PIV_LEN;
+
+        nonce[0] = u8(id_piv.len()).unwrap();
+        nonce[id_end - id_piv.len()..id_end].copy_from_slice(id_piv);
+        nonce[NONCE_LEN - piv.len()..].copy_from_slice(piv);
+
+        for (byte, iv) in nonce.iter_mut().zip(self.common_iv.iter()) {
+            *byte ^= iv;
+        }
+
+        nonce
+    }
+}
+
+// Encrypts `msg` into `out`
+fn seal<I, O>(
+    key: &[u8; KEY_LEN],
+    msg: &Message<I>,
+    mut out: Message<O, Unset>,
+    code: Code,
+    option: &[u8],
+    nonce: &[u8; NONCE_LEN],
+    exchange: &Exchange,
+) -> Result<Message<O>, Error>
+where
+    I: AsSlice<Element = u8>,
+    O: AsMutSlice<Element = u8> + Truncate<u16>,
+{
+    assert_eq!(out.get_token_length(), msg.get_token_length());
+
+    out.set_type(msg.get_type());
+    out.set_message_id(msg.get_message_id());
+    out.token_mut().copy_from_slice(msg.token());
+    out.set_code(code);
+
+    let mut plaintext_len = 1;
+    let mut prev = 0;
+    for opt in msg.options() {
+        let number = opt.number();
+        match number {
+            OptionNumber::Oscore => return Err(Error::Malformed),
+            OptionNumber::ProxyUri => return Err(Error::ProxyUri),
+            _ => {}
+        }
+
+        if is_outer(number) {
+            out.add_option(number, opt.value())
+                .map_err(|_| Error::BufferTooSmall)?;
+        }
+
+        if is_inner(number) {
+            let nr = u16::from(number);
+            let mut buf = [0; 5];
+            plaintext_len += encode_header(nr - prev, u16(opt.value().len()).unwrap(), &mut buf)
+                .len()
+                + opt.value().len();
+            prev = nr;
+        }
+    }
+
+    out.add_option(OptionNumber::Oscore, option)
+        .map_err(|_| Error::BufferTooSmall)?;
+
+    let payload = msg.payload();
+    if !payload.is_empty() {
+        plaintext_len += 1 + payload.len();
+    }
+
+    if out.payload_capacity() < plaintext_len + TAG_LEN {
+        return Err(Error::BufferTooSmall);
+    }
+
+    let mut aad = [0; AAD_LEN];
+    let aad = enc_structure(exchange, &mut aad);
+    Ok(out.set_payload_with(|buf| {
+        buf[0] = msg.get_code().0;
+        let mut cursor = 1;
+        let mut prev = 0;
+        for opt in msg.options().filter(|opt| is_inner(opt.number())) {
+            let nr = u16::from(opt.number());
+            let value = opt.value();
+            let mut hbuf = [0; 5];
+            let header = encode_header(nr - prev, u16(value.len()).unwrap(), &mut hbuf);
+            prev = nr;
+
+            for chunk in &[header, value] {
+                buf[cursor..cursor + chunk.len()].copy_from_slice(chunk);
+                cursor += chunk.len();
+            }
+        }
+
+        if !payload.is_empty() {
+            buf[cursor] = PAYLOAD_MARKER;
+            cursor += 1;
+            buf[cursor..cursor + payload.len()].copy_from_slice(payload);
+            cursor += payload.len();
+        }
+        debug_assert_eq!(cursor, plaintext_len);
+
+        let (plaintext, rest) = buf.split_at_mut(cursor);
+        let tag = AesCcm::new(GenericArray::from_slice(key))
+            .encrypt_in_place_detached(GenericArray::from_slice(nonce), aad, plaintext)
+            .expect("unreachable");
+        rest[..TAG_LEN].copy_from_slice(&tag);
+
+        cursor + TAG_LEN
+    }))
+}
+
+// Decrypts `msg` (in place) and writes the plain message into `out`
+fn open<B, O>(
+    key: &[u8; KEY_LEN],
+    msg: &mut Message<B>,
+    mut out: Message<O, Unset>,
+    nonce: &[u8; NONCE_LEN],
+    exchange: &Exchange,
+) -> Result<Message<O>, Error>
+where
+    B: AsMutSlice<Element = u8>,
+    O: AsMutSlice<Element = u8> + Truncate<u16>,
+{
+    assert_eq!(out.get_token_length(), msg.get_token_length());
+
+    let mut aad = [0; AAD_LEN];
+    let aad = enc_structure(exchange, &mut aad);
+
+    let len = msg.payload().len();
+    // at least the code must be present
+    if len < 1 + TAG_LEN {
+        return Err(Error::Malformed);
+    }
+
+    {
+        let (ciphertext, tag) = msg.payload_mut().split_at_mut(len - TAG_LEN);
+        AesCcm::new(GenericArray::from_slice(key))
+            .decrypt_in_place_detached(
+                GenericArray::from_slice(nonce),
+                aad,
+                ciphertext,
+                GenericArray::from_slice(tag),
+            )
+            .map_err(|_| Error::Decryption)?;
+    }
+
+    let plaintext = &msg.payload()[..len - TAG_LEN];
+    let code = Code(plaintext[0]);
+    let inner = &plaintext[1..];
+    let (options, payload) = match scan(inner).map_err(|_| Error::Malformed)?.1 {
+        Some(marker) => inner.split_at(usize(marker)),
+        None => (inner, &[][..]),
+    };
+    let payload = payload.get(1..).unwrap_or(&[]);
+
+    out.set_type(msg.get_type());
+    out.set_message_id(msg.get_message_id());
+    out.token_mut().copy_from_slice(msg.token());
+    out.set_code(code);
+
+    for opt in msg.options().filter(|opt| is_outer(opt.number())) {
+        // the inner Observe option is the authoritative one
+        if opt.number() != OptionNumber::Observe {
+            out.add_option(opt.number(), opt.value())
+                .map_err(|_| Error::BufferTooSmall)?;
+        }
+    }
+
+    for opt in (Options {
+        number: 0,
+        ptr: options,
+    }) {
+        if !is_inner(opt.number()) {
+            return Err(Error::Malformed);
+        }
+
+        out.add_option(opt.number(), opt.value())
+            .map_err(|_| Error::BufferTooSmall)?;
+    }
+
+    if out.payload_capacity() < payload.len() {
+        return Err(Error::BufferTooSmall);
+    }
+
+    Ok(out.set_payload(payload))
+}
+
+// Class U options (RFC 8613 Section 4.1); Observe is both Class E and U
+fn is_outer(number: OptionNumber) -> bool {
+    matches!(
+        number,
+        OptionNumber::UriHost
+            | OptionNumber::UriPort
+            | OptionNumber::ProxyScheme
+            | OptionNumber::Observe
+    )
+}
+
+// Class E options; unknown options are processed as Class E
+fn is_inner(number: OptionNumber) -> bool {
+    !matches!(
+        number,
+        OptionNumber::UriHost
+            | OptionNumber::UriPort
+            | OptionNumber::ProxyScheme
+            | OptionNumber::ProxyUri
+            | OptionNumber::Oscore
+    )
+}
+
+fn decode_piv(piv: &[u8]) -> u64 {
+    piv.iter().fold(0, |x, byte| x << 8 | u64::from(*byte))
+}
+
+// HKDF-Expand of a Sender Key, Recipient Key or Common IV (RFC 8613 Section 3.2.1)
+fn derive(hkdf: &Hkdf<Sha256>, id: &[u8], id_context: Option<&[u8]>, ty: &str, out: &mut [u8]) {
+    // info = [id: bstr, id_context: bstr / nil, alg_aead: int, type: tstr, L: uint]
+    let mut info = Bytes::<{ 3 + MAX_ID_LEN + 1 + MAX_ID_CONTEXT_LEN + 1 + 3 + 1 }>::empty();
+    info.push(&[0x85]);
+    info.push_bstr(id);
+    if let Some(id_context) = id_context {
+        info.push_bstr(id_context);
+    } else {
+        // null
+        info.push(&[0xf6]);
+    }
+    info.push(&[AES_CCM_16_64_128]);
+    info.push(&[0x60 | u8(ty.len()).unwrap()]);
+    info.push(ty.as_bytes());
+    info.push(&[u8(out.len()).unwrap()]);
+
+    hkdf.expand(info.as_slice(), out).expect("unreachable");
+}
+
+const AAD_LEN: usize = 32;
+
+// Additional Authenticated Data (RFC 8613 Section 5.4)
+fn enc_structure<'a>(exchange: &Exchange, buf: &'a mut [u8; AAD_LEN]) -> &'a [u8] {
+    // external_aad = bstr .cbor [
+    //   oscore_version: 1,
+    //   algorithms: [alg_aead: 10],
+    //   request_kid: bstr,
+    //   request_piv: bstr,
+    //   options: bstr (Class I options; none are defined),
+    // ]
+    let mut external_aad = Bytes::<{ 4 + 1 + MAX_ID_LEN + 1 + MAX_PIV_LEN + 1 }>::empty();
+    external_aad.push(&[0x85, 0x01, 0x81, AES_CCM_16_64_128]);
+    external_aad.push_bstr(exchange.kid.as_slice());
+    external_aad.push_bstr(exchange.piv.as_slice());
+    external_aad.push(&[0x40]);
+
+    // Enc_structure = ["Encrypt0", protected: h'', external_aad]
+    let mut aad = Bytes::<AAD_LEN>::empty();
+    aad.push(&[0x83, 0x68]);
+    aad.push(b"Encrypt0");
+    aad.push(&[0x40]);
+    aad.push_bstr(external_aad.as_slice());
+
+    let len = usize(aad.len);
+    buf[..len].copy_from_slice(aad.as_slice());
+    &buf[..len]
+}
+
+// The OSCORE option of a received message (RFC 8613 Section 6.1)
+struct OscoreOption<'a> {
+    piv: Option<&'a [u8]>,
+    kid_context: Option<&'a [u8]>,
+    kid: Option<&'a [u8]>,
+}
+
+impl<'a> OscoreOption<'a> {
+    fn find<B>(m: &'a Message<B>) -> Result<Self, Error>
+    where
+        B: AsSlice<Element = u8>,
+    {
+        let value = m
+            .options()
+            .find(|opt| opt.number() == OptionNumber::Oscore)
+            .ok_or(Error::NotProtected)?
+            .value();
+
+        let (flags, mut rest) = match value.split_first() {
+            Some((flags, rest)) => (*flags, rest),
+            // all flags unset
+            None => {
+                return Ok(OscoreOption {
+                    piv: None,
+                    kid_context: None,
+                    kid: None,
+                })
+            }
+        };
+
+        let n = usize(flags & flags::N);
+        if flags & flags::RESERVED != 0 || n > MAX_PIV_LEN || rest.len() < n {
+            return Err(Error::Malformed);
+        }
+
+        let piv = if n == 0 {
+            None
+        } else {
+            let (piv, tail) = rest.split_at(n);
+            rest = tail;
+            Some(piv)
+        };
+
+        let kid_context = if flags & flags::H != 0 {
+            let (s, tail) = rest.split_first().ok_or(Error::Malformed)?;
+            let s = usize(*s);
+            if tail.len() < s {
+                return Err(Error::Malformed);
+            }
+            let (kid_context, tail) = tail.split_at(s);
+            rest = tail;
+            Some(kid_context)
+        } else {
+            None
+        };
+
+        let kid = if flags & flags::K != 0 {
+            Some(rest)
+        } else if rest.is_empty() {
+            None
+        } else {
+            return Err(Error::Malformed);
+        };
+
+        Ok(OscoreOption {
+            piv,
+            kid_context,
+            kid,
+        })
+    }
+}
+
+// Sliding window over the received Partial IVs (RFC 8613 Section 7.4)
+struct ReplayWindow {
+    // highest sequence number received so far
+    highest: Option<u64>,
+    // bit `i` is set if `highest - i` has been received
+    bitmap: u32,
+}
+
+impl ReplayWindow {
+    fn new() -> Self {
+        ReplayWindow {
+            highest: None,
+            bitmap: 0,
+        }
+    }
+
+    fn is_fresh(&self, seq: u64) -> bool {
+        match self.highest {
+            None => true,
+            Some(highest) if seq > highest => true,
+            Some(highest) => {
+                let age = highest - seq;
+                age < REPLAY_WINDOW_SIZE && self.bitmap & (1 << age) == 0
+            }
+        }
+    }
+
+    fn update(&mut self, seq: u64) {
+        match self.highest {
+            Some(highest) if seq <= highest => self.bitmap |= 1 << (highest - seq),
+            Some(highest) => {
+                let shift = seq - highest;
+                self.bitmap = if shift < REPLAY_WINDOW_SIZE {
+                    self.bitmap << shift
+                } else {
+                    0
+                } | 1;
+                self.highest = Some(seq);
+            }
+            None => {
+                self.bitmap = 1;
+                self.highest = Some(seq);
+            }
+        }
+    }
+}
+
+// Short byte string with inline storage
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Bytes<const N: usize> {
+    buffer: [u8; N],
+    len: u8,
+}
+
+impl<const N: usize> Bytes<N> {
+    fn empty() -> Self {
+        Bytes {
+            buffer: [0; N],
+            len: 0,
+        }
+    }
+
+    fn new(bytes: &[u8]) -> Result<Self, ()> {
+        if bytes.len() > N {
+            return Err(());
+        }
+
+        let mut this = Self::empty();
+        this.push(bytes);
+        Ok(this)
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        &self.buffer[..usize(self.len)]
+    }
+
+    // NOTE panics if there's not enough space; all callers use statically sized buffers
+    fn push(&mut self, bytes: &[u8]) {
+        let start = usize(self.len);
+        let end = start + bytes.len();
+        self.buffer[start..end].copy_from_slice(bytes);
+        self.len = u8(end).unwrap();
+    }
+
+    // CBOR byte string; only lengths below 24 are needed
+    fn push_bstr(&mut self, bytes: &[u8]) {
+        debug_assert!(bytes.len() < 24);
+
+        self.push(&[0x40 | u8(bytes.len()).unwrap()]);
+        self.push(bytes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::coap::{self, Message};
+
+    use super::{Context, Error, Exchange, ReplayWindow};
+
+    const MASTER_SECRET: [u8; 16] = [
+        0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f,
+        0x10,
+    ];
+    const MASTER_SALT: [u8; 8] = [0x9e, 0x7c, 0xa9, 0x22, 0x23, 0x78, 0x63, 0x40];
+    const ID_CONTEXT: [u8; 8] = [0x37, 0xcb, 0xf3, 0x21, 0x00, 0x17, 0xa2, 0xd3];
+
+    // GET coap://localhost/tv1
+    const REQUEST: &[u8] = &[
+        0x44, 0x01, 0x5d, 0x1f, 0x00, 0x00, 0x39, 0x74, 0x39, 0x6c, 0x6f, 0x63, 0x61, 0x6c, 0x68,
+        0x6f, 0x73, 0x74, 0x83, 0x74, 0x76, 0x31,
+    ];
+
+    // 2.05 Content "Hello World!"
+    const RESPONSE: &[u8] = &[
+        0x64, 0x45, 0x5d, 0x1f, 0x00, 0x00, 0x39, 0x74, 0xff, 0x48, 0x65, 0x6c, 0x6c, 0x6f, 0x20,
+        0x57, 0x6f, 0x72, 0x6c, 0x64, 0x21,
+    ];
+
+    fn client() -> Context {
+        Context::new(&MASTER_SECRET, &MASTER_SALT, &[], &[0x01], None).unwrap()
+    }
+
+    fn server() -> Context {
+        Context::new(&MASTER_SECRET, &MASTER_SALT, &[0x01], &[], None).unwrap()
+    }
+
+    fn protect_request(ctx: &mut Context, buf: &mut [u8]) -> (usize, Exchange) {
+        ctx.set_sequence_number(20);
+
+        let req = Message::parse(REQUEST).unwrap();
+        let (m, exchange) = ctx
+            .protect_request(&req, Message::new(buf, req.get_token_length()))
+            .unwrap();
+        (m.len() as usize, exchange)
+    }
+
+    // RFC 8613 Appendix C.1
+    #[test]
+    fn derivation() {
+        let client = client();
+        assert_eq!(
+            client.sender_key,
+            [
+                0xf0, 0x91, 0x0e, 0xd7, 0x29, 0x5e, 0x6a, 0xd4, 0xb5, 0x4f, 0xc7, 0x93, 0x15, 0x43,
+                0x02, 0xff
+            ]
+        );
+        assert_eq!(
+            client.recipient_key,
+            [
+                0xff, 0xb1, 0x4e, 0x09, 0x3c, 0x94, 0xc9, 0xca, 0xc9, 0x47, 0x16, 0x48, 0xb4, 0xf9,
+                0x87, 0x10
+            ]
+        );
+        assert_eq!(
+            client.common_iv,
+            [0x46, 0x22, 0xd4, 0xdd, 0x6d, 0x94, 0x41, 0x68, 0xee, 0xfb, 0x54, 0x98, 0x7c]
+        );
+
+        let server = server();
+        assert_eq!(server.sender_key, client.recipient_key);
+        assert_eq!(server.recipient_key, client.sender_key);
+        assert_eq!(server.common_iv, client.common_iv);
+
+        // RFC 8613 Appendix C.2 (no Master Salt)
+        let client = Context::new(&MASTER_SECRET, &[], &[0x00], &[0x01], None).unwrap();
+        assert_eq!(
+            client.sender_key,
+            [
+                0x32, 0x1b, 0x26, 0x94, 0x32, 0x53, 0xc7, 0xff, 0xb6, 0x00, 0x3b, 0x0b, 0x64, 0xd7,
+                0x40, 0x41
+            ]
+        );
+        assert_eq!(
+            client.common_iv,
+            [0xbe, 0x35, 0xae, 0x29, 0x7d, 0x2d, 0xac, 0xe9, 0x10, 0xc5, 0x2e, 0x99, 0xf9]
+        );
+
+        // RFC 8613 Appendix C.3 (ID Context)
+        let client = Context::new(
+            &MASTER_SECRET,
+            &MASTER_SALT,
+            &[],
+            &[0x01],
+            Some(&ID_CONTEXT),
+        )
+        .unwrap();
+        assert_eq!(
+            client.sender_key,
+            [
+                0xaf, 0x2a, 0x13, 0x00, 0xa5, 0xe9, 0x57, 0x88, 0xb3, 0x56, 0x33, 0x6e, 0xee, 0xcd,
+                0x2b, 0x92
+            ]
+        );
+        assert_eq!(
+            client.common_iv,
+            [0x2c, 0xa5, 0x8f, 0xb8, 0x5f, 0xf1, 0xb8, 0x1c, 0x0b, 0x71, 0x81, 0xb8, 0x5e]
+        );
+    }
+
+    // RFC 8613 Appendix C.4
+    #[test]
+    fn request() {
+        let mut buf = [0; 64];
+        let (len, _) = protect_request(&mut client(), &mut buf);
+        assert_eq!(
+            &buf[..len],
+            &[
+                0x44, 0x02, 0x5d, 0x1f, 0x00, 0x00, 0x39, 0x74, 0x39, 0x6c, 0x6f, 0x63, 0x61, 0x6c,
+                0x68, 0x6f, 0x73, 0x74, 0x62, 0x09, 0x14, 0xff, 0x61, 0x2f, 0x10, 0x92, 0xf1, 0x77,
+                0x6f, 0x1c, 0x16, 0x68, 0xb3, 0x82, 0x5e,
+            ][..]
+        );
+
+        // the server recovers the original request
+        let mut server = server();
+        let mut m = Message::parse(&mut buf[..len]).unwrap();
+        let mut out = [0; 64];
+        let (req, _) = server
+            .unprotect_request(&mut m, Message::new(&mut out[..], 4))
+            .unwrap();
+        assert_eq!(req.as_bytes(), REQUEST);
+
+        // replayed
+        let mut buf = [0; 64];
+        let (len, _) = protect_request(&mut client(), &mut buf);
+        let mut m = Message::parse(&mut buf[..len]).unwrap();
+        assert_eq!(
+            server
+                .unprotect_request(&mut m, Message::new(&mut out[..], 4))
+                .err(),
+            Some(Error::Replay)
+        );
+    }
+
+    // RFC 8613 Appendix C.5 and C.6
+    #[test]
+    fn request_kid() {
+        let mut client = Context::new(&MASTER_SECRET, &[], &[0x00], &[0x01], None).unwrap();
+        let mut buf = [0; 64];
+        let (len, _) = protect_request(&mut client, &mut buf);
+        assert_eq!(
+            &buf[..len],
+            &[
+                0x44, 0x02, 0x5d, 0x1f, 0x00, 0x00, 0x39, 0x74, 0x39, 0x6c, 0x6f, 0x63, 0x61, 0x6c,
+                0x68, 0x6f, 0x73, 0x74, 0x63, 0x09, 0x14, 0x00, 0xff, 0x4e, 0xd3, 0x39, 0xa5, 0xa3,
+                0x79, 0xb0, 0xb8, 0xbc, 0x73, 0x1f, 0xff, 0xb0,
+            ][..]
+        );
+
+        let mut client = Context::new(
+            &MASTER_SECRET,
+            &MASTER_SALT,
+            &[],
+            &[0x01],
+            Some(&ID_CONTEXT),
+        )
+        .unwrap();
+        let (len, _) = protect_request(&mut client, &mut buf);
+        assert_eq!(
+            &buf[..len],
+            &[
+                0x44, 0x02, 0x5d, 0x1f, 0x00, 0x00, 0x39, 0x74, 0x39, 0x6c, 0x6f, 0x63, 0x61, 0x6c,
+                0x68, 0x6f, 0x73, 0x74, 0x6b, 0x19, 0x14, 0x08, 0x37, 0xcb, 0xf3, 0x21, 0x00, 0x17,
+                0xa2, 0xd3, 0xff, 0x72, 0xcd, 0x72, 0x73, 0xfd, 0x33, 0x1a, 0xc4, 0x5c, 0xff, 0xbe,
+                0x55, 0xc3,
+            ][..]
+        );
+    }
+
+    // RFC 8613 Appendix C.7 and C.8
+    #[test]
+    fn response() {
+        let mut client = client();
+        let mut buf = [0; 64];
+        let (_, exchange) = protect_request(&mut client, &mut buf);
+
+        let resp = Message::parse(RESPONSE).unwrap();
+        let mut server = server();
+
+        let mut buf = [0; 64];
+        let m = server
+            .protect_response(&resp, &exchange, Message::new(&mut buf[..], 4))
+            .unwrap();
+        let len = m.len() as usize;
+        assert_eq!(
+            &buf[..len],
+            &[
+                0x64, 0x44, 0x5d, 0x1f, 0x00, 0x00, 0x39, 0x74, 0x90, 0xff, 0xdb, 0xaa, 0xd1, 0xe9,
+                0xa7, 0xe7, 0xb2, 0xa8, 0x13, 0xd3, 0xc3, 0x15, 0x24, 0x37, 0x83, 0x03, 0xcd, 0xaf,
+                0xae, 0x11, 0x91, 0x06,
+            ][..]
+        );
+
+        let mut m = Message::parse(&mut buf[..len]).unwrap();
+        let mut out = [0; 64];
+        let plain = client
+            .unprotect_response(&mut m, &exchange, Message::new(&mut out[..], 4))
+            .unwrap();
+        assert_eq!(plain.as_bytes(), RESPONSE);
+
+        // with a Partial IV
+        let mut buf = [0; 64];
+        let m = server
+            .protect_response_(&resp, &exchange, Message::new(&mut buf[..], 4), true)
+            .unwrap();
+        let len = m.len() as usize;
+        assert_eq!(
+            &buf[..len],
+            &[
+                0x64, 0x44, 0x5d, 0x1f, 0x00, 0x00, 0x39, 0x74, 0x92, 0x01, 0x00, 0xff, 0x4d, 0x4c,
+                0x13, 0x66, 0x93, 0x84, 0xb6, 0x73, 0x54, 0xb2, 0xb6, 0x17, 0x5f, 0xf4, 0xb8, 0x65,
+                0x8c, 0x66, 0x6a, 0x6c, 0xf8, 0x8e,
+            ][..]
+        );
+
+        let mut m = Message::parse(&mut buf[..len]).unwrap();
+        let plain = client
+            .unprotect_response(&mut m, &exchange, Message::new(&mut out[..], 4))
+            .unwrap();
+        assert_eq!(plain.as_bytes(), RESPONSE);
+    }
+
+    #[test]
+    fn tampered() {
+        let mut client = client();
+        let mut server = server();
+
+        let mut buf = [0; 64];
+        let (len, _) = protect_request(&mut client, &mut buf);
+        buf[len - 1] ^= 1;
+
+        let mut m = Message::parse(&mut buf[..len]).unwrap();
+        let mut out = [0; 64];
+        let err = server
+            .unprotect_request(&mut m, Message::new(&mut out[..], 4))
+            .err()
+            .unwrap();
+        assert_eq!(err, Error::Decryption);
+        assert_eq!(err.response(), Some(coap::Response::BadRequest));
+
+        // not protected
+        let mut buf = [0; 22];
+        buf.copy_from_slice(REQUEST);
+        let mut m = Message::parse(&mut buf[..]).unwrap();
+        assert_eq!(
+            server
+                .unprotect_request(&mut m, Message::new(&mut out[..], 4))
+                .err(),
+            Some(Error::NotProtected)
+        );
+    }
+
+    #[test]
+    fn replay_window() {
+        let mut window = ReplayWindow::new();
+
+        assert!(window.is_fresh(5));
+        window.update(5);
+        assert!(!window.is_fresh(5));
+
+        // out of order, within the window
+        assert!(window.is_fresh(3));
+        window.update(3);
+        assert!(!window.is_fresh(3));
+        assert!(window.is_fresh(4));
+
+        window.update(40);
+        assert!(!window.is_fresh(5));
+        assert!(!window.is_fresh(8));
+        assert!(window.is_fresh(9));
+        assert!(!window.is_fresh(40));
+        assert!(window.is_fresh(41));
+    }
+}
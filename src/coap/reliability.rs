@@ -0,0 +1,485 @@
+//! Reliable messaging (RFC 7252 Section 4)
+//!
+//! `Transmitter` tracks the Confirmable messages that are waiting for an acknowledgement and
+//! tells the caller when to retransmit them (exponential back-off) and when to give up.
+//! `DedupCache` detects duplicated requests and replays the response that was sent the first time.
+//!
+//! There's no clock in this crate so the caller must pass the current time (`now`), *in
+//! milliseconds*, to the methods that need it. Timestamps are allowed to wrap around.
+//!
+//! # Example
+//!
+//! ```
+//! use jnet::coap::{self, reliability::Event, Transmitter};
+//!
+//! let mut transmitter = Transmitter::<1>::new();
+//!
+//! // t = 0 ms: a Confirmable message with ID 1 was sent; `0` is the random component of the
+//! // initial timeout (`ACK_TIMEOUT` in this case)
+//! transmitter.send(1, 0, 0).unwrap();
+//!
+//! assert_eq!(transmitter.poll(1_999), None);
+//! assert_eq!(transmitter.poll(2_000), Some(Event::Retransmit(1)));
+//!
+//! // the acknowledgement arrives
+//! let mut buf = [0; 4];
+//! let mut ack = coap::Message::new(&mut buf[..], 0);
+//! ack.set_type(coap::Type::Acknowledgement);
+//! ack.set_code(coap::Code::EMPTY);
+//! ack.set_message_id(1);
+//! let ack = ack.no_payload();
+//!
+//! assert_eq!(transmitter.received(&ack), Some(Event::Acknowledged(1)));
+//! assert!(transmitter.is_empty());
+//! ```
+
+use as_slice::AsSlice;
+
+use crate::coap::{Message, Type, ACK_TIMEOUT, EXCHANGE_LIFETIME, MAX_RETRANSMIT, NON_LIFETIME};
+
+// ACK_TIMEOUT * (ACK_RANDOM_FACTOR - 1)
+const ACK_TIMEOUT_SPREAD: u32 = ACK_TIMEOUT / 2;
+
+/// Events reported by a `Transmitter`
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Event {
+    /// The message with this ID was acknowledged
+    Acknowledged(u16),
+    /// The message with this ID was rejected with a Reset message
+    Reset(u16),
+    /// The message with this ID must be sent again
+    Retransmit(u16),
+    /// The message with this ID was never acknowledged; the transmitter gave up on it
+    Timeout(u16),
+}
+
+#[derive(Clone, Copy)]
+struct Pending {
+    message_id: u16,
+    // time of the next retransmission (or of the time out)
+    deadline: u32,
+    // current timeout
+    timeout: u32,
+    retransmissions: u8,
+}
+
+/// Tracks up to `N` outstanding Confirmable messages
+///
+/// The transmitter doesn't store the messages; the caller must keep them around until they are
+/// acknowledged or time out
+pub struct Transmitter<const N: usize> {
+    pending: [Option<Pending>; N],
+}
+
+impl<const N: usize> Transmitter<N> {
+    /// Creates a transmitter with no outstanding messages
+    pub const fn new() -> Self {
+        Transmitter { pending: [None; N] }
+    }
+
+    /// Registers a Confirmable message that was just sent
+    ///
+    /// `random` picks the initial timeout from the range `ACK_TIMEOUT..ACK_TIMEOUT *
+    /// ACK_RANDOM_FACTOR`; `0` maps to the start of the range and `u16::MAX` to its end.
+    ///
+    /// Returns an error if the transmitter is full or if a message with the same ID is still
+    /// outstanding
+    pub fn send(&mut self, message_id: u16, now: u32, random: u16) -> Result<(), ()> {
+        if self.is_pending(message_id) {
+            return Err(());
+        }
+
+        let slot = self
+            .pending
+            .iter_mut()
+            .find(|slot| slot.is_none())
+            .ok_or(())?;
+
+        let timeout = ACK_TIMEOUT + (ACK_TIMEOUT_SPREAD * u32::from(random)) / u32::from(u16::MAX);
+        *slot = Some(Pending {
+            message_id,
+            deadline: now.wrapping_add(timeout),
+            timeout,
+            retransmissions: 0,
+        });
+
+        Ok(())
+    }
+
+    /// Advances the retransmission timers
+    ///
+    /// Returns `Event::Retransmit` if a message must be sent again, `Event::Timeout` if a message
+    /// was dropped after `MAX_RETRANSMIT` retransmissions, or `None` if there's nothing to do.
+    /// Call this method until it returns `None`.
+    pub fn poll(&mut self, now: u32) -> Option<Event> {
+        for slot in self.pending.iter_mut() {
+            if let Some(pending) = slot.as_mut() {
+                if (now.wrapping_sub(pending.deadline) as i32) < 0 {
+                    continue;
+                }
+
+                let message_id = pending.message_id;
+                if pending.retransmissions >= MAX_RETRANSMIT {
+                    *slot = None;
+                    return Some(Event::Timeout(message_id));
+                }
+
+                pending.retransmissions += 1;
+                pending.timeout *= 2;
+                pending.deadline = now.wrapping_add(pending.timeout);
+                return Some(Event::Retransmit(message_id));
+            }
+        }
+
+        None
+    }
+
+    /// Processes an incoming message
+    ///
+    /// Returns `Event::Acknowledged` or `Event::Reset` if `m` answers one of the outstanding
+    /// messages, and `None` otherwise
+    pub fn received<B>(&mut self, m: &Message<B>) -> Option<Event>
+    where
+        B: AsSlice<Element = u8>,
+    {
+        let event = match m.get_type() {
+            Type::Acknowledgement => Event::Acknowledged,
+            Type::Reset => Event::Reset,
+            _ => return None,
+        };
+
+        let message_id = m.get_message_id();
+        if self.cancel(message_id) {
+            Some(event(message_id))
+        } else {
+            None
+        }
+    }
+
+    /// Stops tracking the message with the given ID
+    ///
+    /// Returns `true` if the message was outstanding
+    pub fn cancel(&mut self, message_id: u16) -> bool {
+        if let Some(slot) = self
+            .pending
+            .iter_mut()
+            .find(|slot| slot.map(|p| p.message_id == message_id).unwrap_or(false))
+        {
+            *slot = None;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Checks if the message with the given ID is waiting for an acknowledgement
+    pub fn is_pending(&self, message_id: u16) -> bool {
+        self.iter().any(|p| p.message_id == message_id)
+    }
+
+    /// Returns the time at which `poll` must be called next, or `None` if there are no
+    /// outstanding messages
+    pub fn next_deadline(&self, now: u32) -> Option<u32> {
+        self.iter()
+            .map(|p| p.deadline)
+            .min_by_key(|deadline| deadline.wrapping_sub(now) as i32)
+    }
+
+    /// Returns the number of outstanding messages
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    /// Returns `true` if there are no outstanding messages
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /* Private */
+    fn iter(&self) -> impl Iterator<Item = &Pending> {
+        self.pending.iter().filter_map(|slot| slot.as_ref())
+    }
+}
+
+impl<const N: usize> Default for Transmitter<N> {
+    fn default() -> Self {
+        Transmitter::new()
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Exchange<E, const S: usize>
+where
+    E: Copy,
+{
+    endpoint: E,
+    message_id: u16,
+    // time at which the exchange can be forgotten
+    expires: u32,
+    response: [u8; S],
+    // `None` if the response didn't fit in `response`
+    response_length: Option<u16>,
+}
+
+/// Remembers the last `N` requests and their responses (up to `S` bytes long)
+///
+/// `E` is the type used to identify the endpoint of a client, e.g. an (IP address, port) pair.
+/// Requests are remembered for `EXCHANGE_LIFETIME` (Confirmable) or `NON_LIFETIME`
+/// (Non-confirmable); when the cache is full the exchange closest to expiring is forgotten.
+pub struct DedupCache<E, const N: usize, const S: usize>
+where
+    E: Copy,
+{
+    exchanges: [Option<Exchange<E, S>>; N],
+}
+
+impl<E, const N: usize, const S: usize> DedupCache<E, N, S>
+where
+    E: Copy + PartialEq,
+{
+    /// Creates an empty cache
+    pub const fn new() -> Self {
+        DedupCache {
+            exchanges: [None; N],
+        }
+    }
+
+    /// Checks if `req`, sent by `endpoint`, is a duplicate of a recent request
+    ///
+    /// Returns `None` if the request is new and must be processed. Otherwise returns the response
+    /// that was sent the first time, which must be sent again; the response is empty if it was
+    /// too large to be cached, in which case the duplicate should be ignored.
+    pub fn duplicate<B>(&mut self, endpoint: E, req: &Message<B>, now: u32) -> Option<&[u8]>
+    where
+        B: AsSlice<Element = u8>,
+    {
+        self.expire(now);
+
+        let message_id = req.get_message_id();
+        self.exchanges
+            .iter()
+            .filter_map(|slot| slot.as_ref())
+            .find(|e| e.endpoint == endpoint && e.message_id == message_id)
+            .map(|e| match e.response_length {
+                Some(len) => &e.response[..usize::from(len)],
+                None => &[][..],
+            })
+    }
+
+    /// Remembers that `req`, sent by `endpoint`, was answered with `resp`
+    pub fn insert<B>(&mut self, endpoint: E, req: &Message<B>, resp: &[u8], now: u32)
+    where
+        B: AsSlice<Element = u8>,
+    {
+        self.expire(now);
+
+        let message_id = req.get_message_id();
+        let lifetime = if req.get_type() == Type::Confirmable {
+            EXCHANGE_LIFETIME
+        } else {
+            NON_LIFETIME
+        };
+
+        let mut exchange = Exchange {
+            endpoint,
+            message_id,
+            expires: now.wrapping_add(lifetime),
+            response: [0; S],
+            response_length: None,
+        };
+        if resp.len() <= S {
+            exchange.response[..resp.len()].copy_from_slice(resp);
+            exchange.response_length = Some(resp.len() as u16);
+        }
+
+        let slot = if let Some(i) = self.exchanges.iter().position(|slot| {
+            slot.map(|e| e.endpoint == endpoint && e.message_id == message_id)
+                .unwrap_or(false)
+        }) {
+            // replace the previous exchange
+            i
+        } else if let Some(i) = self.exchanges.iter().position(|slot| slot.is_none()) {
+            i
+        } else {
+            // evict the exchange that's closest to expiring
+            let mut oldest = 0;
+            let mut remaining = u32::MAX;
+            for (i, slot) in self.exchanges.iter().enumerate() {
+                if let Some(e) = slot {
+                    let r = e.expires.wrapping_sub(now);
+                    if r < remaining {
+                        oldest = i;
+                        remaining = r;
+                    }
+                }
+            }
+            oldest
+        };
+
+        if let Some(slot) = self.exchanges.get_mut(slot) {
+            *slot = Some(exchange);
+        }
+    }
+
+    /// Returns the number of remembered exchanges
+    pub fn len(&self) -> usize {
+        self.exchanges.iter().filter(|slot| slot.is_some()).count()
+    }
+
+    /// Returns `true` if no exchanges are remembered
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /* Private */
+    fn expire(&mut self, now: u32) {
+        for slot in self.exchanges.iter_mut() {
+            if slot
+                .map(|e| (now.wrapping_sub(e.expires) as i32) >= 0)
+                .unwrap_or(false)
+            {
+                *slot = None;
+            }
+        }
+    }
+}
+
+impl<E, const N: usize, const S: usize> Default for DedupCache<E, N, S>
+where
+    E: Copy + PartialEq,
+{
+    fn default() -> Self {
+        DedupCache::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::coap;
+
+    use super::{DedupCache, Event, Transmitter};
+
+    fn message(buf: &mut [u8], ty: coap::Type, message_id: u16) -> coap::Message<&mut [u8]> {
+        let mut m = coap::Message::new(buf, 0);
+        m.set_type(ty);
+        m.set_code(coap::Method::Get);
+        m.set_message_id(message_id);
+        m.no_payload()
+    }
+
+    #[test]
+    fn backoff() {
+        let mut transmitter = Transmitter::<2>::new();
+
+        // initial timeout = 3 s
+        transmitter.send(1, 0, u16::MAX).unwrap();
+        assert!(transmitter.send(1, 0, 0).is_err());
+        assert_eq!(transmitter.next_deadline(0), Some(3_000));
+
+        let mut now = 0;
+        let mut timeout = 3_000;
+        for _ in 0..coap::MAX_RETRANSMIT {
+            assert_eq!(transmitter.poll(now + timeout - 1), None);
+            now += timeout;
+            assert_eq!(transmitter.poll(now), Some(Event::Retransmit(1)));
+            assert_eq!(transmitter.poll(now), None);
+            timeout *= 2;
+        }
+
+        assert_eq!(transmitter.poll(now + timeout), Some(Event::Timeout(1)));
+        assert!(transmitter.is_empty());
+        // 3 + 6 + 12 + 24 + 48
+        assert_eq!(now + timeout, coap::MAX_TRANSMIT_WAIT);
+    }
+
+    #[test]
+    fn matching() {
+        let mut transmitter = Transmitter::<2>::new();
+        transmitter.send(1, 0, 0).unwrap();
+        transmitter.send(2, 0, 0).unwrap();
+        assert!(transmitter.send(3, 0, 0).is_err());
+
+        let mut buf = [0; 4];
+        // requests and unrelated acknowledgements are ignored
+        assert_eq!(
+            transmitter.received(&message(&mut buf, coap::Type::Confirmable, 1)),
+            None
+        );
+        assert_eq!(
+            transmitter.received(&message(&mut buf, coap::Type::Acknowledgement, 3)),
+            None
+        );
+
+        assert_eq!(
+            transmitter.received(&message(&mut buf, coap::Type::Reset, 2)),
+            Some(Event::Reset(2))
+        );
+        assert_eq!(
+            transmitter.received(&message(&mut buf, coap::Type::Acknowledgement, 1)),
+            Some(Event::Acknowledged(1))
+        );
+        // duplicated acknowledgement
+        assert_eq!(
+            transmitter.received(&message(&mut buf, coap::Type::Acknowledgement, 1)),
+            None
+        );
+        assert_eq!(transmitter.next_deadline(0), None);
+    }
+
+    #[test]
+    fn wrap_around() {
+        let mut transmitter = Transmitter::<1>::new();
+        let now = u32::MAX - 1_000;
+        transmitter.send(1, now, 0).unwrap();
+
+        assert_eq!(transmitter.poll(now.wrapping_add(1_999)), None);
+        assert_eq!(
+            transmitter.poll(now.wrapping_add(2_000)),
+            Some(Event::Retransmit(1))
+        );
+    }
+
+    #[test]
+    fn dedup() {
+        let mut cache = DedupCache::<u16, 2, 8>::new();
+        let mut buf = [0; 4];
+
+        let req = message(&mut buf, coap::Type::Confirmable, 1);
+        assert_eq!(cache.duplicate(1234, &req, 0), None);
+        cache.insert(1234, &req, b"ack", 0);
+
+        assert_eq!(cache.duplicate(1234, &req, 1_000), Some(&b"ack"[..]));
+        // same message ID, different endpoint
+        assert_eq!(cache.duplicate(4321, &req, 1_000), None);
+
+        // too large to be cached
+        let req = message(&mut buf, coap::Type::NonConfirmable, 2);
+        cache.insert(1234, &req, &[0; 9], 0);
+        assert_eq!(cache.duplicate(1234, &req, 1_000), Some(&[][..]));
+
+        // NON exchanges are forgotten first
+        assert_eq!(cache.duplicate(1234, &req, coap::NON_LIFETIME), None);
+        assert_eq!(cache.len(), 1);
+
+        let req = message(&mut buf, coap::Type::Confirmable, 1);
+        assert_eq!(cache.duplicate(1234, &req, coap::EXCHANGE_LIFETIME), None);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn eviction() {
+        let mut cache = DedupCache::<u16, 2, 8>::new();
+        let mut buf = [0; 4];
+
+        for (mid, now) in [(1, 0), (2, 10), (3, 20)].iter().cloned() {
+            let req = message(&mut buf, coap::Type::Confirmable, mid);
+            cache.insert(1, &req, &[], now);
+        }
+
+        assert_eq!(cache.len(), 2);
+        let req = message(&mut buf, coap::Type::Confirmable, 1);
+        assert_eq!(cache.duplicate(1, &req, 30), None);
+        let req = message(&mut buf, coap::Type::Confirmable, 2);
+        assert_eq!(cache.duplicate(1, &req, 30), Some(&[][..]));
+    }
+}
@@ -0,0 +1,757 @@
+//! Resource routing for CoAP servers
+//!
+//! A `Server` dispatches requests to the handlers of a static list of `Resource`s. The server
+//! takes care of the errors that don't depend on the state of the resource: unknown paths (4.04),
+//! methods the resource doesn't support (4.05), unsupported content formats (4.15 and 4.06) and
+//! unrecognized critical options (4.02). It also serves `/.well-known/core` in CoRE Link Format.
+//!
+//! # Example
+//!
+//! ```
+//! use jnet::coap::{
+//!     self,
+//!     server::{Resource, Server},
+//! };
+//!
+//! fn door<'r>(
+//!     open: &mut bool,
+//!     _: &coap::Message<&[u8]>,
+//!     mut resp: coap::Message<&'r mut [u8], coap::Unset>,
+//! ) -> coap::Message<&'r mut [u8]> {
+//!     // the server has already set the response code to 2.05 Content
+//!     resp.add_content_format(coap::ContentFormat::TextPlain).unwrap();
+//!     resp.set_payload(if *open { b"open" } else { b"closed" })
+//! }
+//!
+//! static RESOURCES: &[Resource<'static, bool>] = &[Resource {
+//!     path: &["sensors", "door"],
+//!     methods: &[coap::Method::Get],
+//!     content_formats: &[coap::ContentFormat::TextPlain],
+//!     attributes: "rt=\"door\"",
+//!     handler: door,
+//! }];
+//!
+//! let server = Server::new(RESOURCES);
+//!
+//! let mut buf = [0; 32];
+//! let mut req = coap::Message::new(&mut buf[..], 1);
+//! req.set_code(coap::Method::Get);
+//! req.set_message_id(1);
+//! req.token_mut()[0] = 0x42;
+//! req.add_uri_path("sensors").unwrap();
+//! req.add_uri_path("door").unwrap();
+//! let req = req.no_payload();
+//! let req = coap::Message::parse(req.as_bytes()).unwrap();
+//!
+//! let mut buf = [0; 64];
+//! let resp = coap::Message::new(&mut buf[..], req.get_token_length());
+//! let resp = server.handle(&mut true, &req, resp).unwrap();
+//!
+//! assert_eq!(resp.get_type(), coap::Type::Acknowledgement);
+//! assert_eq!(resp.get_code(), coap::Response::Content.into());
+//! assert_eq!(resp.token(), &[0x42]);
+//! assert_eq!(resp.payload(), b"open");
+//! ```
+
+use core::fmt::{self, Write};
+
+use as_slice::{AsMutSlice, AsSlice};
+use owning_slice::Truncate;
+
+use crate::{
+    coap::{
+        block::{self, Block},
+        link, Code, ContentFormat, Message, Method, OptionNumber, Response, Type, Unset,
+    },
+    traits::TryFrom,
+};
+
+/// Path of the resource discovery entry point
+pub const WELL_KNOWN_CORE: &[&str] = &[".well-known", "core"];
+
+/// Size exponent of the blocks used when `/.well-known/core` doesn't fit in a single response
+// if the client doesn't request a smaller size
+const SZX: u8 = block::MAX_SZX;
+
+/// Size of the largest Block2 option: 1 byte of header (delta <= 12) + a 3-byte value
+const BLOCK2_OPTION_SIZE: usize = 4;
+
+/// A request handler
+///
+/// The handler receives the context passed to `Server::handle`, the request and a response
+/// whose header, token and code have already been filled by the server. The default code is
+/// 2.05 Content for GET, 2.04 Changed for POST and PUT, and 2.02 Deleted for DELETE; the handler
+/// can override it with `set_code`.
+pub type Handler<C> =
+    for<'r> fn(&mut C, &Message<&[u8]>, Message<&'r mut [u8], Unset>) -> Message<&'r mut [u8]>;
+
+/// A resource hosted by a `Server`
+pub struct Resource<'a, C> {
+    /// Path of the resource, one element per Uri-Path segment
+    pub path: &'a [&'a str],
+    /// Methods the resource supports
+    pub methods: &'a [Method],
+    /// Content formats the resource accepts and produces
+    ///
+    /// An empty list disables the Content-Format and Accept checks
+    pub content_formats: &'a [ContentFormat],
+    /// Extra CoRE Link Format attributes, e.g. `rt="temperature-c";if="sensor"`
+    ///
+    /// The `ct` attribute is generated from `content_formats` and must not be included here
+    pub attributes: &'a str,
+    /// The function that handles requests to this resource
+    pub handler: Handler<C>,
+}
+
+/// A CoAP server
+///
+/// `C` is the type of the context that's passed to all the request handlers
+pub struct Server<'a, C> {
+    resources: &'a [Resource<'a, C>],
+}
+
+impl<'a, C> Server<'a, C> {
+    /// Creates a server that hosts the given `resources`
+    pub const fn new(resources: &'a [Resource<'a, C>]) -> Self {
+        Server { resources }
+    }
+
+    /// Returns the resources hosted by this server
+    pub fn resources(&self) -> &'a [Resource<'a, C>] {
+        self.resources
+    }
+
+    /// Handles a request and builds the response
+    ///
+    /// See `prepare_response` for the messages that are not dispatched to a resource: this returns
+    /// a Reset message for a CoAP ping and `None` for the messages that must be ignored.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the token length of `resp` doesn't match the token length of `req`
+    pub fn handle<'r>(
+        &self,
+        ctx: &mut C,
+        req: &Message<&[u8]>,
+        resp: Message<&'r mut [u8], Unset>,
+    ) -> Option<Message<&'r mut [u8]>> {
+        let resp = match prepare_response(req, resp) {
+            Ok(resp) => resp,
+            Err(reply) => return reply,
+        };
+
+        Some(self.dispatch(ctx, req, resp))
+    }
+
+    /* Private */
+    fn dispatch<'r>(
+        &self,
+        ctx: &mut C,
+        req: &Message<&[u8]>,
+        mut resp: Message<&'r mut [u8], Unset>,
+    ) -> Message<&'r mut [u8]> {
+        let code = req.get_code();
+
+        // RFC 7252 - Section 5.4.1. unrecognized critical options
+        if req.options().any(|opt| match opt.number() {
+            // OSCORE protected requests must be unprotected before they reach the server
+            OptionNumber::Unknown(_) | OptionNumber::Oscore => opt.number().is_critical(),
+            _ => false,
+        }) {
+            return error(resp, Response::BadOption);
+        }
+
+        // RFC 7252 - Section 5.10.1. Uri-Path and Uri-Query values are UTF-8 strings
+        if req.uri_path().chain(req.uri_query()).any(|s| s.is_err()) {
+            return error(resp, Response::BadRequest);
+        }
+
+        let method = match Method::try_from(code) {
+            Ok(method) => method,
+            Err(_) => return error(resp, Response::MethodNotAllowed),
+        };
+
+        if req.uri_path().eq(WELL_KNOWN_CORE.iter().cloned().map(Ok)) {
+            return if method == Method::Get {
+                self.well_known_core(req, resp)
+            } else {
+                error(resp, Response::MethodNotAllowed)
+            };
+        }
+
+        let resource = match self
+            .resources
+            .iter()
+            .find(|resource| req.uri_path().eq(resource.path.iter().cloned().map(Ok)))
+        {
+            Some(resource) => resource,
+            None => return error(resp, Response::NotFound),
+        };
+
+        if !resource.methods.contains(&method) {
+            return error(resp, Response::MethodNotAllowed);
+        }
+
+        if !resource.content_formats.is_empty() {
+            if let Some(format) = req.content_format() {
+                if !resource.content_formats.contains(&format) {
+                    return error(resp, Response::UnsupportedContentFormat);
+                }
+            }
+
+            if let Some(format) = req.accept() {
+                if !resource.content_formats.contains(&format) {
+                    return error(resp, Response::NotAcceptable);
+                }
+            }
+        }
+
+        resp.set_code(match method {
+            Method::Get | Method::Fetch => Response::Content,
+            Method::Post | Method::Put => Response::Changed,
+            Method::Delete => Response::Deleted,
+        });
+
+        (resource.handler)(ctx, req, resp)
+    }
+
+    /// Writes the CoRE Link Format description of the hosted resources
+    pub fn write_links<W>(&self, w: &mut W) -> fmt::Result
+    where
+        W: Write,
+    {
+        let mut writer = link::Writer::new(w);
+        for resource in self.resources {
+            let link = writer.link(Path(resource.path))?;
+
+            let link = match resource.content_formats {
+                [] => link,
+                [format] => link.token("ct", u16::from(*format))?,
+                formats => link.attribute("ct", Formats(formats))?,
+            };
+
+            link.raw(resource.attributes)?;
+        }
+
+        Ok(())
+    }
+
+    fn well_known_core<'r>(
+        &self,
+        req: &Message<&[u8]>,
+        mut resp: Message<&'r mut [u8], Unset>,
+    ) -> Message<&'r mut [u8]> {
+        if let Some(format) = req.accept() {
+            if format != ContentFormat::ApplicationLinkFormat {
+                return error(resp, Response::NotAcceptable);
+            }
+        }
+
+        resp.set_code(Response::Content);
+        if resp
+            .add_content_format(ContentFormat::ApplicationLinkFormat)
+            .is_err()
+        {
+            return error(resp, Response::InternalServerError);
+        }
+
+        let mut counter = Window::new(&mut [], 0);
+        self.write_links(&mut counter).ok();
+        let total = counter.total;
+
+        let request = req.get_block2();
+        let range = if request.is_none() && total <= resp.payload_capacity() {
+            0..total
+        } else {
+            // the document doesn't fit in a single response (or the client asked for a specific
+            // block); pick the largest block size that fits
+            let room = resp.payload_capacity().saturating_sub(BLOCK2_OPTION_SIZE);
+            let mut szx = request.map(|block| block.szx()).unwrap_or(SZX);
+            while szx > 0 && usize::from(Block::new(0, false, szx).size()) > room {
+                szx -= 1;
+            }
+
+            if usize::from(Block::new(0, false, szx).size()) > room {
+                resp.remove_option(OptionNumber::ContentFormat);
+                return error(resp, Response::InternalServerError);
+            }
+
+            let (block, range) = match block::select(total, request, szx) {
+                Some(selection) => selection,
+                None => {
+                    resp.remove_option(OptionNumber::ContentFormat);
+                    return error(resp, Response::BadOption);
+                }
+            };
+
+            if resp.add_block2(block).is_err() {
+                resp.remove_option(OptionNumber::ContentFormat);
+                return error(resp, Response::InternalServerError);
+            }
+
+            range
+        };
+
+        resp.set_payload_with(|buf| {
+            let len = range.end - range.start;
+            let mut window = Window::new(&mut buf[..len], range.start);
+            self.write_links(&mut window).ok();
+            len
+        })
+    }
+}
+
+/// What to send back when an incoming message is not dispatched: the Reset message that answers
+/// a CoAP ping, or nothing
+pub type Reply<'r> = Option<Message<&'r mut [u8]>>;
+
+/// Starts the response to an incoming message
+///
+/// If `req` is a Confirmable or Non-confirmable request this sets the type of the response
+/// (piggybacked Acknowledgement or Non-confirmable, respectively), its Message ID and its token,
+/// and returns it so the caller can dispatch the request. Otherwise this returns:
+///
+/// - `Err(Some(rst))` if `req` is a Confirmable empty message (CoAP ping); `rst` is the empty
+///   Reset message that answers it (RFC 7252 Section 4.3)
+/// - `Err(None)` if `req` must be silently ignored (see `is_ignored`)
+///
+/// # Panics
+///
+/// This function panics if the token length of `resp` doesn't match the token length of `req`
+pub fn prepare_response<'r>(
+    req: &Message<&[u8]>,
+    mut resp: Message<&'r mut [u8], Unset>,
+) -> Result<Message<&'r mut [u8], Unset>, Reply<'r>> {
+    assert_eq!(resp.get_token_length(), req.get_token_length());
+
+    if is_ignored(req) {
+        return Err(None);
+    }
+
+    if req.get_code() == Code::EMPTY {
+        resp.set_type(Type::Reset);
+        resp.set_code(Code::EMPTY);
+        resp.set_message_id(req.get_message_id());
+        return Err(Some(resp.no_payload()));
+    }
+
+    resp.set_type(if req.get_type() == Type::Confirmable {
+        Type::Acknowledgement
+    } else {
+        Type::NonConfirmable
+    });
+    resp.set_message_id(req.get_message_id());
+    resp.token_mut().copy_from_slice(req.token());
+
+    Ok(resp)
+}
+
+/// Returns `true` if no reply must be sent to the incoming message `m`
+///
+/// These messages are ignored: Acknowledgement and Reset messages, responses, empty messages
+/// other than CoAP pings and requests carried in an Acknowledgement or Reset message. A caller
+/// that has to set up the response buffer before calling `Server::handle` can use this function
+/// to skip that work.
+pub fn is_ignored(m: &Message<&[u8]>) -> bool {
+    let code = m.get_code();
+
+    if code == Code::EMPTY {
+        // RFC 7252 - Section 4.1. an empty message has no token, options or payload
+        let is_ping = m.get_type() == Type::Confirmable
+            && m.get_token_length() == 0
+            && m.options().next().is_none()
+            && m.payload().is_empty();
+
+        !is_ping
+    } else {
+        match m.get_type() {
+            Type::Confirmable | Type::NonConfirmable => !code.is_request(),
+            Type::Acknowledgement | Type::Reset => true,
+        }
+    }
+}
+
+// formats a path as a URI reference
+struct Path<'a>(&'a [&'a str]);
+
+impl fmt::Display for Path<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            return f.write_char('/');
+        }
+
+        for segment in self.0 {
+            write!(f, "/{}", segment)?;
+        }
+
+        Ok(())
+    }
+}
+
+// formats a list of content formats as a space-separated list of numbers
+struct Formats<'a>(&'a [ContentFormat]);
+
+impl fmt::Display for Formats<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, format) in self.0.iter().enumerate() {
+            if i != 0 {
+                f.write_char(' ')?;
+            }
+            write!(f, "{}", u16::from(*format))?;
+        }
+
+        Ok(())
+    }
+}
+
+fn error<B>(mut resp: Message<B, Unset>, code: Response) -> Message<B>
+where
+    B: AsSlice<Element = u8> + AsMutSlice<Element = u8> + Truncate<u16>,
+{
+    resp.set_code(code);
+    resp.no_payload()
+}
+
+/// A `fmt::Write` sink that keeps the bytes in the range `skip..skip + buffer.len()` of the
+/// output and counts the total number of bytes written
+struct Window<'a> {
+    buffer: &'a mut [u8],
+    skip: usize,
+    total: usize,
+}
+
+impl<'a> Window<'a> {
+    fn new(buffer: &'a mut [u8], skip: usize) -> Self {
+        Window {
+            buffer,
+            skip,
+            total: 0,
+        }
+    }
+}
+
+impl Write for Window<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            if let Some(i) = self.total.checked_sub(self.skip) {
+                if let Some(slot) = self.buffer.get_mut(i) {
+                    *slot = byte;
+                }
+            }
+
+            self.total += 1;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::coap::{self, block::Block};
+
+    use super::{is_ignored, Resource, Server};
+
+    fn led<'r>(
+        state: &mut bool,
+        req: &coap::Message<&[u8]>,
+        resp: coap::Message<&'r mut [u8], coap::Unset>,
+    ) -> coap::Message<&'r mut [u8]> {
+        if req.get_code() == coap::Method::Put.into() {
+            *state = req.payload() == b"on";
+            resp.no_payload()
+        } else {
+            resp.set_payload(if *state { b"on" } else { b"off" })
+        }
+    }
+
+    static RESOURCES: &[Resource<'static, bool>] = &[
+        Resource {
+            path: &["led"],
+            methods: &[coap::Method::Get, coap::Method::Put],
+            content_formats: &[coap::ContentFormat::TextPlain],
+            attributes: "rt=\"light\"",
+            handler: led,
+        },
+        Resource {
+            path: &["config", "led"],
+            methods: &[coap::Method::Get],
+            content_formats: &[
+                coap::ContentFormat::TextPlain,
+                coap::ContentFormat::ApplicationJson,
+            ],
+            attributes: "",
+            handler: led,
+        },
+    ];
+
+    fn request<'a>(
+        buf: &'a mut [u8],
+        method: coap::Method,
+        path: &str,
+        f: impl FnOnce(&mut coap::Message<&mut [u8], coap::Unset>),
+        payload: &[u8],
+    ) -> coap::Message<&'a [u8]> {
+        let mut m = coap::Message::new(&mut buf[..], 2);
+        m.set_type(coap::Type::Confirmable);
+        m.set_code(method);
+        m.set_message_id(0xbeef);
+        m.token_mut().copy_from_slice(&[1, 2]);
+        for segment in path.split('/') {
+            m.add_uri_path(segment).unwrap();
+        }
+        f(&mut m);
+        let len = m.set_payload(payload).len();
+        coap::Message::parse(&buf[..usize::from(len)]).unwrap()
+    }
+
+    #[test]
+    fn dispatch() {
+        let server = Server::new(RESOURCES);
+        let mut state = false;
+
+        let mut buf = [0; 64];
+        let req = request(&mut buf, coap::Method::Put, "led", |_| {}, b"on");
+        let mut buf = [0; 64];
+        let resp = coap::Message::new(&mut buf[..], 2);
+        let resp = server.handle(&mut state, &req, resp).unwrap();
+        assert!(state);
+        assert_eq!(resp.get_type(), coap::Type::Acknowledgement);
+        assert_eq!(resp.get_message_id(), 0xbeef);
+        assert_eq!(resp.token(), &[1, 2]);
+        assert_eq!(resp.get_code(), coap::Response::Changed.into());
+
+        let mut buf = [0; 64];
+        let req = request(&mut buf, coap::Method::Get, "led", |_| {}, b"");
+        let mut buf = [0; 64];
+        let resp = coap::Message::new(&mut buf[..], 2);
+        let resp = server.handle(&mut state, &req, resp).unwrap();
+        assert_eq!(resp.get_code(), coap::Response::Content.into());
+        assert_eq!(resp.payload(), b"on");
+    }
+
+    #[test]
+    fn errors() {
+        let server = Server::new(RESOURCES);
+        let mut state = false;
+
+        let mut check = |method, path, f: fn(&mut coap::Message<&mut [u8], coap::Unset>), code| {
+            let mut buf = [0; 64];
+            let req = request(&mut buf, method, path, f, b"");
+            let mut buf = [0; 64];
+            let resp = coap::Message::new(&mut buf[..], 2);
+            let resp = server.handle(&mut state, &req, resp).unwrap();
+            assert_eq!(resp.get_code(), coap::Code::from(code));
+            assert!(resp.payload().is_empty());
+        };
+
+        check(coap::Method::Get, "door", |_| {}, coap::Response::NotFound);
+        check(
+            coap::Method::Get,
+            "config",
+            |_| {},
+            coap::Response::NotFound,
+        );
+        check(
+            coap::Method::Delete,
+            "led",
+            |_| {},
+            coap::Response::MethodNotAllowed,
+        );
+        check(
+            coap::Method::Put,
+            "led",
+            |m| {
+                m.add_content_format(coap::ContentFormat::ApplicationJson)
+                    .unwrap()
+            },
+            coap::Response::UnsupportedContentFormat,
+        );
+        check(
+            coap::Method::Get,
+            "led",
+            |m| m.add_accept(coap::ContentFormat::ApplicationXml).unwrap(),
+            coap::Response::NotAcceptable,
+        );
+        check(
+            coap::Method::Get,
+            "led",
+            |m| {
+                m.add_option(coap::OptionNumber::Unknown(65001), b"")
+                    .unwrap()
+            },
+            coap::Response::BadOption,
+        );
+        check(
+            coap::Method::Put,
+            ".well-known/core",
+            |_| {},
+            coap::Response::MethodNotAllowed,
+        );
+        // Uri-Path and Uri-Query values must be UTF-8
+        check(
+            coap::Method::Get,
+            "led",
+            |m| m.add_option(coap::OptionNumber::UriPath, &[0xff]).unwrap(),
+            coap::Response::BadRequest,
+        );
+        check(
+            coap::Method::Get,
+            "led",
+            |m| {
+                m.add_option(coap::OptionNumber::UriQuery, &[0xc3, 0x28])
+                    .unwrap()
+            },
+            coap::Response::BadRequest,
+        );
+    }
+
+    #[test]
+    fn ping() {
+        let server = Server::new(RESOURCES);
+
+        let mut buf = [0; 4];
+        let mut m = coap::Message::new(&mut buf[..], 0);
+        m.set_type(coap::Type::Confirmable);
+        m.set_code(coap::Code::EMPTY);
+        m.set_message_id(7);
+        let len = m.no_payload().len();
+        let req = coap::Message::parse(&buf[..usize::from(len)]).unwrap();
+        assert!(!is_ignored(&req));
+
+        let mut buf = [0; 16];
+        let resp = coap::Message::new(&mut buf[..], 0);
+        let resp = server.handle(&mut false, &req, resp).unwrap();
+        assert_eq!(resp.get_type(), coap::Type::Reset);
+        assert_eq!(resp.get_code(), coap::Code::EMPTY);
+        assert_eq!(resp.get_message_id(), 7);
+        assert_eq!(resp.get_token_length(), 0);
+        assert_eq!(resp.as_bytes().len(), 4);
+    }
+
+    #[test]
+    fn ignore() {
+        let server = Server::new(RESOURCES);
+        let mut state = false;
+
+        let mut check = |ty, code: coap::Code, token: &[u8]| {
+            let mut buf = [0; 16];
+            let mut m = coap::Message::new(&mut buf[..], token.len() as u8);
+            m.set_type(ty);
+            m.set_code(code);
+            m.set_message_id(7);
+            m.token_mut().copy_from_slice(token);
+            let len = m.no_payload().len();
+            let req = coap::Message::parse(&buf[..usize::from(len)]).unwrap();
+            assert!(is_ignored(&req));
+
+            let mut buf = [0; 16];
+            let resp = coap::Message::new(&mut buf[..], req.get_token_length());
+            assert!(server.handle(&mut state, &req, resp).is_none());
+        };
+
+        // empty Acknowledgement and Reset
+        check(coap::Type::Acknowledgement, coap::Code::EMPTY, &[]);
+        check(coap::Type::Reset, coap::Code::EMPTY, &[]);
+        // Non-confirmable empty message
+        check(coap::Type::NonConfirmable, coap::Code::EMPTY, &[]);
+        // responses
+        check(
+            coap::Type::Acknowledgement,
+            coap::Response::Content.into(),
+            &[1],
+        );
+        check(
+            coap::Type::Confirmable,
+            coap::Response::Content.into(),
+            &[1],
+        );
+        // requests carried in Acknowledgement or Reset messages
+        check(coap::Type::Acknowledgement, coap::Method::Get.into(), &[1]);
+        check(coap::Type::Reset, coap::Method::Get.into(), &[1]);
+    }
+
+    #[test]
+    fn well_known_core() {
+        const LINKS: &[u8] = b"</led>;ct=0;rt=\"light\",</config/led>;ct=\"0 50\"";
+
+        let server = Server::new(RESOURCES);
+
+        let mut buf = [0; 64];
+        let req = request(&mut buf, coap::Method::Get, ".well-known/core", |_| {}, b"");
+        let mut buf = [0; 128];
+        let resp = coap::Message::new(&mut buf[..], 2);
+        let resp = server.handle(&mut false, &req, resp).unwrap();
+        assert_eq!(resp.get_code(), coap::Response::Content.into());
+        assert_eq!(
+            resp.content_format(),
+            Some(coap::ContentFormat::ApplicationLinkFormat)
+        );
+        assert_eq!(resp.get_block2(), None);
+        assert_eq!(resp.payload(), LINKS);
+
+        // the document doesn't fit; it's sent in 16-byte blocks
+        let mut body = [0; 64];
+        let mut len = 0;
+        let mut num = 0;
+        loop {
+            let mut buf = [0; 64];
+            let req = request(
+                &mut buf,
+                coap::Method::Get,
+                ".well-known/core",
+                |m| {
+                    if num != 0 {
+                        m.add_block2(Block::new(num, false, 0)).unwrap()
+                    }
+                },
+                b"",
+            );
+            let mut buf = [0; 30];
+            let resp = coap::Message::new(&mut buf[..], 2);
+            let resp = server.handle(&mut false, &req, resp).unwrap();
+
+            let block = resp.get_block2().unwrap();
+            assert_eq!(block.num(), num);
+            assert_eq!(block.size(), 16);
+            body[len..len + resp.payload().len()].copy_from_slice(resp.payload());
+            len += resp.payload().len();
+
+            if !block.more() {
+                break;
+            }
+            num += 1;
+        }
+
+        assert_eq!(&body[..len], LINKS);
+
+        // the requested block number doesn't fit in 20 bits at the server's block size
+        let mut buf = [0; 64];
+        let req = request(
+            &mut buf,
+            coap::Method::Get,
+            ".well-known/core",
+            |m| {
+                m.add_option(coap::OptionNumber::Block2, &[0xff, 0xff, 0xf6])
+                    .unwrap()
+            },
+            b"",
+        );
+        let mut buf = [0; 128];
+        let resp = coap::Message::new(&mut buf[..], 2);
+        let resp = server.handle(&mut false, &req, resp).unwrap();
+        assert_eq!(resp.get_code(), coap::Response::BadOption.into());
+        assert_eq!(resp.content_format(), None);
+        assert!(resp.payload().is_empty());
+
+        // not even the smallest block fits in the response
+        let mut buf = [0; 64];
+        let req = request(&mut buf, coap::Method::Get, ".well-known/core", |_| {}, b"");
+        let mut buf = [0; 24];
+        let resp = coap::Message::new(&mut buf[..], 2);
+        let resp = server.handle(&mut false, &req, resp).unwrap();
+        assert_eq!(resp.get_code(), coap::Response::InternalServerError.into());
+        assert_eq!(resp.content_format(), None);
+        assert!(resp.payload().is_empty());
+    }
+}
@@ -0,0 +1,661 @@
+//! CoAP over TCP (RFC 8323)
+//!
+//! Over reliable transports the message has no Version, Type or Message ID fields. Instead the
+//! header carries the length of the message so that messages can be delimited in a byte stream:
+//!
+//! ``` text
+//! +--------+--------+-----------------+--------+---------+---------+----------+
+//! |  Len   |  TKL   | Extended Length |  Code  |  Token  | Options | Payload  |
+//! | 4 bits | 4 bits | 0/1/2/4 bytes   | 1 byte | 0..8 B  |   ...   | 0xFF ... |
+//! +--------+--------+-----------------+--------+---------+---------+----------+
+//! ```
+//!
+//! The options and the payload are encoded exactly like in the UDP variant. Signaling messages
+//! (codes 7.01 to 7.05) manage the connection.
+//!
+//! # Example
+//!
+//! ```
+//! use jnet::coap::{self, tcp};
+//!
+//! // answer a Ping with a Pong that echoes its token
+//! let mut buf = [0; 16];
+//! let mut ping = tcp::Message::new(&mut buf[..], 1);
+//! ping.set_code(tcp::Signal::Ping);
+//! ping.token_mut()[0] = 0x42;
+//! let ping = ping.no_payload();
+//! let ping = tcp::Message::parse(ping.as_bytes()).unwrap();
+//!
+//! let mut buf = [0; 16];
+//! let mut pong = tcp::Message::new(&mut buf[..], ping.get_token_length());
+//! pong.set_code(tcp::Signal::Pong);
+//! pong.token_mut().copy_from_slice(ping.token());
+//! let pong = pong.no_payload();
+//!
+//! assert_eq!(pong.as_bytes(), &[0x01, 0xe3, 0x42]);
+//! ```
+
+use core::{fmt, marker::PhantomData, option::Option as CoreOption, str};
+
+use as_slice::{AsMutSlice, AsSlice};
+use byteorder::{ByteOrder, NetworkEndian as NE};
+use cast::{u16, usize};
+use owning_slice::Truncate;
+
+use crate::{
+    coap::{
+        block, decode_uint, encode_header, encode_uint, no_response, observe, scan, Code,
+        ContentFormat, Error, Method, OptionNumber, Options, Position, Response, Set, Strings,
+        Unset, Values, NO_PAYLOAD, PAYLOAD_MARKER,
+    },
+    traits::{TryFrom, UncheckedIndex},
+};
+
+/* Header format */
+const LEN_TKL: usize = 0;
+
+mod tkl {
+    pub const MASK: u8 = (1 << SIZE) - 1;
+    pub const OFFSET: u8 = 0;
+    pub const SIZE: u8 = 4;
+}
+
+mod len {
+    pub const MASK: u8 = (1 << SIZE) - 1;
+    pub const OFFSET: u8 = super::tkl::OFFSET + super::tkl::SIZE;
+    pub const SIZE: u8 = 4;
+}
+
+// The Extended Length field is an 8-bit unsigned integer
+const LEN8: u8 = 13;
+const OFFSET8: u32 = 13;
+
+// The Extended Length field is a 16-bit unsigned integer
+const LEN16: u8 = 14;
+const OFFSET16: u32 = 269;
+
+// The Extended Length field is a 32-bit unsigned integer
+const LEN32: u8 = 15;
+const OFFSET32: u32 = 65805;
+
+/// Default value of the Max-Message-Size option
+pub const DEFAULT_MAX_MESSAGE_SIZE: u32 = 1152;
+
+/* Signaling option numbers */
+/// Max-Message-Size option (CSM)
+pub const MAX_MESSAGE_SIZE: u16 = 2;
+
+/// Block-Wise-Transfer option (CSM)
+pub const BLOCK_WISE_TRANSFER: u16 = 4;
+
+/// Custody option (Ping and Pong)
+pub const CUSTODY: u16 = 2;
+
+/// Alternative-Address option (Release)
+pub const ALTERNATIVE_ADDRESS: u16 = 2;
+
+/// Hold-Off option (Release)
+pub const HOLD_OFF: u16 = 4;
+
+/// Bad-CSM-Option option (Abort)
+pub const BAD_CSM_OPTION: u16 = 2;
+
+/// Returns the length of the message at the start of `bytes`
+///
+/// Use this to delimit messages in a byte stream. Returns `None` if `bytes` doesn't contain the
+/// whole header yet.
+pub fn message_length(bytes: &[u8]) -> CoreOption<usize> {
+    let head = *bytes.first()?;
+    let ext = ext_size(get!(head, len));
+    let ext_bytes = bytes.get(1..1 + ext)?;
+    let body = body_length(get!(head, len), ext_bytes);
+
+    // Len/TKL + Extended Length + Code + Token + Options & Payload
+    usize(body).checked_add(2 + ext + usize::from(get!(head, tkl)))
+}
+
+/// CoAP over TCP message
+// NOTE Invariants
+// - Same as the UDP variant: options are always valid
+// - While the payload is `Unset` the Len nibble is set to `LEN32`, i.e. space for the largest
+//   Extended Length field is reserved. The header is compacted when the payload is set.
+pub struct Message<BUFFER, PAYLOAD = Set>
+where
+    BUFFER: AsSlice<Element = u8>,
+    PAYLOAD: 'static,
+{
+    _payload: PhantomData<PAYLOAD>,
+    buffer: BUFFER,
+    // Position of the `PAYLOAD_MARKER`; see the UDP variant
+    marker: u16,
+    /// Highest option number stored in the Options field
+    number: u16,
+}
+
+impl<B, P> Message<B, P>
+where
+    B: AsSlice<Element = u8>,
+{
+    /* Getters */
+    /// Returns the Token Length (TKL) field of the header
+    pub fn get_token_length(&self) -> u8 {
+        get!(self.as_slice()[LEN_TKL], tkl)
+    }
+
+    /// Returns the Code field of the header
+    pub fn get_code(&self) -> Code {
+        Code(self.as_slice()[self.code_index()])
+    }
+
+    /// View into the Token field of the header
+    pub fn token(&self) -> &[u8] {
+        let start = self.code_index() + 1;
+        let end = start + usize(self.get_token_length());
+        unsafe { self.as_slice().r(start..end) }
+    }
+
+    /// Returns the byte representation of this message
+    pub fn as_bytes(&self) -> &[u8] {
+        if typeid!(P == Unset) {
+            unsafe { self.buffer.as_slice().rt(..usize(self.marker)) }
+        } else {
+            self.buffer.as_slice()
+        }
+    }
+
+    /// Returns the length (header + data) of the message
+    pub fn len(&self) -> u16 {
+        u16(self.as_bytes().len()).unwrap()
+    }
+
+    option_getters!();
+
+    /// Returns the value of the Max-Message-Size option of a CSM message, if present
+    ///
+    /// If the option is absent the default value, `DEFAULT_MAX_MESSAGE_SIZE`, applies
+    pub fn max_message_size(&self) -> CoreOption<u32> {
+        self.signal_option(Signal::Csm, MAX_MESSAGE_SIZE)
+            .and_then(decode_uint)
+    }
+
+    /// Checks if a CSM message has the Block-Wise-Transfer option
+    pub fn block_wise_transfer(&self) -> bool {
+        self.signal_option(Signal::Csm, BLOCK_WISE_TRANSFER)
+            .is_some()
+    }
+
+    /// Checks if a Ping or Pong message has the Custody option
+    pub fn custody(&self) -> bool {
+        self.signal_option(Signal::Ping, CUSTODY).is_some()
+            || self.signal_option(Signal::Pong, CUSTODY).is_some()
+    }
+
+    /// Returns the value of the Alternative-Address option of a Release message, if present
+    pub fn alternative_address(&self) -> CoreOption<&str> {
+        self.signal_option(Signal::Release, ALTERNATIVE_ADDRESS)
+            .and_then(|value| str::from_utf8(value).ok())
+    }
+
+    /// Returns the value of the Hold-Off option (in seconds) of a Release message, if present
+    pub fn hold_off(&self) -> CoreOption<u32> {
+        self.signal_option(Signal::Release, HOLD_OFF)
+            .and_then(decode_uint)
+    }
+
+    /// Returns the value of the Bad-CSM-Option option of an Abort message, if present
+    pub fn bad_csm_option(&self) -> CoreOption<u16> {
+        self.signal_option(Signal::Abort, BAD_CSM_OPTION)
+            .and_then(decode_uint)
+            .and_then(|x| u16(x).ok())
+    }
+
+    /* Private */
+    fn as_slice(&self) -> &[u8] {
+        self.buffer.as_slice()
+    }
+
+    fn code_index(&self) -> usize {
+        1 + ext_size(get!(self.as_slice()[LEN_TKL], len))
+    }
+
+    /// Returns the index at which the options start
+    fn options_start(&self) -> u8 {
+        (self.code_index() + 1) as u8 + self.get_token_length()
+    }
+
+    // Returns the value of the first `number` option, if this is a `signal` message
+    fn signal_option(&self, signal: Signal, number: u16) -> CoreOption<&[u8]> {
+        if self.get_code() != signal.into() {
+            return None;
+        }
+
+        self.options()
+            .find(|opt| u16::from(opt.number()) == number)
+            .map(|opt| opt.value())
+    }
+
+    unsafe fn unchecked(buffer: B) -> Self {
+        Message {
+            _payload: PhantomData,
+            buffer,
+            marker: NO_PAYLOAD,
+            number: 0,
+        }
+    }
+}
+
+impl<B, P> Message<B, P>
+where
+    B: AsMutSlice<Element = u8>,
+{
+    /* Setters */
+    /// Sets the Code field of the header
+    pub fn set_code<C>(&mut self, code: C)
+    where
+        C: Into<Code>,
+    {
+        let i = self.code_index();
+        self.as_mut_slice()[i] = code.into().0;
+    }
+
+    /// Mutable view into the Token field
+    pub fn token_mut(&mut self) -> &mut [u8] {
+        let start = self.code_index() + 1;
+        let end = start + usize(self.get_token_length());
+        unsafe { self.as_mut_slice().rm(start..end) }
+    }
+
+    /* Private */
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        self.buffer.as_mut_slice()
+    }
+}
+
+impl<B> Message<B, Set>
+where
+    B: AsSlice<Element = u8>,
+{
+    /* Public constructors */
+    /// Parses bytes into a CoAP over TCP message
+    ///
+    /// `bytes` must contain exactly one message; see `message_length`
+    pub fn parse(bytes: B) -> Result<Self, B> {
+        let len = bytes.as_slice().len();
+
+        match message_length(bytes.as_slice()) {
+            Some(n) if n == len && len <= usize::from(u16::MAX) => {}
+            _ => return Err(bytes),
+        }
+
+        let m = unsafe { Message::<B, Set>::unchecked(bytes) };
+        if m.get_token_length() > 8 {
+            // reserved
+            return Err(m.buffer);
+        }
+
+        let opts_start = m.options_start();
+        let bytes = m.buffer;
+        if let Ok((number, marker)) = unsafe { scan(bytes.as_slice().rf(usize(opts_start)..)) } {
+            Ok(Message {
+                _payload: PhantomData,
+                buffer: bytes,
+                number,
+                marker: marker
+                    .map(|m| m + u16::from(opts_start))
+                    .unwrap_or(NO_PAYLOAD),
+            })
+        } else {
+            Err(bytes)
+        }
+    }
+
+    /// View into the payload
+    pub fn payload(&self) -> &[u8] {
+        if self.marker == NO_PAYLOAD {
+            &[]
+        } else {
+            unsafe { self.as_slice().rf(usize(self.marker + 1)..) }
+        }
+    }
+}
+
+impl<B> Message<B, Set>
+where
+    B: AsMutSlice<Element = u8>,
+{
+    /// Mutable view into the payload
+    pub fn payload_mut(&mut self) -> &mut [u8] {
+        if self.marker == NO_PAYLOAD {
+            &mut []
+        } else {
+            let start = self.marker + 1;
+            unsafe { self.as_mut_slice().rfm(usize(start)..) }
+        }
+    }
+}
+
+impl<B> Message<B, Unset>
+where
+    B: AsMutSlice<Element = u8>,
+{
+    /* Constructors */
+    /// Transforms the given buffer into a CoAP over TCP message
+    ///
+    /// This constructor sets the Token Length field to `token_length`
+    ///
+    /// NOTE The message will span the whole buffer. The message can't be longer than 65535 bytes
+    ///
+    /// # Panics
+    ///
+    /// This constructor panics if
+    ///
+    /// - `token_length` is NOT in the range `0..=8`.
+    /// - The buffer is not large enough to contain the header and the token
+    pub fn new(buffer: B, token_length: u8) -> Self {
+        assert!(token_length <= 8);
+
+        unsafe {
+            let mut m = Message::unchecked(buffer);
+            // reserve space for the largest Extended Length field
+            let mut head = 0;
+            set!(head, len, LEN32);
+            set!(head, tkl, token_length);
+            m.as_mut_slice()[LEN_TKL] = head;
+            m.marker = u16::from(m.options_start());
+            assert!(m.as_slice().len() >= usize(m.marker));
+            m
+        }
+    }
+
+    /// Creates a signaling message with no token
+    ///
+    /// # Panics
+    ///
+    /// This constructor panics if the buffer is not large enough to contain the header
+    pub fn signal(buffer: B, signal: Signal) -> Self {
+        let mut m = Message::new(buffer, 0);
+        m.set_code(signal);
+        m
+    }
+
+    option_setters!();
+
+    /// Adds a Max-Message-Size option to this (CSM) message
+    ///
+    /// Returns an error if there's no space in the message to add the option
+    pub fn add_max_message_size(&mut self, size: u32) -> Result<(), Error> {
+        let mut buf = [0; 4];
+        let value = encode_uint(size, &mut buf);
+        self.add_option(MAX_MESSAGE_SIZE.into(), value)
+    }
+
+    /// Adds a Block-Wise-Transfer option to this (CSM) message
+    ///
+    /// Returns an error if there's no space in the message to add the option
+    pub fn add_block_wise_transfer(&mut self) -> Result<(), Error> {
+        self.add_option(BLOCK_WISE_TRANSFER.into(), &[])
+    }
+
+    /// Adds a Custody option to this (Ping or Pong) message
+    ///
+    /// Returns an error if there's no space in the message to add the option
+    pub fn add_custody(&mut self) -> Result<(), Error> {
+        self.add_option(CUSTODY.into(), &[])
+    }
+
+    /// Adds an Alternative-Address option to this (Release) message
+    ///
+    /// Returns an error if there's no space in the message to add the option
+    pub fn add_alternative_address(&mut self, address: &str) -> Result<(), Error> {
+        self.add_option(ALTERNATIVE_ADDRESS.into(), address.as_bytes())
+    }
+
+    /// Adds a Hold-Off option (in seconds) to this (Release) message
+    ///
+    /// Returns an error if there's no space in the message to add the option
+    pub fn add_hold_off(&mut self, seconds: u32) -> Result<(), Error> {
+        let mut buf = [0; 4];
+        let value = encode_uint(seconds, &mut buf);
+        self.add_option(HOLD_OFF.into(), value)
+    }
+
+    /// Adds a Bad-CSM-Option option to this (Abort) message
+    ///
+    /// Returns an error if there's no space in the message to add the option
+    pub fn add_bad_csm_option(&mut self, number: u16) -> Result<(), Error> {
+        let mut buf = [0; 4];
+        let value = encode_uint(u32::from(number), &mut buf);
+        self.add_option(BAD_CSM_OPTION.into(), value)
+    }
+}
+
+impl<B> Message<B, Unset>
+where
+    B: AsMutSlice<Element = u8> + Truncate<u16>,
+{
+    /// Fills the payload with the given data and adjusts the length of the message
+    pub fn set_payload(mut self, data: &[u8]) -> Message<B> {
+        if data.is_empty() {
+            return self.no_payload();
+        }
+
+        let marker = usize(self.marker);
+        let end = marker + 1 + data.len();
+        let bytes = self.as_mut_slice();
+        bytes[marker] = PAYLOAD_MARKER;
+        bytes[marker + 1..end].copy_from_slice(data);
+
+        self.finish(end, true)
+    }
+
+    /// Finishing constructing this message by leaving the payload empty and truncating the message
+    pub fn no_payload(self) -> Message<B> {
+        let end = usize(self.marker);
+        self.finish(end, false)
+    }
+
+    // Writes the length of the message into the header, shrinking the Extended Length field as
+    // much as possible
+    fn finish(mut self, end: usize, payload: bool) -> Message<B> {
+        let start = usize(self.options_start());
+        let body = (end - start) as u32;
+
+        let (len4, ext) = if body < OFFSET8 {
+            (body as u8, 0)
+        } else if body < OFFSET16 {
+            (LEN8, 1)
+        } else if body < OFFSET32 {
+            (LEN16, 2)
+        } else {
+            (LEN32, 4)
+        };
+
+        // Code + Token + Options + Payload move closer to the Len/TKL byte
+        let gap = 4 - ext;
+        let code = 1 + 4;
+        let bytes = self.as_mut_slice();
+        bytes.copy_within(code..end, code - gap);
+        set!(bytes[LEN_TKL], len, len4);
+        match ext {
+            0 => {}
+            1 => bytes[1] = (body - OFFSET8) as u8,
+            2 => NE::write_u16(&mut bytes[1..3], (body - OFFSET16) as u16),
+            _ => NE::write_u32(&mut bytes[1..5], body - OFFSET32),
+        }
+
+        self.buffer.truncate(u16(end - gap).unwrap());
+
+        Message {
+            _payload: PhantomData,
+            buffer: self.buffer,
+            marker: if payload {
+                self.marker - gap as u16
+            } else {
+                NO_PAYLOAD
+            },
+            number: self.number,
+        }
+    }
+}
+
+impl<B, P> fmt::Debug for Message<B, P>
+where
+    B: AsSlice<Element = u8>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut s = f.debug_struct("coap::tcp::Message");
+
+        let code = self.get_code();
+        if let Ok(method) = Method::try_from(code) {
+            s.field("code", &method);
+        } else if let Ok(resp) = Response::try_from(code) {
+            s.field("code", &resp);
+        } else if let Ok(signal) = Signal::try_from(code) {
+            s.field("code", &signal);
+        } else {
+            s.field("code", &code);
+        }
+
+        if !self.token().is_empty() {
+            s.field("token", &self.token());
+        }
+
+        s.field("options", &self.options().count());
+
+        s.finish()
+    }
+}
+
+code!(
+    /// Signaling Codes (RFC 8323 Section 11.1)
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum Signal {
+        /// Capabilities and Settings Message
+        Csm = (7, 1),
+        /// Ping
+        Ping = (7, 2),
+        /// Pong
+        Pong = (7, 3),
+        /// Release
+        Release = (7, 4),
+        /// Abort
+        Abort = (7, 5),
+    }
+);
+
+// Size of the Extended Length field for the given Len nibble
+fn ext_size(len4: u8) -> usize {
+    match len4 {
+        LEN8 => 1,
+        LEN16 => 2,
+        LEN32 => 4,
+        _ => 0,
+    }
+}
+
+// Length of Options + Payload
+fn body_length(len4: u8, ext: &[u8]) -> u32 {
+    match len4 {
+        LEN8 => u32::from(ext[0]) + OFFSET8,
+        LEN16 => u32::from(NE::read_u16(ext)) + OFFSET16,
+        LEN32 => NE::read_u32(ext).saturating_add(OFFSET32),
+        _ => u32::from(len4),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::coap::{self, tcp};
+
+    #[test]
+    fn parse() {
+        // GET /temperatures with an 8-bit Extended Length field
+        let bytes = [
+            0xd1, 0x00, 0x01, 0x42, 0xbc, b't', b'e', b'm', b'p', b'e', b'r', b'a', b't', b'u',
+            b'r', b'e', b's',
+        ];
+        assert_eq!(tcp::message_length(&bytes[..2]), Some(bytes.len()));
+        // truncated
+        assert!(tcp::Message::parse(&bytes[..bytes.len() - 1]).is_err());
+
+        let m = tcp::Message::parse(&bytes[..]).unwrap();
+        assert_eq!(m.get_code(), coap::Method::Get.into());
+        assert_eq!(m.token(), &[0x42]);
+        assert!(m.uri_path().eq(["temperatures"].iter().cloned().map(Ok)));
+        assert!(m.payload().is_empty());
+
+        // need more bytes
+        assert_eq!(tcp::message_length(&[]), None);
+        assert_eq!(tcp::message_length(&[0xe0, 0x00]), None);
+    }
+
+    #[test]
+    fn new() {
+        // (payload length, size of the Extended Length field)
+        for &(len, ext) in &[(0, 0), (8, 0), (9, 1), (264, 1), (265, 2), (1000, 2)] {
+            let mut buf = [0; 1100];
+            let mut m = tcp::Message::new(&mut buf[..], 2);
+            m.set_code(coap::Response::Content);
+            m.token_mut().copy_from_slice(&[1, 2]);
+            m.add_content_format(coap::ContentFormat::TextPlain)
+                .unwrap();
+            m.add_max_age(30).unwrap();
+            let m = m.set_payload(&[0xaa; 1000][..len]);
+
+            // Content-Format (1 byte) + Max-Age (2 bytes) + payload marker + payload
+            let body = if len == 0 { 3 } else { 4 + len };
+            assert_eq!(usize::from(m.len()), 1 + ext + 1 + 2 + body);
+            assert_eq!(
+                tcp::message_length(m.as_bytes()),
+                Some(usize::from(m.len()))
+            );
+
+            let m = tcp::Message::parse(m.as_bytes()).unwrap();
+            assert_eq!(m.get_code(), coap::Response::Content.into());
+            assert_eq!(m.token(), &[1, 2]);
+            assert_eq!(m.content_format(), Some(coap::ContentFormat::TextPlain));
+            assert_eq!(m.max_age(), Some(30));
+            assert_eq!(m.payload().len(), len);
+            assert!(m.payload().iter().all(|b| *b == 0xaa));
+        }
+    }
+
+    #[test]
+    fn signals() {
+        let mut buf = [0; 16];
+        let mut csm = tcp::Message::signal(&mut buf[..], tcp::Signal::Csm);
+        csm.add_block_wise_transfer().unwrap();
+        csm.add_max_message_size(2048).unwrap();
+        let csm = csm.no_payload();
+        // Max-Message-Size (2) is inserted before Block-Wise-Transfer (4)
+        assert_eq!(csm.as_bytes(), &[0x40, 0xe1, 0x22, 0x08, 0x00, 0x20]);
+
+        let csm = tcp::Message::parse(csm.as_bytes()).unwrap();
+        assert_eq!(csm.max_message_size(), Some(2048));
+        assert!(csm.block_wise_transfer());
+        // not a Release message
+        assert_eq!(csm.hold_off(), None);
+
+        let mut buf = [0; 48];
+        let mut release = tcp::Message::signal(&mut buf[..], tcp::Signal::Release);
+        release.add_hold_off(60).unwrap();
+        release
+            .add_alternative_address("coap+tcp://[2001:db8::1]")
+            .unwrap();
+        let release = release.no_payload();
+        let release = tcp::Message::parse(release.as_bytes()).unwrap();
+        assert_eq!(
+            release.alternative_address(),
+            Some("coap+tcp://[2001:db8::1]")
+        );
+        assert_eq!(release.hold_off(), Some(60));
+
+        let mut buf = [0; 32];
+        let mut abort = tcp::Message::signal(&mut buf[..], tcp::Signal::Abort);
+        abort.add_bad_csm_option(2).unwrap();
+        let abort = abort.set_payload(b"bad");
+        let abort = tcp::Message::parse(abort.as_bytes()).unwrap();
+        assert_eq!(abort.bad_csm_option(), Some(2));
+        assert_eq!(abort.payload(), b"bad");
+    }
+}
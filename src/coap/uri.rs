@@ -0,0 +1,476 @@
+//! `coap` and `coaps` URIs (RFC 7252 Section 6)
+//!
+//! `decompose` turns a URI into the Uri-Host, Uri-Port, Uri-Path and Uri-Query options of a
+//! request (Section 6.4) and `compose` writes the URI of a request back from those options
+//! (Section 6.5).
+//!
+//! # Example
+//!
+//! ```
+//! use jnet::coap::{self, uri};
+//!
+//! let mut buf = [0; 64];
+//! let mut req = coap::Message::new(&mut buf[..], 0);
+//! req.set_code(coap::Method::Get);
+//! let uri = "coap://[2001:db8::1]/sensors/temp%20c?unit=C";
+//! let endpoint = uri::decompose(uri, &mut req).unwrap();
+//! let req = req.no_payload();
+//!
+//! assert_eq!(endpoint.host, "2001:db8::1");
+//! assert_eq!(endpoint.port, coap::PORT);
+//! assert!(req.uri_path().eq(["sensors", "temp c"].iter().cloned().map(Ok)));
+//! assert!(req.uri_query().eq(["unit=C"].iter().cloned().map(Ok)));
+//!
+//! let mut s = String::new();
+//! uri::compose(&req, &endpoint, &mut s).unwrap();
+//! assert_eq!(s, uri);
+//! ```
+
+use core::fmt;
+
+use as_slice::{AsMutSlice, AsSlice};
+
+use crate::coap::{self, Message, OptionNumber, Unset};
+
+/// CoAP default DTLS port (`coaps` scheme)
+pub const SECURE_PORT: u16 = 5684;
+
+/// Maximum length of an option value
+const MAX_VALUE_LENGTH: usize = 255;
+
+/// The endpoint a request must be sent to
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Endpoint<'a> {
+    /// The host, as it appears in the URI; IP literals don't include the square brackets
+    pub host: &'a str,
+    /// The port
+    pub port: u16,
+    /// `true` for the `coaps` scheme
+    pub secure: bool,
+}
+
+/// URI decomposition error
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Error {
+    /// The URI is not an absolute `coap` or `coaps` URI
+    Scheme,
+    /// The URI has a fragment component
+    Fragment,
+    /// The host or the port are not valid
+    Authority,
+    /// The URI contains an invalid percent-encoding
+    PercentEncoding,
+    /// The options could not be added to the message
+    Message(coap::Error),
+}
+
+/// Adds the Uri-Host, Uri-Port, Uri-Path and Uri-Query options that correspond to `uri` to the
+/// request `m` (RFC 7252 Section 6.4)
+///
+/// Uri-Host is only added if the host is a name, not an IP address, and Uri-Port is only added if
+/// the port is not the default one. Returns the endpoint the request must be sent to.
+pub fn decompose<'u, B>(uri: &'u str, m: &mut Message<B, Unset>) -> Result<Endpoint<'u>, Error>
+where
+    B: AsMutSlice<Element = u8>,
+{
+    let colon = uri.find(':').ok_or(Error::Scheme)?;
+    let (scheme, rest) = (&uri[..colon], &uri[colon + 1..]);
+    let secure = if scheme.eq_ignore_ascii_case("coap") {
+        false
+    } else if scheme.eq_ignore_ascii_case("coaps") {
+        true
+    } else {
+        return Err(Error::Scheme);
+    };
+
+    if !rest.starts_with("//") {
+        return Err(Error::Scheme);
+    }
+    let rest = &rest[2..];
+
+    if rest.contains('#') {
+        return Err(Error::Fragment);
+    }
+
+    let end = rest.find(&['/', '?'][..]).unwrap_or(rest.len());
+    let (authority, rest) = (&rest[..end], &rest[end..]);
+    let (path, query) = match rest.find('?') {
+        Some(i) => (&rest[..i], Some(&rest[i + 1..])),
+        None => (rest, None),
+    };
+
+    // host [ ":" port ]
+    let (host, port, is_ip) = if authority.starts_with('[') {
+        let close = authority.find(']').ok_or(Error::Authority)?;
+        (&authority[1..close], &authority[close + 1..], true)
+    } else {
+        let end = authority.find(':').unwrap_or(authority.len());
+        let host = &authority[..end];
+        (host, &authority[end..], is_ipv4(host))
+    };
+
+    if host.is_empty() || host.contains('@') {
+        return Err(Error::Authority);
+    }
+
+    let default_port = if secure { SECURE_PORT } else { coap::PORT };
+    let port = match port {
+        "" | ":" => default_port,
+        _ if port.starts_with(':') && port[1..].bytes().all(|b| b.is_ascii_digit()) => {
+            port[1..].parse().map_err(|_| Error::Authority)?
+        }
+        _ => return Err(Error::Authority),
+    };
+
+    let mut buf = [0; MAX_VALUE_LENGTH];
+    if !is_ip {
+        let value = percent_decode(host, &mut buf)?;
+        value.make_ascii_lowercase();
+        m.add_checked_option(OptionNumber::UriHost, value)
+            .map_err(Error::Message)?;
+    }
+
+    if port != default_port {
+        m.add_uri_port(port).map_err(Error::Message)?;
+    }
+
+    if !path.is_empty() && path != "/" {
+        for segment in path[1..].split('/') {
+            let value = percent_decode(segment, &mut buf)?;
+            m.add_checked_option(OptionNumber::UriPath, value)
+                .map_err(Error::Message)?;
+        }
+    }
+
+    if let Some(query) = query {
+        for argument in query.split('&') {
+            let value = percent_decode(argument, &mut buf)?;
+            m.add_checked_option(OptionNumber::UriQuery, value)
+                .map_err(Error::Message)?;
+        }
+    }
+
+    Ok(Endpoint { host, port, secure })
+}
+
+/// Writes the URI of the request `m` that was sent to (or received on) `endpoint` (RFC 7252
+/// Section 6.5)
+///
+/// The Uri-Host and Uri-Port options take precedence over the host and port of `endpoint`
+pub fn compose<B, P, W>(m: &Message<B, P>, endpoint: &Endpoint<'_>, w: &mut W) -> fmt::Result
+where
+    B: AsSlice<Element = u8>,
+    W: fmt::Write,
+{
+    let (scheme, default_port) = if endpoint.secure {
+        ("coaps", SECURE_PORT)
+    } else {
+        ("coap", coap::PORT)
+    };
+    w.write_str(scheme)?;
+    w.write_str("://")?;
+
+    let host = m
+        .values(OptionNumber::UriHost)
+        .next()
+        .unwrap_or(endpoint.host.as_bytes());
+    if host.contains(&b':') {
+        // IPv6 literal
+        w.write_char('[')?;
+        percent_encode(host, w, |b| b == b':' || is_reg_name(b))?;
+        w.write_char(']')?;
+    } else {
+        percent_encode(host, w, is_reg_name)?;
+    }
+
+    let port = m.uri_port().unwrap_or(endpoint.port);
+    if port != default_port {
+        write!(w, ":{}", port)?;
+    }
+
+    let mut path = m.values(OptionNumber::UriPath).peekable();
+    if path.peek().is_none() {
+        w.write_char('/')?;
+    }
+    for segment in path {
+        w.write_char('/')?;
+        percent_encode(segment, w, is_pchar)?;
+    }
+
+    for (i, argument) in m.values(OptionNumber::UriQuery).enumerate() {
+        w.write_char(if i == 0 { '?' } else { '&' })?;
+        percent_encode(argument, w, |b| {
+            b != b'&' && (is_pchar(b) || b == b'/' || b == b'?')
+        })?;
+    }
+
+    Ok(())
+}
+
+fn is_ipv4(host: &str) -> bool {
+    let mut octets = 0;
+    for octet in host.split('.') {
+        // decimal octets without leading zeros
+        let valid = match octet.len() {
+            1 => true,
+            2 | 3 => !octet.starts_with('0'),
+            _ => false,
+        } && octet.bytes().all(|b| b.is_ascii_digit())
+            && octet.parse::<u8>().is_ok();
+
+        if !valid {
+            return false;
+        }
+
+        octets += 1;
+    }
+
+    octets == 4
+}
+
+fn percent_decode<'b>(s: &str, buf: &'b mut [u8; MAX_VALUE_LENGTH]) -> Result<&'b mut [u8], Error> {
+    let mut bytes = s.bytes();
+    let mut n = 0;
+
+    while let Some(byte) = bytes.next() {
+        let byte = if byte == b'%' {
+            let hi = bytes.next().and_then(hex).ok_or(Error::PercentEncoding)?;
+            let lo = bytes.next().and_then(hex).ok_or(Error::PercentEncoding)?;
+            hi << 4 | lo
+        } else {
+            byte
+        };
+
+        *buf.get_mut(n)
+            .ok_or(Error::Message(coap::Error::OptionLength))? = byte;
+        n += 1;
+    }
+
+    Ok(&mut buf[..n])
+}
+
+fn percent_encode<W>(bytes: &[u8], w: &mut W, is_allowed: impl Fn(u8) -> bool) -> fmt::Result
+where
+    W: fmt::Write,
+{
+    for &byte in bytes {
+        if is_allowed(byte) {
+            w.write_char(char::from(byte))?;
+        } else {
+            write!(w, "%{:02X}", byte)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn hex(digit: u8) -> Option<u8> {
+    char::from(digit).to_digit(16).map(|x| x as u8)
+}
+
+// RFC 3986 Section 2.3
+fn is_unreserved(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'-' || b == b'.' || b == b'_' || b == b'~'
+}
+
+// RFC 3986 Section 2.2
+fn is_sub_delim(b: u8) -> bool {
+    b"!$&'()*+,;=".contains(&b)
+}
+
+// RFC 3986 Section 3.2.2
+fn is_reg_name(b: u8) -> bool {
+    is_unreserved(b) || is_sub_delim(b)
+}
+
+// RFC 3986 Section 3.3
+fn is_pchar(b: u8) -> bool {
+    is_unreserved(b) || is_sub_delim(b) || b == b':' || b == b'@'
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coap::{self, Message, OptionNumber},
+        fmt::Buffer,
+    };
+
+    use super::{Endpoint, Error};
+
+    // decomposes `uri`, checks the options and composes the URI back
+    fn roundtrip(uri: &str, options: &[(OptionNumber, &[u8])], endpoint: Endpoint<'_>) -> Buffer {
+        let mut buf = [0; 128];
+        let mut m = Message::new(&mut buf[..], 0);
+        assert_eq!(super::decompose(uri, &mut m), Ok(endpoint));
+        let m = m.no_payload();
+
+        assert!(m
+            .options()
+            .map(|opt| (opt.number(), opt.value()))
+            .eq(options.iter().cloned()));
+
+        let mut s = Buffer::new();
+        super::compose(&m, &endpoint, &mut s).unwrap();
+        s
+    }
+
+    #[test]
+    fn rfc7252() {
+        // RFC 7252 Section 6.3: these three URIs are equivalent
+        let options: &[(OptionNumber, &[u8])] = &[
+            (OptionNumber::UriHost, b"example.com"),
+            (OptionNumber::UriPath, b"~sensors"),
+            (OptionNumber::UriPath, b"temp.xml"),
+        ];
+
+        for uri in &[
+            "coap://example.com:5683/~sensors/temp.xml",
+            "coap://EXAMPLE.com/%7Esensors/temp.xml",
+            "coap://EXAMPLE.com:/%7esensors/temp.xml",
+        ] {
+            let host = &uri[7..18];
+            let endpoint = Endpoint {
+                host,
+                port: coap::PORT,
+                secure: false,
+            };
+
+            assert_eq!(
+                roundtrip(uri, options, endpoint).as_str(),
+                "coap://example.com/~sensors/temp.xml"
+            );
+        }
+    }
+
+    #[test]
+    fn addresses() {
+        let endpoint = Endpoint {
+            host: "192.168.1.1",
+            port: 61616,
+            secure: false,
+        };
+        assert_eq!(
+            roundtrip(
+                "coap://192.168.1.1:61616",
+                &[(OptionNumber::UriPort, &[0xf0, 0xb0])],
+                endpoint
+            )
+            .as_str(),
+            "coap://192.168.1.1:61616/"
+        );
+
+        let endpoint = Endpoint {
+            host: "fe80::1",
+            port: super::SECURE_PORT,
+            secure: true,
+        };
+        assert_eq!(
+            roundtrip("coaps://[fe80::1]/", &[], endpoint).as_str(),
+            "coaps://[fe80::1]/"
+        );
+
+        // not an IPv4 address
+        let endpoint = Endpoint {
+            host: "192.168.1.01",
+            port: coap::PORT,
+            secure: false,
+        };
+        roundtrip(
+            "coap://192.168.1.01",
+            &[(OptionNumber::UriHost, b"192.168.1.01")],
+            endpoint,
+        );
+    }
+
+    #[test]
+    fn path_and_query() {
+        let endpoint = Endpoint {
+            host: "10.0.0.1",
+            port: coap::PORT,
+            secure: false,
+        };
+
+        assert_eq!(
+            roundtrip(
+                "coap://10.0.0.1/a%2Fb//c%20d/?x=1&y=%26&&z/?",
+                &[
+                    (OptionNumber::UriPath, b"a/b"),
+                    (OptionNumber::UriPath, b""),
+                    (OptionNumber::UriPath, b"c d"),
+                    (OptionNumber::UriPath, b""),
+                    (OptionNumber::UriQuery, b"x=1"),
+                    (OptionNumber::UriQuery, b"y=&"),
+                    (OptionNumber::UriQuery, b""),
+                    (OptionNumber::UriQuery, b"z/?"),
+                ],
+                endpoint
+            )
+            .as_str(),
+            "coap://10.0.0.1/a%2Fb//c%20d/?x=1&y=%26&&z/?"
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let mut buf = [0; 32];
+        let mut m = Message::new(&mut buf[..], 0);
+
+        assert_eq!(
+            super::decompose("http://example.com", &mut m),
+            Err(Error::Scheme)
+        );
+        assert_eq!(super::decompose("coap:/a", &mut m), Err(Error::Scheme));
+        assert_eq!(super::decompose("/a/b", &mut m), Err(Error::Scheme));
+        assert_eq!(
+            super::decompose("coap://10.0.0.1/a#b", &mut m),
+            Err(Error::Fragment)
+        );
+        assert_eq!(super::decompose("coap:///a", &mut m), Err(Error::Authority));
+        assert_eq!(
+            super::decompose("coap://10.0.0.1:65536", &mut m),
+            Err(Error::Authority)
+        );
+        assert_eq!(
+            super::decompose("coap://10.0.0.1:+1", &mut m),
+            Err(Error::Authority)
+        );
+        assert_eq!(
+            super::decompose("coap://[::1", &mut m),
+            Err(Error::Authority)
+        );
+        assert_eq!(
+            super::decompose("coap://10.0.0.1/%2", &mut m),
+            Err(Error::PercentEncoding)
+        );
+        assert_eq!(
+            super::decompose("coap://10.0.0.1/%zz", &mut m),
+            Err(Error::PercentEncoding)
+        );
+        assert_eq!(
+            super::decompose("coap://10.0.0.1/a-long-path-that-does-not-fit", &mut m),
+            Err(Error::Message(coap::Error::BufferTooSmall))
+        );
+    }
+
+    #[test]
+    fn compose_options() {
+        let endpoint = Endpoint {
+            host: "10.0.0.1",
+            port: 1234,
+            secure: false,
+        };
+
+        // Uri-Host and Uri-Port override the endpoint; non-ASCII bytes are percent-encoded
+        let mut buf = [0; 64];
+        let mut m = Message::new(&mut buf[..], 0);
+        m.add_option(OptionNumber::UriHost, "bücher.example".as_bytes())
+            .unwrap();
+        m.add_uri_port(coap::PORT).unwrap();
+        m.add_uri_path("a b").unwrap();
+        let m = m.no_payload();
+
+        let mut s = Buffer::new();
+        super::compose(&m, &endpoint, &mut s).unwrap();
+        assert_eq!(s.as_str(), "coap://b%C3%BCcher.example/a%20b");
+    }
+}
@@ -31,3 +31,37 @@ where
         write!(f, "\"{}\"", self.0)
     }
 }
+
+/// A fixed capacity `fmt::Write` sink for the tests
+#[cfg(test)]
+pub struct Buffer {
+    bytes: [u8; 128],
+    len: usize,
+}
+
+#[cfg(test)]
+impl Buffer {
+    pub fn new() -> Self {
+        Buffer {
+            bytes: [0; 128],
+            len: 0,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.bytes[..self.len]).unwrap()
+    }
+}
+
+#[cfg(test)]
+impl fmt::Write for Buffer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        self.bytes
+            .get_mut(self.len..end)
+            .ok_or(fmt::Error)?
+            .copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
@@ -6,17 +6,18 @@
 //!
 //! [rfc]: https://tools.ietf.org/html/rfc792
 
-use core::fmt;
 use core::marker::PhantomData;
 use core::ops::{Range, RangeFrom};
+use core::{cmp, fmt};
 
 use as_slice::{AsMutSlice, AsSlice};
 use byteorder::{ByteOrder, NetworkEndian as NE};
-use cast::usize;
+use cast::{u16, usize};
+use owning_slice::Truncate;
 
 use crate::{
+    checksum,
     fmt::Hex,
-    ipv4,
     sealed::Echo,
     traits::{TryFrom, TryInto, UncheckedIndex},
     Invalid, Unknown, Valid,
@@ -50,6 +51,20 @@ pub enum EchoReply {}
 /// [Type State] The Echo Request type
 pub enum EchoRequest {}
 
+/// [Type State] The Time Exceeded type
+pub enum TimeExceeded {}
+
+/// Codes of the Time Exceeded message
+pub mod time_exceeded {
+    /// Time to live exceeded in transit
+    pub const TTL: u8 = 0;
+    /// Fragment reassembly time exceeded
+    pub const REASSEMBLY: u8 = 1;
+}
+
+/// Number of payload bytes of the discarded datagram that an ICMP error message carries
+pub const ERROR_PAYLOAD_SIZE: usize = 8;
+
 /* EchoRequest */
 impl<B> Message<B, EchoRequest, Invalid>
 where
@@ -69,6 +84,38 @@ where
     }
 }
 
+/* TimeExceeded */
+impl<B> Message<B, TimeExceeded, Invalid>
+where
+    B: AsSlice<Element = u8> + AsMutSlice<Element = u8> + Truncate<u16>,
+{
+    /* Constructors */
+    /// Transforms the input buffer into a Time Exceeded ICMP message
+    ///
+    /// `datagram` is the discarded datagram; the message carries its header plus the first
+    /// `ERROR_PAYLOAD_SIZE` bytes of its payload. `header_len` is the length of its header.
+    ///
+    /// # Panics
+    ///
+    /// This constructor panics if the given `buffer` is not large enough to contain the message
+    pub fn time_exceeded(buffer: B, code: u8, datagram: &[u8], header_len: usize) -> Self {
+        let original = &datagram[..cmp::min(datagram.len(), header_len + ERROR_PAYLOAD_SIZE)];
+        let len = usize(HEADER_SIZE) + original.len();
+        assert!(buffer.as_slice().len() >= len);
+
+        let mut packet: Message<B, Unknown, Invalid> = unsafe { Message::unchecked(buffer) };
+        packet.buffer.truncate(u16(len).unwrap());
+
+        packet.set_type(Type::TimeExceeded);
+        packet.set_code(code);
+        // unused
+        packet.header_mut_()[IDENT.start..SEQ_NO.end].copy_from_slice(&[0; 4]);
+        packet.payload_mut().copy_from_slice(original);
+
+        unsafe { Message::unchecked(packet.buffer) }
+    }
+}
+
 /* EchoReply OR EchoRequest */
 impl<B, E, C> Message<B, E, C>
 where
@@ -118,7 +165,7 @@ where
 
         let packet: Self = unsafe { Message::unchecked(bytes) };
 
-        if ipv4::verify_checksum(packet.as_bytes()) {
+        if checksum::verify(packet.as_bytes()) {
             Ok(packet)
         } else {
             Err(packet.buffer)
@@ -202,6 +249,21 @@ where
     }
 }
 
+impl<B, C> TryFrom<Message<B, Unknown, C>> for Message<B, TimeExceeded, C>
+where
+    B: AsSlice<Element = u8>,
+{
+    type Error = Message<B, Unknown, C>;
+
+    fn try_from(p: Message<B, Unknown, C>) -> Result<Self, Message<B, Unknown, C>> {
+        if p.get_type() == Type::TimeExceeded {
+            Ok(unsafe { Message::unchecked(p.buffer) })
+        } else {
+            Err(p)
+        }
+    }
+}
+
 impl<B, C> TryFrom<Message<B, Unknown, C>> for Message<B, EchoRequest, C>
 where
     B: AsSlice<Element = u8>,
@@ -312,7 +374,7 @@ where
 
     /// Updates the Checksum field of the header
     pub fn update_checksum(mut self) -> Message<B, T, Valid> {
-        let cksum = ipv4::compute_checksum(&self.as_bytes(), CHECKSUM.start);
+        let cksum = checksum::compute(self.as_bytes(), CHECKSUM.start);
         NE::write_u16(&mut self.header_mut_()[CHECKSUM], cksum);
 
         unsafe { Message::unchecked(self.buffer) }
@@ -382,11 +444,33 @@ full_range!(
         EchoReply = 0,
         /// Destination Unreachable
         DestinationUnreachable = 3,
+        /// Source Quench (deprecated)
+        SourceQuench = 4,
+        /// Redirect
+        Redirect = 5,
         /// Echo Request
         EchoRequest = 8,
+        /// Time Exceeded
+        TimeExceeded = 11,
+        /// Parameter Problem
+        ParameterProblem = 12,
     }
 );
 
+impl Type {
+    /// Checks if this is the type of an error message
+    pub fn is_error(&self) -> bool {
+        matches!(
+            *self,
+            Type::DestinationUnreachable
+                | Type::SourceQuench
+                | Type::Redirect
+                | Type::TimeExceeded
+                | Type::ParameterProblem
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use rand::{self, RngCore};
@@ -10,6 +10,19 @@
 //! - [RFC 2461: Neighbor Discovery for IP Version 6 (IPv6)][1]
 //!
 //! [1]: https://tools.ietf.org/html/rfc2461
+//!
+//! - [RFC 2710: Multicast Listener Discovery (MLD) for IPv6][2]
+//!
+//! [2]: https://tools.ietf.org/html/rfc2710
+//!
+//! - [RFC 3810: Multicast Listener Discovery Version 2 (MLDv2) for IPv6][3]
+//!
+//! [3]: https://tools.ietf.org/html/rfc3810
+//!
+//! - [RFC 6775: Neighbor Discovery Optimization for IPv6 over Low-Power Wireless Personal Area
+//!   Networks (6LoWPANs)][4]
+//!
+//! [4]: https://tools.ietf.org/html/rfc6775
 
 use core::{
     fmt,
@@ -19,10 +32,23 @@ use core::{
 
 use as_slice::{AsMutSlice, AsSlice};
 use byteorder::{ByteOrder, NetworkEndian as NE};
+use cast::u32;
 use owning_slice::Truncate;
 
 pub use crate::icmp::{EchoReply, EchoRequest};
+
+pub use self::lowpan_nd::{DuplicateAddressConfirmation, DuplicateAddressRequest};
+pub use self::mld::{
+    MulticastListenerDone, MulticastListenerQuery, MulticastListenerQueryV2,
+    MulticastListenerReport, MulticastListenerReportV2,
+};
+
+pub mod lowpan_nd;
+pub mod mld;
+pub mod ping;
+pub mod registration;
 use crate::{
+    checksum::Checksum,
     fmt::Quoted,
     ieee802154, ipv6, mac,
     sealed::Echo,
@@ -66,6 +92,26 @@ mod override_ {
 
 const TARGET: Range<usize> = 8..24;
 
+// RouterAdvertisement
+const CUR_HOP_LIMIT: usize = 4;
+const M_O: usize = 5;
+const ROUTER_LIFETIME: Range<usize> = 6..8;
+const REACHABLE_TIME: Range<usize> = 8..12;
+const RETRANS_TIMER: Range<usize> = 12..16;
+const RA_OPTIONS: usize = 16;
+
+mod managed {
+    pub const MASK: u8 = (1 << SIZE) - 1;
+    pub const OFFSET: usize = 7;
+    pub const SIZE: usize = 1;
+}
+
+mod other {
+    pub const MASK: u8 = (1 << SIZE) - 1;
+    pub const OFFSET: usize = 6;
+    pub const SIZE: usize = 1;
+}
+
 /// ICMPv6 Message
 // TODO add 'Checksum = {Valid,Unknown}' type state
 pub struct Message<BUFFER, TYPE>
@@ -117,42 +163,16 @@ where
 
     /* Miscellaneous */
     pub(crate) fn compute_checksum(&self, src: ipv6::Addr, dest: ipv6::Addr) -> u16 {
-        const NEXT_HEADER: u8 = 58;
-
-        let mut sum: u32 = 0;
-
-        // Pseudo-header
-        for chunk in src.0.chunks_exact(2).chain(dest.0.chunks_exact(2)) {
-            sum += u32::from(NE::read_u16(chunk));
-        }
-
-        // XXX should this be just `as u16`?
-        let len = self.as_slice().len() as u32;
-        sum += len >> 16;
-        sum += len & 0xffff;
-
-        sum += u32::from(NEXT_HEADER);
-
-        // ICMPv6 message
-        for (i, chunk) in self.as_slice().chunks(2).enumerate() {
-            if i == 1 {
-                // this is the checksum field, skip
-                continue;
-            }
-
-            if chunk.len() == 1 {
-                sum += u32::from(chunk[0]) << 8;
-            } else {
-                sum += u32::from(NE::read_u16(chunk));
-            }
-        }
-
-        // fold carry-over
-        while sum >> 16 != 0 {
-            sum = (sum & 0xffff) + (sum >> 16);
-        }
-
-        !(sum as u16)
+        let bytes = self.as_slice();
+
+        let mut sum = Checksum::ipv6_pseudo_header(
+            src,
+            dest,
+            ipv6::NextHeader::Ipv6Icmp.into(),
+            u32(bytes.len()).unwrap(),
+        );
+        sum.add_message(bytes, CHECKSUM.start);
+        sum.finish()
     }
 
     /// Verifies the 'Checksum' field
@@ -333,6 +353,89 @@ where
     }
 }
 
+impl<B> Message<B, NeighborSolicitation>
+where
+    B: AsMutSlice<Element = u8> + Truncate<u8>,
+{
+    /* Constructors */
+    /// Transforms the input buffer into a Neighbor Solicitation ICMPv6 message
+    ///
+    /// `source_ll_opt_size` is the size of the 'Source Link-layer Address' option *in units of 8
+    /// octets*. A value of `0` means that the option will be omitted.
+    ///
+    /// All these fields need to be filled by the caller
+    ///
+    /// - Target Address field
+    /// - Source Link-layer Address option
+    pub fn neighbor_solicitation(buffer: B, source_ll_opt_size: u8) -> Self {
+        Message::neighbor_solicitation_(buffer, source_ll_opt_size, 0)
+    }
+
+    // NOTE `extra` bytes are reserved after the 'Source Link-layer Address' option
+    fn neighbor_solicitation_(mut buffer: B, source_ll_opt_size: u8, extra: u8) -> Self {
+        let end = 24 + usize::from(source_ll_opt_size) * 8;
+        let size = end + usize::from(extra);
+        assert!(buffer.as_slice().len() >= size && size <= usize::from(u8::max_value()));
+
+        buffer.truncate(size as u8);
+
+        // clear reserved field
+        unsafe { buffer.as_mut_slice().rm(4..8).copy_from_slice(&[0; 4]) };
+
+        // set option type and length, and clear its contents (padding)
+        if source_ll_opt_size != 0 {
+            unsafe {
+                let bytes = buffer.as_mut_slice();
+                *bytes.gum(24) = OptionType::SourceLinkLayerAddress.into();
+                *bytes.gum(25) = source_ll_opt_size;
+                for byte in bytes.rm(26..end) {
+                    *byte = 0;
+                }
+            }
+        }
+
+        let mut m: Message<B, Unknown> = unsafe { Message::unchecked(buffer) };
+
+        m.set_type(Type::NeighborSolicitation);
+        m.set_code(0);
+
+        unsafe { Message::unchecked(m.buffer) }
+    }
+}
+
+impl<B> Message<B, NeighborSolicitation>
+where
+    B: AsMutSlice<Element = u8>,
+{
+    /* Setters */
+    /// Sets the 'Target Address' field
+    pub fn set_target(&mut self, addr: ipv6::Addr) {
+        unsafe {
+            self.as_mut_slice().rm(TARGET).copy_from_slice(&addr.0);
+        }
+    }
+
+    // NOTE(unsafe) caller must ensure that the 'Source Link-layer Address' exists
+    pub(crate) unsafe fn set_source_ieee802154_addr(&mut self, addr: ieee802154::ExtendedAddr) {
+        let opt = self.source_ll_mut().unwrap_or_else(|| debug_unreachable!());
+
+        NE::write_u64(opt.rtm(..8), addr.0);
+    }
+
+    /// Mutable view into the 'Source Link-layer address' option
+    pub fn source_ll_mut(&mut self) -> Option<&mut [u8]> {
+        OptionsMut::new(unsafe { self.as_mut_slice().rfm(24..) })
+            .filter_map(|opt| {
+                if opt.ty == OptionType::SourceLinkLayerAddress {
+                    Some(opt.contents)
+                } else {
+                    None
+                }
+            })
+            .next()
+    }
+}
+
 impl<B> fmt::Debug for Message<B, NeighborSolicitation>
 where
     B: AsSlice<Element = u8>,
@@ -607,6 +710,105 @@ where
     }
 }
 
+/// [Type state]
+pub enum RouterAdvertisement {}
+
+impl<B> TryFrom<Message<B, Unknown>> for Message<B, RouterAdvertisement>
+where
+    B: AsSlice<Element = u8>,
+{
+    type Error = Message<B, Unknown>;
+
+    fn try_from(m: Message<B, Unknown>) -> Result<Self, Message<B, Unknown>> {
+        // RFC 4861 - Section 6.1.2.  Validation of Router Advertisement Messages
+        // "ICMP Code is 0"
+        // "ICMP length (derived from the IP length) is 16 or more octets"
+        // "All included options have a length that is greater than zero"
+        if m.get_type() == Type::RouterAdvertisement
+            && m.get_code() == 0
+            && m.as_slice().len() >= RA_OPTIONS
+            && Options::are_valid(&m.as_slice()[RA_OPTIONS..])
+        {
+            Ok(unsafe { Message::unchecked(m.buffer) })
+        } else {
+            Err(m)
+        }
+    }
+}
+
+impl<B> Message<B, RouterAdvertisement>
+where
+    B: AsSlice<Element = u8>,
+{
+    /* Getters */
+    /// Reads the 'Cur Hop Limit' field
+    pub fn get_cur_hop_limit(&self) -> u8 {
+        unsafe { *self.as_slice().gu(CUR_HOP_LIMIT) }
+    }
+
+    /// Reads the 'Managed address configuration' flag
+    pub fn get_managed(&self) -> bool {
+        unsafe { get!(self.as_slice().gu(M_O), managed) == 1 }
+    }
+
+    /// Reads the 'Other configuration' flag
+    pub fn get_other(&self) -> bool {
+        unsafe { get!(self.as_slice().gu(M_O), other) == 1 }
+    }
+
+    /// Reads the 'Router Lifetime' field (in seconds)
+    pub fn get_router_lifetime(&self) -> u16 {
+        unsafe { NE::read_u16(self.as_slice().r(ROUTER_LIFETIME)) }
+    }
+
+    /// Reads the 'Reachable Time' field (in milliseconds)
+    pub fn get_reachable_time(&self) -> u32 {
+        unsafe { NE::read_u32(self.as_slice().r(REACHABLE_TIME)) }
+    }
+
+    /// Reads the 'Retrans Timer' field (in milliseconds)
+    pub fn get_retrans_timer(&self) -> u32 {
+        unsafe { NE::read_u32(self.as_slice().r(RETRANS_TIMER)) }
+    }
+
+    /// Reads the 'Source Link-layer address' option
+    // NOTE this contains padding
+    pub fn get_source_ll(&self) -> Option<&[u8]> {
+        self.options()
+            .filter_map(|opt| {
+                if opt.ty == OptionType::SourceLinkLayerAddress {
+                    Some(opt.contents)
+                } else {
+                    None
+                }
+            })
+            .next()
+    }
+
+    fn options(&self) -> Options<'_> {
+        // NOTE(unsafe) the options were validated in `try_from`
+        unsafe { Options::new(self.as_slice().rf(RA_OPTIONS..)) }
+    }
+}
+
+impl<B> fmt::Debug for Message<B, RouterAdvertisement>
+where
+    B: AsSlice<Element = u8>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("icmpv6::Message<RouterAdvertisement>")
+            .field("checksum", &self.get_checksum())
+            .field("cur_hop_limit", &self.get_cur_hop_limit())
+            .field("managed", &self.get_managed())
+            .field("other", &self.get_other())
+            .field("router_lifetime", &self.get_router_lifetime())
+            .field("reachable_time", &self.get_reachable_time())
+            .field("retrans_timer", &self.get_retrans_timer())
+            .field("source_ll", &self.get_source_ll())
+            .finish()
+    }
+}
+
 impl<B, E> Message<B, E>
 where
     B: AsSlice<Element = u8>,
@@ -677,6 +879,23 @@ where
     }
 }
 
+impl<B> Message<B, EchoRequest>
+where
+    B: AsMutSlice<Element = u8>,
+{
+    /// Transforms the input buffer into a Echo Request ICMPv6 message
+    ///
+    /// NOTE The message will span the whole buffer; use `set_payload` to shrink it
+    pub fn echo_request(buffer: B) -> Self {
+        assert!(buffer.as_slice().len() >= 8);
+
+        let mut m: Message<B, Unknown> = unsafe { Message::unchecked(buffer) };
+        m.set_type(Type::EchoRequest);
+        m.set_code(0);
+        unsafe { Message::unchecked(m.buffer) }
+    }
+}
+
 impl<B> Message<B, EchoReply>
 where
     B: AsMutSlice<Element = u8>,
@@ -690,7 +909,13 @@ where
         m.set_code(0);
         unsafe { Message::unchecked(m.buffer) }
     }
+}
 
+impl<B, E> Message<B, E>
+where
+    B: AsMutSlice<Element = u8>,
+    E: Echo,
+{
     /// Sets the 'Identifier' field
     pub fn set_identifier(&mut self, id: u16) {
         unsafe { NE::write_u16(self.as_mut_slice().rm(IDENTIFIER), id) }
@@ -706,11 +931,12 @@ where
     }
 }
 
-impl<B> Message<B, EchoReply>
+impl<B, E> Message<B, E>
 where
     B: AsMutSlice<Element = u8> + Truncate<u8>,
+    E: Echo,
 {
-    /// Fills the payload with the given data and adjusts the length of the UDP packet
+    /// Fills the payload with the given data and adjusts the length of the message
     pub fn set_payload(&mut self, data: &[u8]) {
         let dlen = data.len();
         self.payload_mut()[..dlen].copy_from_slice(data);
@@ -827,6 +1053,12 @@ full_range!(
         EchoRequest = 128,
         /// Echo reply
         EchoReply = 129,
+        /// Multicast listener query
+        MulticastListenerQuery = 130,
+        /// Multicast listener report
+        MulticastListenerReport = 131,
+        /// Multicast listener done
+        MulticastListenerDone = 132,
         /// Router solicitation
         RouterSolicitation = 133,
         /// Router advertisement
@@ -835,6 +1067,12 @@ full_range!(
         NeighborSolicitation = 135,
         /// Neighbor advertisement
         NeighborAdvertisement = 136,
+        /// Version 2 multicast listener report
+        MulticastListenerReportV2 = 143,
+        /// Duplicate address request
+        DuplicateAddressRequest = 157,
+        /// Duplicate address confirmation
+        DuplicateAddressConfirmation = 158,
     }
 );
 
@@ -853,5 +1091,11 @@ full_range!(
         RedirectedHeader = 4,
         // MTU
         Mtu = 5,
+        // Address registration
+        AddressRegistration = 33,
+        // 6LoWPAN context
+        SixLowpanContext = 34,
+        // Authoritative border router
+        AuthoritativeBorderRouter = 35,
     }
 );
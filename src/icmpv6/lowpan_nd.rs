@@ -0,0 +1,752 @@
+//! 6LoWPAN Neighbor Discovery
+//!
+//! # References
+//!
+//! - [RFC 6775: Neighbor Discovery Optimization for IPv6 over Low-Power Wireless Personal Area
+//!   Networks (6LoWPANs)][0]
+//!
+//! [0]: https://tools.ietf.org/html/rfc6775
+
+use core::{
+    fmt,
+    ops::{Range, RangeFrom},
+};
+
+use as_slice::{AsMutSlice, AsSlice};
+use byteorder::{ByteOrder, NetworkEndian as NE};
+use owning_slice::Truncate;
+
+use crate::{
+    fmt::Quoted,
+    icmpv6::{
+        Message, NeighborAdvertisement, NeighborSolicitation, OptionType, Options, OptionsMut,
+        RouterAdvertisement, Type,
+    },
+    ieee802154, ipv6,
+    sealed::DuplicateAddress,
+    traits::{TryFrom, UncheckedIndex},
+    Unknown,
+};
+
+// NOTE the offsets of the options are relative to the start of the option *contents*, i.e. they
+// don't include the 'Type' and 'Length' fields
+
+/* Address Registration Option */
+const ARO_STATUS: usize = 0;
+const ARO_RESERVED: Range<usize> = 1..4;
+const ARO_LIFETIME: Range<usize> = 4..6;
+const ARO_EUI64: Range<usize> = 6..14;
+
+/// Size of the Address Registration Option
+pub const ARO_SIZE: u8 = ARO_EUI64.end as u8 + 2;
+
+/* 6LoWPAN Context Option */
+const CONTEXT_LENGTH: usize = 0;
+const C_CID: usize = 1;
+mod cid {
+    pub const MASK: u8 = (1 << SIZE) - 1;
+    pub const OFFSET: usize = 0;
+    pub const SIZE: usize = 4;
+}
+
+mod c {
+    pub const MASK: u8 = (1 << SIZE) - 1;
+    pub const OFFSET: usize = super::cid::OFFSET + super::cid::SIZE;
+    pub const SIZE: usize = 1;
+}
+
+const CONTEXT_VALID_LIFETIME: Range<usize> = 4..6;
+const CONTEXT_PREFIX: RangeFrom<usize> = 6..;
+
+/* Authoritative Border Router Option */
+const VERSION_LOW: Range<usize> = 0..2;
+const VERSION_HIGH: Range<usize> = 2..4;
+const ABRO_VALID_LIFETIME: Range<usize> = 4..6;
+const BORDER_ROUTER_ADDRESS: Range<usize> = 6..22;
+
+/* Duplicate Address Request / Confirmation */
+const STATUS: usize = 4;
+const RESERVED: usize = 5;
+const REGISTRATION_LIFETIME: Range<usize> = 6..8;
+const EUI64: Range<usize> = 8..16;
+const REGISTERED_ADDRESS: Range<usize> = 16..32;
+
+/// Size of a Duplicate Address Request / Confirmation message
+pub const DUPLICATE_ADDRESS_SIZE: u8 = REGISTERED_ADDRESS.end as u8;
+
+/// [Type state] Duplicate Address Request
+pub enum DuplicateAddressRequest {}
+
+/// [Type state] Duplicate Address Confirmation
+pub enum DuplicateAddressConfirmation {}
+
+/* Neighbor Solicitation */
+impl<B> Message<B, NeighborSolicitation>
+where
+    B: AsMutSlice<Element = u8> + Truncate<u8>,
+{
+    /* Constructors */
+    /// Transforms the input buffer into a Neighbor Solicitation ICMPv6 message that carries an
+    /// Address Registration Option
+    ///
+    /// `source_ll_opt_size` is the size of the 'Source Link-layer Address' option *in units of 8
+    /// octets*. RFC 6775 requires this option to be present.
+    ///
+    /// The 'Status' field of the ARO is set to `Success`. All these fields need to be filled by
+    /// the caller
+    ///
+    /// - Target Address field
+    /// - Source Link-layer Address option
+    /// - Registration Lifetime and EUI-64 fields of the ARO
+    pub fn neighbor_solicitation_with_aro(buffer: B, source_ll_opt_size: u8) -> Self {
+        let mut m = Message::neighbor_solicitation_(buffer, source_ll_opt_size, ARO_SIZE);
+
+        let start = 24 + usize::from(source_ll_opt_size) * 8;
+        unsafe { init_aro(m.as_mut_slice().rfm(start..)) }
+
+        m
+    }
+}
+
+impl<B> Message<B, NeighborSolicitation>
+where
+    B: AsSlice<Element = u8>,
+{
+    /// Returns the Address Registration Option, if present
+    pub fn get_aro(&self) -> Option<AddressRegistration<&[u8]>> {
+        unsafe { aro(Options::new(self.as_slice().rf(24..))) }
+    }
+}
+
+impl<B> Message<B, NeighborSolicitation>
+where
+    B: AsMutSlice<Element = u8>,
+{
+    /// Mutable view into the Address Registration Option, if present
+    pub fn aro_mut(&mut self) -> Option<AddressRegistration<&mut [u8]>> {
+        aro_mut(OptionsMut::new(unsafe { self.as_mut_slice().rfm(24..) }))
+    }
+}
+
+/* Neighbor Advertisement */
+impl<B> Message<B, NeighborAdvertisement>
+where
+    B: AsMutSlice<Element = u8> + Truncate<u8>,
+{
+    /* Constructors */
+    /// Transforms the input buffer into a Neighbor Advertisement ICMPv6 message that carries an
+    /// Address Registration Option
+    ///
+    /// This is the answer a router sends to a Neighbor Solicitation that contains an ARO. All the
+    /// flags are cleared. All these fields need to be filled by the caller
+    ///
+    /// - Target Address field
+    /// - Status, Registration Lifetime and EUI-64 fields of the ARO
+    pub fn neighbor_advertisement_with_aro(mut buffer: B) -> Self {
+        let size = 24 + ARO_SIZE;
+        assert!(buffer.as_slice().len() >= usize::from(size));
+
+        buffer.truncate(size);
+
+        unsafe {
+            // clear flags and reserved field
+            buffer.as_mut_slice().rm(4..8).copy_from_slice(&[0; 4]);
+            init_aro(buffer.as_mut_slice().rfm(24..));
+        }
+
+        let mut m: Message<B, Unknown> = unsafe { Message::unchecked(buffer) };
+        m.set_type(Type::NeighborAdvertisement);
+        m.set_code(0);
+
+        unsafe { Message::unchecked(m.buffer) }
+    }
+}
+
+impl<B> Message<B, NeighborAdvertisement>
+where
+    B: AsSlice<Element = u8>,
+{
+    /// Returns the Address Registration Option, if present
+    pub fn get_aro(&self) -> Option<AddressRegistration<&[u8]>> {
+        unsafe { aro(Options::new(self.as_slice().rf(24..))) }
+    }
+}
+
+impl<B> Message<B, NeighborAdvertisement>
+where
+    B: AsMutSlice<Element = u8>,
+{
+    /// Mutable view into the Address Registration Option, if present
+    pub fn aro_mut(&mut self) -> Option<AddressRegistration<&mut [u8]>> {
+        aro_mut(OptionsMut::new(unsafe { self.as_mut_slice().rfm(24..) }))
+    }
+}
+
+/* Router Advertisement */
+impl<B> Message<B, RouterAdvertisement>
+where
+    B: AsSlice<Element = u8>,
+{
+    /// Returns an iterator over the 6LoWPAN Context Options
+    pub fn contexts(&self) -> Contexts<'_> {
+        Contexts {
+            options: self.options(),
+        }
+    }
+
+    /// Returns the Authoritative Border Router Option, if present
+    pub fn get_abro(&self) -> Option<AuthoritativeBorderRouter<'_>> {
+        self.options()
+            .filter(|opt| opt.ty == OptionType::AuthoritativeBorderRouter)
+            .filter_map(|opt| AuthoritativeBorderRouter::new(opt.contents))
+            .next()
+    }
+}
+
+/* Duplicate Address Request / Confirmation */
+impl<B, D> Message<B, D>
+where
+    B: AsSlice<Element = u8>,
+    D: DuplicateAddress,
+{
+    /* Getters */
+    /// Reads the 'Status' field
+    pub fn get_status(&self) -> Status {
+        Status::from(unsafe { *self.as_slice().gu(STATUS) })
+    }
+
+    /// Reads the 'Registration Lifetime' field (in units of 60 seconds)
+    pub fn get_registration_lifetime(&self) -> u16 {
+        unsafe { NE::read_u16(self.as_slice().r(REGISTRATION_LIFETIME)) }
+    }
+
+    /// Reads the 'EUI-64' field
+    pub fn get_eui64(&self) -> ieee802154::ExtendedAddr {
+        ieee802154::ExtendedAddr(unsafe { NE::read_u64(self.as_slice().r(EUI64)) })
+    }
+
+    /// Reads the 'Registered Address' field
+    pub fn get_registered_address(&self) -> ipv6::Addr {
+        unsafe { ipv6::Addr(*(self.as_slice().as_ptr().add(REGISTERED_ADDRESS.start) as *const _)) }
+    }
+}
+
+impl<B, D> Message<B, D>
+where
+    B: AsMutSlice<Element = u8>,
+    D: DuplicateAddress,
+{
+    /* Setters */
+    /// Sets the 'Status' field
+    pub fn set_status(&mut self, status: Status) {
+        unsafe { *self.as_mut_slice().gum(STATUS) = status.into() }
+    }
+
+    /// Sets the 'Registration Lifetime' field (in units of 60 seconds)
+    pub fn set_registration_lifetime(&mut self, lifetime: u16) {
+        unsafe { NE::write_u16(self.as_mut_slice().rm(REGISTRATION_LIFETIME), lifetime) }
+    }
+
+    /// Sets the 'EUI-64' field
+    pub fn set_eui64(&mut self, eui64: ieee802154::ExtendedAddr) {
+        unsafe { NE::write_u64(self.as_mut_slice().rm(EUI64), eui64.0) }
+    }
+
+    /// Sets the 'Registered Address' field
+    pub fn set_registered_address(&mut self, addr: ipv6::Addr) {
+        unsafe {
+            self.as_mut_slice()
+                .rm(REGISTERED_ADDRESS)
+                .copy_from_slice(&addr.0)
+        }
+    }
+}
+
+impl<B, D> Message<B, D>
+where
+    B: AsMutSlice<Element = u8> + Truncate<u8>,
+    D: DuplicateAddress,
+{
+    fn duplicate_address(mut buffer: B, ty: Type) -> Self {
+        assert!(buffer.as_slice().len() >= usize::from(DUPLICATE_ADDRESS_SIZE));
+
+        buffer.truncate(DUPLICATE_ADDRESS_SIZE);
+
+        let mut m: Message<B, Unknown> = unsafe { Message::unchecked(buffer) };
+        m.set_type(ty);
+        m.set_code(0);
+
+        // clear the 'Status' and 'Reserved' fields
+        unsafe {
+            *m.as_mut_slice().gum(STATUS) = 0;
+            *m.as_mut_slice().gum(RESERVED) = 0;
+        }
+
+        unsafe { Message::unchecked(m.buffer) }
+    }
+}
+
+impl<B> Message<B, DuplicateAddressRequest>
+where
+    B: AsMutSlice<Element = u8> + Truncate<u8>,
+{
+    /* Constructors */
+    /// Transforms the input buffer into a Duplicate Address Request
+    ///
+    /// The 'Status' field is set to `Success`. The 'Registration Lifetime', 'EUI-64' and
+    /// 'Registered Address' fields need to be filled by the caller
+    pub fn duplicate_address_request(buffer: B) -> Self {
+        Message::duplicate_address(buffer, Type::DuplicateAddressRequest)
+    }
+}
+
+impl<B> Message<B, DuplicateAddressConfirmation>
+where
+    B: AsMutSlice<Element = u8> + Truncate<u8>,
+{
+    /* Constructors */
+    /// Transforms the input buffer into a Duplicate Address Confirmation
+    ///
+    /// The 'Status' field is set to `Success`. The 'Registration Lifetime', 'EUI-64' and
+    /// 'Registered Address' fields need to be filled by the caller
+    pub fn duplicate_address_confirmation(buffer: B) -> Self {
+        Message::duplicate_address(buffer, Type::DuplicateAddressConfirmation)
+    }
+}
+
+/// Turns a request into a confirmation; the 'Status' field must be updated by the caller
+impl<B> From<Message<B, DuplicateAddressRequest>> for Message<B, DuplicateAddressConfirmation>
+where
+    B: AsMutSlice<Element = u8>,
+{
+    fn from(m: Message<B, DuplicateAddressRequest>) -> Self {
+        let mut m: Message<B, Unknown> = unsafe { Message::unchecked(m.buffer) };
+        m.set_type(Type::DuplicateAddressConfirmation);
+        unsafe { Message::unchecked(m.buffer) }
+    }
+}
+
+impl<B> TryFrom<Message<B, Unknown>> for Message<B, DuplicateAddressRequest>
+where
+    B: AsSlice<Element = u8>,
+{
+    type Error = Message<B, Unknown>;
+
+    fn try_from(m: Message<B, Unknown>) -> Result<Self, Message<B, Unknown>> {
+        // RFC 6775 - Section 8.2.3. Processing a Duplicate Address Request
+        // "ICMP Code is 0"
+        // "ICMP length (derived from the IP length) is 32 or more octets"
+        if m.get_type() == Type::DuplicateAddressRequest
+            && m.get_code() == 0
+            && m.as_slice().len() >= usize::from(DUPLICATE_ADDRESS_SIZE)
+        {
+            Ok(unsafe { Message::unchecked(m.buffer) })
+        } else {
+            Err(m)
+        }
+    }
+}
+
+impl<B> TryFrom<Message<B, Unknown>> for Message<B, DuplicateAddressConfirmation>
+where
+    B: AsSlice<Element = u8>,
+{
+    type Error = Message<B, Unknown>;
+
+    fn try_from(m: Message<B, Unknown>) -> Result<Self, Message<B, Unknown>> {
+        if m.get_type() == Type::DuplicateAddressConfirmation
+            && m.get_code() == 0
+            && m.as_slice().len() >= usize::from(DUPLICATE_ADDRESS_SIZE)
+        {
+            Ok(unsafe { Message::unchecked(m.buffer) })
+        } else {
+            Err(m)
+        }
+    }
+}
+
+impl<B> fmt::Debug for Message<B, DuplicateAddressRequest>
+where
+    B: AsSlice<Element = u8>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("icmpv6::Message<DuplicateAddressRequest>")
+            .field("checksum", &self.get_checksum())
+            .field("status", &self.get_status())
+            .field("registration_lifetime", &self.get_registration_lifetime())
+            .field("eui64", &self.get_eui64())
+            .field("registered_address", &Quoted(self.get_registered_address()))
+            .finish()
+    }
+}
+
+impl<B> fmt::Debug for Message<B, DuplicateAddressConfirmation>
+where
+    B: AsSlice<Element = u8>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("icmpv6::Message<DuplicateAddressConfirmation>")
+            .field("checksum", &self.get_checksum())
+            .field("status", &self.get_status())
+            .field("registration_lifetime", &self.get_registration_lifetime())
+            .field("eui64", &self.get_eui64())
+            .field("registered_address", &Quoted(self.get_registered_address()))
+            .finish()
+    }
+}
+
+/// View into an Address Registration Option (ARO)
+pub struct AddressRegistration<B>
+where
+    B: AsSlice<Element = u8>,
+{
+    // NOTE the option contents; always `ARO_SIZE - 2` bytes long
+    bytes: B,
+}
+
+impl<B> AddressRegistration<B>
+where
+    B: AsSlice<Element = u8>,
+{
+    fn new(bytes: B) -> Option<Self> {
+        if bytes.as_slice().len() == usize::from(ARO_SIZE) - 2 {
+            Some(AddressRegistration { bytes })
+        } else {
+            None
+        }
+    }
+
+    /// Reads the 'Status' field
+    pub fn get_status(&self) -> Status {
+        Status::from(unsafe { *self.bytes.as_slice().gu(ARO_STATUS) })
+    }
+
+    /// Reads the 'Registration Lifetime' field (in units of 60 seconds)
+    pub fn get_registration_lifetime(&self) -> u16 {
+        unsafe { NE::read_u16(self.bytes.as_slice().r(ARO_LIFETIME)) }
+    }
+
+    /// Reads the 'EUI-64' field
+    pub fn get_eui64(&self) -> ieee802154::ExtendedAddr {
+        ieee802154::ExtendedAddr(unsafe { NE::read_u64(self.bytes.as_slice().r(ARO_EUI64)) })
+    }
+}
+
+impl<B> AddressRegistration<B>
+where
+    B: AsMutSlice<Element = u8>,
+{
+    /// Sets the 'Status' field
+    pub fn set_status(&mut self, status: Status) {
+        unsafe { *self.bytes.as_mut_slice().gum(ARO_STATUS) = status.into() }
+    }
+
+    /// Sets the 'Registration Lifetime' field (in units of 60 seconds)
+    pub fn set_registration_lifetime(&mut self, lifetime: u16) {
+        unsafe { NE::write_u16(self.bytes.as_mut_slice().rm(ARO_LIFETIME), lifetime) }
+    }
+
+    /// Sets the 'EUI-64' field
+    pub fn set_eui64(&mut self, eui64: ieee802154::ExtendedAddr) {
+        unsafe { NE::write_u64(self.bytes.as_mut_slice().rm(ARO_EUI64), eui64.0) }
+    }
+}
+
+impl<B> fmt::Debug for AddressRegistration<B>
+where
+    B: AsSlice<Element = u8>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("lowpan_nd::AddressRegistration")
+            .field("status", &self.get_status())
+            .field("registration_lifetime", &self.get_registration_lifetime())
+            .field("eui64", &self.get_eui64())
+            .finish()
+    }
+}
+
+/// View into a 6LoWPAN Context Option (6CO)
+#[derive(Clone, Copy)]
+pub struct SixLowpanContext<'a> {
+    // NOTE the option contents
+    bytes: &'a [u8],
+}
+
+impl<'a> SixLowpanContext<'a> {
+    fn new(bytes: &'a [u8]) -> Option<Self> {
+        // RFC 6775 - Section 4.2. "The Length field is 2 or 3 depending on the Context Length"
+        if bytes.len() != 14 && bytes.len() != 22 {
+            return None;
+        }
+
+        let context = SixLowpanContext { bytes };
+        if usize::from(context.get_context_length()) > (bytes.len() - CONTEXT_PREFIX.start) * 8 {
+            None
+        } else {
+            Some(context)
+        }
+    }
+
+    /// Reads the 'Context Length' field (in bits)
+    pub fn get_context_length(&self) -> u8 {
+        unsafe { *self.bytes.gu(CONTEXT_LENGTH) }
+    }
+
+    /// Reads the 'C' (Compression) flag
+    pub fn get_c(&self) -> bool {
+        unsafe { get!(*self.bytes.gu(C_CID), c) == 1 }
+    }
+
+    /// Reads the 'CID' (Context Identifier) field
+    pub fn get_cid(&self) -> u8 {
+        unsafe { get!(*self.bytes.gu(C_CID), cid) }
+    }
+
+    /// Reads the 'Valid Lifetime' field (in units of 60 seconds)
+    pub fn get_valid_lifetime(&self) -> u16 {
+        unsafe { NE::read_u16(self.bytes.r(CONTEXT_VALID_LIFETIME)) }
+    }
+
+    /// Returns the context prefix
+    ///
+    /// Only the first `get_context_length` bits are meaningful; the rest of the address is zeroed
+    pub fn get_prefix(&self) -> ipv6::Addr {
+        let mut addr = ipv6::Addr::UNSPECIFIED;
+
+        let prefix = unsafe { self.bytes.rf(CONTEXT_PREFIX) };
+        let len = usize::from(self.get_context_length());
+        let (whole, rest) = (len / 8, len % 8);
+
+        addr.0[..whole].copy_from_slice(&prefix[..whole]);
+        if rest != 0 {
+            addr.0[whole] = prefix[whole] & !(0xff >> rest);
+        }
+
+        addr
+    }
+}
+
+impl<'a> fmt::Debug for SixLowpanContext<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("lowpan_nd::SixLowpanContext")
+            .field("context_length", &self.get_context_length())
+            .field("c", &self.get_c())
+            .field("cid", &self.get_cid())
+            .field("valid_lifetime", &self.get_valid_lifetime())
+            .field("prefix", &Quoted(self.get_prefix()))
+            .finish()
+    }
+}
+
+/// Iterator over the 6LoWPAN Context Options of a Router Advertisement
+pub struct Contexts<'a> {
+    options: Options<'a>,
+}
+
+impl<'a> Iterator for Contexts<'a> {
+    type Item = SixLowpanContext<'a>;
+
+    fn next(&mut self) -> Option<SixLowpanContext<'a>> {
+        loop {
+            let opt = self.options.next()?;
+
+            if opt.ty == OptionType::SixLowpanContext {
+                if let Some(context) = SixLowpanContext::new(opt.contents) {
+                    return Some(context);
+                }
+            }
+        }
+    }
+}
+
+/// View into an Authoritative Border Router Option (ABRO)
+#[derive(Clone, Copy)]
+pub struct AuthoritativeBorderRouter<'a> {
+    // NOTE the option contents; always 22 bytes long
+    bytes: &'a [u8],
+}
+
+impl<'a> AuthoritativeBorderRouter<'a> {
+    fn new(bytes: &'a [u8]) -> Option<Self> {
+        if bytes.len() == BORDER_ROUTER_ADDRESS.end {
+            Some(AuthoritativeBorderRouter { bytes })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the version number, which is split in the 'Version Low' and 'Version High' fields
+    pub fn get_version(&self) -> u32 {
+        unsafe {
+            u32::from(NE::read_u16(self.bytes.r(VERSION_HIGH))) << 16
+                | u32::from(NE::read_u16(self.bytes.r(VERSION_LOW)))
+        }
+    }
+
+    /// Reads the 'Valid Lifetime' field (in units of 60 seconds)
+    ///
+    /// A value of zero means that the default value (10,000 minutes) must be used
+    pub fn get_valid_lifetime(&self) -> u16 {
+        unsafe { NE::read_u16(self.bytes.r(ABRO_VALID_LIFETIME)) }
+    }
+
+    /// Reads the '6LBR Address' field
+    pub fn get_address(&self) -> ipv6::Addr {
+        unsafe { ipv6::Addr(*(self.bytes.as_ptr().add(BORDER_ROUTER_ADDRESS.start) as *const _)) }
+    }
+}
+
+impl<'a> fmt::Debug for AuthoritativeBorderRouter<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("lowpan_nd::AuthoritativeBorderRouter")
+            .field("version", &self.get_version())
+            .field("valid_lifetime", &self.get_valid_lifetime())
+            .field("address", &Quoted(self.get_address()))
+            .finish()
+    }
+}
+
+// NOTE `bytes` must be at least `ARO_SIZE` bytes long
+unsafe fn init_aro(bytes: &mut [u8]) {
+    *bytes.gum(0) = OptionType::AddressRegistration.into();
+    *bytes.gum(1) = ARO_SIZE / 8;
+
+    let contents = bytes.rm(2..usize::from(ARO_SIZE));
+    *contents.gum(ARO_STATUS) = Status::Success.into();
+    for byte in contents.rfm(ARO_RESERVED.start..) {
+        *byte = 0;
+    }
+}
+
+fn aro(options: Options<'_>) -> Option<AddressRegistration<&[u8]>> {
+    options
+        .filter(|opt| opt.ty == OptionType::AddressRegistration)
+        .filter_map(|opt| AddressRegistration::new(opt.contents))
+        .next()
+}
+
+fn aro_mut(options: OptionsMut<'_>) -> Option<AddressRegistration<&mut [u8]>> {
+    options
+        .filter(|opt| opt.ty == OptionType::AddressRegistration)
+        .filter_map(|opt| AddressRegistration::new(opt.contents))
+        .next()
+}
+
+full_range!(
+    u8,
+    /// Status of an address registration
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub enum Status {
+        /// Success
+        Success = 0,
+        /// Duplicate Address
+        DuplicateAddress = 1,
+        /// Neighbor Cache Full
+        NeighborCacheFull = 2,
+    }
+);
+
+#[cfg(test)]
+mod tests {
+    use crate::{icmpv6, ieee802154, ipv6};
+
+    use super::Status;
+
+    const EUI64: ieee802154::ExtendedAddr = ieee802154::ExtendedAddr(0x0011_2233_4455_6677);
+    const ADDR: ipv6::Addr = ipv6::Addr([
+        0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0x02, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77,
+    ]);
+
+    #[test]
+    fn neighbor_solicitation_with_aro() {
+        let mut buf = [0xff; 64];
+
+        let mut ns = icmpv6::Message::neighbor_solicitation_with_aro(&mut buf[..], 2);
+        ns.set_target(ADDR);
+        {
+            let mut aro = ns.aro_mut().unwrap();
+            aro.set_registration_lifetime(60);
+            aro.set_eui64(EUI64);
+        }
+        assert_eq!(ns.as_bytes().len(), 24 + 16 + 16);
+
+        let ns = icmpv6::Message::parse(ns.as_bytes())
+            .unwrap()
+            .downcast::<icmpv6::NeighborSolicitation>()
+            .unwrap();
+        assert_eq!(ns.get_target(), ADDR);
+        assert_eq!(ns.get_source_ll(), Some(&[0; 14][..]));
+
+        let aro = ns.get_aro().unwrap();
+        assert_eq!(aro.get_status(), Status::Success);
+        assert_eq!(aro.get_registration_lifetime(), 60);
+        assert_eq!(aro.get_eui64(), EUI64);
+    }
+
+    #[test]
+    fn duplicate_address() {
+        let mut buf = [0xff; 40];
+
+        let mut dar = icmpv6::Message::duplicate_address_request(&mut buf[..]);
+        dar.set_registration_lifetime(60);
+        dar.set_eui64(EUI64);
+        dar.set_registered_address(ADDR);
+
+        let mut dac: icmpv6::Message<_, icmpv6::DuplicateAddressConfirmation> = dar.into();
+        dac.set_status(Status::DuplicateAddress);
+        assert_eq!(dac.as_bytes().len(), 32);
+
+        let dac = icmpv6::Message::parse(dac.as_bytes())
+            .unwrap()
+            .downcast::<icmpv6::DuplicateAddressConfirmation>()
+            .unwrap();
+        assert_eq!(dac.get_status(), Status::DuplicateAddress);
+        assert_eq!(dac.get_registration_lifetime(), 60);
+        assert_eq!(dac.get_eui64(), EUI64);
+        assert_eq!(dac.get_registered_address(), ADDR);
+    }
+
+    #[test]
+    fn router_advertisement() {
+        #[rustfmt::skip]
+        let bytes = [
+            134, 0, 0, 0, // type, code, checksum
+            64, 0, 0x07, 0x08, // cur hop limit, M/O, router lifetime
+            0, 0, 0, 0, // reachable time
+            0, 0, 0, 0, // retrans timer
+            // 6CO: length = 2, context length = 64, C = 1, CID = 1
+            34, 2, 64, 0x11, 0, 0, 0, 10,
+            0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0,
+            // ABRO
+            35, 3, 0, 2, 0, 1, 0, 0,
+            0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
+        ];
+
+        let ra = icmpv6::Message::parse(&bytes[..])
+            .unwrap()
+            .downcast::<icmpv6::RouterAdvertisement>()
+            .unwrap();
+        assert_eq!(ra.get_cur_hop_limit(), 64);
+        assert_eq!(ra.get_router_lifetime(), 0x0708);
+
+        let mut contexts = ra.contexts();
+        let context = contexts.next().unwrap();
+        assert_eq!(context.get_context_length(), 64);
+        assert!(context.get_c());
+        assert_eq!(context.get_cid(), 1);
+        assert_eq!(context.get_valid_lifetime(), 10);
+        assert_eq!(
+            context.get_prefix(),
+            ipv6::Addr([0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0])
+        );
+        assert!(contexts.next().is_none());
+
+        let abro = ra.get_abro().unwrap();
+        assert_eq!(abro.get_version(), 0x0001_0002);
+        assert_eq!(abro.get_valid_lifetime(), 0);
+        assert_eq!(
+            abro.get_address(),
+            ipv6::Addr([0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1])
+        );
+    }
+}
@@ -0,0 +1,911 @@
+//! MLD: Multicast Listener Discovery
+//!
+//! # References
+//!
+//! - [RFC 2710: Multicast Listener Discovery (MLD) for IPv6][0]
+//!
+//! [0]: https://tools.ietf.org/html/rfc2710
+//!
+//! - [RFC 3810: Multicast Listener Discovery Version 2 (MLDv2) for IPv6][1]
+//!
+//! [1]: https://tools.ietf.org/html/rfc3810
+
+use core::{fmt, ops::Range};
+
+use as_slice::{AsMutSlice, AsSlice};
+use byteorder::{ByteOrder, NetworkEndian as NE};
+use owning_slice::Truncate;
+
+use crate::{
+    fmt::Quoted,
+    icmpv6::{Message, Type},
+    ipv6,
+    sealed::Mldv1,
+    traits::{TryFrom, UncheckedIndex},
+    Unknown,
+};
+
+/* MLDv1 message format */
+const MAXIMUM_RESPONSE_DELAY: Range<usize> = 4..6;
+const RESERVED: Range<usize> = 6..8;
+const MULTICAST_ADDRESS: Range<usize> = 8..24;
+
+/// Size of a MLDv1 message
+pub const MLDV1_SIZE: u8 = MULTICAST_ADDRESS.end as u8;
+
+/* MLDv2 Query format */
+const MAXIMUM_RESPONSE_CODE: Range<usize> = 4..6;
+// + MULTICAST_ADDRESS
+const S_QRV: usize = 24;
+mod qrv {
+    pub const MASK: u8 = (1 << SIZE) - 1;
+    pub const OFFSET: usize = 0;
+    pub const SIZE: usize = 3;
+}
+
+mod s {
+    pub const MASK: u8 = (1 << SIZE) - 1;
+    pub const OFFSET: usize = super::qrv::OFFSET + super::qrv::SIZE;
+    pub const SIZE: usize = 1;
+}
+
+const QQIC: usize = 25;
+const NUMBER_OF_SOURCES: Range<usize> = 26..28;
+const SOURCES: usize = 28;
+
+/// Minimum size of a MLDv2 Query
+pub const MLDV2_QUERY_MIN_SIZE: u8 = SOURCES as u8;
+
+/* MLDv2 Report format */
+const NUMBER_OF_RECORDS: Range<usize> = 6..8;
+const RECORDS: usize = 8;
+
+/* Multicast Address Record format */
+const RECORD_TYPE: usize = 0;
+const AUX_DATA_LEN: usize = 1;
+const RECORD_NUMBER_OF_SOURCES: Range<usize> = 2..4;
+const RECORD_MULTICAST_ADDRESS: Range<usize> = 4..20;
+const RECORD_SOURCES: usize = 20;
+
+const ADDR_SIZE: usize = 16;
+
+/// [Type state] Multicast Listener Query (MLDv1)
+pub enum MulticastListenerQuery {}
+
+/// [Type state] Multicast Listener Report (MLDv1)
+pub enum MulticastListenerReport {}
+
+/// [Type state] Multicast Listener Done (MLDv1)
+pub enum MulticastListenerDone {}
+
+/// [Type state] Multicast Listener Query (MLDv2)
+pub enum MulticastListenerQueryV2 {}
+
+/// [Type state] Multicast Listener Report (MLDv2)
+pub enum MulticastListenerReportV2 {}
+
+/* MLDv1 */
+impl<B, M> Message<B, M>
+where
+    B: AsSlice<Element = u8>,
+    M: Mldv1,
+{
+    /* Getters */
+    /// Reads the 'Multicast Address' field
+    pub fn get_multicast_address(&self) -> ipv6::Addr {
+        unsafe { ipv6::Addr(*(self.as_slice().as_ptr().add(MULTICAST_ADDRESS.start) as *const _)) }
+    }
+}
+
+impl<B, M> Message<B, M>
+where
+    B: AsMutSlice<Element = u8>,
+    M: Mldv1,
+{
+    /* Setters */
+    /// Sets the 'Multicast Address' field
+    pub fn set_multicast_address(&mut self, addr: ipv6::Addr) {
+        unsafe {
+            self.as_mut_slice()
+                .rm(MULTICAST_ADDRESS)
+                .copy_from_slice(&addr.0)
+        }
+    }
+}
+
+impl<B, M> Message<B, M>
+where
+    B: AsMutSlice<Element = u8> + Truncate<u8>,
+    M: Mldv1,
+{
+    fn mldv1(mut buffer: B, ty: Type) -> Self {
+        assert!(buffer.as_slice().len() >= usize::from(MLDV1_SIZE));
+
+        buffer.truncate(MLDV1_SIZE);
+
+        let mut m: Message<B, Unknown> = unsafe { Message::unchecked(buffer) };
+        m.set_type(ty);
+        m.set_code(0);
+
+        // clear the 'Maximum Response Delay' and 'Reserved' fields
+        unsafe {
+            m.as_mut_slice()
+                .rm(MAXIMUM_RESPONSE_DELAY.start..RESERVED.end)
+                .copy_from_slice(&[0; 4])
+        };
+
+        unsafe { Message::unchecked(m.buffer) }
+    }
+}
+
+impl<B> fmt::Debug for Message<B, MulticastListenerQuery>
+where
+    B: AsSlice<Element = u8>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("icmpv6::Message<MulticastListenerQuery>")
+            .field("checksum", &self.get_checksum())
+            .field("maximum_response_delay", &self.get_maximum_response_delay())
+            .field("multicast_address", &Quoted(self.get_multicast_address()))
+            .finish()
+    }
+}
+
+impl<B> fmt::Debug for Message<B, MulticastListenerReport>
+where
+    B: AsSlice<Element = u8>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("icmpv6::Message<MulticastListenerReport>")
+            .field("checksum", &self.get_checksum())
+            .field("multicast_address", &Quoted(self.get_multicast_address()))
+            .finish()
+    }
+}
+
+impl<B> fmt::Debug for Message<B, MulticastListenerDone>
+where
+    B: AsSlice<Element = u8>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("icmpv6::Message<MulticastListenerDone>")
+            .field("checksum", &self.get_checksum())
+            .field("multicast_address", &Quoted(self.get_multicast_address()))
+            .finish()
+    }
+}
+
+impl<B> Message<B, MulticastListenerQuery>
+where
+    B: AsSlice<Element = u8>,
+{
+    /// Reads the 'Maximum Response Delay' field (in milliseconds)
+    pub fn get_maximum_response_delay(&self) -> u16 {
+        unsafe { NE::read_u16(self.as_slice().r(MAXIMUM_RESPONSE_DELAY)) }
+    }
+}
+
+impl<B> Message<B, MulticastListenerQuery>
+where
+    B: AsMutSlice<Element = u8>,
+{
+    /// Sets the 'Maximum Response Delay' field (in milliseconds)
+    pub fn set_maximum_response_delay(&mut self, delay: u16) {
+        unsafe { NE::write_u16(self.as_mut_slice().rm(MAXIMUM_RESPONSE_DELAY), delay) }
+    }
+}
+
+impl<B> Message<B, MulticastListenerQuery>
+where
+    B: AsMutSlice<Element = u8> + Truncate<u8>,
+{
+    /* Constructors */
+    /// Transforms the input buffer into a (MLDv1) Multicast Listener Query
+    ///
+    /// The 'Maximum Response Delay' field is set to zero. The 'Multicast Address' field needs to
+    /// be filled by the caller; use the unspecified address for a General Query.
+    pub fn multicast_listener_query(buffer: B) -> Self {
+        Message::mldv1(buffer, Type::MulticastListenerQuery)
+    }
+}
+
+impl<B> Message<B, MulticastListenerReport>
+where
+    B: AsMutSlice<Element = u8> + Truncate<u8>,
+{
+    /* Constructors */
+    /// Transforms the input buffer into a (MLDv1) Multicast Listener Report
+    ///
+    /// The 'Multicast Address' field needs to be filled by the caller
+    pub fn multicast_listener_report(buffer: B) -> Self {
+        Message::mldv1(buffer, Type::MulticastListenerReport)
+    }
+}
+
+impl<B> Message<B, MulticastListenerDone>
+where
+    B: AsMutSlice<Element = u8> + Truncate<u8>,
+{
+    /* Constructors */
+    /// Transforms the input buffer into a (MLDv1) Multicast Listener Done
+    ///
+    /// The 'Multicast Address' field needs to be filled by the caller
+    pub fn multicast_listener_done(buffer: B) -> Self {
+        Message::mldv1(buffer, Type::MulticastListenerDone)
+    }
+}
+
+impl<B> TryFrom<Message<B, Unknown>> for Message<B, MulticastListenerQuery>
+where
+    B: AsSlice<Element = u8>,
+{
+    type Error = Message<B, Unknown>;
+
+    fn try_from(m: Message<B, Unknown>) -> Result<Self, Message<B, Unknown>> {
+        // RFC 3810 - Section 8.1. Query Version Distinctions
+        // "MLDv1 Query: length = 24 octets"
+        if m.get_type() == Type::MulticastListenerQuery
+            && m.as_slice().len() == usize::from(MLDV1_SIZE)
+        {
+            Ok(unsafe { Message::unchecked(m.buffer) })
+        } else {
+            Err(m)
+        }
+    }
+}
+
+impl<B> TryFrom<Message<B, Unknown>> for Message<B, MulticastListenerReport>
+where
+    B: AsSlice<Element = u8>,
+{
+    type Error = Message<B, Unknown>;
+
+    fn try_from(m: Message<B, Unknown>) -> Result<Self, Message<B, Unknown>> {
+        if m.get_type() == Type::MulticastListenerReport
+            && m.as_slice().len() >= usize::from(MLDV1_SIZE)
+        {
+            Ok(unsafe { Message::unchecked(m.buffer) })
+        } else {
+            Err(m)
+        }
+    }
+}
+
+impl<B> TryFrom<Message<B, Unknown>> for Message<B, MulticastListenerDone>
+where
+    B: AsSlice<Element = u8>,
+{
+    type Error = Message<B, Unknown>;
+
+    fn try_from(m: Message<B, Unknown>) -> Result<Self, Message<B, Unknown>> {
+        if m.get_type() == Type::MulticastListenerDone
+            && m.as_slice().len() >= usize::from(MLDV1_SIZE)
+        {
+            Ok(unsafe { Message::unchecked(m.buffer) })
+        } else {
+            Err(m)
+        }
+    }
+}
+
+/* MLDv2 Query */
+impl<B> Message<B, MulticastListenerQueryV2>
+where
+    B: AsSlice<Element = u8>,
+{
+    /* Getters */
+    /// Reads the 'Maximum Response Code' field
+    pub fn get_maximum_response_code(&self) -> u16 {
+        unsafe { NE::read_u16(self.as_slice().r(MAXIMUM_RESPONSE_CODE)) }
+    }
+
+    /// Returns the Maximum Response Delay, in milliseconds, encoded in the 'Maximum Response Code'
+    /// field
+    pub fn get_maximum_response_delay(&self) -> u32 {
+        // RFC 3810 - Section 5.1.3. Maximum Response Code
+        let code = self.get_maximum_response_code();
+
+        if code < 32768 {
+            u32::from(code)
+        } else {
+            let exp = (code >> 12) & 0b111;
+            let mant = code & 0xfff;
+
+            u32::from(mant | 0x1000) << (exp + 3)
+        }
+    }
+
+    /// Reads the 'Multicast Address' field
+    pub fn get_multicast_address(&self) -> ipv6::Addr {
+        unsafe { ipv6::Addr(*(self.as_slice().as_ptr().add(MULTICAST_ADDRESS.start) as *const _)) }
+    }
+
+    /// Reads the 'S' (Suppress Router-Side Processing) flag
+    pub fn get_s(&self) -> bool {
+        unsafe { get!(self.as_slice().gu(S_QRV), s) == 1 }
+    }
+
+    /// Reads the 'QRV' (Querier's Robustness Variable) field
+    pub fn get_qrv(&self) -> u8 {
+        unsafe { get!(self.as_slice().gu(S_QRV), qrv) }
+    }
+
+    /// Reads the 'QQIC' (Querier's Query Interval Code) field
+    pub fn get_qqic(&self) -> u8 {
+        unsafe { *self.as_slice().gu(QQIC) }
+    }
+
+    /// Returns the Querier's Query Interval, in seconds, encoded in the 'QQIC' field
+    pub fn get_qqi(&self) -> u16 {
+        // RFC 3810 - Section 5.1.9. QQIC (Querier's Query Interval Code)
+        let code = self.get_qqic();
+
+        if code < 128 {
+            u16::from(code)
+        } else {
+            let exp = (code >> 4) & 0b111;
+            let mant = code & 0xf;
+
+            u16::from(mant | 0x10) << (exp + 3)
+        }
+    }
+
+    /// Reads the 'Number of Sources' field
+    pub fn get_number_of_sources(&self) -> u16 {
+        unsafe { NE::read_u16(self.as_slice().r(NUMBER_OF_SOURCES)) }
+    }
+
+    /// Returns an iterator over the 'Source Address' fields
+    pub fn sources(&self) -> Sources<'_> {
+        let end = SOURCES + usize::from(self.get_number_of_sources()) * ADDR_SIZE;
+
+        Sources {
+            bytes: unsafe { self.as_slice().r(SOURCES..end) },
+        }
+    }
+}
+
+impl<B> Message<B, MulticastListenerQueryV2>
+where
+    B: AsMutSlice<Element = u8>,
+{
+    /* Setters */
+    /// Sets the 'Maximum Response Code' field
+    pub fn set_maximum_response_code(&mut self, code: u16) {
+        unsafe { NE::write_u16(self.as_mut_slice().rm(MAXIMUM_RESPONSE_CODE), code) }
+    }
+
+    /// Sets the 'Multicast Address' field
+    pub fn set_multicast_address(&mut self, addr: ipv6::Addr) {
+        unsafe {
+            self.as_mut_slice()
+                .rm(MULTICAST_ADDRESS)
+                .copy_from_slice(&addr.0)
+        }
+    }
+
+    /// Sets the 'S' (Suppress Router-Side Processing) flag
+    pub fn set_s(&mut self, s: bool) {
+        unsafe { set!(*self.as_mut_slice().gum(S_QRV), s, if s { 1 } else { 0 }) }
+    }
+
+    /// Sets the 'QRV' (Querier's Robustness Variable) field
+    pub fn set_qrv(&mut self, qrv: u8) {
+        unsafe { set!(*self.as_mut_slice().gum(S_QRV), qrv, qrv) }
+    }
+
+    /// Sets the 'QQIC' (Querier's Query Interval Code) field
+    pub fn set_qqic(&mut self, qqic: u8) {
+        unsafe { *self.as_mut_slice().gum(QQIC) = qqic }
+    }
+}
+
+impl<B> Message<B, MulticastListenerQueryV2>
+where
+    B: AsMutSlice<Element = u8> + Truncate<u8>,
+{
+    /* Constructors */
+    /// Transforms the input buffer into a (MLDv2) Multicast Listener Query that contains the given
+    /// `sources`
+    ///
+    /// The 'Maximum Response Code', 'S', 'QRV' and 'QQIC' fields are set to zero. The 'Multicast
+    /// Address' field needs to be filled by the caller; use the unspecified address for a General Query.
+    ///
+    /// # Panics
+    ///
+    /// This constructor panics if the buffer is not large enough to contain the message
+    pub fn multicast_listener_query_v2(mut buffer: B, sources: &[ipv6::Addr]) -> Self {
+        let size = SOURCES + sources.len() * ADDR_SIZE;
+        assert!(buffer.as_slice().len() >= size && size <= usize::from(u8::max_value()));
+
+        buffer.truncate(size as u8);
+
+        let mut m: Message<B, Unknown> = unsafe { Message::unchecked(buffer) };
+        m.set_type(Type::MulticastListenerQuery);
+        m.set_code(0);
+
+        let bytes = m.as_mut_slice();
+        unsafe {
+            bytes
+                .rm(MAXIMUM_RESPONSE_CODE.start..MULTICAST_ADDRESS.start)
+                .copy_from_slice(&[0; 4]);
+            bytes
+                .rm(S_QRV..NUMBER_OF_SOURCES.start)
+                .copy_from_slice(&[0; 2]);
+            NE::write_u16(bytes.rm(NUMBER_OF_SOURCES), sources.len() as u16);
+
+            for (chunk, source) in bytes
+                .rfm(SOURCES..)
+                .chunks_exact_mut(ADDR_SIZE)
+                .zip(sources)
+            {
+                chunk.copy_from_slice(&source.0);
+            }
+        }
+
+        unsafe { Message::unchecked(m.buffer) }
+    }
+}
+
+impl<B> TryFrom<Message<B, Unknown>> for Message<B, MulticastListenerQueryV2>
+where
+    B: AsSlice<Element = u8>,
+{
+    type Error = Message<B, Unknown>;
+
+    fn try_from(m: Message<B, Unknown>) -> Result<Self, Message<B, Unknown>> {
+        // RFC 3810 - Section 8.1. Query Version Distinctions
+        // "MLDv2 Query: length >= 28 octets"
+        let len = m.as_slice().len();
+        if m.get_type() == Type::MulticastListenerQuery && len >= SOURCES {
+            let nsources = usize::from(unsafe { NE::read_u16(m.as_slice().r(NUMBER_OF_SOURCES)) });
+
+            if len >= SOURCES + nsources * ADDR_SIZE {
+                return Ok(unsafe { Message::unchecked(m.buffer) });
+            }
+        }
+
+        Err(m)
+    }
+}
+
+impl<B> fmt::Debug for Message<B, MulticastListenerQueryV2>
+where
+    B: AsSlice<Element = u8>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("icmpv6::Message<MulticastListenerQueryV2>")
+            .field("checksum", &self.get_checksum())
+            .field("maximum_response_code", &self.get_maximum_response_code())
+            .field("multicast_address", &Quoted(self.get_multicast_address()))
+            .field("s", &self.get_s())
+            .field("qrv", &self.get_qrv())
+            .field("qqic", &self.get_qqic())
+            .field("number_of_sources", &self.get_number_of_sources())
+            .finish()
+    }
+}
+
+/* MLDv2 Report */
+impl<B> Message<B, MulticastListenerReportV2>
+where
+    B: AsSlice<Element = u8>,
+{
+    /* Getters */
+    /// Reads the 'Nr of Mcast Address Records' field
+    pub fn get_number_of_records(&self) -> u16 {
+        unsafe { NE::read_u16(self.as_slice().r(NUMBER_OF_RECORDS)) }
+    }
+
+    /// Returns an iterator over the Multicast Address Records of this report
+    pub fn records(&self) -> AddressRecords<'_> {
+        AddressRecords {
+            bytes: unsafe { self.as_slice().rf(RECORDS..) },
+            count: self.get_number_of_records(),
+        }
+    }
+}
+
+impl<B> Message<B, MulticastListenerReportV2>
+where
+    B: AsMutSlice<Element = u8> + Truncate<u8>,
+{
+    /* Constructors */
+    /// Transforms the input buffer into a (MLDv2) Multicast Listener Report that contains the given
+    /// `records`
+    ///
+    /// # Panics
+    ///
+    /// This constructor panics if the buffer is not large enough to contain the message
+    pub fn multicast_listener_report_v2(mut buffer: B, records: &[Record<'_>]) -> Self {
+        let size = records.iter().fold(RECORDS, |size, r| size + r.size());
+        assert!(buffer.as_slice().len() >= size && size <= usize::from(u8::max_value()));
+
+        buffer.truncate(size as u8);
+
+        let mut m: Message<B, Unknown> = unsafe { Message::unchecked(buffer) };
+        m.set_type(Type::MulticastListenerReportV2);
+        m.set_code(0);
+
+        let bytes = m.as_mut_slice();
+        unsafe {
+            bytes.rm(4..6).copy_from_slice(&[0; 2]);
+            NE::write_u16(bytes.rm(NUMBER_OF_RECORDS), records.len() as u16);
+
+            let mut cursor = RECORDS;
+            for record in records {
+                let end = cursor + record.size();
+                record.write(bytes.rm(cursor..end));
+                cursor = end;
+            }
+        }
+
+        unsafe { Message::unchecked(m.buffer) }
+    }
+}
+
+impl<B> TryFrom<Message<B, Unknown>> for Message<B, MulticastListenerReportV2>
+where
+    B: AsSlice<Element = u8>,
+{
+    type Error = Message<B, Unknown>;
+
+    fn try_from(m: Message<B, Unknown>) -> Result<Self, Message<B, Unknown>> {
+        if m.get_type() == Type::MulticastListenerReportV2 && m.as_slice().len() >= RECORDS {
+            let count = unsafe { NE::read_u16(m.as_slice().r(NUMBER_OF_RECORDS)) };
+
+            if AddressRecords::are_valid(&m.as_slice()[RECORDS..], count) {
+                return Ok(unsafe { Message::unchecked(m.buffer) });
+            }
+        }
+
+        Err(m)
+    }
+}
+
+impl<B> fmt::Debug for Message<B, MulticastListenerReportV2>
+where
+    B: AsSlice<Element = u8>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("icmpv6::Message<MulticastListenerReportV2>")
+            .field("checksum", &self.get_checksum())
+            .field("number_of_records", &self.get_number_of_records())
+            .finish()
+    }
+}
+
+/// Multicast Address Record to be included in a (MLDv2) Multicast Listener Report
+#[derive(Clone, Copy, Debug)]
+pub struct Record<'a> {
+    /// Record type
+    pub record_type: RecordType,
+    /// Multicast address the record pertains to
+    pub multicast_address: ipv6::Addr,
+    /// Source addresses
+    pub sources: &'a [ipv6::Addr],
+}
+
+impl<'a> Record<'a> {
+    fn size(&self) -> usize {
+        RECORD_SOURCES + self.sources.len() * ADDR_SIZE
+    }
+
+    // NOTE `bytes.len()` must be equal to `self.size()`
+    unsafe fn write(&self, bytes: &mut [u8]) {
+        debug_assert_eq!(bytes.len(), self.size());
+
+        *bytes.gum(RECORD_TYPE) = self.record_type.into();
+        // RFC 3810 - Section 5.2.6. "MLDv2 does not define any auxiliary data"
+        *bytes.gum(AUX_DATA_LEN) = 0;
+        NE::write_u16(
+            bytes.rm(RECORD_NUMBER_OF_SOURCES),
+            self.sources.len() as u16,
+        );
+        bytes
+            .rm(RECORD_MULTICAST_ADDRESS)
+            .copy_from_slice(&self.multicast_address.0);
+
+        for (chunk, source) in bytes
+            .rfm(RECORD_SOURCES..)
+            .chunks_exact_mut(ADDR_SIZE)
+            .zip(self.sources)
+        {
+            chunk.copy_from_slice(&source.0);
+        }
+    }
+}
+
+/// View into a Multicast Address Record of a (MLDv2) Multicast Listener Report
+#[derive(Clone, Copy)]
+pub struct AddressRecord<'a> {
+    // NOTE spans the whole record, including the auxiliary data
+    bytes: &'a [u8],
+}
+
+impl<'a> AddressRecord<'a> {
+    /// Reads the 'Record Type' field
+    pub fn get_record_type(&self) -> RecordType {
+        RecordType::from(unsafe { *self.bytes.gu(RECORD_TYPE) })
+    }
+
+    /// Reads the 'Number of Sources' field
+    pub fn get_number_of_sources(&self) -> u16 {
+        unsafe { NE::read_u16(self.bytes.r(RECORD_NUMBER_OF_SOURCES)) }
+    }
+
+    /// Reads the 'Multicast Address' field
+    pub fn get_multicast_address(&self) -> ipv6::Addr {
+        unsafe {
+            ipv6::Addr(*(self.bytes.as_ptr().add(RECORD_MULTICAST_ADDRESS.start) as *const _))
+        }
+    }
+
+    /// Returns an iterator over the 'Source Address' fields
+    pub fn sources(&self) -> Sources<'a> {
+        Sources {
+            bytes: unsafe { self.bytes.r(RECORD_SOURCES..self.aux_data_start()) },
+        }
+    }
+
+    /// Immutable view into the 'Auxiliary Data' field
+    pub fn aux_data(&self) -> &'a [u8] {
+        unsafe { self.bytes.rf(self.aux_data_start()..) }
+    }
+
+    fn aux_data_start(&self) -> usize {
+        RECORD_SOURCES + usize::from(self.get_number_of_sources()) * ADDR_SIZE
+    }
+}
+
+impl<'a> fmt::Debug for AddressRecord<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("mld::AddressRecord")
+            .field("record_type", &self.get_record_type())
+            .field("multicast_address", &Quoted(self.get_multicast_address()))
+            .field("number_of_sources", &self.get_number_of_sources())
+            .finish()
+    }
+}
+
+/// Iterator over the Multicast Address Records of a (MLDv2) Multicast Listener Report
+pub struct AddressRecords<'a> {
+    bytes: &'a [u8],
+    count: u16,
+}
+
+impl<'a> AddressRecords<'a> {
+    // Size of the record at the start of `bytes`, if `bytes` contains a complete record
+    fn record_size(bytes: &[u8]) -> Option<usize> {
+        if bytes.len() < RECORD_SOURCES {
+            return None;
+        }
+
+        let aux_data_len = usize::from(bytes[AUX_DATA_LEN]) * 4;
+        let nsources = usize::from(NE::read_u16(&bytes[RECORD_NUMBER_OF_SOURCES]));
+        let size = RECORD_SOURCES + nsources * ADDR_SIZE + aux_data_len;
+
+        if bytes.len() < size {
+            None
+        } else {
+            Some(size)
+        }
+    }
+
+    fn are_valid(mut bytes: &[u8], count: u16) -> bool {
+        for _ in 0..count {
+            if let Some(size) = Self::record_size(bytes) {
+                bytes = &bytes[size..];
+            } else {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+impl<'a> Iterator for AddressRecords<'a> {
+    type Item = AddressRecord<'a>;
+
+    fn next(&mut self) -> Option<AddressRecord<'a>> {
+        if self.count == 0 {
+            return None;
+        }
+
+        // NOTE(unwrap) the records were validated in `try_from`
+        let size = Self::record_size(self.bytes).unwrap_or_else(|| unsafe { debug_unreachable!() });
+        let bytes = unsafe { self.bytes.rt(..size) };
+        self.bytes = unsafe { self.bytes.rf(size..) };
+        self.count -= 1;
+
+        Some(AddressRecord { bytes })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::from(self.count), Some(usize::from(self.count)))
+    }
+}
+
+/// Iterator over a list of source addresses
+pub struct Sources<'a> {
+    // NOTE the length of this slice is always a multiple of `ADDR_SIZE`
+    bytes: &'a [u8],
+}
+
+impl<'a> ExactSizeIterator for Sources<'a> {}
+
+impl<'a> Iterator for Sources<'a> {
+    type Item = ipv6::Addr;
+
+    fn next(&mut self) -> Option<ipv6::Addr> {
+        if self.bytes.is_empty() {
+            None
+        } else {
+            unsafe {
+                let addr = ipv6::Addr(*(self.bytes.as_ptr() as *const _));
+                self.bytes = self.bytes.rf(ADDR_SIZE..);
+                Some(addr)
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.bytes.len() / ADDR_SIZE;
+        (n, Some(n))
+    }
+}
+
+full_range!(
+    u8,
+    /// Multicast Address Record type
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub enum RecordType {
+        /// MODE_IS_INCLUDE
+        ModeIsInclude = 1,
+        /// MODE_IS_EXCLUDE
+        ModeIsExclude = 2,
+        /// CHANGE_TO_INCLUDE_MODE
+        ChangeToIncludeMode = 3,
+        /// CHANGE_TO_EXCLUDE_MODE
+        ChangeToExcludeMode = 4,
+        /// ALLOW_NEW_SOURCES
+        AllowNewSources = 5,
+        /// BLOCK_OLD_SOURCES
+        BlockOldSources = 6,
+    }
+);
+
+#[cfg(test)]
+mod tests {
+    use crate::{icmpv6, ipv6};
+
+    use super::{Record, RecordType};
+
+    const GROUP: ipv6::Addr = ipv6::Addr([0xff, 0x05, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xfd]);
+    const SOURCE: ipv6::Addr =
+        ipv6::Addr([0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x01]);
+
+    #[test]
+    fn mldv1() {
+        let mut buf = [0xff; 32];
+
+        let mut m = icmpv6::Message::multicast_listener_report(&mut buf[..]);
+        m.set_multicast_address(GROUP);
+        assert_eq!(m.as_bytes().len(), 24);
+
+        let m = icmpv6::Message::parse(m.as_bytes())
+            .unwrap()
+            .downcast::<icmpv6::MulticastListenerReport>()
+            .unwrap();
+        assert_eq!(m.get_code(), 0);
+        assert_eq!(m.get_multicast_address(), GROUP);
+
+        let mut m = icmpv6::Message::multicast_listener_query(&mut buf[..]);
+        m.set_maximum_response_delay(10_000);
+        m.set_multicast_address(ipv6::Addr::UNSPECIFIED);
+        let bytes = m.as_bytes();
+
+        // a 24-byte query is a MLDv1 query
+        let m = icmpv6::Message::parse(bytes).unwrap();
+        let m = m
+            .downcast::<icmpv6::MulticastListenerQueryV2>()
+            .unwrap_err()
+            .downcast::<icmpv6::MulticastListenerQuery>()
+            .unwrap();
+        assert_eq!(m.get_maximum_response_delay(), 10_000);
+        assert!(m.get_multicast_address().is_unspecified());
+    }
+
+    #[test]
+    fn query_v2() {
+        let mut buf = [0xff; 64];
+
+        let mut m = icmpv6::Message::multicast_listener_query_v2(&mut buf[..], &[SOURCE]);
+        m.set_multicast_address(GROUP);
+        m.set_maximum_response_code(0x8001);
+        m.set_s(true);
+        m.set_qrv(2);
+        m.set_qqic(125);
+        assert_eq!(m.as_bytes().len(), 28 + 16);
+
+        let m = icmpv6::Message::parse(m.as_bytes())
+            .unwrap()
+            .downcast::<icmpv6::MulticastListenerQueryV2>()
+            .unwrap();
+        assert_eq!(m.get_maximum_response_code(), 0x8001);
+        // exp = 0, mant = 1
+        assert_eq!(m.get_maximum_response_delay(), 0x1001 << 3);
+        assert_eq!(m.get_multicast_address(), GROUP);
+        assert_eq!(m.get_s(), true);
+        assert_eq!(m.get_qrv(), 2);
+        assert_eq!(m.get_qqi(), 125);
+        assert_eq!(m.get_number_of_sources(), 1);
+        let mut sources = m.sources();
+        assert_eq!(sources.next(), Some(SOURCE));
+        assert_eq!(sources.next(), None);
+    }
+
+    #[test]
+    fn report_v2() {
+        let mut buf = [0xff; 128];
+
+        let m = icmpv6::Message::multicast_listener_report_v2(
+            &mut buf[..],
+            &[
+                Record {
+                    record_type: RecordType::ChangeToExcludeMode,
+                    multicast_address: GROUP,
+                    sources: &[],
+                },
+                Record {
+                    record_type: RecordType::AllowNewSources,
+                    multicast_address: GROUP,
+                    sources: &[SOURCE, SOURCE],
+                },
+            ],
+        );
+        assert_eq!(m.as_bytes().len(), 8 + 20 + 20 + 32);
+
+        let m = icmpv6::Message::parse(m.as_bytes())
+            .unwrap()
+            .downcast::<icmpv6::MulticastListenerReportV2>()
+            .unwrap();
+        assert_eq!(m.get_number_of_records(), 2);
+
+        let mut records = m.records();
+
+        let first = records.next().unwrap();
+        assert_eq!(first.get_record_type(), RecordType::ChangeToExcludeMode);
+        assert_eq!(first.get_multicast_address(), GROUP);
+        assert_eq!(first.sources().count(), 0);
+        assert_eq!(first.aux_data(), &[]);
+
+        let second = records.next().unwrap();
+        assert_eq!(second.get_record_type(), RecordType::AllowNewSources);
+        assert_eq!(second.sources().len(), 2);
+        assert!(second.sources().all(|source| source == SOURCE));
+
+        assert!(records.next().is_none());
+    }
+
+    #[test]
+    fn report_v2_truncated() {
+        let mut buf = [0xff; 128];
+
+        let m = icmpv6::Message::multicast_listener_report_v2(
+            &mut buf[..],
+            &[Record {
+                record_type: RecordType::ModeIsExclude,
+                multicast_address: GROUP,
+                sources: &[SOURCE],
+            }],
+        );
+        let len = m.as_bytes().len();
+
+        assert!(icmpv6::Message::parse(&buf[..len - 1])
+            .unwrap()
+            .downcast::<icmpv6::MulticastListenerReportV2>()
+            .is_err());
+    }
+}
@@ -0,0 +1,206 @@
+//! Ping client logic
+//!
+//! `Session` keeps track of the Echo Requests that are waiting for a reply and matches incoming
+//! Echo Replies against them. There's no clock in this crate so the caller must pass the current
+//! time (`now`) to the methods that need it. The unit of time is up to the caller (e.g. timer
+//! ticks or milliseconds); the round trip times reported by `Session` will be in the same unit.
+//!
+//! # Example
+//!
+//! ```
+//! use jnet::icmpv6::{self, ping};
+//!
+//! let mut session = ping::Session::<4>::new(0x1234);
+//!
+//! // t = 10: send a request
+//! let mut buf = [0; 16];
+//! let mut request = icmpv6::Message::echo_request(&mut buf[..]);
+//! let seq = session.request(&mut request, 10).unwrap();
+//! request.set_payload(b"ping");
+//!
+//! // the remote end answers with the same identifier, sequence number and payload
+//! let reply: icmpv6::Message<_, icmpv6::EchoReply> = request.into();
+//!
+//! // t = 25: the reply arrives
+//! let pong = session.reply(&reply, 25).unwrap();
+//! assert_eq!(pong.sequence_number, seq);
+//! assert_eq!(pong.rtt, 15);
+//! ```
+
+use as_slice::{AsMutSlice, AsSlice};
+
+use crate::icmpv6::{EchoReply, EchoRequest, Message};
+
+/// Ping session
+///
+/// A session can have up to `N` outstanding Echo Requests
+pub struct Session<const N: usize> {
+    identifier: u16,
+    sequence_number: u16,
+    // (sequence number, time at which the request was sent)
+    outstanding: [Option<(u16, u32)>; N],
+}
+
+/// A matched Echo Reply
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Pong {
+    /// Sequence number of the Echo Request / Reply
+    pub sequence_number: u16,
+    /// Round trip time
+    pub rtt: u32,
+}
+
+impl<const N: usize> Session<N> {
+    /// Creates a new session that will use the given `identifier` in its Echo Requests
+    pub const fn new(identifier: u16) -> Self {
+        Session {
+            identifier,
+            sequence_number: 0,
+            outstanding: [None; N],
+        }
+    }
+
+    /// Returns the identifier used by this session
+    pub fn identifier(&self) -> u16 {
+        self.identifier
+    }
+
+    /// Returns the number of Echo Requests that are waiting for a reply
+    pub fn outstanding(&self) -> usize {
+        self.outstanding
+            .iter()
+            .filter(|slot| slot.is_some())
+            .count()
+    }
+
+    /// Fills the 'Identifier' and 'Sequence number' fields of the `request` and records the time
+    /// at which it was sent
+    ///
+    /// Returns the sequence number assigned to the request, or an error if there are already `N`
+    /// outstanding requests
+    pub fn request<B>(&mut self, request: &mut Message<B, EchoRequest>, now: u32) -> Result<u16, ()>
+    where
+        B: AsMutSlice<Element = u8>,
+    {
+        let slot = self
+            .outstanding
+            .iter_mut()
+            .find(|slot| slot.is_none())
+            .ok_or(())?;
+
+        let seq = self.sequence_number;
+        self.sequence_number = seq.wrapping_add(1);
+
+        *slot = Some((seq, now));
+
+        request.set_identifier(self.identifier);
+        request.set_sequence_number(seq);
+
+        Ok(seq)
+    }
+
+    /// Matches the `reply` against the outstanding Echo Requests
+    ///
+    /// Returns `None` if the reply doesn't belong to this session or if it's a duplicate of a
+    /// reply that was already processed
+    pub fn reply<B>(&mut self, reply: &Message<B, EchoReply>, now: u32) -> Option<Pong>
+    where
+        B: AsSlice<Element = u8>,
+    {
+        if reply.get_identifier() != self.identifier {
+            return None;
+        }
+
+        let seq = reply.get_sequence_number();
+        let slot = self
+            .outstanding
+            .iter_mut()
+            .find(|slot| slot.map(|(s, _)| s == seq).unwrap_or(false))?;
+
+        let (_, sent) = slot.take()?;
+
+        Some(Pong {
+            sequence_number: seq,
+            rtt: now.wrapping_sub(sent),
+        })
+    }
+
+    /// Removes an outstanding Echo Request that has been waiting for a reply for `timeout` or
+    /// longer
+    ///
+    /// Returns the sequence number of the expired request. Call this method repeatedly until it
+    /// returns `None` to remove all the expired requests.
+    pub fn expire(&mut self, now: u32, timeout: u32) -> Option<u16> {
+        self.outstanding.iter_mut().find_map(|slot| match *slot {
+            Some((seq, sent)) if now.wrapping_sub(sent) >= timeout => {
+                *slot = None;
+                Some(seq)
+            }
+            _ => None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::icmpv6::{self, EchoReply};
+
+    use super::{Pong, Session};
+
+    #[test]
+    fn session() {
+        let mut session = Session::<2>::new(7);
+
+        let mut a = [0; 8];
+        let mut b = [0; 8];
+        let mut c = [0; 8];
+
+        let mut ra = icmpv6::Message::echo_request(&mut a[..]);
+        let mut rb = icmpv6::Message::echo_request(&mut b[..]);
+        let mut rc = icmpv6::Message::echo_request(&mut c[..]);
+
+        assert_eq!(session.request(&mut ra, 0), Ok(0));
+        assert_eq!(session.request(&mut rb, 5), Ok(1));
+        // full
+        assert_eq!(session.request(&mut rc, 6), Err(()));
+        assert_eq!(session.outstanding(), 2);
+
+        // out of order reply
+        let pb: icmpv6::Message<_, EchoReply> = rb.into();
+        assert_eq!(
+            session.reply(&pb, 8),
+            Some(Pong {
+                sequence_number: 1,
+                rtt: 3
+            })
+        );
+        // duplicate
+        assert_eq!(session.reply(&pb, 9), None);
+
+        // `ra` times out
+        assert_eq!(session.expire(9, 10), None);
+        assert_eq!(session.expire(10, 10), Some(0));
+        assert_eq!(session.outstanding(), 0);
+
+        // late reply
+        let pa: icmpv6::Message<_, EchoReply> = ra.into();
+        assert_eq!(session.reply(&pa, 11), None);
+
+        // the slot is free again
+        assert_eq!(session.request(&mut rc, 12), Ok(2));
+    }
+
+    #[test]
+    fn foreign_identifier() {
+        let mut session = Session::<1>::new(7);
+
+        let mut buf = [0; 8];
+        let mut request = icmpv6::Message::echo_request(&mut buf[..]);
+        session.request(&mut request, 0).unwrap();
+        request.set_identifier(8);
+
+        let reply: icmpv6::Message<_, EchoReply> = request.into();
+        assert_eq!(session.reply(&reply, 1), None);
+        assert_eq!(session.outstanding(), 1);
+    }
+}
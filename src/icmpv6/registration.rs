@@ -0,0 +1,344 @@
+//! Host-side address registration (RFC 6775)
+//!
+//! `Registration` registers one address with the default router by sending it Neighbor
+//! Solicitations that carry an Address Registration Option (ARO), and refreshes the registration
+//! before it expires. There's no clock in this crate so the caller must pass the current time
+//! (`now`), *in seconds*, to the methods that need it.
+//!
+//! # Example
+//!
+//! ```
+//! use jnet::{
+//!     icmpv6::{self, lowpan_nd::Status, registration::{Registration, State}},
+//!     ieee802154, ipv6,
+//! };
+//!
+//! let addr = ipv6::Addr([0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+//! let eui64 = ieee802154::ExtendedAddr(0x0011_2233_4455_6677);
+//! let mut registration = Registration::new(addr, eui64, 60);
+//!
+//! // t = 0: a Neighbor Solicitation must be sent to the default router
+//! assert!(registration.poll(0));
+//! let mut buf = [0; 64];
+//! let mut ns = icmpv6::Message::neighbor_solicitation_with_aro(&mut buf[..], 2);
+//! registration.request(&mut ns, 0).unwrap();
+//! assert_eq!(registration.state(), State::Registering);
+//!
+//! // t = 1: the router accepts the registration
+//! let mut buf = [0; 64];
+//! let mut na = icmpv6::Message::neighbor_advertisement_with_aro(&mut buf[..]);
+//! na.set_target(addr);
+//! {
+//!     let mut aro = na.aro_mut().unwrap();
+//!     aro.set_registration_lifetime(60);
+//!     aro.set_eui64(eui64);
+//! }
+//! assert_eq!(registration.advertisement(&na, 1), Some(Status::Success));
+//! assert_eq!(registration.state(), State::Registered);
+//!
+//! // nothing to do until it's time to refresh the registration
+//! assert!(!registration.poll(2));
+//! assert!(registration.poll(1 + 45 * 60));
+//! ```
+
+use as_slice::{AsMutSlice, AsSlice};
+
+use crate::{
+    icmpv6::{lowpan_nd::Status, Message, NeighborAdvertisement, NeighborSolicitation},
+    ieee802154, ipv6,
+};
+
+/// Time between retransmissions of an unanswered Neighbor Solicitation, in seconds
+// RFC 4861 - Section 10. RETRANS_TIMER
+pub const RETRANS_TIMER: u32 = 1;
+
+/// Number of Neighbor Solicitations that will be sent before giving up
+// RFC 4861 - Section 10. MAX_UNICAST_SOLICIT
+pub const MAX_UNICAST_SOLICIT: u8 = 3;
+
+/// State of a `Registration`
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum State {
+    /// The address has not been registered yet, or the registration expired
+    Unregistered,
+    /// A Neighbor Solicitation was sent; waiting for the router's answer
+    Registering,
+    /// The router accepted the registration
+    Registered,
+    /// The router rejected the registration
+    Rejected(Status),
+    /// The router didn't answer any of the Neighbor Solicitations
+    Unreachable,
+}
+
+/// Registration of a single address with the default router
+pub struct Registration {
+    address: ipv6::Addr,
+    eui64: ieee802154::ExtendedAddr,
+    // requested lifetime, in minutes
+    lifetime: u16,
+    state: State,
+    // number of solicitations sent since the last answer
+    attempts: u8,
+    // time at which the last solicitation was sent
+    sent: u32,
+    // time at which the registration was confirmed
+    since: u32,
+    // lifetime granted by the router, in seconds
+    granted: u32,
+}
+
+impl Registration {
+    /// Creates a new registration of `address`
+    ///
+    /// `lifetime` is the registration lifetime that will be requested, in units of 60 seconds
+    pub const fn new(address: ipv6::Addr, eui64: ieee802154::ExtendedAddr, lifetime: u16) -> Self {
+        Registration {
+            address,
+            eui64,
+            lifetime,
+            state: State::Unregistered,
+            attempts: 0,
+            sent: 0,
+            since: 0,
+            granted: 0,
+        }
+    }
+
+    /// Returns the address being registered
+    pub fn address(&self) -> ipv6::Addr {
+        self.address
+    }
+
+    /// Returns the current state of the registration
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    /// Advances the state machine
+    ///
+    /// Returns `true` if a Neighbor Solicitation must be sent to the default router (see
+    /// `request`). The registration is refreshed once three quarters of the granted lifetime
+    /// have elapsed.
+    pub fn poll(&mut self, now: u32) -> bool {
+        match self.state {
+            State::Unregistered => true,
+
+            State::Registering => {
+                if now.wrapping_sub(self.sent) < RETRANS_TIMER {
+                    false
+                } else if self.attempts >= MAX_UNICAST_SOLICIT {
+                    self.state = State::Unreachable;
+                    false
+                } else {
+                    true
+                }
+            }
+
+            State::Registered => {
+                let elapsed = now.wrapping_sub(self.since);
+
+                if elapsed >= self.granted {
+                    // the registration expired; start over
+                    self.state = State::Unregistered;
+                    self.attempts = 0;
+                    true
+                } else if elapsed < self.granted - self.granted / 4 {
+                    false
+                } else {
+                    // refresh; retransmit like in the `Registering` state but keep the current
+                    // registration until it expires
+                    self.attempts == 0
+                        || (now.wrapping_sub(self.sent) >= RETRANS_TIMER
+                            && self.attempts < MAX_UNICAST_SOLICIT)
+                }
+            }
+
+            State::Rejected(_) | State::Unreachable => false,
+        }
+    }
+
+    /// Fills the 'Target Address' field and the Address Registration Option of `ns`
+    ///
+    /// Returns an error if `ns` doesn't contain an Address Registration Option (see
+    /// `Message::neighbor_solicitation_with_aro`)
+    pub fn request<B>(
+        &mut self,
+        ns: &mut Message<B, NeighborSolicitation>,
+        now: u32,
+    ) -> Result<(), ()>
+    where
+        B: AsMutSlice<Element = u8>,
+    {
+        {
+            let mut aro = ns.aro_mut().ok_or(())?;
+            aro.set_status(Status::Success);
+            aro.set_registration_lifetime(self.lifetime);
+            aro.set_eui64(self.eui64);
+        }
+        ns.set_target(self.address);
+
+        match self.state {
+            State::Registering | State::Registered => {}
+            _ => {
+                self.state = State::Registering;
+                self.attempts = 0;
+            }
+        }
+
+        self.attempts = self.attempts.saturating_add(1);
+        self.sent = now;
+
+        Ok(())
+    }
+
+    /// Processes a Neighbor Advertisement sent by the router
+    ///
+    /// Returns the status of the registration, or `None` if the advertisement is not an answer to
+    /// this registration
+    pub fn advertisement<B>(
+        &mut self,
+        na: &Message<B, NeighborAdvertisement>,
+        now: u32,
+    ) -> Option<Status>
+    where
+        B: AsSlice<Element = u8>,
+    {
+        if na.get_target() != self.address {
+            return None;
+        }
+
+        let aro = na.get_aro()?;
+        if aro.get_eui64() != self.eui64 {
+            return None;
+        }
+
+        let status = aro.get_status();
+        if status == Status::Success {
+            let lifetime = aro.get_registration_lifetime();
+
+            if lifetime == 0 {
+                // the registration was removed
+                self.state = State::Unregistered;
+            } else {
+                self.state = State::Registered;
+                self.since = now;
+                self.granted = u32::from(lifetime) * 60;
+            }
+        } else {
+            self.state = State::Rejected(status);
+        }
+
+        self.attempts = 0;
+
+        Some(status)
+    }
+
+    /// Restarts the registration process
+    ///
+    /// Use this after a `Rejected` or `Unreachable` state, or after switching default routers
+    pub fn restart(&mut self) {
+        self.state = State::Unregistered;
+        self.attempts = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        icmpv6::{self, lowpan_nd::Status},
+        ieee802154, ipv6,
+    };
+
+    use super::{Registration, State};
+
+    const ADDR: ipv6::Addr = ipv6::Addr([
+        0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0x02, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77,
+    ]);
+    const EUI64: ieee802154::ExtendedAddr = ieee802154::ExtendedAddr(0x0011_2233_4455_6677);
+
+    fn answer(registration: &mut Registration, status: Status, lifetime: u16, now: u32) {
+        let mut buf = [0; 64];
+        let mut na = icmpv6::Message::neighbor_advertisement_with_aro(&mut buf[..]);
+        na.set_target(ADDR);
+        {
+            let mut aro = na.aro_mut().unwrap();
+            aro.set_status(status);
+            aro.set_registration_lifetime(lifetime);
+            aro.set_eui64(EUI64);
+        }
+
+        assert_eq!(registration.advertisement(&na, now), Some(status));
+    }
+
+    fn solicit(registration: &mut Registration, now: u32) {
+        let mut buf = [0; 64];
+        let mut ns = icmpv6::Message::neighbor_solicitation_with_aro(&mut buf[..], 2);
+        registration.request(&mut ns, now).unwrap();
+
+        assert_eq!(ns.get_target(), ADDR);
+        let aro = ns.get_aro().unwrap();
+        assert_eq!(aro.get_registration_lifetime(), 10);
+        assert_eq!(aro.get_eui64(), EUI64);
+    }
+
+    #[test]
+    fn refresh() {
+        let mut registration = Registration::new(ADDR, EUI64, 10);
+
+        assert!(registration.poll(0));
+        solicit(&mut registration, 0);
+        assert_eq!(registration.state(), State::Registering);
+
+        answer(&mut registration, Status::Success, 10, 0);
+        assert_eq!(registration.state(), State::Registered);
+
+        // refresh after 7.5 minutes
+        assert!(!registration.poll(449));
+        assert!(registration.poll(450));
+        solicit(&mut registration, 450);
+        // still registered while the refresh is in flight
+        assert_eq!(registration.state(), State::Registered);
+
+        answer(&mut registration, Status::Success, 10, 451);
+        assert!(!registration.poll(900));
+
+        // unanswered refreshes; the registration eventually expires
+        assert!(registration.poll(901));
+        solicit(&mut registration, 901);
+        assert!(!registration.poll(901));
+        assert!(registration.poll(902));
+        assert!(registration.poll(1051));
+        assert_eq!(registration.state(), State::Unregistered);
+    }
+
+    #[test]
+    fn retransmissions() {
+        let mut registration = Registration::new(ADDR, EUI64, 10);
+
+        for now in 0..3 {
+            assert!(registration.poll(now));
+            solicit(&mut registration, now);
+        }
+
+        assert!(!registration.poll(3));
+        assert_eq!(registration.state(), State::Unreachable);
+
+        registration.restart();
+        assert!(registration.poll(4));
+    }
+
+    #[test]
+    fn rejected() {
+        let mut registration = Registration::new(ADDR, EUI64, 10);
+
+        solicit(&mut registration, 0);
+        answer(&mut registration, Status::DuplicateAddress, 0, 1);
+
+        assert_eq!(
+            registration.state(),
+            State::Rejected(Status::DuplicateAddress)
+        );
+        assert!(!registration.poll(2));
+    }
+}
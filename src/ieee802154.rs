@@ -9,13 +9,14 @@
 // NOTE(dev) unlike other networking protocol 802.15.4 uses the LITTLE endian byte order
 
 use core::fmt;
+use core::str::FromStr;
 
 use as_slice::{AsMutSlice, AsSlice};
 use byteorder::{ByteOrder, NetworkEndian as NE, LE};
 use owning_slice::Truncate;
 
 use crate::{
-    icmpv6, ipv6,
+    icmpv6, ipv6, mac,
     sixlowpan::{iphc, nhc},
     traits::UncheckedIndex,
 };
@@ -507,6 +508,35 @@ where
         self.buffer.truncate(self.payload + plen as u8);
     }
 
+    /// Fills the payload with an 'Echo Request' ICMPv6 message
+    pub fn echo_request<F>(&mut self, src: ipv6::Addr, dest: ipv6::Addr, f: F)
+    where
+        F: FnOnce(&mut icmpv6::Message<&mut [u8], icmpv6::EchoRequest>),
+    {
+        const HOP_LIMIT: u8 = 64;
+
+        let ctxt = iphc::Context {
+            source: self.get_src_addr(),
+            destination: self.get_dest_addr(),
+        };
+
+        let mut packet = iphc::Packet::new(
+            self.payload_mut(),
+            Some(ipv6::NextHeader::Ipv6Icmp),
+            HOP_LIMIT,
+            src,
+            dest,
+            &ctxt,
+        );
+
+        let mut message = icmpv6::Message::echo_request(packet.payload_mut());
+        f(&mut message);
+        message.update_checksum(src, dest);
+
+        let len = (message.as_bytes().len() + packet.header().len() + self.header().len()) as u8;
+        self.buffer.truncate(len);
+    }
+
     /// Fills the buffer with an 'Echo Reply' ICMPv6 message
     pub fn echo_reply<F>(&mut self, src: ipv6::Addr, dest: ipv6::Addr, f: F)
     where
@@ -578,6 +608,44 @@ where
         self.buffer.truncate(len);
     }
 
+    /// Fills the payload with a 'Neighbor Solicitation' ICMPv6 message that carries an Address
+    /// Registration Option (RFC 6775)
+    pub fn neighbor_solicitation_with_aro<F>(
+        &mut self,
+        src: ipv6::Addr,
+        dest: ipv6::Addr,
+        source_ll_addr: ExtendedAddr,
+        f: F,
+    ) where
+        F: FnOnce(&mut icmpv6::Message<&mut [u8], icmpv6::NeighborSolicitation>),
+    {
+        const HOP_LIMIT: u8 = 255;
+
+        let ctxt = iphc::Context {
+            source: self.get_src_addr(),
+            destination: self.get_dest_addr(),
+        };
+
+        let mut packet = iphc::Packet::new(
+            self.payload_mut(),
+            Some(ipv6::NextHeader::Ipv6Icmp),
+            HOP_LIMIT,
+            src,
+            dest,
+            &ctxt,
+        );
+
+        let mut message = icmpv6::Message::neighbor_solicitation_with_aro(packet.payload_mut(), 2);
+        f(&mut message);
+        unsafe {
+            message.set_source_ieee802154_addr(source_ll_addr);
+        }
+        message.update_checksum(src, dest);
+
+        let len = (message.as_bytes().len() + packet.header().len() + self.header().len()) as u8;
+        self.buffer.truncate(len);
+    }
+
     /// Fills the payload with a UDP packet
     pub fn udp<F>(
         &mut self,
@@ -753,13 +821,36 @@ impl Addr {
     }
 }
 
+impl fmt::Display for Addr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Addr::Short(sa) => sa.fmt(f),
+            Addr::Extended(ea) => ea.fmt(f),
+        }
+    }
+}
+
+impl FromStr for Addr {
+    type Err = ();
+
+    /// Parses either a short address (e.g. `0xbeef`) or an extended address (e.g.
+    /// `20:19:02:20:00:23:59:59`)
+    fn from_str(s: &str) -> Result<Self, ()> {
+        if s.contains(&[':', '-'][..]) {
+            s.parse().map(Addr::Extended)
+        } else {
+            s.parse().map(Addr::Short)
+        }
+    }
+}
+
 /// PAN identifier
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub struct PanId(pub u16);
 
 impl fmt::Display for PanId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{:#04x}", self.0)
+        write!(f, "{:#06x}", self.0)
     }
 }
 
@@ -779,7 +870,27 @@ pub struct ShortAddr(pub u16);
 
 impl fmt::Display for ShortAddr {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{:#04x}", self.0)
+        write!(f, "{:#06x}", self.0)
+    }
+}
+
+impl FromStr for ShortAddr {
+    type Err = ();
+
+    /// Parses up to 4 hex digits, with or without a `0x` prefix
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let digits = s
+            .strip_prefix("0x")
+            .or_else(|| s.strip_prefix("0X"))
+            .unwrap_or(s);
+
+        if digits.is_empty() || digits.len() > 4 || !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(());
+        }
+
+        u16::from_str_radix(digits, 16)
+            .map(ShortAddr)
+            .map_err(|_| ())
     }
 }
 
@@ -805,6 +916,12 @@ impl From<ShortAddr> for Addr {
 pub struct ExtendedAddr(pub u64);
 
 impl ExtendedAddr {
+    /// Creates an address from an array of bytes in network endianness, e.g. the EUI-64 printed on
+    /// a radio module
+    pub const fn from_ne_bytes(bytes: [u8; 8]) -> Self {
+        ExtendedAddr(u64::from_be_bytes(bytes))
+    }
+
     // Network endianness bytes
     /// Serializes the address into an array of bytes using network endianness
     pub fn ne_bytes(&self) -> [u8; 8] {
@@ -858,6 +975,17 @@ impl fmt::Display for ExtendedAddr {
     }
 }
 
+impl FromStr for ExtendedAddr {
+    type Err = ();
+
+    /// Parses an address in colon (`20:19:02:20:00:23:59:59`) or dash notation
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let mut bytes = [0; 8];
+        mac::parse_hex_bytes(s, &mut bytes)?;
+        Ok(ExtendedAddr::from_ne_bytes(bytes))
+    }
+}
+
 impl From<ExtendedAddr> for Addr {
     fn from(ea: ExtendedAddr) -> Addr {
         Addr::Extended(ea)
@@ -866,9 +994,63 @@ impl From<ExtendedAddr> for Addr {
 
 #[cfg(test)]
 mod tests {
+    use core::fmt::Write;
+
     use rand::{self, RngCore};
 
     use super::{Addr, ExtendedAddr, Frame, PanId, ShortAddr, SrcDest, Type};
+    use crate::fmt::Buffer;
+
+    #[test]
+    fn display() {
+        for (addr, expected) in &[
+            (Addr::Short(ShortAddr(0x12)), "0x0012"),
+            (Addr::Short(ShortAddr(0xbeef)), "0xbeef"),
+            (
+                Addr::Extended(ExtendedAddr(0x20_19_02_20_00_23_59_59)),
+                "20:19:02:20:00:23:59:59",
+            ),
+        ] {
+            let mut buf = Buffer::new();
+            write!(buf, "{}", addr).unwrap();
+            assert_eq!(buf.as_str(), *expected);
+            assert_eq!(expected.parse(), Ok(*addr));
+        }
+
+        let mut buf = Buffer::new();
+        write!(buf, "{}", PanId(0xab)).unwrap();
+        assert_eq!(buf.as_str(), "0x00ab");
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!("0xbeef".parse(), Ok(ShortAddr(0xbeef)));
+        assert_eq!("12".parse(), Ok(ShortAddr(0x12)));
+        assert_eq!(
+            "20:19:02:20:00:23:59:59".parse(),
+            Ok(ExtendedAddr(0x20_19_02_20_00_23_59_59))
+        );
+        assert_eq!(
+            "20-19-02-20-00-23-59-59".parse(),
+            Ok(Addr::Extended(ExtendedAddr(0x20_19_02_20_00_23_59_59)))
+        );
+        assert_eq!("0xffff".parse(), Ok(Addr::Short(ShortAddr::BROADCAST)));
+        assert_eq!(
+            ExtendedAddr::from_ne_bytes([0x20, 0x19, 0x02, 0x20, 0x00, 0x23, 0x59, 0x59]),
+            ExtendedAddr(0x20_19_02_20_00_23_59_59)
+        );
+
+        for s in &[
+            "",
+            "0x",
+            "0x12345",
+            "xyz",
+            "20:19:02:20:00:23:59",
+            "20:19:02:20",
+        ] {
+            assert!(s.parse::<Addr>().is_err(), "{}", s);
+        }
+    }
 
     #[test]
     fn data() {
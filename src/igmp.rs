@@ -0,0 +1,1050 @@
+//! IGMP: Internet Group Management Protocol
+//!
+//! # References
+//!
+//! - [RFC 2236: Internet Group Management Protocol, Version 2][0]
+//!
+//! [0]: https://tools.ietf.org/html/rfc2236
+//!
+//! - [RFC 3376: Internet Group Management Protocol, Version 3][1]
+//!
+//! [1]: https://tools.ietf.org/html/rfc3376
+
+use core::fmt;
+use core::marker::PhantomData;
+use core::ops::Range;
+
+use as_slice::{AsMutSlice, AsSlice};
+use byteorder::{ByteOrder, NetworkEndian as NE};
+use owning_slice::Truncate;
+
+use crate::{
+    checksum,
+    fmt::{Hex, Quoted},
+    ipv4,
+    sealed::Igmpv2,
+    traits::{TryFrom, TryInto, UncheckedIndex},
+    Invalid, Unknown, Valid,
+};
+
+/* IGMPv2 message format */
+const TYPE: usize = 0;
+const MAX_RESP_TIME: usize = 1;
+const CHECKSUM: Range<usize> = 2..4;
+const GROUP_ADDRESS: Range<usize> = 4..8;
+
+/// Size of an IGMPv2 message
+pub const V2_SIZE: u8 = GROUP_ADDRESS.end as u8;
+
+/* IGMPv3 Membership Query format */
+const MAX_RESP_CODE: usize = 1;
+// + CHECKSUM + GROUP_ADDRESS
+const S_QRV: usize = 8;
+mod qrv {
+    pub const MASK: u8 = (1 << SIZE) - 1;
+    pub const OFFSET: usize = 0;
+    pub const SIZE: usize = 3;
+}
+
+mod s {
+    pub const MASK: u8 = (1 << SIZE) - 1;
+    pub const OFFSET: usize = super::qrv::OFFSET + super::qrv::SIZE;
+    pub const SIZE: usize = 1;
+}
+
+const QQIC: usize = 9;
+const NUMBER_OF_SOURCES: Range<usize> = 10..12;
+const SOURCES: usize = 12;
+
+/// Minimum size of an IGMPv3 Membership Query
+pub const V3_QUERY_MIN_SIZE: u8 = SOURCES as u8;
+
+/* IGMPv3 Membership Report format */
+const NUMBER_OF_RECORDS: Range<usize> = 6..8;
+const RECORDS: usize = 8;
+
+/* Group Record format */
+const RECORD_TYPE: usize = 0;
+const AUX_DATA_LEN: usize = 1;
+const RECORD_NUMBER_OF_SOURCES: Range<usize> = 2..4;
+const RECORD_MULTICAST_ADDRESS: Range<usize> = 4..8;
+const RECORD_SOURCES: usize = 8;
+
+const ADDR_SIZE: usize = 4;
+
+/// IGMP Message
+pub struct Message<BUFFER, TYPE, CHECKSUM>
+where
+    BUFFER: AsSlice<Element = u8>,
+    TYPE: 'static,
+{
+    buffer: BUFFER,
+    _type: PhantomData<TYPE>,
+    _checksum: PhantomData<CHECKSUM>,
+}
+
+/// [Type State] Membership Query (IGMPv1 / IGMPv2)
+pub enum MembershipQuery {}
+
+/// [Type State] Membership Query (IGMPv3)
+pub enum MembershipQueryV3 {}
+
+/// [Type State] Version 2 Membership Report
+pub enum MembershipReportV2 {}
+
+/// [Type State] Leave Group
+pub enum LeaveGroup {}
+
+/// [Type State] Version 3 Membership Report
+pub enum MembershipReportV3 {}
+
+/* Unknown */
+impl<B> Message<B, Unknown, Valid>
+where
+    B: AsSlice<Element = u8>,
+{
+    /* Constructors */
+    /// Parses the input bytes into an IGMP message
+    ///
+    /// This function verifies the checksum of the message
+    pub fn parse(bytes: B) -> Result<Self, B> {
+        let len = bytes.as_slice().len();
+        if len < usize::from(V2_SIZE) || len % 2 != 0 {
+            return Err(bytes);
+        }
+
+        let m: Self = unsafe { Message::unchecked(bytes) };
+
+        if checksum::verify(m.as_bytes()) {
+            Ok(m)
+        } else {
+            Err(m.buffer)
+        }
+    }
+}
+
+impl<B, C> Message<B, Unknown, C>
+where
+    B: AsSlice<Element = u8>,
+{
+    /// Downcasts this message with unknown type into a specific type
+    pub fn downcast<TYPE>(self) -> Result<Message<B, TYPE, C>, Self>
+    where
+        Self: TryInto<Message<B, TYPE, C>, Error = Self>,
+    {
+        self.try_into()
+    }
+}
+
+/* IGMPv2 */
+impl<B, M, C> Message<B, M, C>
+where
+    B: AsSlice<Element = u8>,
+    M: Igmpv2,
+{
+    /* Getters */
+    /// Reads the 'Group Address' field
+    pub fn get_group_address(&self) -> ipv4::Addr {
+        unsafe { ipv4::Addr(*(self.as_slice().as_ptr().add(GROUP_ADDRESS.start) as *const _)) }
+    }
+}
+
+impl<B, M> Message<B, M, Invalid>
+where
+    B: AsSlice<Element = u8> + AsMutSlice<Element = u8>,
+    M: Igmpv2,
+{
+    /* Setters */
+    /// Sets the 'Group Address' field
+    pub fn set_group_address(&mut self, addr: ipv4::Addr) {
+        unsafe {
+            self.as_mut_slice()
+                .rm(GROUP_ADDRESS)
+                .copy_from_slice(&addr.0)
+        }
+    }
+}
+
+impl<B, M> Message<B, M, Invalid>
+where
+    B: AsSlice<Element = u8> + AsMutSlice<Element = u8> + Truncate<u16>,
+    M: Igmpv2,
+{
+    fn v2(mut buffer: B, ty: Type) -> Self {
+        assert!(buffer.as_slice().len() >= usize::from(V2_SIZE));
+
+        buffer.truncate(u16::from(V2_SIZE));
+
+        let mut m: Self = unsafe { Message::unchecked(buffer) };
+        m.set_type(ty);
+        unsafe { *m.as_mut_slice().gum(MAX_RESP_TIME) = 0 }
+
+        m
+    }
+}
+
+impl<B, C> Message<B, MembershipQuery, C>
+where
+    B: AsSlice<Element = u8>,
+{
+    /// Reads the 'Max Resp Time' field (in units of 1/10 second)
+    ///
+    /// A value of zero indicates that this is an IGMPv1 query
+    pub fn get_max_resp_time(&self) -> u8 {
+        unsafe { *self.as_slice().gu(MAX_RESP_TIME) }
+    }
+}
+
+impl<B> Message<B, MembershipQuery, Invalid>
+where
+    B: AsSlice<Element = u8> + AsMutSlice<Element = u8>,
+{
+    /// Sets the 'Max Resp Time' field (in units of 1/10 second)
+    pub fn set_max_resp_time(&mut self, time: u8) {
+        unsafe { *self.as_mut_slice().gum(MAX_RESP_TIME) = time }
+    }
+}
+
+impl<B> Message<B, MembershipQuery, Invalid>
+where
+    B: AsSlice<Element = u8> + AsMutSlice<Element = u8> + Truncate<u16>,
+{
+    /* Constructors */
+    /// Transforms the input buffer into a (IGMPv2) Membership Query
+    ///
+    /// The 'Max Resp Time' field is set to zero. The 'Group Address' field needs to be filled by
+    /// the caller; use the unspecified address for a General Query.
+    pub fn membership_query(buffer: B) -> Self {
+        Message::v2(buffer, Type::MembershipQuery)
+    }
+}
+
+impl<B> Message<B, MembershipReportV2, Invalid>
+where
+    B: AsSlice<Element = u8> + AsMutSlice<Element = u8> + Truncate<u16>,
+{
+    /* Constructors */
+    /// Transforms the input buffer into a Version 2 Membership Report
+    ///
+    /// The 'Group Address' field needs to be filled by the caller
+    pub fn membership_report_v2(buffer: B) -> Self {
+        Message::v2(buffer, Type::MembershipReportV2)
+    }
+}
+
+impl<B> Message<B, LeaveGroup, Invalid>
+where
+    B: AsSlice<Element = u8> + AsMutSlice<Element = u8> + Truncate<u16>,
+{
+    /* Constructors */
+    /// Transforms the input buffer into a Leave Group message
+    ///
+    /// The 'Group Address' field needs to be filled by the caller
+    pub fn leave_group(buffer: B) -> Self {
+        Message::v2(buffer, Type::LeaveGroup)
+    }
+}
+
+impl<B, C> TryFrom<Message<B, Unknown, C>> for Message<B, MembershipQuery, C>
+where
+    B: AsSlice<Element = u8>,
+{
+    type Error = Message<B, Unknown, C>;
+
+    fn try_from(m: Message<B, Unknown, C>) -> Result<Self, Message<B, Unknown, C>> {
+        // RFC 3376 - Section 7.1. Query Version Distinctions
+        // "IGMPv1 Query: length = 8 octets AND Max Resp Code field is zero"
+        // "IGMPv2 Query: length = 8 octets AND Max Resp Code field is non-zero"
+        if m.get_type() == Type::MembershipQuery && m.as_slice().len() == usize::from(V2_SIZE) {
+            Ok(unsafe { Message::unchecked(m.buffer) })
+        } else {
+            Err(m)
+        }
+    }
+}
+
+impl<B, C> TryFrom<Message<B, Unknown, C>> for Message<B, MembershipReportV2, C>
+where
+    B: AsSlice<Element = u8>,
+{
+    type Error = Message<B, Unknown, C>;
+
+    fn try_from(m: Message<B, Unknown, C>) -> Result<Self, Message<B, Unknown, C>> {
+        if m.get_type() == Type::MembershipReportV2 {
+            Ok(unsafe { Message::unchecked(m.buffer) })
+        } else {
+            Err(m)
+        }
+    }
+}
+
+impl<B, C> TryFrom<Message<B, Unknown, C>> for Message<B, LeaveGroup, C>
+where
+    B: AsSlice<Element = u8>,
+{
+    type Error = Message<B, Unknown, C>;
+
+    fn try_from(m: Message<B, Unknown, C>) -> Result<Self, Message<B, Unknown, C>> {
+        if m.get_type() == Type::LeaveGroup {
+            Ok(unsafe { Message::unchecked(m.buffer) })
+        } else {
+            Err(m)
+        }
+    }
+}
+
+/* IGMPv3 Membership Query */
+impl<B, C> Message<B, MembershipQueryV3, C>
+where
+    B: AsSlice<Element = u8>,
+{
+    /* Getters */
+    /// Reads the 'Max Resp Code' field
+    pub fn get_max_resp_code(&self) -> u8 {
+        unsafe { *self.as_slice().gu(MAX_RESP_CODE) }
+    }
+
+    /// Returns the Max Resp Time, in units of 1/10 second, encoded in the 'Max Resp Code' field
+    pub fn get_max_resp_time(&self) -> u16 {
+        // RFC 3376 - Section 4.1.1. Max Resp Code
+        decode(self.get_max_resp_code())
+    }
+
+    /// Reads the 'Group Address' field
+    pub fn get_group_address(&self) -> ipv4::Addr {
+        unsafe { ipv4::Addr(*(self.as_slice().as_ptr().add(GROUP_ADDRESS.start) as *const _)) }
+    }
+
+    /// Reads the 'S' (Suppress Router-Side Processing) flag
+    pub fn get_s(&self) -> bool {
+        unsafe { get!(self.as_slice().gu(S_QRV), s) == 1 }
+    }
+
+    /// Reads the 'QRV' (Querier's Robustness Variable) field
+    pub fn get_qrv(&self) -> u8 {
+        unsafe { get!(self.as_slice().gu(S_QRV), qrv) }
+    }
+
+    /// Reads the 'QQIC' (Querier's Query Interval Code) field
+    pub fn get_qqic(&self) -> u8 {
+        unsafe { *self.as_slice().gu(QQIC) }
+    }
+
+    /// Returns the Querier's Query Interval, in seconds, encoded in the 'QQIC' field
+    pub fn get_qqi(&self) -> u16 {
+        // RFC 3376 - Section 4.1.7. QQIC (Querier's Query Interval Code)
+        decode(self.get_qqic())
+    }
+
+    /// Reads the 'Number of Sources' field
+    pub fn get_number_of_sources(&self) -> u16 {
+        unsafe { NE::read_u16(self.as_slice().r(NUMBER_OF_SOURCES)) }
+    }
+
+    /// Returns an iterator over the 'Source Address' fields
+    pub fn sources(&self) -> Sources<'_> {
+        let end = SOURCES + usize::from(self.get_number_of_sources()) * ADDR_SIZE;
+
+        Sources {
+            bytes: unsafe { self.as_slice().r(SOURCES..end) },
+        }
+    }
+}
+
+impl<B> Message<B, MembershipQueryV3, Invalid>
+where
+    B: AsSlice<Element = u8> + AsMutSlice<Element = u8>,
+{
+    /* Setters */
+    /// Sets the 'Max Resp Code' field
+    pub fn set_max_resp_code(&mut self, code: u8) {
+        unsafe { *self.as_mut_slice().gum(MAX_RESP_CODE) = code }
+    }
+
+    /// Sets the 'Group Address' field
+    pub fn set_group_address(&mut self, addr: ipv4::Addr) {
+        unsafe {
+            self.as_mut_slice()
+                .rm(GROUP_ADDRESS)
+                .copy_from_slice(&addr.0)
+        }
+    }
+
+    /// Sets the 'S' (Suppress Router-Side Processing) flag
+    pub fn set_s(&mut self, s: bool) {
+        unsafe { set!(*self.as_mut_slice().gum(S_QRV), s, if s { 1 } else { 0 }) }
+    }
+
+    /// Sets the 'QRV' (Querier's Robustness Variable) field
+    pub fn set_qrv(&mut self, qrv: u8) {
+        unsafe { set!(*self.as_mut_slice().gum(S_QRV), qrv, qrv) }
+    }
+
+    /// Sets the 'QQIC' (Querier's Query Interval Code) field
+    pub fn set_qqic(&mut self, qqic: u8) {
+        unsafe { *self.as_mut_slice().gum(QQIC) = qqic }
+    }
+}
+
+impl<B> Message<B, MembershipQueryV3, Invalid>
+where
+    B: AsSlice<Element = u8> + AsMutSlice<Element = u8> + Truncate<u16>,
+{
+    /* Constructors */
+    /// Transforms the input buffer into a (IGMPv3) Membership Query that contains the given
+    /// `sources`
+    ///
+    /// The 'Max Resp Code', 'S', 'QRV' and 'QQIC' fields are set to zero. The 'Group Address'
+    /// field needs to be filled by the caller; use the unspecified address for a General Query.
+    ///
+    /// # Panics
+    ///
+    /// This constructor panics if the buffer is not large enough to contain the message
+    pub fn membership_query_v3(mut buffer: B, sources: &[ipv4::Addr]) -> Self {
+        let size = SOURCES + sources.len() * ADDR_SIZE;
+        assert!(buffer.as_slice().len() >= size && size <= usize::from(u16::max_value()));
+
+        buffer.truncate(size as u16);
+
+        let mut m: Self = unsafe { Message::unchecked(buffer) };
+        m.set_type(Type::MembershipQuery);
+
+        let bytes = m.as_mut_slice();
+        unsafe {
+            *bytes.gum(MAX_RESP_CODE) = 0;
+            bytes
+                .rm(S_QRV..NUMBER_OF_SOURCES.start)
+                .copy_from_slice(&[0; 2]);
+            NE::write_u16(bytes.rm(NUMBER_OF_SOURCES), sources.len() as u16);
+
+            for (chunk, source) in bytes
+                .rfm(SOURCES..)
+                .chunks_exact_mut(ADDR_SIZE)
+                .zip(sources)
+            {
+                chunk.copy_from_slice(&source.0);
+            }
+        }
+
+        m
+    }
+}
+
+impl<B, C> TryFrom<Message<B, Unknown, C>> for Message<B, MembershipQueryV3, C>
+where
+    B: AsSlice<Element = u8>,
+{
+    type Error = Message<B, Unknown, C>;
+
+    fn try_from(m: Message<B, Unknown, C>) -> Result<Self, Message<B, Unknown, C>> {
+        // RFC 3376 - Section 7.1. Query Version Distinctions
+        // "IGMPv3 Query: length >= 12 octets"
+        let len = m.as_slice().len();
+        if m.get_type() == Type::MembershipQuery && len >= SOURCES {
+            let nsources = usize::from(unsafe { NE::read_u16(m.as_slice().r(NUMBER_OF_SOURCES)) });
+
+            if len >= SOURCES + nsources * ADDR_SIZE {
+                return Ok(unsafe { Message::unchecked(m.buffer) });
+            }
+        }
+
+        Err(m)
+    }
+}
+
+/* IGMPv3 Membership Report */
+impl<B, C> Message<B, MembershipReportV3, C>
+where
+    B: AsSlice<Element = u8>,
+{
+    /* Getters */
+    /// Reads the 'Number of Group Records' field
+    pub fn get_number_of_records(&self) -> u16 {
+        unsafe { NE::read_u16(self.as_slice().r(NUMBER_OF_RECORDS)) }
+    }
+
+    /// Returns an iterator over the Group Records of this report
+    pub fn records(&self) -> GroupRecords<'_> {
+        GroupRecords {
+            bytes: unsafe { self.as_slice().rf(RECORDS..) },
+            count: self.get_number_of_records(),
+        }
+    }
+}
+
+impl<B> Message<B, MembershipReportV3, Invalid>
+where
+    B: AsSlice<Element = u8> + AsMutSlice<Element = u8> + Truncate<u16>,
+{
+    /* Constructors */
+    /// Transforms the input buffer into a Version 3 Membership Report that contains the given
+    /// `records`
+    ///
+    /// # Panics
+    ///
+    /// This constructor panics if the buffer is not large enough to contain the message
+    pub fn membership_report_v3(mut buffer: B, records: &[Record<'_>]) -> Self {
+        let size = records.iter().fold(RECORDS, |size, r| size + r.size());
+        assert!(buffer.as_slice().len() >= size && size <= usize::from(u16::max_value()));
+
+        buffer.truncate(size as u16);
+
+        let mut m: Self = unsafe { Message::unchecked(buffer) };
+        m.set_type(Type::MembershipReportV3);
+
+        let bytes = m.as_mut_slice();
+        unsafe {
+            // clear the 'Reserved' fields
+            *bytes.gum(1) = 0;
+            bytes.rm(4..6).copy_from_slice(&[0; 2]);
+            NE::write_u16(bytes.rm(NUMBER_OF_RECORDS), records.len() as u16);
+
+            let mut cursor = RECORDS;
+            for record in records {
+                let end = cursor + record.size();
+                record.write(bytes.rm(cursor..end));
+                cursor = end;
+            }
+        }
+
+        m
+    }
+}
+
+impl<B, C> TryFrom<Message<B, Unknown, C>> for Message<B, MembershipReportV3, C>
+where
+    B: AsSlice<Element = u8>,
+{
+    type Error = Message<B, Unknown, C>;
+
+    fn try_from(m: Message<B, Unknown, C>) -> Result<Self, Message<B, Unknown, C>> {
+        if m.get_type() == Type::MembershipReportV3 && m.as_slice().len() >= RECORDS {
+            let count = unsafe { NE::read_u16(m.as_slice().r(NUMBER_OF_RECORDS)) };
+
+            if GroupRecords::are_valid(&m.as_slice()[RECORDS..], count) {
+                return Ok(unsafe { Message::unchecked(m.buffer) });
+            }
+        }
+
+        Err(m)
+    }
+}
+
+/* TYPE */
+impl<B, T, C> Message<B, T, C>
+where
+    B: AsSlice<Element = u8>,
+{
+    /* Constructors */
+    unsafe fn unchecked(buffer: B) -> Self {
+        Message {
+            buffer,
+            _type: PhantomData,
+            _checksum: PhantomData,
+        }
+    }
+
+    /* Getters */
+    /// Returns the Type field of the message
+    pub fn get_type(&self) -> Type {
+        if typeid!(T == MembershipQuery) || typeid!(T == MembershipQueryV3) {
+            Type::MembershipQuery
+        } else if typeid!(T == MembershipReportV2) {
+            Type::MembershipReportV2
+        } else if typeid!(T == LeaveGroup) {
+            Type::LeaveGroup
+        } else if typeid!(T == MembershipReportV3) {
+            Type::MembershipReportV3
+        } else {
+            unsafe { *self.as_slice().gu(TYPE) }.into()
+        }
+    }
+
+    /// Returns the length of this message
+    pub fn len(&self) -> u16 {
+        self.as_slice().len() as u16
+    }
+
+    /// Returns the byte representation of this message
+    pub fn as_bytes(&self) -> &[u8] {
+        self.as_slice()
+    }
+
+    /* Private */
+    fn as_slice(&self) -> &[u8] {
+        self.buffer.as_slice()
+    }
+
+    fn get_checksum(&self) -> u16 {
+        unsafe { NE::read_u16(self.as_slice().r(CHECKSUM)) }
+    }
+}
+
+impl<B, T> Message<B, T, Invalid>
+where
+    B: AsSlice<Element = u8> + AsMutSlice<Element = u8>,
+{
+    /// Updates the Checksum field of the message
+    pub fn update_checksum(mut self) -> Message<B, T, Valid> {
+        let cksum = checksum::compute(self.as_bytes(), CHECKSUM.start);
+        unsafe { NE::write_u16(self.as_mut_slice().rm(CHECKSUM), cksum) }
+
+        unsafe { Message::unchecked(self.buffer) }
+    }
+
+    /* Private */
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        self.buffer.as_mut_slice()
+    }
+
+    fn set_type(&mut self, ty: Type) {
+        unsafe { *self.as_mut_slice().gum(TYPE) = ty.into() }
+    }
+}
+
+impl<B, T, C> Clone for Message<B, T, C>
+where
+    B: AsSlice<Element = u8> + Clone,
+{
+    fn clone(&self) -> Self {
+        Message {
+            buffer: self.buffer.clone(),
+            _type: PhantomData,
+            _checksum: PhantomData,
+        }
+    }
+}
+
+impl<B, C> fmt::Debug for Message<B, Unknown, C>
+where
+    B: AsSlice<Element = u8>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("igmp::Message")
+            .field("type", &self.get_type())
+            .field("checksum", &Hex(self.get_checksum()))
+            .finish()
+    }
+}
+
+impl<B, C> fmt::Debug for Message<B, MembershipQuery, C>
+where
+    B: AsSlice<Element = u8>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("igmp::Message<MembershipQuery>")
+            .field("max_resp_time", &self.get_max_resp_time())
+            .field("checksum", &Hex(self.get_checksum()))
+            .field("group_address", &Quoted(self.get_group_address()))
+            .finish()
+    }
+}
+
+impl<B, C> fmt::Debug for Message<B, MembershipReportV2, C>
+where
+    B: AsSlice<Element = u8>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("igmp::Message<MembershipReportV2>")
+            .field("checksum", &Hex(self.get_checksum()))
+            .field("group_address", &Quoted(self.get_group_address()))
+            .finish()
+    }
+}
+
+impl<B, C> fmt::Debug for Message<B, LeaveGroup, C>
+where
+    B: AsSlice<Element = u8>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("igmp::Message<LeaveGroup>")
+            .field("checksum", &Hex(self.get_checksum()))
+            .field("group_address", &Quoted(self.get_group_address()))
+            .finish()
+    }
+}
+
+impl<B, C> fmt::Debug for Message<B, MembershipQueryV3, C>
+where
+    B: AsSlice<Element = u8>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("igmp::Message<MembershipQueryV3>")
+            .field("max_resp_code", &self.get_max_resp_code())
+            .field("checksum", &Hex(self.get_checksum()))
+            .field("group_address", &Quoted(self.get_group_address()))
+            .field("s", &self.get_s())
+            .field("qrv", &self.get_qrv())
+            .field("qqic", &self.get_qqic())
+            .field("number_of_sources", &self.get_number_of_sources())
+            .finish()
+    }
+}
+
+impl<B, C> fmt::Debug for Message<B, MembershipReportV3, C>
+where
+    B: AsSlice<Element = u8>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("igmp::Message<MembershipReportV3>")
+            .field("checksum", &Hex(self.get_checksum()))
+            .field("number_of_records", &self.get_number_of_records())
+            .finish()
+    }
+}
+
+/// Group Record to be included in a Version 3 Membership Report
+#[derive(Clone, Copy, Debug)]
+pub struct Record<'a> {
+    /// Record type
+    pub record_type: RecordType,
+    /// Multicast address the record pertains to
+    pub multicast_address: ipv4::Addr,
+    /// Source addresses
+    pub sources: &'a [ipv4::Addr],
+}
+
+impl<'a> Record<'a> {
+    fn size(&self) -> usize {
+        RECORD_SOURCES + self.sources.len() * ADDR_SIZE
+    }
+
+    // NOTE `bytes.len()` must be equal to `self.size()`
+    unsafe fn write(&self, bytes: &mut [u8]) {
+        debug_assert_eq!(bytes.len(), self.size());
+
+        *bytes.gum(RECORD_TYPE) = self.record_type.into();
+        // RFC 3376 - Section 4.2.6. "IGMPv3 does not define any auxiliary data"
+        *bytes.gum(AUX_DATA_LEN) = 0;
+        NE::write_u16(
+            bytes.rm(RECORD_NUMBER_OF_SOURCES),
+            self.sources.len() as u16,
+        );
+        bytes
+            .rm(RECORD_MULTICAST_ADDRESS)
+            .copy_from_slice(&self.multicast_address.0);
+
+        for (chunk, source) in bytes
+            .rfm(RECORD_SOURCES..)
+            .chunks_exact_mut(ADDR_SIZE)
+            .zip(self.sources)
+        {
+            chunk.copy_from_slice(&source.0);
+        }
+    }
+}
+
+/// View into a Group Record of a Version 3 Membership Report
+#[derive(Clone, Copy)]
+pub struct GroupRecord<'a> {
+    // NOTE spans the whole record, including the auxiliary data
+    bytes: &'a [u8],
+}
+
+impl<'a> GroupRecord<'a> {
+    /// Reads the 'Record Type' field
+    pub fn get_record_type(&self) -> RecordType {
+        RecordType::from(unsafe { *self.bytes.gu(RECORD_TYPE) })
+    }
+
+    /// Reads the 'Number of Sources' field
+    pub fn get_number_of_sources(&self) -> u16 {
+        unsafe { NE::read_u16(self.bytes.r(RECORD_NUMBER_OF_SOURCES)) }
+    }
+
+    /// Reads the 'Multicast Address' field
+    pub fn get_multicast_address(&self) -> ipv4::Addr {
+        unsafe {
+            ipv4::Addr(*(self.bytes.as_ptr().add(RECORD_MULTICAST_ADDRESS.start) as *const _))
+        }
+    }
+
+    /// Returns an iterator over the 'Source Address' fields
+    pub fn sources(&self) -> Sources<'a> {
+        Sources {
+            bytes: unsafe { self.bytes.r(RECORD_SOURCES..self.aux_data_start()) },
+        }
+    }
+
+    /// Immutable view into the 'Auxiliary Data' field
+    pub fn aux_data(&self) -> &'a [u8] {
+        unsafe { self.bytes.rf(self.aux_data_start()..) }
+    }
+
+    fn aux_data_start(&self) -> usize {
+        RECORD_SOURCES + usize::from(self.get_number_of_sources()) * ADDR_SIZE
+    }
+}
+
+impl<'a> fmt::Debug for GroupRecord<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("igmp::GroupRecord")
+            .field("record_type", &self.get_record_type())
+            .field("multicast_address", &Quoted(self.get_multicast_address()))
+            .field("number_of_sources", &self.get_number_of_sources())
+            .finish()
+    }
+}
+
+/// Iterator over the Group Records of a Version 3 Membership Report
+pub struct GroupRecords<'a> {
+    bytes: &'a [u8],
+    count: u16,
+}
+
+impl<'a> GroupRecords<'a> {
+    // Size of the record at the start of `bytes`, if `bytes` contains a complete record
+    fn record_size(bytes: &[u8]) -> Option<usize> {
+        if bytes.len() < RECORD_SOURCES {
+            return None;
+        }
+
+        let aux_data_len = usize::from(bytes[AUX_DATA_LEN]) * 4;
+        let nsources = usize::from(NE::read_u16(&bytes[RECORD_NUMBER_OF_SOURCES]));
+        let size = RECORD_SOURCES + nsources * ADDR_SIZE + aux_data_len;
+
+        if bytes.len() < size {
+            None
+        } else {
+            Some(size)
+        }
+    }
+
+    fn are_valid(mut bytes: &[u8], count: u16) -> bool {
+        for _ in 0..count {
+            if let Some(size) = Self::record_size(bytes) {
+                bytes = &bytes[size..];
+            } else {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+impl<'a> Iterator for GroupRecords<'a> {
+    type Item = GroupRecord<'a>;
+
+    fn next(&mut self) -> Option<GroupRecord<'a>> {
+        if self.count == 0 {
+            return None;
+        }
+
+        // NOTE(unwrap) the records were validated in `try_from`
+        let size = Self::record_size(self.bytes).unwrap_or_else(|| unsafe { debug_unreachable!() });
+        let bytes = unsafe { self.bytes.rt(..size) };
+        self.bytes = unsafe { self.bytes.rf(size..) };
+        self.count -= 1;
+
+        Some(GroupRecord { bytes })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::from(self.count), Some(usize::from(self.count)))
+    }
+}
+
+/// Iterator over a list of source addresses
+pub struct Sources<'a> {
+    // NOTE the length of this slice is always a multiple of `ADDR_SIZE`
+    bytes: &'a [u8],
+}
+
+impl<'a> ExactSizeIterator for Sources<'a> {}
+
+impl<'a> Iterator for Sources<'a> {
+    type Item = ipv4::Addr;
+
+    fn next(&mut self) -> Option<ipv4::Addr> {
+        if self.bytes.is_empty() {
+            None
+        } else {
+            unsafe {
+                let addr = ipv4::Addr(*(self.bytes.as_ptr() as *const _));
+                self.bytes = self.bytes.rf(ADDR_SIZE..);
+                Some(addr)
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.bytes.len() / ADDR_SIZE;
+        (n, Some(n))
+    }
+}
+
+// Decodes the floating point representation used by the 'Max Resp Code' and 'QQIC' fields
+fn decode(code: u8) -> u16 {
+    if code < 128 {
+        u16::from(code)
+    } else {
+        let exp = (code >> 4) & 0b111;
+        let mant = code & 0xf;
+
+        u16::from(mant | 0x10) << (exp + 3)
+    }
+}
+
+full_range!(
+    u8,
+    /// IGMP types
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub enum Type {
+        /// Membership Query
+        MembershipQuery = 0x11,
+        /// Version 1 Membership Report
+        MembershipReportV1 = 0x12,
+        /// Version 2 Membership Report
+        MembershipReportV2 = 0x16,
+        /// Leave Group
+        LeaveGroup = 0x17,
+        /// Version 3 Membership Report
+        MembershipReportV3 = 0x22,
+    }
+);
+
+full_range!(
+    u8,
+    /// Group Record type
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub enum RecordType {
+        /// MODE_IS_INCLUDE
+        ModeIsInclude = 1,
+        /// MODE_IS_EXCLUDE
+        ModeIsExclude = 2,
+        /// CHANGE_TO_INCLUDE_MODE
+        ChangeToIncludeMode = 3,
+        /// CHANGE_TO_EXCLUDE_MODE
+        ChangeToExcludeMode = 4,
+        /// ALLOW_NEW_SOURCES
+        AllowNewSources = 5,
+        /// BLOCK_OLD_SOURCES
+        BlockOldSources = 6,
+    }
+);
+
+#[cfg(test)]
+mod tests {
+    use crate::{igmp, ipv4};
+
+    use super::{Record, RecordType};
+
+    const GROUP: ipv4::Addr = ipv4::Addr([239, 1, 2, 3]);
+    const SOURCE: ipv4::Addr = ipv4::Addr([192, 168, 0, 1]);
+
+    #[test]
+    fn v2() {
+        let mut buf = [0xff; 16];
+
+        let mut m = igmp::Message::membership_report_v2(&mut buf[..]);
+        m.set_group_address(GROUP);
+        let m = m.update_checksum();
+        assert_eq!(m.as_bytes(), &[0x16, 0, 0xf8, 0xfa, 239, 1, 2, 3]);
+
+        let m = igmp::Message::parse(m.as_bytes())
+            .unwrap()
+            .downcast::<igmp::MembershipReportV2>()
+            .unwrap();
+        assert_eq!(m.get_group_address(), GROUP);
+
+        let mut m = igmp::Message::membership_query(&mut buf[..]);
+        m.set_max_resp_time(100);
+        m.set_group_address(ipv4::Addr::UNSPECIFIED);
+        let m = m.update_checksum();
+        let bytes = m.as_bytes();
+
+        // an 8-byte query is an IGMPv2 query
+        let m = igmp::Message::parse(bytes).unwrap();
+        let m = m
+            .downcast::<igmp::MembershipQueryV3>()
+            .unwrap_err()
+            .downcast::<igmp::MembershipQuery>()
+            .unwrap();
+        assert_eq!(m.get_max_resp_time(), 100);
+        assert_eq!(m.get_group_address(), ipv4::Addr::UNSPECIFIED);
+
+        // bad checksum
+        let mut bytes = [0x17, 0, 0, 0, 239, 1, 2, 3];
+        assert!(igmp::Message::parse(&bytes[..]).is_err());
+
+        let m = igmp::Message::leave_group(&mut bytes[..]).update_checksum();
+        assert!(igmp::Message::parse(m.as_bytes())
+            .unwrap()
+            .downcast::<igmp::LeaveGroup>()
+            .is_ok());
+    }
+
+    #[test]
+    fn query_v3() {
+        let mut buf = [0xff; 32];
+
+        let mut m = igmp::Message::membership_query_v3(&mut buf[..], &[SOURCE]);
+        m.set_group_address(GROUP);
+        m.set_max_resp_code(0x81);
+        m.set_s(true);
+        m.set_qrv(2);
+        m.set_qqic(125);
+        let m = m.update_checksum();
+        assert_eq!(m.as_bytes().len(), 12 + 4);
+
+        let m = igmp::Message::parse(m.as_bytes())
+            .unwrap()
+            .downcast::<igmp::MembershipQueryV3>()
+            .unwrap();
+        assert_eq!(m.get_max_resp_code(), 0x81);
+        // exp = 0, mant = 1
+        assert_eq!(m.get_max_resp_time(), 0x11 << 3);
+        assert_eq!(m.get_group_address(), GROUP);
+        assert_eq!(m.get_s(), true);
+        assert_eq!(m.get_qrv(), 2);
+        assert_eq!(m.get_qqi(), 125);
+        let mut sources = m.sources();
+        assert_eq!(sources.next(), Some(SOURCE));
+        assert_eq!(sources.next(), None);
+    }
+
+    #[test]
+    fn report_v3() {
+        let mut buf = [0xff; 64];
+
+        let m = igmp::Message::membership_report_v3(
+            &mut buf[..],
+            &[
+                Record {
+                    record_type: RecordType::ChangeToExcludeMode,
+                    multicast_address: GROUP,
+                    sources: &[],
+                },
+                Record {
+                    record_type: RecordType::AllowNewSources,
+                    multicast_address: GROUP,
+                    sources: &[SOURCE, SOURCE],
+                },
+            ],
+        )
+        .update_checksum();
+        assert_eq!(m.as_bytes().len(), 8 + 8 + 8 + 8);
+
+        let m = igmp::Message::parse(m.as_bytes())
+            .unwrap()
+            .downcast::<igmp::MembershipReportV3>()
+            .unwrap();
+        assert_eq!(m.get_number_of_records(), 2);
+
+        let mut records = m.records();
+
+        let first = records.next().unwrap();
+        assert_eq!(first.get_record_type(), RecordType::ChangeToExcludeMode);
+        assert_eq!(first.get_multicast_address(), GROUP);
+        assert_eq!(first.sources().count(), 0);
+        assert_eq!(first.aux_data(), &[]);
+
+        let second = records.next().unwrap();
+        assert_eq!(second.get_record_type(), RecordType::AllowNewSources);
+        assert_eq!(second.sources().len(), 2);
+        assert!(second.sources().all(|source| source == SOURCE));
+
+        assert!(records.next().is_none());
+    }
+}
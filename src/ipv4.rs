@@ -5,20 +5,31 @@
 //! - [RFC 791: Internet protocol][rfc]
 //!
 //! [rfc]: https://tools.ietf.org/html/rfc791
+//!
+//! - [RFC 1108: U.S. Department of Defense Security Options for the Internet Protocol][rfc1108]
+//!
+//! [rfc1108]: https://tools.ietf.org/html/rfc1108
+//!
+//! - [RFC 2113: IP Router Alert Option][rfc2113]
+//!
+//! [rfc2113]: https://tools.ietf.org/html/rfc2113
 
 use core::marker::PhantomData;
 use core::ops::Range;
-use core::{fmt, u16};
+use core::option::Option as CoreOption;
+use core::str::FromStr;
+use core::{cmp, fmt, u16};
 
 use as_slice::{AsMutSlice, AsSlice};
 use byteorder::{ByteOrder, NetworkEndian as NE};
-use cast::{u16, u32, usize};
+use cast::{u16, usize};
 use hash32_derive::Hash32;
 use owning_slice::{IntoSliceFrom, Truncate};
 
 use crate::{
+    checksum,
     fmt::Hex,
-    icmp,
+    icmp, igmp,
     traits::{UncheckedIndex, UxxExt},
     udp, Invalid, Valid,
 };
@@ -84,6 +95,40 @@ const DESTINATION: Range<usize> = 16..20;
 /// Minimum size of the IPv4 header
 pub const MIN_HEADER_SIZE: u8 = DESTINATION.end as u8;
 
+/// Maximum size of the IPv4 header (IHL = 15)
+pub const MAX_HEADER_SIZE: u8 = 60;
+
+/* Option types */
+// NOTE these include the 'copied' flag and the 'option class'
+const EOL: u8 = 0;
+const NOP: u8 = 1;
+const SECURITY: u8 = 130;
+const LSRR: u8 = 131;
+const SSRR: u8 = 137;
+const RR: u8 = 7;
+const TIMESTAMP: u8 = 68;
+const ROUTER_ALERT: u8 = 148;
+
+/* Option format */
+// Type (1 byte) and Length (1 byte)
+const OPTION_HEADER_SIZE: usize = 2;
+// smallest legal value of the 'pointer' field of the route options
+const ROUTE_MIN_POINTER: u8 = 4;
+// smallest legal value of the 'pointer' field of the Timestamp option
+const TIMESTAMP_MIN_POINTER: u8 = 5;
+
+mod flg {
+    pub const MASK: u8 = (1 << SIZE) - 1;
+    pub const OFFSET: usize = 0;
+    pub const SIZE: usize = 4;
+}
+
+mod oflw {
+    pub const MASK: u8 = (1 << SIZE) - 1;
+    pub const OFFSET: usize = super::flg::OFFSET + super::flg::SIZE;
+    pub const SIZE: usize = 4;
+}
+
 /// IPv4 packet
 pub struct Packet<BUFFER, CHECKSUM>
 where
@@ -118,6 +163,9 @@ where
             Err(packet.buffer)
         } else if total_len < header_len {
             Err(packet.buffer)
+        } else if usize(header_len) > packet.as_slice().len() {
+            // input doesn't contain the options
+            Err(packet.buffer)
         } else if packet.get_version() != 4 {
             Err(packet.buffer)
         } else {
@@ -215,6 +263,16 @@ where
         unsafe { Addr(*(self.as_slice().as_ptr().add(DESTINATION.start) as *const _)) }
     }
 
+    /// Returns an iterator over the options of the header
+    ///
+    /// The iterator stops at the End of Option List option (which is yielded) or at the first
+    /// malformed option
+    pub fn options(&self) -> Options<'_> {
+        Options {
+            bytes: unsafe { self.header().rf(usize(MIN_HEADER_SIZE)..) },
+        }
+    }
+
     /* Miscellaneous */
     /// Immutable view into the header
     pub fn header(&self) -> &[u8] {
@@ -264,7 +322,7 @@ where
     }
 
     fn verify_header_checksum(&self) -> bool {
-        verify_checksum(self.header())
+        checksum::verify(self.header())
     }
 }
 
@@ -388,20 +446,78 @@ where
         self.truncate(len);
     }
 
-    /// Fills the payload with an UDP packet
-    pub fn udp<F>(&mut self, f: F)
+    /// Writes options into the header
+    ///
+    /// The options written by `f` are padded with End of Option List octets to a multiple of 4
+    /// bytes and the IHL field is updated accordingly. Any previous options are overwritten.
+    ///
+    /// NOTE this moves the start of the payload so it must be called *before* the payload is
+    /// filled
+    ///
+    /// # Panics
+    ///
+    /// The methods of `OptionsWriter` panic if the options don't fit in the header (see
+    /// `MAX_HEADER_SIZE`) or in the buffer
+    pub fn set_options<F>(&mut self, f: F)
     where
-        F: FnOnce(&mut udp::Packet<&mut [u8]>),
+        F: FnOnce(&mut OptionsWriter<'_>),
     {
-        self.set_protocol(Protocol::Udp);
+        let start = usize(MIN_HEADER_SIZE);
+        let end = cmp::min(self.as_slice().len(), usize(MAX_HEADER_SIZE));
+
         let len = {
-            let mut udp = udp::Packet::new(self.payload_mut());
-            f(&mut udp);
-            udp.len()
+            let mut writer = OptionsWriter {
+                bytes: unsafe { self.as_mut_slice().rm(start..end) },
+                len: 0,
+            };
+            f(&mut writer);
+            writer.finish()
         };
+
+        // NOTE(cast) `len <= MAX_HEADER_SIZE - MIN_HEADER_SIZE`
+        unsafe { self.set_ihl((MIN_HEADER_SIZE + len as u8) / 4) }
+    }
+
+    /// Fills the payload with an IGMP message
+    ///
+    /// `f` must build the message in the buffer it's given and return it. This method also adds
+    /// the Router Alert option to the header and sets the TTL to 1, as RFC 2236 and RFC 3376
+    /// require
+    pub fn igmp<T, F>(&mut self, f: F)
+    where
+        F: FnOnce(&mut [u8]) -> igmp::Message<&mut [u8], T, Invalid>,
+        T: 'static,
+    {
+        // "Router shall examine packet"
+        self.set_options(|opts| opts.router_alert(0));
+
+        self.set_ttl(1);
+        self.set_protocol(Protocol::Igmp);
+        let len = f(self.payload_mut()).update_checksum().len();
         self.truncate(len);
     }
 
+    /// Fills the payload with an UDP packet
+    ///
+    /// The Checksum field of the UDP packet is left zeroed (no checksum); see `udp_with_checksum`
+    pub fn udp<F>(&mut self, f: F)
+    where
+        F: FnOnce(&mut udp::Packet<&mut [u8]>),
+    {
+        self.udp_(false, f)
+    }
+
+    /// Fills the payload with an UDP packet and computes its checksum
+    ///
+    /// NOTE the Source and Destination fields of this packet must be set *before* calling this
+    /// method as they are part of the UDP pseudo-header
+    pub fn udp_with_checksum<F>(&mut self, f: F)
+    where
+        F: FnOnce(&mut udp::Packet<&mut [u8]>),
+    {
+        self.udp_(true, f)
+    }
+
     /// Truncates the *payload* to the specified length
     pub fn truncate(&mut self, len: u16) {
         if self.payload_len() > len {
@@ -410,6 +526,26 @@ where
             self.buffer.truncate(total_len);
         }
     }
+
+    /* Private */
+    fn udp_<F>(&mut self, checksum: bool, f: F)
+    where
+        F: FnOnce(&mut udp::Packet<&mut [u8]>),
+    {
+        let src = self.get_source();
+        let dest = self.get_destination();
+
+        self.set_protocol(Protocol::Udp);
+        let len = {
+            let mut udp = udp::Packet::new(self.payload_mut());
+            f(&mut udp);
+            if checksum {
+                udp.update_ipv4_checksum(src, dest);
+            }
+            udp.len()
+        };
+        self.truncate(len);
+    }
 }
 
 impl<B> Packet<B, Valid>
@@ -512,7 +648,7 @@ where
     /* Miscellaneous */
     /// Updates the Checksum field of the header
     pub fn update_checksum(mut self) -> Packet<B, Valid> {
-        let cksum = compute_checksum(&self.header(), CHECKSUM.start);
+        let cksum = checksum::compute(self.header(), CHECKSUM.start);
         NE::write_u16(&mut self.header_mut_()[CHECKSUM], cksum);
 
         Packet {
@@ -603,6 +739,121 @@ where
         packet.set_destination(addr);
         packet
     }
+
+    /* In-place setters */
+    /// Sets the TTL (Time To Live) field of the header and incrementally updates the Checksum
+    /// field (RFC 1624)
+    pub fn update_ttl(&mut self, ttl: u8) {
+        let proto = self.header_()[PROTOCOL];
+        self.update_field(TTL..PROTOCOL + 1, &[ttl, proto]);
+    }
+
+    /// Sets the Source (IP address) field of the header and incrementally updates the Checksum
+    /// field (RFC 1624)
+    ///
+    /// NOTE the UDP and TCP checksums also cover this address, through the pseudo-header; this
+    /// method doesn't update them
+    pub fn update_source(&mut self, addr: Addr) {
+        self.update_field(SOURCE, &addr.0);
+    }
+
+    /// Sets the Destination (IP address) field of the header and incrementally updates the
+    /// Checksum field (RFC 1624)
+    ///
+    /// NOTE the UDP and TCP checksums also cover this address, through the pseudo-header; this
+    /// method doesn't update them
+    pub fn update_destination(&mut self, addr: Addr) {
+        self.update_field(DESTINATION, &addr.0);
+    }
+
+    /* Miscellaneous */
+    /// Prepares this packet to be forwarded to the next hop
+    ///
+    /// If the TTL field is larger than 1 this method decrements it, incrementally updating the
+    /// Checksum field (RFC 1624), and returns `Ok`. Otherwise the packet must be discarded and this
+    /// method returns `Err`, leaving the packet unchanged. The error contains an ICMP Time Exceeded
+    /// message addressed to the source of this packet, built in `buffer` and sent from `source`,
+    /// unless RFC 1812 forbids sending one; e.g. when this packet is itself an ICMP error message,
+    /// a non-initial fragment or a broadcast / multicast packet.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the Time Exceeded message doesn't fit in `buffer`. A buffer of
+    /// `MAX_HEADER_SIZE + icmp::HEADER_SIZE + MAX_HEADER_SIZE + icmp::ERROR_PAYLOAD_SIZE` bytes
+    /// always suffices.
+    pub fn forward<'b>(
+        &mut self,
+        source: Addr,
+        buffer: &'b mut [u8],
+    ) -> Result<(), CoreOption<Packet<&'b mut [u8], Valid>>> {
+        let ttl = self.get_ttl();
+        if ttl > 1 {
+            self.update_ttl(ttl - 1);
+            return Ok(());
+        }
+
+        if !self.may_send_icmp_error() {
+            return Err(None);
+        }
+
+        let mut ip = Packet::new(buffer);
+        ip.set_source(source);
+        ip.set_destination(self.get_source());
+        ip.set_protocol(Protocol::Icmp);
+        let len = icmp::Message::time_exceeded(
+            ip.payload_mut(),
+            icmp::time_exceeded::TTL,
+            self.as_slice(),
+            usize(self.header_len()),
+        )
+        .update_checksum()
+        .len();
+        ip.truncate(len);
+
+        Err(Some(ip.update_checksum()))
+    }
+
+    /* Private */
+    // RFC 1812 - Section 4.3.2.7 When Not to Send ICMP Errors
+    fn may_send_icmp_error(&self) -> bool {
+        let src = self.get_source();
+        let dest = self.get_destination();
+
+        if dest.is_multicast() || dest == Addr::BROADCAST {
+            return false;
+        }
+
+        if src == Addr::UNSPECIFIED
+            || src == Addr::BROADCAST
+            || src.is_multicast()
+            || src.0[0] == Addr::LOOPBACK.0[0]
+        {
+            return false;
+        }
+
+        if self.get_fragment_offset() != 0 {
+            return false;
+        }
+
+        if self.get_protocol() == Protocol::Icmp {
+            if let Some(ty) = self.payload().first() {
+                if icmp::Type::from(*ty).is_error() {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+
+    // NOTE `range` must start at an even offset
+    fn update_field(&mut self, range: Range<usize>, new: &[u8]) {
+        let header = self.header_mut_();
+        let cksum = NE::read_u16(&header[CHECKSUM]);
+        let cksum = checksum::update_bytes(cksum, &header[range.clone()], new);
+        header[range].copy_from_slice(new);
+        NE::write_u16(&mut header[CHECKSUM], cksum);
+    }
 }
 
 /// NOTE excludes the payload
@@ -641,6 +892,28 @@ impl Addr {
 
     /// Unspecified address
     pub const UNSPECIFIED: Self = Addr([0; 4]);
+
+    /// Limited broadcast address
+    pub const BROADCAST: Self = Addr([255; 4]);
+
+    /// All systems on this subnet multicast address
+    pub const ALL_SYSTEMS: Self = Addr([224, 0, 0, 1]);
+
+    /// All routers on this subnet multicast address
+    pub const ALL_ROUTERS: Self = Addr([224, 0, 0, 2]);
+
+    /// All IGMPv3-capable multicast routers address
+    pub const ALL_IGMPV3_ROUTERS: Self = Addr([224, 0, 0, 22]);
+
+    /// Creates an address from its four octets, e.g. `Addr::new(192, 168, 1, 33)`
+    pub const fn new(a: u8, b: u8, c: u8, d: u8) -> Self {
+        Addr([a, b, c, d])
+    }
+
+    /// Is this a multicast address?
+    pub fn is_multicast(&self) -> bool {
+        self.0[0] & 0xf0 == 0xe0
+    }
 }
 
 impl fmt::Debug for Addr {
@@ -668,6 +941,498 @@ impl fmt::Display for Addr {
     }
 }
 
+impl FromStr for Addr {
+    type Err = ();
+
+    /// Parses an address in dotted decimal notation, e.g. `192.168.1.33`
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let mut addr = [0; 4];
+        let mut parts = s.split('.');
+        for byte in addr.iter_mut() {
+            *byte = parse_decimal(parts.next().ok_or(())?)?;
+        }
+
+        if parts.next().is_some() {
+            Err(())
+        } else {
+            Ok(Addr(addr))
+        }
+    }
+}
+
+/// IPv4 network in CIDR notation, e.g. `192.168.1.0/24`
+///
+/// The host bits of the address are kept so a `Cidr` can also describe an interface address, e.g.
+/// `192.168.1.33/24`; use `network` to clear them
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Cidr {
+    addr: Addr,
+    prefix_len: u8,
+}
+
+impl Cidr {
+    /// Creates a CIDR block from an address and a prefix length
+    ///
+    /// Returns an error if `prefix_len` is greater than 32
+    pub const fn new(addr: Addr, prefix_len: u8) -> Result<Self, ()> {
+        if prefix_len > 32 {
+            Err(())
+        } else {
+            Ok(Cidr { addr, prefix_len })
+        }
+    }
+
+    /// Returns the address
+    pub fn address(&self) -> Addr {
+        self.addr
+    }
+
+    /// Returns the prefix length
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    /// Returns the netmask, e.g. `255.255.255.0` for a `/24` block
+    pub fn netmask(&self) -> Addr {
+        Addr(self.mask().to_be_bytes())
+    }
+
+    /// Returns this block with the host bits of the address cleared
+    pub fn network(&self) -> Self {
+        Cidr {
+            addr: Addr((u32::from_be_bytes(self.addr.0) & self.mask()).to_be_bytes()),
+            prefix_len: self.prefix_len,
+        }
+    }
+
+    /// Returns the directed broadcast address of this block
+    ///
+    /// NOTE `/31` and `/32` blocks have no broadcast address (RFC 3021); this returns the last
+    /// address of the block in that case too
+    pub fn broadcast(&self) -> Addr {
+        Addr((u32::from_be_bytes(self.addr.0) | !self.mask()).to_be_bytes())
+    }
+
+    /// Is `addr` part of this block?
+    pub fn contains(&self, addr: Addr) -> bool {
+        (u32::from_be_bytes(self.addr.0) ^ u32::from_be_bytes(addr.0)) & self.mask() == 0
+    }
+
+    fn mask(&self) -> u32 {
+        (!0u32)
+            .checked_shl(32 - u32::from(self.prefix_len))
+            .unwrap_or(0)
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix_len)
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = ();
+
+    /// Parses a CIDR block in `a.b.c.d/len` format
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let (addr, len) = s.split_once('/').ok_or(())?;
+        Cidr::new(addr.parse()?, parse_decimal(len)?)
+    }
+}
+
+// parses a decimal `u8`; unlike `u8::from_str` this rejects signs and leading zeros (some
+// parsers read `010` as an octal number)
+fn parse_decimal(s: &str) -> Result<u8, ()> {
+    if s.is_empty()
+        || s.len() > 3
+        || (s.len() > 1 && s.starts_with('0'))
+        || !s.bytes().all(|b| b.is_ascii_digit())
+    {
+        return Err(());
+    }
+
+    s.parse().map_err(|_| ())
+}
+
+/// IPv4 header option
+#[derive(Clone, Copy, Debug)]
+pub enum Option<'a> {
+    /// End of Option List
+    EndOfOptionList,
+    /// No Operation
+    NoOperation,
+    /// Security; the value of this option is not interpreted
+    Security(&'a [u8]),
+    /// Loose Source and Record Route
+    LooseSourceRoute(Route<'a>),
+    /// Strict Source and Record Route
+    StrictSourceRoute(Route<'a>),
+    /// Record Route
+    RecordRoute(Route<'a>),
+    /// Internet Timestamp
+    Timestamp(Timestamp<'a>),
+    /// Router Alert
+    RouterAlert(u16),
+    /// Unknown option, or known option with an invalid length: (type, value)
+    Unknown(u8, &'a [u8]),
+}
+
+/// Iterator over the options of an IPv4 header
+pub struct Options<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Iterator for Options<'a> {
+    type Item = Option<'a>;
+
+    fn next(&mut self) -> CoreOption<Option<'a>> {
+        let ty = *self.bytes.first()?;
+
+        match ty {
+            EOL => {
+                self.bytes = &[];
+                return Some(Option::EndOfOptionList);
+            }
+            NOP => {
+                self.bytes = unsafe { self.bytes.rf(1..) };
+                return Some(Option::NoOperation);
+            }
+            _ => {}
+        }
+
+        let len = self.bytes.get(1).map(|len| usize(*len)).unwrap_or(0);
+        if len < OPTION_HEADER_SIZE || len > self.bytes.len() {
+            // malformed option
+            self.bytes = &[];
+            return None;
+        }
+
+        let value = unsafe { self.bytes.r(OPTION_HEADER_SIZE..len) };
+        self.bytes = unsafe { self.bytes.rf(len..) };
+
+        Some(match ty {
+            SECURITY => Option::Security(value),
+            LSRR | SSRR | RR if value.len() % 4 == 1 => {
+                let route = Route { bytes: value };
+
+                match ty {
+                    LSRR => Option::LooseSourceRoute(route),
+                    SSRR => Option::StrictSourceRoute(route),
+                    _ => Option::RecordRoute(route),
+                }
+            }
+            TIMESTAMP if value.len() % 4 == 2 => Option::Timestamp(Timestamp { bytes: value }),
+            ROUTER_ALERT if value.len() == 2 => Option::RouterAlert(NE::read_u16(value)),
+            _ => Option::Unknown(ty, value),
+        })
+    }
+}
+
+/// View into the value of a route option (Record Route, Loose Source and Record Route or Strict
+/// Source and Record Route)
+#[derive(Clone, Copy)]
+pub struct Route<'a> {
+    // NOTE `pointer` + route data
+    bytes: &'a [u8],
+}
+
+impl<'a> Route<'a> {
+    /// Reads the 'pointer' field
+    ///
+    /// This is an offset, relative to the start of the option, to the next address slot
+    pub fn get_pointer(&self) -> u8 {
+        unsafe { *self.bytes.gu(0) }
+    }
+
+    /// Returns an iterator over all the address slots of the route
+    pub fn addresses(&self) -> Addresses<'a> {
+        Addresses {
+            bytes: unsafe { self.bytes.rf(1..) },
+        }
+    }
+
+    /// Returns an iterator over the address slots that precede the 'pointer', i.e. the addresses
+    /// that have already been recorded
+    pub fn recorded(&self) -> Addresses<'a> {
+        let end = usize(self.get_pointer().saturating_sub(ROUTE_MIN_POINTER)) / 4 * 4;
+        let bytes = unsafe { self.bytes.rf(1..) };
+
+        Addresses {
+            bytes: unsafe { bytes.rt(..cmp::min(end, bytes.len())) },
+        }
+    }
+}
+
+impl<'a> fmt::Debug for Route<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ipv4::Route")
+            .field("pointer", &self.get_pointer())
+            .field("slots", &self.addresses().len())
+            .finish()
+    }
+}
+
+/// View into the value of an Internet Timestamp option
+#[derive(Clone, Copy)]
+pub struct Timestamp<'a> {
+    // NOTE `pointer` + `oflw`/`flg` + timestamp data
+    bytes: &'a [u8],
+}
+
+impl<'a> Timestamp<'a> {
+    /// Reads the 'pointer' field
+    ///
+    /// This is an offset, relative to the start of the option, to the next free slot
+    pub fn get_pointer(&self) -> u8 {
+        unsafe { *self.bytes.gu(0) }
+    }
+
+    /// Reads the 'oflw' (overflow) field
+    pub fn get_overflow(&self) -> u8 {
+        unsafe { get!(*self.bytes.gu(1), oflw) }
+    }
+
+    /// Reads the 'flg' (flag) field
+    pub fn get_flag(&self) -> TimestampFlag {
+        unsafe { get!(*self.bytes.gu(1), flg) }.into()
+    }
+
+    /// Returns an iterator over the (address, timestamp) entries that precede the 'pointer', i.e.
+    /// the entries that have already been recorded
+    ///
+    /// The address is `None` if the flag is `TimestampsOnly`. Nothing is yielded if the flag is
+    /// unknown
+    pub fn recorded(&self) -> TimestampEntries<'a> {
+        let with_address = match self.get_flag() {
+            TimestampFlag::TimestampsOnly => false,
+            TimestampFlag::AddressAndTimestamp | TimestampFlag::Prespecified => true,
+            TimestampFlag::Unknown(_) => {
+                return TimestampEntries {
+                    bytes: &[],
+                    with_address: false,
+                };
+            }
+        };
+
+        let entry_size = if with_address { 8 } else { 4 };
+        let bytes = unsafe { self.bytes.rf(2..) };
+        let end = usize(self.get_pointer().saturating_sub(TIMESTAMP_MIN_POINTER)) / entry_size
+            * entry_size;
+
+        TimestampEntries {
+            bytes: unsafe { bytes.rt(..cmp::min(end, bytes.len() / entry_size * entry_size)) },
+            with_address,
+        }
+    }
+}
+
+impl<'a> fmt::Debug for Timestamp<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ipv4::Timestamp")
+            .field("pointer", &self.get_pointer())
+            .field("overflow", &self.get_overflow())
+            .field("flag", &self.get_flag())
+            .finish()
+    }
+}
+
+/// Iterator over a list of addresses
+pub struct Addresses<'a> {
+    // NOTE the length of this slice is always a multiple of 4
+    bytes: &'a [u8],
+}
+
+impl<'a> ExactSizeIterator for Addresses<'a> {}
+
+impl<'a> Iterator for Addresses<'a> {
+    type Item = Addr;
+
+    fn next(&mut self) -> CoreOption<Addr> {
+        if self.bytes.is_empty() {
+            None
+        } else {
+            unsafe {
+                let addr = Addr(*(self.bytes.as_ptr() as *const _));
+                self.bytes = self.bytes.rf(4..);
+                Some(addr)
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, CoreOption<usize>) {
+        let n = self.bytes.len() / 4;
+        (n, Some(n))
+    }
+}
+
+/// Iterator over the entries of an Internet Timestamp option
+pub struct TimestampEntries<'a> {
+    // NOTE the length of this slice is always a multiple of the entry size
+    bytes: &'a [u8],
+    with_address: bool,
+}
+
+impl<'a> Iterator for TimestampEntries<'a> {
+    type Item = (CoreOption<Addr>, u32);
+
+    fn next(&mut self) -> CoreOption<(CoreOption<Addr>, u32)> {
+        if self.bytes.is_empty() {
+            None
+        } else if self.with_address {
+            unsafe {
+                let addr = Addr(*(self.bytes.as_ptr() as *const _));
+                let timestamp = NE::read_u32(self.bytes.r(4..8));
+                self.bytes = self.bytes.rf(8..);
+                Some((Some(addr), timestamp))
+            }
+        } else {
+            unsafe {
+                let timestamp = NE::read_u32(self.bytes.rt(..4));
+                self.bytes = self.bytes.rf(4..);
+                Some((None, timestamp))
+            }
+        }
+    }
+}
+
+/// Writes options into an IPv4 header; see `Packet::set_options`
+pub struct OptionsWriter<'a> {
+    bytes: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> OptionsWriter<'a> {
+    /// Writes a No Operation option
+    pub fn no_operation(&mut self) {
+        self.push(1)[0] = NOP;
+    }
+
+    /// Writes a Security option with the given `value`
+    pub fn security(&mut self, value: &[u8]) {
+        self.option(SECURITY, value.len()).copy_from_slice(value);
+    }
+
+    /// Writes a Loose Source and Record Route option with the given `route`
+    pub fn loose_source_route(&mut self, route: &[Addr]) {
+        let data = self.route(LSRR, route.len());
+
+        for (chunk, addr) in data.chunks_exact_mut(4).zip(route) {
+            chunk.copy_from_slice(&addr.0);
+        }
+    }
+
+    /// Writes a Strict Source and Record Route option with the given `route`
+    pub fn strict_source_route(&mut self, route: &[Addr]) {
+        let data = self.route(SSRR, route.len());
+
+        for (chunk, addr) in data.chunks_exact_mut(4).zip(route) {
+            chunk.copy_from_slice(&addr.0);
+        }
+    }
+
+    /// Writes a Record Route option with room for `slots` addresses
+    pub fn record_route(&mut self, slots: u8) {
+        for byte in self.route(RR, usize(slots)) {
+            *byte = 0;
+        }
+    }
+
+    /// Writes an Internet Timestamp option with room for `slots` timestamps
+    pub fn timestamp(&mut self, slots: u8) {
+        for byte in self.timestamp_(TimestampFlag::TimestampsOnly, usize(slots) * 4) {
+            *byte = 0;
+        }
+    }
+
+    /// Writes an Internet Timestamp option with room for `slots` (address, timestamp) entries
+    pub fn timestamp_with_address(&mut self, slots: u8) {
+        for byte in self.timestamp_(TimestampFlag::AddressAndTimestamp, usize(slots) * 8) {
+            *byte = 0;
+        }
+    }
+
+    /// Writes an Internet Timestamp option with the given prespecified addresses
+    pub fn timestamp_prespecified(&mut self, addresses: &[Addr]) {
+        let data = self.timestamp_(TimestampFlag::Prespecified, addresses.len() * 8);
+
+        for (chunk, addr) in data.chunks_exact_mut(8).zip(addresses) {
+            chunk[..4].copy_from_slice(&addr.0);
+            chunk[4..].copy_from_slice(&[0; 4]);
+        }
+    }
+
+    /// Writes a Router Alert option with the given `value`
+    pub fn router_alert(&mut self, value: u16) {
+        NE::write_u16(self.option(ROUTER_ALERT, 2), value);
+    }
+
+    /* Private */
+    // Reserves `len` bytes
+    fn push(&mut self, len: usize) -> &mut [u8] {
+        let start = self.len;
+        let end = start + len;
+        assert!(
+            end <= self.bytes.len(),
+            "IPv4 options don't fit in the header"
+        );
+
+        self.len = end;
+        &mut self.bytes[start..end]
+    }
+
+    // Writes the Type and Length fields and returns the value part of the option
+    fn option(&mut self, ty: u8, value_len: usize) -> &mut [u8] {
+        let len = OPTION_HEADER_SIZE + value_len;
+        assert!(len <= usize(u8::max_value()));
+
+        let bytes = self.push(len);
+        bytes[0] = ty;
+        bytes[1] = len as u8;
+        &mut bytes[OPTION_HEADER_SIZE..]
+    }
+
+    // Writes the 'pointer' field and returns the route data
+    fn route(&mut self, ty: u8, slots: usize) -> &mut [u8] {
+        let value = self.option(ty, 1 + slots * 4);
+        value[0] = ROUTE_MIN_POINTER;
+        &mut value[1..]
+    }
+
+    // Writes the 'pointer', 'oflw' and 'flg' fields and returns the timestamp data
+    fn timestamp_(&mut self, flag: TimestampFlag, data_len: usize) -> &mut [u8] {
+        let value = self.option(TIMESTAMP, 2 + data_len);
+        value[0] = TIMESTAMP_MIN_POINTER;
+        value[1] = 0;
+        set!(value[1], flg, u8::from(flag));
+        &mut value[2..]
+    }
+
+    // Pads the options to a multiple of 4 bytes; returns the padded length
+    fn finish(mut self) -> usize {
+        let padding = (4 - self.len % 4) % 4;
+        for byte in self.push(padding) {
+            *byte = EOL;
+        }
+
+        self.len
+    }
+}
+
+full_range!(
+    u8,
+    /// Flag of the Internet Timestamp option
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub enum TimestampFlag {
+        /// Timestamps only
+        TimestampsOnly = 0,
+        /// Each timestamp is preceded by the address of the registering entity
+        AddressAndTimestamp = 1,
+        /// The address fields are prespecified
+        Prespecified = 3,
+    }
+);
+
 // From https://www.iana.org/assignments/protocol-numbers/protocol-numbers.xhtml
 // ("Last Updated: 2017-10-13")
 full_range!(
@@ -1128,47 +1893,11 @@ impl Protocol {
     }
 }
 
-/// Computes the IPv4 checksum of the header
-pub(crate) fn compute_checksum(header: &[u8], cksum_pos: usize) -> u16 {
-    let mut sum = 0u32;
-    let skip = cksum_pos / 2;
-
-    // Header length is always even
-    debug_assert_eq!(header.len() % 2, 0);
-    for (i, chunk) in header.chunks_exact(2).enumerate() {
-        if i == skip {
-            // skip checksum field
-            continue;
-        }
-        sum = sum.wrapping_add(u32(NE::read_u16(chunk)));
-    }
-
-    loop {
-        let carry = sum.high();
-        if carry == 0 {
-            break;
-        }
-        sum = u32(sum.low()) + u32(carry);
-    }
-
-    !sum.low()
-}
-
-/// Verifies the IPv4 checksum of the header
-pub(crate) fn verify_checksum(header: &[u8]) -> bool {
-    debug_assert!(header.len() % 2 == 0);
-
-    let mut sum = 0u32;
-    for chunk in header.chunks_exact(2) {
-        sum = sum.wrapping_add(u32(NE::read_u16(chunk)));
-    }
-
-    sum.low() + sum.high() == 0xffff
-}
-
 #[cfg(test)]
 mod tests {
-    use crate::ipv4;
+    use core::fmt::Write;
+
+    use crate::{checksum, fmt::Buffer, icmp, igmp, ipv4};
 
     #[test]
     fn checksum() {
@@ -1177,10 +1906,131 @@ mod tests {
             0x00, 0x01, 0xc0, 0xa8, 0x00, 0xc7,
         ];
 
-        assert_eq!(
-            super::compute_checksum(&header, super::CHECKSUM.start),
-            0xb861
-        )
+        assert_eq!(checksum::compute(&header, super::CHECKSUM.start), 0xb861)
+    }
+
+    #[test]
+    fn incremental_update() {
+        let mut chunk = [0; 32];
+
+        let mut ip = ipv4::Packet::new(&mut chunk[..]);
+        ip.set_protocol(ipv4::Protocol::Udp);
+        ip.set_source(ipv4::Addr([192, 168, 0, 1]));
+        ip.set_destination(ipv4::Addr([192, 168, 0, 199]));
+        let mut ip = ip.update_checksum();
+
+        ip.update_ttl(ip.get_ttl() - 1);
+        ip.update_source(ipv4::Addr([10, 0, 0, 1]));
+        ip.update_destination(ipv4::Addr([255, 255, 255, 255]));
+
+        let ip = ipv4::Packet::parse(ip.as_bytes()).unwrap();
+        assert_eq!(ip.get_ttl(), 63);
+        assert_eq!(ip.get_protocol(), ipv4::Protocol::Udp);
+        assert_eq!(ip.get_source(), ipv4::Addr([10, 0, 0, 1]));
+        assert_eq!(ip.get_destination(), ipv4::Addr([255, 255, 255, 255]));
+    }
+
+    #[test]
+    fn parse() {
+        const IP: ipv4::Addr = ipv4::Addr::new(192, 168, 1, 33);
+        assert_eq!("192.168.1.33".parse(), Ok(IP));
+        assert_eq!("0.0.0.0".parse(), Ok(ipv4::Addr::UNSPECIFIED));
+        assert_eq!("255.255.255.255".parse(), Ok(ipv4::Addr::BROADCAST));
+
+        for s in &[
+            "",
+            "192.168.1",
+            "192.168.1.33.1",
+            "192.168.1.",
+            "192.168.256.1",
+            "1.2.-3.4",
+            "01.2.3.4",
+            "10.0.0.010",
+        ] {
+            assert!(s.parse::<ipv4::Addr>().is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn cidr() {
+        let cidr: ipv4::Cidr = "192.168.1.33/24".parse().unwrap();
+        assert_eq!(cidr.address(), ipv4::Addr([192, 168, 1, 33]));
+        assert_eq!(cidr.prefix_len(), 24);
+        assert_eq!(cidr.netmask(), ipv4::Addr([255, 255, 255, 0]));
+        assert_eq!(cidr.network().address(), ipv4::Addr([192, 168, 1, 0]));
+        assert_eq!(cidr.broadcast(), ipv4::Addr([192, 168, 1, 255]));
+        assert!(cidr.contains(ipv4::Addr([192, 168, 1, 1])));
+        assert!(!cidr.contains(ipv4::Addr([192, 168, 2, 1])));
+        let mut buf = Buffer::new();
+        write!(buf, "{}", cidr).unwrap();
+        assert_eq!(buf.as_str(), "192.168.1.33/24");
+
+        let any: ipv4::Cidr = "0.0.0.0/0".parse().unwrap();
+        assert_eq!(any.netmask(), ipv4::Addr::UNSPECIFIED);
+        assert!(any.contains(ipv4::Addr::BROADCAST));
+
+        let host: ipv4::Cidr = "10.0.0.1/32".parse().unwrap();
+        assert_eq!(host.netmask(), ipv4::Addr::BROADCAST);
+        assert!(host.contains(ipv4::Addr([10, 0, 0, 1])));
+        assert!(!host.contains(ipv4::Addr([10, 0, 0, 2])));
+
+        for s in &[
+            "10.0.0.1",
+            "10.0.0.1/33",
+            "10.0.0/8",
+            "10.0.0.0.0/8",
+            "10.0.0.256/8",
+            "10.0.+0.0/8",
+            "10.0.0.0/",
+            "10.0.0.0/08",
+        ] {
+            assert!(s.parse::<ipv4::Cidr>().is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn forward() {
+        const HOST: ipv4::Addr = ipv4::Addr([192, 168, 0, 33]);
+        const ROUTER: ipv4::Addr = ipv4::Addr([192, 168, 0, 1]);
+
+        let mut chunk = [0; 48];
+        let mut ip = ipv4::Packet::new(&mut chunk[..]);
+        ip.set_source(HOST);
+        ip.set_destination(ipv4::Addr([10, 0, 0, 1]));
+        ip.set_ttl(2);
+        ip.udp(|udp| {
+            udp.set_destination(1337);
+            udp.set_payload(b"Hello, world!");
+        });
+        let mut ip = ip.update_checksum();
+
+        let mut buf = [0; 96];
+        assert!(ip.forward(ROUTER, &mut buf).is_ok());
+        assert_eq!(ip.get_ttl(), 1);
+        assert!(ipv4::Packet::parse(ip.as_bytes()).is_ok());
+
+        let time_exceeded = ip.forward(ROUTER, &mut buf).unwrap_err().unwrap();
+        assert_eq!(ip.get_ttl(), 1);
+
+        let te = ipv4::Packet::parse(time_exceeded.as_bytes()).unwrap();
+        assert_eq!(te.get_source(), ROUTER);
+        assert_eq!(te.get_destination(), HOST);
+        assert_eq!(te.get_protocol(), ipv4::Protocol::Icmp);
+        let icmp = icmp::Message::parse(te.payload())
+            .unwrap()
+            .downcast::<icmp::TimeExceeded>()
+            .unwrap();
+        assert_eq!(icmp.get_code(), icmp::time_exceeded::TTL);
+        // original header + first 8 bytes of the datagram
+        assert_eq!(icmp.payload(), &ip.as_bytes()[..28]);
+
+        // no ICMP errors about ICMP errors
+        let len = usize::from(te.len());
+        let mut chunk = [0; 96];
+        chunk[..len].copy_from_slice(te.as_bytes());
+        let mut te = ipv4::Packet::parse(&mut chunk[..len]).unwrap();
+        te.update_ttl(1);
+        assert!(te.forward(ROUTER, &mut buf).unwrap_err().is_none());
     }
 
     #[test]
@@ -1202,6 +2052,157 @@ mod tests {
             0x00, 0x01, 0xc0, 0xa8, 0x00, 0xc7,
         ];
 
-        assert!(super::verify_checksum(&header))
+        assert!(checksum::verify(&header))
+    }
+
+    #[test]
+    fn options() {
+        const HOP: ipv4::Addr = ipv4::Addr([10, 0, 0, 1]);
+
+        let mut chunk = [0xff; 64];
+
+        let mut ip = ipv4::Packet::new(&mut chunk[..]);
+        ip.set_options(|opts| {
+            opts.router_alert(0);
+            opts.loose_source_route(&[HOP]);
+            opts.no_operation();
+            opts.timestamp(1);
+        });
+        // 4 + 7 + 1 + 8 = 20
+        assert_eq!(ip.get_ihl(), 10);
+        ip.set_protocol(ipv4::Protocol::Udp);
+        ip.truncate(0);
+        let ip = ip.update_checksum();
+
+        let ip = ipv4::Packet::parse(ip.as_bytes()).unwrap();
+        let mut options = ip.options();
+
+        match options.next() {
+            Some(ipv4::Option::RouterAlert(0)) => {}
+            _ => panic!(),
+        }
+
+        match options.next() {
+            Some(ipv4::Option::LooseSourceRoute(route)) => {
+                assert_eq!(route.get_pointer(), 4);
+                assert_eq!(route.addresses().next(), Some(HOP));
+                assert_eq!(route.recorded().len(), 0);
+            }
+            _ => panic!(),
+        }
+
+        match options.next() {
+            Some(ipv4::Option::NoOperation) => {}
+            _ => panic!(),
+        }
+
+        match options.next() {
+            Some(ipv4::Option::Timestamp(ts)) => {
+                assert_eq!(ts.get_flag(), ipv4::TimestampFlag::TimestampsOnly);
+                assert_eq!(ts.get_pointer(), 5);
+                assert_eq!(ts.get_overflow(), 0);
+                assert_eq!(ts.recorded().count(), 0);
+            }
+            _ => panic!(),
+        }
+
+        assert!(options.next().is_none());
+    }
+
+    #[test]
+    fn options_padding() {
+        let mut chunk = [0xff; 64];
+
+        let mut ip = ipv4::Packet::new(&mut chunk[..]);
+        ip.set_options(|opts| opts.record_route(1));
+        // 3 + 4 = 7 -> padded to 8
+        assert_eq!(ip.get_ihl(), 7);
+        assert_eq!(ip.header()[27], 0);
+
+        let mut options = ip.options();
+        match options.next() {
+            Some(ipv4::Option::RecordRoute(route)) => {
+                assert_eq!(route.addresses().len(), 1);
+            }
+            _ => panic!(),
+        }
+        match options.next() {
+            Some(ipv4::Option::EndOfOptionList) => {}
+            _ => panic!(),
+        }
+        assert!(options.next().is_none());
+
+        // options are overwritten
+        ip.set_options(|_| {});
+        assert_eq!(ip.get_ihl(), 5);
+
+        let route = [ipv4::Addr([10, 0, 0, 1]), ipv4::Addr([10, 0, 1, 1])];
+        ip.set_options(|opts| opts.strict_source_route(&route));
+        // 3 + 8 = 11 -> padded to 12
+        assert_eq!(ip.get_ihl(), 8);
+        assert_eq!(
+            &ip.header()[20..31],
+            &[137, 11, 4, 10, 0, 0, 1, 10, 0, 1, 1]
+        );
+        match ip.options().next() {
+            Some(ipv4::Option::StrictSourceRoute(r)) => {
+                assert!(r.addresses().eq(route.iter().cloned()));
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn timestamp_recorded() {
+        let header = [
+            68, 12,   // type, length
+            13,   // pointer: 1 entry recorded
+            0x11, // oflw = 1, flg = AddressAndTimestamp
+            10, 0, 0, 1, 0, 0, 0, 42, // entry #1
+        ];
+
+        let ts = ipv4::Timestamp {
+            bytes: &header[2..],
+        };
+        assert_eq!(ts.get_overflow(), 1);
+        assert_eq!(ts.get_flag(), ipv4::TimestampFlag::AddressAndTimestamp);
+
+        let mut entries = ts.recorded();
+        assert_eq!(entries.next(), Some((Some(ipv4::Addr([10, 0, 0, 1])), 42)));
+        assert_eq!(entries.next(), None);
+    }
+
+    #[test]
+    fn igmp() {
+        const GROUP: ipv4::Addr = ipv4::Addr([239, 1, 2, 3]);
+
+        let mut chunk = [0xff; 64];
+
+        let mut ip = ipv4::Packet::new(&mut chunk[..]);
+        ip.set_source(ipv4::Addr([192, 168, 0, 33]));
+        ip.set_destination(GROUP);
+        ip.igmp(|buf| {
+            let mut m = igmp::Message::membership_report_v2(buf);
+            m.set_group_address(GROUP);
+            m
+        });
+        let ip = ip.update_checksum();
+        assert_eq!(ip.len(), 24 + 8);
+
+        let ip = ipv4::Packet::parse(ip.as_bytes()).unwrap();
+        assert_eq!(ip.get_ihl(), 6);
+        assert_eq!(ip.get_ttl(), 1);
+        assert_eq!(ip.get_protocol(), ipv4::Protocol::Igmp);
+        assert_eq!(&ip.header()[20..], &[0x94, 0x04, 0x00, 0x00]);
+        match ip.options().next() {
+            Some(ipv4::Option::RouterAlert(0)) => {}
+            _ => panic!(),
+        }
+
+        let m = igmp::Message::parse(ip.payload())
+            .unwrap()
+            .downcast::<igmp::MembershipReportV2>()
+            .unwrap();
+        assert_eq!(m.get_group_address(), GROUP);
     }
 }
@@ -5,10 +5,15 @@
 //! - [RFC 4291 IP Version 6 Addressing Architecture][rfc]
 //!
 //! [rfc]: https://tools.ietf.org/html/rfc4291
+//!
+//! - [RFC 2711 IPv6 Router Alert Option][rfc2711]
+//!
+//! [rfc2711]: https://tools.ietf.org/html/rfc2711
 
 use core::{
     fmt,
     ops::{Range, RangeFrom, RangeTo},
+    str::FromStr,
     u16,
 };
 
@@ -19,7 +24,7 @@ use hash32_derive::Hash32;
 use owning_slice::Truncate;
 
 pub use crate::ipv4::Protocol as NextHeader;
-use crate::{fmt::Quoted, icmpv6, mac, traits::UncheckedIndex, udp};
+use crate::{fmt::Quoted, icmpv6, ipv4, mac, traits::UncheckedIndex, udp};
 
 /* Packet structure */
 const V: usize = 0;
@@ -49,6 +54,16 @@ const PAYLOAD: RangeFrom<usize> = 40..;
 /// Fixed header size, in bytes
 pub const HEADER_SIZE: u8 = DESTINATION.end as u8;
 
+// Hop-by-Hop Options header that contains a Router Alert option (see RFC 2711)
+const ROUTER_ALERT: [u8; 8] = [
+    58, // Next Header = ICMPv6
+    0,  // Hdr Ext Len = 0 (8 octets)
+    5,  // Option Type = Router Alert
+    2,  // Opt Data Len
+    0, 0, // Value = Multicast Listener Discovery message
+    1, 0, // PadN
+];
+
 /// IPv6 packet
 pub struct Packet<BUFFER>
 where
@@ -315,6 +330,86 @@ where
         self.truncate(len);
     }
 
+    /// Fills the payload with an Echo Request ICMPv6 message
+    pub fn echo_request(
+        &mut self,
+        f: impl FnOnce(&mut icmpv6::Message<&mut [u8], icmpv6::EchoRequest>),
+    ) {
+        let src = self.get_source();
+        let dest = self.get_destination();
+
+        self.set_next_header(NextHeader::Ipv6Icmp);
+
+        let mut message = icmpv6::Message::echo_request(self.payload_mut());
+
+        f(&mut message);
+
+        message.update_checksum(src, dest);
+
+        let len = message.as_bytes().len() as u16;
+        self.truncate(len);
+    }
+
+    /// Fills the payload with a (MLDv1) Multicast Listener Query
+    ///
+    /// The ICMPv6 message will be preceded by a Hop-by-Hop Options header that contains a Router
+    /// Alert option, and the 'Hop limit' will be set to 1
+    pub fn multicast_listener_query(
+        &mut self,
+        f: impl FnOnce(&mut icmpv6::Message<&mut [u8], icmpv6::MulticastListenerQuery>),
+    ) {
+        self.router_alert(|buf| icmpv6::Message::multicast_listener_query(buf), f)
+    }
+
+    /// Fills the payload with a (MLDv1) Multicast Listener Report
+    ///
+    /// See `multicast_listener_query` for details
+    pub fn multicast_listener_report(
+        &mut self,
+        f: impl FnOnce(&mut icmpv6::Message<&mut [u8], icmpv6::MulticastListenerReport>),
+    ) {
+        self.router_alert(|buf| icmpv6::Message::multicast_listener_report(buf), f)
+    }
+
+    /// Fills the payload with a (MLDv1) Multicast Listener Done
+    ///
+    /// See `multicast_listener_query` for details
+    pub fn multicast_listener_done(
+        &mut self,
+        f: impl FnOnce(&mut icmpv6::Message<&mut [u8], icmpv6::MulticastListenerDone>),
+    ) {
+        self.router_alert(|buf| icmpv6::Message::multicast_listener_done(buf), f)
+    }
+
+    /// Fills the payload with a (MLDv2) Multicast Listener Query that contains the given `sources`
+    ///
+    /// See `multicast_listener_query` for details
+    pub fn multicast_listener_query_v2(
+        &mut self,
+        sources: &[Addr],
+        f: impl FnOnce(&mut icmpv6::Message<&mut [u8], icmpv6::MulticastListenerQueryV2>),
+    ) {
+        self.router_alert(
+            |buf| icmpv6::Message::multicast_listener_query_v2(buf, sources),
+            f,
+        )
+    }
+
+    /// Fills the payload with a (MLDv2) Multicast Listener Report that contains the given
+    /// `records`
+    ///
+    /// See `multicast_listener_query` for details
+    pub fn multicast_listener_report_v2(
+        &mut self,
+        records: &[icmpv6::mld::Record<'_>],
+        f: impl FnOnce(&mut icmpv6::Message<&mut [u8], icmpv6::MulticastListenerReportV2>),
+    ) {
+        self.router_alert(
+            |buf| icmpv6::Message::multicast_listener_report_v2(buf, records),
+            f,
+        )
+    }
+
     /// Fills the payload with a UDP packet
     pub fn udp(&mut self, f: impl FnOnce(&mut udp::Packet<&mut [u8]>)) {
         let src = self.get_source();
@@ -339,6 +434,34 @@ where
             self.buffer.truncate(len + u16(HEADER_SIZE));
         }
     }
+
+    // Fills the payload with a Hop-by-Hop Options header that contains a Router Alert option
+    // followed by the ICMPv6 message created by `new`
+    fn router_alert<T>(
+        &mut self,
+        new: impl FnOnce(&mut [u8]) -> icmpv6::Message<&mut [u8], T>,
+        f: impl FnOnce(&mut icmpv6::Message<&mut [u8], T>),
+    ) {
+        let src = self.get_source();
+        let dest = self.get_destination();
+
+        // NOTE `set_next_header` rejects extension headers
+        self.header_mut()[NEXT_HEADER] = NextHeader::Hopopt.into();
+        self.set_hop_limit(1);
+
+        let payload = self.payload_mut();
+        assert!(payload.len() >= ROUTER_ALERT.len());
+        payload[..ROUTER_ALERT.len()].copy_from_slice(&ROUTER_ALERT);
+
+        let mut message = new(&mut payload[ROUTER_ALERT.len()..]);
+
+        f(&mut message);
+
+        message.update_checksum(src, dest);
+
+        let len = (ROUTER_ALERT.len() + message.as_bytes().len()) as u16;
+        self.truncate(len);
+    }
 }
 
 impl<B> fmt::Debug for Packet<B>
@@ -379,6 +502,35 @@ impl Addr {
     /// All link-local routers multicast address
     pub const ALL_ROUTERS: Self = Addr([0xff, 0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2]);
 
+    /// Creates an address from its eight 16-bit pieces, most significant first
+    pub const fn from_segments(segments: [u16; 8]) -> Self {
+        let mut bytes = [0; 16];
+        let mut i = 0;
+        while i < segments.len() {
+            let [hi, lo] = segments[i].to_be_bytes();
+            bytes[2 * i] = hi;
+            bytes[2 * i + 1] = lo;
+            i += 1;
+        }
+
+        Addr(bytes)
+    }
+
+    /// Returns the eight 16-bit pieces of the address
+    pub fn segments(&self) -> [u16; 8] {
+        let mut words = [0; 8];
+        for (word, chunk) in words.iter_mut().zip(self.0.chunks(2)) {
+            *word = NE::read_u16(chunk);
+        }
+        words
+    }
+
+    // RFC 4291 - Section 2.5.5.2
+    /// Is this an IPv4-mapped address (`::ffff:a.b.c.d`)?
+    pub fn is_ipv4_mapped(&self) -> bool {
+        self.0[..12] == [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff]
+    }
+
     // Section 2.5.6
     /// Is this a link local address?
     pub fn is_link_local(&self) -> bool {
@@ -419,30 +571,310 @@ impl Addr {
     }
 }
 
+// RFC 5952 - Section 4. A Recommendation for IPv6 Text Representation
 impl fmt::Display for Addr {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut is_first = true;
+        fn words(f: &mut fmt::Formatter<'_>, words: &[u16]) -> fmt::Result {
+            let mut is_first = true;
+
+            for word in words {
+                if is_first {
+                    is_first = false;
+                } else {
+                    f.write_str(":")?;
+                }
+
+                write!(f, "{:x}", word)?;
+            }
+
+            Ok(())
+        }
 
-        for chunk in self.0.chunks(2) {
-            if is_first {
-                is_first = false;
+        // Section 5
+        if self.is_ipv4_mapped() {
+            let ipv4 = ipv4::Addr([self.0[12], self.0[13], self.0[14], self.0[15]]);
+            return write!(f, "::ffff:{}", ipv4);
+        }
+
+        let segments = self.segments();
+
+        // Section 4.2: `::` replaces the longest run of 2 or more zero words; the first one if
+        // there's a tie
+        let (mut start, mut len) = (0, 0);
+        let (mut run_start, mut run_len) = (0, 0);
+        for (i, word) in segments.iter().enumerate() {
+            if *word == 0 {
+                if run_len == 0 {
+                    run_start = i;
+                }
+                run_len += 1;
+
+                if run_len > len {
+                    start = run_start;
+                    len = run_len;
+                }
             } else {
-                f.write_str(":")?;
+                run_len = 0;
+            }
+        }
+
+        if len < 2 {
+            words(f, &segments)
+        } else {
+            words(f, &segments[..start])?;
+            f.write_str("::")?;
+            words(f, &segments[start + len..])
+        }
+    }
+}
+
+impl FromStr for Addr {
+    type Err = ();
+
+    /// Parses an address in the text format of RFC 4291 Section 2.2, e.g. `2001:db8::1` or
+    /// `::ffff:192.168.1.33`
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let mut words = [0; 8];
+
+        if let Some(i) = s.find("::") {
+            let head = parse_words(&s[..i], &mut words, false)?;
+
+            let mut tail = [0; 8];
+            let n = parse_words(&s[i + 2..], &mut tail, true)?;
+
+            // `::` stands for at least one zero word
+            if head + n > 7 {
+                return Err(());
             }
 
-            write!(f, "{:x}", NE::read_u16(chunk))?;
+            words[8 - n..].copy_from_slice(&tail[..n]);
+        } else if parse_words(s, &mut words, true)? != 8 {
+            return Err(());
+        }
+
+        Ok(Addr::from_segments(words))
+    }
+}
+
+/// IPv6 prefix, e.g. `2001:db8::/32`
+///
+/// The bits of the address past the prefix are kept so a `Prefix` can also describe an interface
+/// address; use `network` to clear them
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Prefix {
+    addr: Addr,
+    len: u8,
+}
+
+impl Prefix {
+    /// Creates a prefix from an address and a prefix length
+    ///
+    /// Returns an error if `len` is greater than 128
+    pub const fn new(addr: Addr, len: u8) -> Result<Self, ()> {
+        if len > 128 {
+            Err(())
+        } else {
+            Ok(Prefix { addr, len })
+        }
+    }
+
+    /// Returns the address
+    pub fn address(&self) -> Addr {
+        self.addr
+    }
+
+    /// Returns the prefix length
+    pub fn prefix_len(&self) -> u8 {
+        self.len
+    }
+
+    /// Returns the netmask, e.g. `ffff:ffff:ffff:ffff::` for a `/64` prefix
+    pub fn netmask(&self) -> Addr {
+        Addr(self.mask().to_be_bytes())
+    }
+
+    /// Returns this prefix with the bits of the address past the prefix cleared
+    pub fn network(&self) -> Self {
+        Prefix {
+            addr: Addr((u128::from_be_bytes(self.addr.0) & self.mask()).to_be_bytes()),
+            len: self.len,
         }
+    }
+
+    /// Is `addr` covered by this prefix?
+    pub fn contains(&self, addr: Addr) -> bool {
+        (u128::from_be_bytes(self.addr.0) ^ u128::from_be_bytes(addr.0)) & self.mask() == 0
+    }
 
-        Ok(())
+    fn mask(&self) -> u128 {
+        (!0u128).checked_shl(128 - u32::from(self.len)).unwrap_or(0)
     }
 }
 
+impl fmt::Display for Prefix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.len)
+    }
+}
+
+impl FromStr for Prefix {
+    type Err = ();
+
+    /// Parses a prefix in `addr/len` format, where `addr` may use the `::` shorthand
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let (addr, len) = s.split_once('/').ok_or(())?;
+        if len.is_empty() || len.len() > 3 || !len.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(());
+        }
+
+        Prefix::new(addr.parse()?, len.parse().map_err(|_| ())?)
+    }
+}
+
+// parses colon separated hex words into `words`; returns the number of words
+//
+// if `ipv4` is set the last group may be an IPv4 address in dotted decimal notation, which
+// counts as two words
+fn parse_words(s: &str, words: &mut [u16; 8], ipv4: bool) -> Result<usize, ()> {
+    if s.is_empty() {
+        return Ok(0);
+    }
+
+    let mut n = 0;
+    let mut groups = s.split(':').peekable();
+    while let Some(group) = groups.next() {
+        if ipv4 && groups.peek().is_none() && group.contains('.') {
+            if n + 2 > words.len() {
+                return Err(());
+            }
+
+            let addr = group.parse::<ipv4::Addr>()?;
+            words[n] = NE::read_u16(&addr.0[..2]);
+            words[n + 1] = NE::read_u16(&addr.0[2..]);
+            return Ok(n + 2);
+        }
+
+        if n == words.len()
+            || group.is_empty()
+            || group.len() > 4
+            || !group.bytes().all(|b| b.is_ascii_hexdigit())
+        {
+            return Err(());
+        }
+
+        words[n] = u32::from_str_radix(group, 16).map_err(|_| ())? as u16;
+        n += 1;
+    }
+
+    Ok(n)
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::ipv6;
+    use core::fmt::Write;
+
+    use crate::{fmt::Buffer, icmpv6, ipv6};
 
     use super::HEADER_SIZE;
 
+    #[test]
+    fn text() {
+        // RFC 5952 - Section 4
+        for (text, canonical) in &[
+            ("2001:0db8:0000:0000:0000:0000:0002:0001", "2001:db8::2:1"),
+            ("2001:DB8:0:0:1:0:0:1", "2001:db8::1:0:0:1"),
+            ("2001:db8:0:1:1:1:1:1", "2001:db8:0:1:1:1:1:1"),
+            ("2001:0:0:1:0:0:0:1", "2001:0:0:1::1"),
+            ("fe80::1", "fe80::1"),
+            ("ff02::1", "ff02::1"),
+            ("1::", "1::"),
+            ("::", "::"),
+            ("::1", "::1"),
+            ("0:0:0:0:0:ffff:c0a8:121", "::ffff:192.168.1.33"),
+            ("::ffff:192.168.1.33", "::ffff:192.168.1.33"),
+            ("64:ff9b::192.0.2.33", "64:ff9b::c000:221"),
+        ] {
+            let addr: ipv6::Addr = text.parse().unwrap();
+
+            let mut buf = Buffer::new();
+            write!(buf, "{}", addr).unwrap();
+            assert_eq!(buf.as_str(), *canonical);
+            assert_eq!(canonical.parse(), Ok(addr));
+        }
+
+        const ROUTER: ipv6::Addr = ipv6::Addr::from_segments([0xfe80, 0, 0, 0, 0, 0, 0, 1]);
+        assert_eq!(ROUTER.segments(), [0xfe80, 0, 0, 0, 0, 0, 0, 1]);
+        assert_eq!("fe80::1".parse(), Ok(ROUTER));
+
+        for s in &[
+            "",
+            ":",
+            ":::",
+            "1:2:3:4:5:6:7",
+            "1:2:3:4:5:6:7:8:9",
+            "1::2::3",
+            "1:2:3:4:5:6:7::8",
+            "12345::",
+            "::g",
+            "::1.2.3.4:5",
+            "1.2.3.4::",
+            "1:2:3:4:5:6:7:1.2.3.4",
+            "::ffff:1.2.3",
+        ] {
+            assert!(s.parse::<ipv6::Addr>().is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn prefix() {
+        let prefix: ipv6::Prefix = "2001:db8:0:1::42/64".parse().unwrap();
+        assert_eq!(
+            prefix.address(),
+            ipv6::Addr([0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0x42])
+        );
+        assert_eq!(prefix.prefix_len(), 64);
+        assert_eq!(
+            prefix.netmask(),
+            ipv6::Addr([0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0, 0, 0, 0, 0])
+        );
+        assert_eq!(
+            prefix.network().address(),
+            ipv6::Addr([0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0])
+        );
+        assert!(prefix.contains(ipv6::Addr([
+            0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 1, 0xff, 0, 0, 0, 0, 0, 0, 1
+        ])));
+        assert!(!prefix.contains(ipv6::Addr::LOOPBACK));
+        let mut buf = Buffer::new();
+        write!(buf, "{}", prefix).unwrap();
+        assert_eq!(buf.as_str(), "2001:db8:0:1::42/64");
+
+        let any: ipv6::Prefix = "::/0".parse().unwrap();
+        assert_eq!(any.address(), ipv6::Addr::UNSPECIFIED);
+        assert!(any.contains(ipv6::Addr::ALL_NODES));
+
+        let loopback: ipv6::Prefix = "::1/128".parse().unwrap();
+        assert_eq!(loopback.address(), ipv6::Addr::LOOPBACK);
+        assert!(!loopback.contains(ipv6::Addr::UNSPECIFIED));
+
+        let all_nodes: ipv6::Prefix = "ff02::1/128".parse().unwrap();
+        assert_eq!(all_nodes.address(), ipv6::Addr::ALL_NODES);
+
+        for s in &[
+            "::1",
+            "::1/129",
+            "1:2:3:4:5:6:7/64",
+            "1:2:3:4:5:6:7:8:9/64",
+            "1:2:3:4::5:6:7:8/64",
+            "1::2::3/64",
+            "12345::/16",
+            "g::/16",
+            ":1::/16",
+        ] {
+            assert!(s.parse::<ipv6::Prefix>().is_err(), "{}", s);
+        }
+    }
+
     #[test]
     fn solicited_node() {
         let unicast = ipv6::Addr([
@@ -455,6 +887,64 @@ mod tests {
         );
     }
 
+    #[test]
+    fn echo_request() {
+        const PAYLOAD: &[u8] = b"ping";
+
+        let src = ipv6::Addr([0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        let dest = ipv6::Addr([0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2]);
+
+        let mut chunk = [0; 128];
+
+        let mut ip = ipv6::Packet::new(&mut chunk[..]);
+        ip.set_source(src);
+        ip.set_destination(dest);
+        ip.echo_request(|m| {
+            m.set_identifier(1);
+            m.set_sequence_number(2);
+            m.set_payload(PAYLOAD);
+        });
+
+        let ip = ipv6::Packet::parse(ip.as_bytes()).unwrap();
+        assert_eq!(ip.get_next_header(), ipv6::NextHeader::Ipv6Icmp);
+        assert_eq!(usize::from(ip.get_length()), 8 + PAYLOAD.len());
+
+        let m = icmpv6::Message::parse(ip.payload()).unwrap();
+        assert!(m.verify_checksum(src, dest));
+
+        let m = m.downcast::<icmpv6::EchoRequest>().unwrap();
+        assert_eq!(m.get_identifier(), 1);
+        assert_eq!(m.get_sequence_number(), 2);
+        assert_eq!(m.payload(), PAYLOAD);
+    }
+
+    #[test]
+    fn multicast_listener_report() {
+        const GROUP: ipv6::Addr =
+            ipv6::Addr([0xff, 0x05, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x01, 0x87]);
+
+        let src = ipv6::Addr([0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        let dest = GROUP;
+
+        let mut chunk = [0; 128];
+
+        let mut ip = ipv6::Packet::new(&mut chunk[..]);
+        ip.set_source(src);
+        ip.set_destination(dest);
+        ip.multicast_listener_report(|m| m.set_multicast_address(GROUP));
+
+        assert_eq!(ip.get_next_header(), ipv6::NextHeader::Hopopt);
+        assert_eq!(ip.get_hop_limit(), 1);
+        assert_eq!(ip.get_length(), 8 + 24);
+        assert_eq!(&ip.payload()[..8], &[58, 0, 5, 2, 0, 0, 1, 0]);
+
+        let m = icmpv6::Message::parse(&ip.payload()[8..]).unwrap();
+        assert!(m.verify_checksum(src, dest));
+
+        let m = m.downcast::<icmpv6::MulticastListenerReport>().unwrap();
+        assert_eq!(m.get_multicast_address(), GROUP);
+    }
+
     #[test]
     fn new() {
         const SZ: usize = 128;
@@ -79,7 +79,7 @@
 //!         udp.coap(0, |mut coap| {
 //!             coap.set_type(coap::Type::Confirmable);
 //!             coap.set_code(coap::Method::Put);
-//!             coap.add_option(coap::OptionNumber::UriPath, b"led");
+//!             coap.add_option(coap::OptionNumber::UriPath, b"led").unwrap();
 //!             coap.set_payload(b"on")
 //!         })
 //!     });
@@ -107,6 +107,8 @@ mod fmt;
 mod sealed;
 mod traits;
 
+pub mod checksum;
+
 // Medium Access Control layer
 pub mod ether;
 pub mod ieee802154;
@@ -117,16 +119,19 @@ pub mod arp;
 // Network layer
 pub mod ipv4;
 pub mod ipv6;
+pub mod route;
 pub mod sixlowpan;
 
 pub mod icmp;
 pub mod icmpv6;
+pub mod igmp;
 
 // Transport layer
 pub mod udp;
 
 // Application layer
 pub mod coap;
+pub mod lwm2m;
 
 /// [Type State] Unknown
 pub enum Unknown {}
@@ -0,0 +1,1013 @@
+//! LwM2M: Lightweight Machine to Machine
+//!
+//! A LwM2M client exposes its data as *Objects*, which have one or more *Instances*, which have
+//! *Resources*. Each level is addressed by a numeric Uri-Path segment: `/3/0/1` is the Model
+//! Number resource (1) of the first instance (0) of the Device object (3).
+//!
+//! This module implements the client side of the protocol: the registration interface (`/rd`
+//! requests) and the device management interface, which dispatches Read, Write and Execute
+//! requests to `Object` implementations. Payloads use the OMA-TLV content format (see the `tlv`
+//! module), which is the only format the device management interface serves: a Read request that
+//! asks for, e.g., SenML JSON (110) or SenML CBOR (112) with the Accept option gets a 4.06 (Not
+//! Acceptable) response.
+//!
+//! # References
+//!
+//! - [OMA LwM2M TS 1.0.2][spec]
+//!
+//! [spec]: http://www.openmobilealliance.org/release/LightweightM2M/V1_0_2-20180209-A/OMA-TS-LightweightM2M-V1_0_2-20180209-A.pdf
+//!
+//! # Example
+//!
+//! ```
+//! use jnet::{
+//!     coap,
+//!     lwm2m::{
+//!         device,
+//!         tlv::{self, Value},
+//!         Client, Error, Object, Resource,
+//!     },
+//! };
+//!
+//! struct Device;
+//!
+//! impl Object for Device {
+//!     fn id(&self) -> u16 {
+//!         device::ID
+//!     }
+//!
+//!     fn resources(&self) -> &[Resource] {
+//!         device::RESOURCES
+//!     }
+//!
+//!     fn read(&mut self, _: u16, resource: u16, w: &mut tlv::Writer<'_>) -> Result<(), Error> {
+//!         match resource {
+//!             device::MANUFACTURER => w.resource(resource, Value::String("ACME")),
+//!             _ => Err(Error::NotFound),
+//!         }
+//!     }
+//! }
+//!
+//! let mut device = Device;
+//! let mut objects: [&mut dyn Object; 1] = [&mut device];
+//! let mut client = Client::new("node-1", &mut objects);
+//!
+//! // GET /3/0/0
+//! let mut buf = [0; 32];
+//! let mut req = coap::Message::new(&mut buf[..], 0);
+//! req.set_code(coap::Method::Get);
+//! for segment in &["3", "0", "0"] {
+//!     req.add_uri_path(segment).unwrap();
+//! }
+//! let req = req.no_payload();
+//! let req = coap::Message::parse(req.as_bytes()).unwrap();
+//!
+//! let mut buf = [0; 64];
+//! let resp = client.handle(&req, coap::Message::new(&mut buf[..], 0)).unwrap();
+//!
+//! assert_eq!(resp.get_code(), coap::Response::Content.into());
+//! let manufacturer = tlv::parse(resp.payload()).next().unwrap().unwrap();
+//! assert_eq!(manufacturer.as_str(), Ok("ACME"));
+//! ```
+
+use core::fmt::{self, Write};
+
+use as_slice::{AsMutSlice, AsSlice};
+use owning_slice::Truncate;
+
+use crate::{
+    coap::{self, link, server, ContentFormat, Message, Method, OptionNumber, Response, Unset},
+    traits::TryFrom,
+};
+
+pub mod connectivity;
+pub mod device;
+pub mod firmware;
+pub mod tlv;
+
+/// LwM2M version advertised during registration
+pub const VERSION: &str = "1.0";
+
+/// Largest Object, Instance or Resource ID; 65535 is reserved
+pub const MAX_ID: u16 = 65534;
+
+/// Size of the largest Uri-Query option
+const MAX_QUERY_SIZE: usize = 255;
+
+/// LwM2M error
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Error {
+    /// The Object, Instance or Resource doesn't exist
+    NotFound,
+    /// The operation is not supported by the target
+    MethodNotAllowed,
+    /// The payload is malformed or doesn't match the target
+    Malformed,
+    /// There's no space left in the buffer
+    BufferTooSmall,
+}
+
+impl Error {
+    /// Returns the CoAP response code that reports this error
+    pub fn response(&self) -> Response {
+        match *self {
+            Error::NotFound => Response::NotFound,
+            Error::MethodNotAllowed => Response::MethodNotAllowed,
+            Error::Malformed => Response::BadRequest,
+            Error::BufferTooSmall => Response::InternalServerError,
+        }
+    }
+}
+
+impl From<coap::Error> for Error {
+    fn from(e: coap::Error) -> Self {
+        match e {
+            coap::Error::OptionLength => Error::Malformed,
+            coap::Error::BufferTooSmall => Error::BufferTooSmall,
+        }
+    }
+}
+
+/// Path to an Object, Object Instance, Resource or Resource Instance
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Path {
+    /// Object ID
+    pub object: u16,
+    /// Object Instance ID
+    pub instance: Option<u16>,
+    /// Resource ID
+    pub resource: Option<u16>,
+    /// Resource Instance ID
+    pub resource_instance: Option<u16>,
+}
+
+impl Path {
+    /// Parses a path from its segments, e.g. the Uri-Path options of a request
+    ///
+    /// Returns an error if there are less than one or more than four segments, or if a segment is
+    /// not a decimal ID in the range `0..=MAX_ID`
+    pub fn parse<'a, I>(segments: I) -> Result<Self, ()>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let mut ids = [None; 4];
+        for (n, segment) in segments.into_iter().enumerate() {
+            *ids.get_mut(n).ok_or(())? = Some(parse_id(segment)?);
+        }
+
+        Ok(Path {
+            object: ids[0].ok_or(())?,
+            instance: ids[1],
+            resource: ids[2],
+            resource_instance: ids[3],
+        })
+    }
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "/{}", self.object)?;
+
+        for id in [self.instance, self.resource, self.resource_instance]
+            .iter()
+            .filter_map(|id| *id)
+        {
+            write!(f, "/{}", id)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Operations supported by a Resource
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Operations {
+    /// Read-only
+    Read,
+    /// Write-only
+    Write,
+    /// Read and Write
+    ReadWrite,
+    /// Execute
+    Execute,
+}
+
+impl Operations {
+    /// Checks if the Resource can be read
+    pub fn is_readable(&self) -> bool {
+        matches!(*self, Operations::Read | Operations::ReadWrite)
+    }
+
+    /// Checks if the Resource can be written
+    pub fn is_writable(&self) -> bool {
+        matches!(*self, Operations::Write | Operations::ReadWrite)
+    }
+
+    /// Checks if the Resource can be executed
+    pub fn is_executable(&self) -> bool {
+        *self == Operations::Execute
+    }
+}
+
+/// Description of a Resource
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Resource {
+    /// Resource ID
+    pub id: u16,
+    /// Supported operations
+    pub operations: Operations,
+    /// Whether the Resource has multiple instances
+    pub multiple: bool,
+}
+
+impl Resource {
+    /// Describes a single-instance Resource
+    pub const fn new(id: u16, operations: Operations) -> Self {
+        Resource {
+            id,
+            operations,
+            multiple: false,
+        }
+    }
+
+    /// Describes a multiple-instance Resource
+    pub const fn multiple(id: u16, operations: Operations) -> Self {
+        Resource {
+            id,
+            operations,
+            multiple: true,
+        }
+    }
+}
+
+/// A LwM2M Object
+///
+/// The `Client` checks that the target Instance and Resource exist, and that the Resource
+/// supports the requested operation, before calling these methods.
+pub trait Object {
+    /// Returns the Object ID
+    fn id(&self) -> u16;
+
+    /// Returns the IDs of the existing Object Instances
+    fn instances(&self) -> &[u16] {
+        &[0]
+    }
+
+    /// Returns the Resources of this Object
+    fn resources(&self) -> &[Resource];
+
+    /// Writes the value of a readable Resource as a Resource or Multiple Resource TLV
+    ///
+    /// Returning `Error::NotFound` omits an optional Resource from the Instance-level reads
+    fn read(&mut self, instance: u16, resource: u16, w: &mut tlv::Writer<'_>) -> Result<(), Error>;
+
+    /// Writes a writable Resource; `value` is a Resource or Multiple Resource TLV
+    fn write(&mut self, instance: u16, value: &tlv::Tlv<'_>) -> Result<(), Error> {
+        let _ = (instance, value);
+        Err(Error::MethodNotAllowed)
+    }
+
+    /// Executes an executable Resource
+    fn execute(&mut self, instance: u16, resource: u16, arguments: &[u8]) -> Result<(), Error> {
+        let _ = (instance, resource, arguments);
+        Err(Error::MethodNotAllowed)
+    }
+}
+
+/// A LwM2M client
+pub struct Client<'a> {
+    endpoint: &'a str,
+    objects: &'a mut [&'a mut dyn Object],
+}
+
+impl<'a> Client<'a> {
+    /// Creates a client named `endpoint` that hosts the given `objects`
+    pub fn new(endpoint: &'a str, objects: &'a mut [&'a mut dyn Object]) -> Self {
+        Client { endpoint, objects }
+    }
+
+    /// Returns the Endpoint Client Name
+    pub fn endpoint(&self) -> &'a str {
+        self.endpoint
+    }
+
+    /// Turns `m` into a Register request (`POST /rd`)
+    ///
+    /// The payload lists the hosted Object Instances in CoRE Link Format. The Security Object
+    /// (0) is not listed, as required by the specification.
+    pub fn register<B>(&self, mut m: Message<B, Unset>, lifetime: u32) -> Result<Message<B>, Error>
+    where
+        B: AsSlice<Element = u8> + AsMutSlice<Element = u8> + Truncate<u16>,
+    {
+        m.set_code(Method::Post);
+        m.add_uri_path("rd")?;
+        add_query(&mut m, format_args!("ep={}", self.endpoint))?;
+        add_query(&mut m, format_args!("lt={}", lifetime))?;
+        add_query(&mut m, format_args!("lwm2m={}", VERSION))?;
+        m.add_uri_query("b=U")?;
+        m.add_content_format(ContentFormat::ApplicationLinkFormat)?;
+
+        m.try_set_payload_with(|buf| {
+            let mut cursor = link::Cursor::new(buf);
+            self.write_links(&mut cursor)
+                .map_err(|_| Error::BufferTooSmall)?;
+            Ok(cursor.len())
+        })
+        .map_err(|(_, e)| e)
+    }
+
+    /// Turns `m` into an Update request (`POST` to the `location` returned by the server)
+    pub fn update<'l, I, B>(
+        &self,
+        location: I,
+        mut m: Message<B, Unset>,
+        lifetime: Option<u32>,
+    ) -> Result<Message<B>, Error>
+    where
+        I: IntoIterator<Item = &'l str>,
+        B: AsSlice<Element = u8> + AsMutSlice<Element = u8> + Truncate<u16>,
+    {
+        m.set_code(Method::Post);
+        for segment in location {
+            m.add_uri_path(segment)?;
+        }
+
+        if let Some(lifetime) = lifetime {
+            add_query(&mut m, format_args!("lt={}", lifetime))?;
+        }
+
+        Ok(m.no_payload())
+    }
+
+    /// Turns `m` into a De-register request (`DELETE` to the `location` returned by the server)
+    pub fn deregister<'l, I, B>(
+        &self,
+        location: I,
+        mut m: Message<B, Unset>,
+    ) -> Result<Message<B>, Error>
+    where
+        I: IntoIterator<Item = &'l str>,
+        B: AsSlice<Element = u8> + AsMutSlice<Element = u8> + Truncate<u16>,
+    {
+        m.set_code(Method::Delete);
+        for segment in location {
+            m.add_uri_path(segment)?;
+        }
+
+        Ok(m.no_payload())
+    }
+
+    /// Writes the links to the hosted Object Instances in CoRE Link Format
+    pub fn write_links<W>(&self, w: &mut W) -> fmt::Result
+    where
+        W: Write,
+    {
+        let mut writer = link::Writer::new(w);
+        for object in self.objects.iter().filter(|object| object.id() != 0) {
+            let mut path = Path {
+                object: object.id(),
+                instance: None,
+                resource: None,
+                resource_instance: None,
+            };
+
+            if object.instances().is_empty() {
+                writer.link(path)?;
+            }
+
+            for instance in object.instances() {
+                path.instance = Some(*instance);
+                writer.link(path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handles a device management request and builds the response
+    ///
+    /// - GET reads an Object, an Object Instance or a Resource
+    /// - PUT writes a Resource or the Resources of an Object Instance
+    /// - POST executes a Resource or does a partial update of an Object Instance
+    ///
+    /// Like `coap::server::Server::handle`, this returns a Reset message for a CoAP ping and `None`
+    /// for the messages that must be ignored (see `coap::server::prepare_response`)
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the token length of `resp` doesn't match the token length of `req`
+    pub fn handle<'r>(
+        &mut self,
+        req: &Message<&[u8]>,
+        resp: Message<&'r mut [u8], Unset>,
+    ) -> Option<Message<&'r mut [u8]>> {
+        let resp = match server::prepare_response(req, resp) {
+            Ok(resp) => resp,
+            Err(reply) => return reply,
+        };
+
+        Some(self.dispatch(req, resp))
+    }
+
+    /* Private */
+    fn dispatch<'r>(
+        &mut self,
+        req: &Message<&[u8]>,
+        mut resp: Message<&'r mut [u8], Unset>,
+    ) -> Message<&'r mut [u8]> {
+        let code = req.get_code();
+
+        // RFC 7252 - Section 5.4.1. unrecognized critical options
+        if req.options().any(|opt| match opt.number() {
+            OptionNumber::Unknown(_) | OptionNumber::Oscore => opt.number().is_critical(),
+            _ => false,
+        }) {
+            return error(resp, Response::BadOption);
+        }
+
+        // RFC 7252 - Section 5.10.1. Uri-Path and Uri-Query values are UTF-8 strings
+        if req.uri_path().chain(req.uri_query()).any(|s| s.is_err()) {
+            return error(resp, Response::BadRequest);
+        }
+
+        let method = match Method::try_from(code) {
+            Ok(method) => method,
+            Err(_) => return error(resp, Response::MethodNotAllowed),
+        };
+
+        // NOTE all the segments are valid UTF-8 at this point
+        let path = match Path::parse(req.uri_path().filter_map(Result::ok)) {
+            Ok(path) => path,
+            Err(()) => return error(resp, Response::NotFound),
+        };
+
+        let object = match self
+            .objects
+            .iter_mut()
+            .find(|object| object.id() == path.object)
+        {
+            Some(object) => &mut **object,
+            None => return error(resp, Response::NotFound),
+        };
+
+        if let Some(instance) = path.instance {
+            if !object.instances().contains(&instance) {
+                return error(resp, Response::NotFound);
+            }
+        }
+
+        let resource = match path.resource {
+            Some(id) => match find(object, id) {
+                Some(resource) => Some(resource),
+                None => return error(resp, Response::NotFound),
+            },
+            None => None,
+        };
+
+        // Resource Instances can only be accessed through their Multiple Resource
+        if path.resource_instance.is_some() {
+            return error(resp, Response::MethodNotAllowed);
+        }
+
+        match (method, path.instance, resource) {
+            (Method::Get, _, _) => read(object, &path, resource, req, resp),
+            (Method::Put, Some(instance), _) => write(object, instance, resource, req, resp),
+            (Method::Post, Some(instance), Some(resource)) => {
+                if !resource.operations.is_executable() {
+                    return error(resp, Response::MethodNotAllowed);
+                }
+
+                match object.execute(instance, resource.id, req.payload()) {
+                    Ok(()) => {
+                        resp.set_code(Response::Changed);
+                        resp.no_payload()
+                    }
+                    Err(e) => error(resp, e.response()),
+                }
+            }
+            (Method::Post, Some(instance), None) => write(object, instance, None, req, resp),
+            _ => error(resp, Response::MethodNotAllowed),
+        }
+    }
+}
+
+fn read<'r>(
+    object: &mut dyn Object,
+    path: &Path,
+    resource: Option<Resource>,
+    req: &Message<&[u8]>,
+    mut resp: Message<&'r mut [u8], Unset>,
+) -> Message<&'r mut [u8]> {
+    if let Some(format) = req.accept() {
+        if format != ContentFormat::ApplicationVndOmaLwm2mTlv {
+            return error(resp, Response::NotAcceptable);
+        }
+    }
+
+    if let Some(resource) = resource {
+        if !resource.operations.is_readable() {
+            return error(resp, Response::MethodNotAllowed);
+        }
+    }
+
+    resp.set_code(Response::Content);
+    if resp
+        .add_content_format(ContentFormat::ApplicationVndOmaLwm2mTlv)
+        .is_err()
+    {
+        return error(resp, Response::InternalServerError);
+    }
+
+    let res = resp.try_set_payload_with(|buf| {
+        let mut w = tlv::Writer::new(buf);
+        match (path.instance, resource) {
+            (Some(instance), Some(resource)) => object.read(instance, resource.id, &mut w)?,
+            (Some(instance), None) => read_instance(object, instance, &mut w)?,
+            (None, _) => {
+                // NOTE index loop because `read_instance` needs a mutable reference to `object`
+                for i in 0..object.instances().len() {
+                    let instance = object.instances()[i];
+                    w.object_instance(instance, |w| read_instance(object, instance, w))?;
+                }
+            }
+        }
+
+        Ok::<_, Error>(w.len())
+    });
+
+    match res {
+        Ok(resp) => resp,
+        Err((mut resp, e)) => {
+            resp.remove_option(OptionNumber::ContentFormat);
+            error(resp, e.response())
+        }
+    }
+}
+
+// writes all the readable Resources of an Object Instance
+fn read_instance(
+    object: &mut dyn Object,
+    instance: u16,
+    w: &mut tlv::Writer<'_>,
+) -> Result<(), Error> {
+    for i in 0..object.resources().len() {
+        let resource = object.resources()[i];
+        if !resource.operations.is_readable() {
+            continue;
+        }
+
+        match object.read(instance, resource.id, w) {
+            Ok(()) | Err(Error::NotFound) => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(())
+}
+
+fn write<'r>(
+    object: &mut dyn Object,
+    instance: u16,
+    resource: Option<Resource>,
+    req: &Message<&[u8]>,
+    mut resp: Message<&'r mut [u8], Unset>,
+) -> Message<&'r mut [u8]> {
+    if let Some(resource) = resource {
+        if !resource.operations.is_writable() {
+            return error(resp, Response::MethodNotAllowed);
+        }
+    }
+
+    if req.content_format() != Some(ContentFormat::ApplicationVndOmaLwm2mTlv) {
+        return error(resp, Response::UnsupportedContentFormat);
+    }
+
+    // the Resources of an Object Instance may come wrapped in an Object Instance TLV
+    let mut tlvs = tlv::parse(req.payload());
+    if let Some(Ok(first)) = tlvs.clone().next() {
+        if resource.is_none() && first.kind() == tlv::Kind::ObjectInstance && first.id() == instance
+        {
+            tlvs = first.children();
+        }
+    }
+
+    // validate the whole payload before modifying the Object
+    for tlv in tlvs.clone() {
+        let tlv = match tlv {
+            Ok(tlv) => tlv,
+            Err(e) => return error(resp, e.response()),
+        };
+
+        let target = match resource {
+            Some(resource) if tlv.id() != resource.id => return error(resp, Response::BadRequest),
+            Some(resource) => resource,
+            None => match find(object, tlv.id()) {
+                Some(resource) if resource.operations.is_writable() => resource,
+                Some(_) => return error(resp, Response::MethodNotAllowed),
+                None => return error(resp, Response::NotFound),
+            },
+        };
+
+        let kind = if target.multiple {
+            tlv::Kind::MultipleResource
+        } else {
+            tlv::Kind::Resource
+        };
+
+        if tlv.kind() != kind {
+            return error(resp, Response::BadRequest);
+        }
+    }
+
+    for tlv in tlvs {
+        // NOTE(unwrap) validated above
+        if let Err(e) = object.write(instance, &tlv.unwrap()) {
+            return error(resp, e.response());
+        }
+    }
+
+    resp.set_code(Response::Changed);
+    resp.no_payload()
+}
+
+fn find(object: &dyn Object, id: u16) -> Option<Resource> {
+    object
+        .resources()
+        .iter()
+        .find(|resource| resource.id == id)
+        .cloned()
+}
+
+fn parse_id(segment: &str) -> Result<u16, ()> {
+    if segment.is_empty() || !segment.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(());
+    }
+
+    match segment.parse() {
+        Ok(id) if id <= MAX_ID => Ok(id),
+        _ => Err(()),
+    }
+}
+
+fn add_query<B>(m: &mut Message<B, Unset>, args: fmt::Arguments<'_>) -> Result<(), Error>
+where
+    B: AsSlice<Element = u8> + AsMutSlice<Element = u8>,
+{
+    let mut buf = [0; MAX_QUERY_SIZE];
+    let mut cursor = link::Cursor::new(&mut buf);
+    cursor.write_fmt(args).map_err(|_| Error::BufferTooSmall)?;
+    m.add_uri_query(cursor.finish())?;
+    Ok(())
+}
+
+fn error<B>(mut resp: Message<B, Unset>, code: Response) -> Message<B>
+where
+    B: AsSlice<Element = u8> + AsMutSlice<Element = u8> + Truncate<u16>,
+{
+    resp.set_code(code);
+    resp.no_payload()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::coap;
+
+    use super::{
+        device,
+        tlv::{self, Kind, Value},
+        Client, Error, Object, Path, Resource,
+    };
+
+    struct Device {
+        time: i64,
+        reboots: u8,
+    }
+
+    impl Object for Device {
+        fn id(&self) -> u16 {
+            device::ID
+        }
+
+        fn resources(&self) -> &[Resource] {
+            device::RESOURCES
+        }
+
+        fn read(&mut self, _: u16, resource: u16, w: &mut tlv::Writer<'_>) -> Result<(), Error> {
+            match resource {
+                device::MANUFACTURER => w.resource(resource, Value::String("ACME")),
+                device::ERROR_CODE => {
+                    w.multiple_resource(resource, |w| w.resource_instance(0, Value::Integer(0)))
+                }
+                device::CURRENT_TIME => w.resource(resource, Value::Time(self.time)),
+                _ => Err(Error::NotFound),
+            }
+        }
+
+        fn write(&mut self, _: u16, value: &tlv::Tlv<'_>) -> Result<(), Error> {
+            match value.id() {
+                device::CURRENT_TIME => {
+                    self.time = value.as_integer()?;
+                    Ok(())
+                }
+                _ => Err(Error::MethodNotAllowed),
+            }
+        }
+
+        fn execute(&mut self, _: u16, resource: u16, _: &[u8]) -> Result<(), Error> {
+            match resource {
+                device::REBOOT => {
+                    self.reboots += 1;
+                    Ok(())
+                }
+                _ => Err(Error::MethodNotAllowed),
+            }
+        }
+    }
+
+    fn request<'a>(
+        buf: &'a mut [u8],
+        method: coap::Method,
+        path: &[&str],
+        payload: &[u8],
+    ) -> coap::Message<&'a [u8]> {
+        let mut m = coap::Message::new(&mut buf[..], 0);
+        m.set_type(coap::Type::Confirmable);
+        m.set_code(method);
+        for segment in path {
+            m.add_uri_path(segment).unwrap();
+        }
+        if !payload.is_empty() {
+            m.add_content_format(coap::ContentFormat::ApplicationVndOmaLwm2mTlv)
+                .unwrap();
+        }
+        let len = m.set_payload(payload).len();
+        coap::Message::parse(&buf[..usize::from(len)]).unwrap()
+    }
+
+    #[test]
+    fn path() {
+        assert_eq!(
+            Path::parse(["3", "0", "13"].iter().cloned()),
+            Ok(Path {
+                object: 3,
+                instance: Some(0),
+                resource: Some(13),
+                resource_instance: None,
+            })
+        );
+
+        assert!(Path::parse(None).is_err());
+        assert!(Path::parse(["3", "x"].iter().cloned()).is_err());
+        assert!(Path::parse(["3", "+1"].iter().cloned()).is_err());
+        assert!(Path::parse(["65535"].iter().cloned()).is_err());
+        assert!(Path::parse(["1", "2", "3", "4", "5"].iter().cloned()).is_err());
+    }
+
+    #[test]
+    fn register() {
+        let mut device = Device {
+            time: 0,
+            reboots: 0,
+        };
+        let mut objects: [&mut dyn Object; 1] = [&mut device];
+        let client = Client::new("node-1", &mut objects);
+
+        let mut buf = [0; 128];
+        let m = client
+            .register(coap::Message::new(&mut buf[..], 0), 300)
+            .unwrap();
+
+        assert_eq!(m.get_code(), coap::Method::Post.into());
+        assert!(m.uri_path().eq(["rd"].iter().cloned().map(Ok)));
+        assert!(m.uri_query().eq(["ep=node-1", "lt=300", "lwm2m=1.0", "b=U"]
+            .iter()
+            .cloned()
+            .map(Ok)));
+        assert_eq!(
+            m.content_format(),
+            Some(coap::ContentFormat::ApplicationLinkFormat)
+        );
+        assert_eq!(m.payload(), b"</3/0>");
+
+        let mut buf = [0; 16];
+        assert_eq!(
+            client
+                .register(coap::Message::new(&mut buf[..], 0), 300)
+                .err(),
+            Some(Error::BufferTooSmall)
+        );
+
+        // the endpoint name doesn't fit in a Uri-Query option
+        let name = [b'x'; 300];
+        let mut device = Device {
+            time: 0,
+            reboots: 0,
+        };
+        let mut objects: [&mut dyn Object; 1] = [&mut device];
+        let client = Client::new(core::str::from_utf8(&name).unwrap(), &mut objects);
+        let mut buf = [0; 512];
+        assert_eq!(
+            client
+                .register(coap::Message::new(&mut buf[..], 0), 300)
+                .err(),
+            Some(Error::BufferTooSmall)
+        );
+    }
+
+    #[test]
+    fn read() {
+        let mut device = Device {
+            time: 1_367_491_215,
+            reboots: 0,
+        };
+        let mut objects: [&mut dyn Object; 1] = [&mut device];
+        let mut client = Client::new("node-1", &mut objects);
+
+        // single Resource
+        let mut buf = [0; 32];
+        let req = request(&mut buf, coap::Method::Get, &["3", "0", "13"], &[]);
+        let mut buf = [0; 64];
+        let resp = client
+            .handle(&req, coap::Message::new(&mut buf[..], 0))
+            .unwrap();
+        assert_eq!(resp.get_type(), coap::Type::Acknowledgement);
+        assert_eq!(resp.get_code(), coap::Response::Content.into());
+        assert_eq!(resp.payload(), &[0xc4, 0x0d, 0x51, 0x82, 0x42, 0x8f]);
+
+        // Object: one Object Instance with the Resources that `read` knows about
+        let mut buf = [0; 32];
+        let req = request(&mut buf, coap::Method::Get, &["3"], &[]);
+        let mut buf = [0; 64];
+        let resp = client
+            .handle(&req, coap::Message::new(&mut buf[..], 0))
+            .unwrap();
+        assert_eq!(resp.get_code(), coap::Response::Content.into());
+        let instance = tlv::parse(resp.payload()).next().unwrap().unwrap();
+        assert_eq!(instance.kind(), Kind::ObjectInstance);
+        assert!(instance
+            .children()
+            .map(|tlv| tlv.unwrap().id())
+            .eq([0, 11, 13].iter().cloned()));
+
+        // only OMA-TLV is served
+        for format in &[
+            coap::ContentFormat::ApplicationSenmlJson,
+            coap::ContentFormat::ApplicationSenmlCbor,
+        ] {
+            let mut buf = [0; 32];
+            let mut req = coap::Message::new(&mut buf[..], 0);
+            req.set_type(coap::Type::Confirmable);
+            req.set_code(coap::Method::Get);
+            for segment in &["3", "0", "13"] {
+                req.add_uri_path(segment).unwrap();
+            }
+            req.add_accept(*format).unwrap();
+            let len = req.no_payload().len();
+            let req = coap::Message::parse(&buf[..usize::from(len)]).unwrap();
+            let mut buf = [0; 64];
+            let resp = client
+                .handle(&req, coap::Message::new(&mut buf[..], 0))
+                .unwrap();
+            assert_eq!(resp.get_code(), coap::Response::NotAcceptable.into());
+            assert_eq!(resp.content_format(), None);
+        }
+
+        // a Uri-Path that's not valid UTF-8
+        let mut buf = [0; 32];
+        let mut req = coap::Message::new(&mut buf[..], 0);
+        req.set_type(coap::Type::Confirmable);
+        req.set_code(coap::Method::Get);
+        req.add_uri_path("3").unwrap();
+        req.add_option(coap::OptionNumber::UriPath, &[0xff])
+            .unwrap();
+        let len = req.no_payload().len();
+        let req = coap::Message::parse(&buf[..usize::from(len)]).unwrap();
+        let mut buf = [0; 64];
+        let resp = client
+            .handle(&req, coap::Message::new(&mut buf[..], 0))
+            .unwrap();
+        assert_eq!(resp.get_code(), coap::Response::BadRequest.into());
+
+        // errors
+        for (path, code) in &[
+            (&["3", "0", "4"][..], coap::Response::MethodNotAllowed),
+            (&["3", "0", "1"][..], coap::Response::NotFound),
+            (&["3", "1"][..], coap::Response::NotFound),
+            (&["4"][..], coap::Response::NotFound),
+        ] {
+            let mut buf = [0; 32];
+            let req = request(&mut buf, coap::Method::Get, path, &[]);
+            let mut buf = [0; 64];
+            let resp = client
+                .handle(&req, coap::Message::new(&mut buf[..], 0))
+                .unwrap();
+            assert_eq!(resp.get_code(), (*code).into());
+            assert_eq!(resp.content_format(), None);
+        }
+
+        // the response doesn't fit in the buffer
+        let mut buf = [0; 32];
+        let req = request(&mut buf, coap::Method::Get, &["3"], &[]);
+        let mut buf = [0; 16];
+        let resp = client
+            .handle(&req, coap::Message::new(&mut buf[..], 0))
+            .unwrap();
+        assert_eq!(resp.get_code(), coap::Response::InternalServerError.into());
+        assert_eq!(resp.content_format(), None);
+    }
+
+    #[test]
+    fn empty() {
+        let mut device = Device {
+            time: 0,
+            reboots: 0,
+        };
+        let mut objects: [&mut dyn Object; 1] = [&mut device];
+        let mut client = Client::new("node-1", &mut objects);
+
+        let mut message = |ty| {
+            let mut buf = [0; 4];
+            let mut m = coap::Message::new(&mut buf[..], 0);
+            m.set_type(ty);
+            m.set_code(coap::Code::EMPTY);
+            m.set_message_id(7);
+            m.no_payload();
+            let req = coap::Message::parse(&buf[..]).unwrap();
+
+            let mut buf = [0; 16];
+            client
+                .handle(&req, coap::Message::new(&mut buf[..], 0))
+                .map(|resp| (resp.get_type(), resp.get_code(), resp.len()))
+        };
+
+        // e.g. the server acknowledges a Confirmable notification
+        assert_eq!(message(coap::Type::Acknowledgement), None);
+        assert_eq!(message(coap::Type::Reset), None);
+
+        // CoAP ping
+        assert_eq!(
+            message(coap::Type::Confirmable),
+            Some((coap::Type::Reset, coap::Code::EMPTY, 4))
+        );
+    }
+
+    #[test]
+    fn write_execute() {
+        let mut device = Device {
+            time: 0,
+            reboots: 0,
+        };
+
+        {
+            let mut objects: [&mut dyn Object; 1] = [&mut device];
+            let mut client = Client::new("node-1", &mut objects);
+
+            let mut payload = [0; 8];
+            let mut w = tlv::Writer::new(&mut payload);
+            w.resource(device::CURRENT_TIME, Value::Time(42)).unwrap();
+            let payload = w.finish();
+
+            let mut buf = [0; 32];
+            let req = request(&mut buf, coap::Method::Put, &["3", "0", "13"], payload);
+            let mut buf = [0; 32];
+            let resp = client
+                .handle(&req, coap::Message::new(&mut buf[..], 0))
+                .unwrap();
+            assert_eq!(resp.get_code(), coap::Response::Changed.into());
+
+            // Manufacturer is read-only
+            let mut payload = [0; 8];
+            let mut w = tlv::Writer::new(&mut payload);
+            w.resource(device::MANUFACTURER, Value::String("x"))
+                .unwrap();
+            let payload = w.finish();
+
+            let mut buf = [0; 32];
+            let req = request(&mut buf, coap::Method::Post, &["3", "0"], payload);
+            let mut buf = [0; 32];
+            let resp = client
+                .handle(&req, coap::Message::new(&mut buf[..], 0))
+                .unwrap();
+            assert_eq!(resp.get_code(), coap::Response::MethodNotAllowed.into());
+
+            let mut buf = [0; 32];
+            let req = request(&mut buf, coap::Method::Post, &["3", "0", "4"], &[]);
+            let mut buf = [0; 32];
+            let resp = client
+                .handle(&req, coap::Message::new(&mut buf[..], 0))
+                .unwrap();
+            assert_eq!(resp.get_code(), coap::Response::Changed.into());
+
+            let mut buf = [0; 32];
+            let req = request(&mut buf, coap::Method::Post, &["3", "0", "13"], &[]);
+            let mut buf = [0; 32];
+            let resp = client
+                .handle(&req, coap::Message::new(&mut buf[..], 0))
+                .unwrap();
+            assert_eq!(resp.get_code(), coap::Response::MethodNotAllowed.into());
+        }
+
+        assert_eq!(device.time, 42);
+        assert_eq!(device.reboots, 1);
+    }
+}
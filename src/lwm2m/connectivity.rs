@@ -0,0 +1,76 @@
+//! Connectivity Monitoring object (ID 4)
+//!
+//! See OMA LwM2M TS 1.0.2 Appendix E.5 "LwM2M Object: Connectivity Monitoring"
+
+use crate::lwm2m::{Operations, Resource};
+
+/// Object ID
+pub const ID: u16 = 4;
+
+/// Network Bearer (Integer)
+pub const NETWORK_BEARER: u16 = 0;
+/// Available Network Bearer (multiple Integer)
+pub const AVAILABLE_NETWORK_BEARER: u16 = 1;
+/// Radio Signal Strength, in dBm (Integer)
+pub const RADIO_SIGNAL_STRENGTH: u16 = 2;
+/// Link Quality (Integer)
+pub const LINK_QUALITY: u16 = 3;
+/// IP Addresses (multiple String)
+pub const IP_ADDRESSES: u16 = 4;
+/// Router IP Addresses (multiple String)
+pub const ROUTER_IP_ADDRESSES: u16 = 5;
+/// Link Utilization, in percent (Integer)
+pub const LINK_UTILIZATION: u16 = 6;
+/// APN (multiple String)
+pub const APN: u16 = 7;
+/// Cell ID (Integer)
+pub const CELL_ID: u16 = 8;
+/// SMNC (Integer)
+pub const SMNC: u16 = 9;
+/// SMCC (Integer)
+pub const SMCC: u16 = 10;
+
+/// Values of the Network Bearer resource
+pub mod bearer {
+    /// GSM cellular network
+    pub const GSM: i64 = 0;
+    /// TD-SCDMA cellular network
+    pub const TD_SCDMA: i64 = 1;
+    /// WCDMA cellular network
+    pub const WCDMA: i64 = 2;
+    /// CDMA2000 cellular network
+    pub const CDMA2000: i64 = 3;
+    /// WiMAX cellular network
+    pub const WIMAX: i64 = 4;
+    /// LTE-TDD cellular network
+    pub const LTE_TDD: i64 = 5;
+    /// LTE-FDD cellular network
+    pub const LTE_FDD: i64 = 6;
+    /// WLAN network
+    pub const WLAN: i64 = 21;
+    /// Bluetooth network
+    pub const BLUETOOTH: i64 = 22;
+    /// IEEE 802.15.4 network
+    pub const IEEE_802_15_4: i64 = 23;
+    /// Ethernet
+    pub const ETHERNET: i64 = 41;
+    /// DSL
+    pub const DSL: i64 = 42;
+    /// PLC
+    pub const PLC: i64 = 43;
+}
+
+/// All the resources of the Connectivity Monitoring object
+pub static RESOURCES: &[Resource] = &[
+    Resource::new(NETWORK_BEARER, Operations::Read),
+    Resource::multiple(AVAILABLE_NETWORK_BEARER, Operations::Read),
+    Resource::new(RADIO_SIGNAL_STRENGTH, Operations::Read),
+    Resource::new(LINK_QUALITY, Operations::Read),
+    Resource::multiple(IP_ADDRESSES, Operations::Read),
+    Resource::multiple(ROUTER_IP_ADDRESSES, Operations::Read),
+    Resource::new(LINK_UTILIZATION, Operations::Read),
+    Resource::multiple(APN, Operations::Read),
+    Resource::new(CELL_ID, Operations::Read),
+    Resource::new(SMNC, Operations::Read),
+    Resource::new(SMCC, Operations::Read),
+];
@@ -0,0 +1,79 @@
+//! Device object (ID 3)
+//!
+//! See OMA LwM2M TS 1.0.2 Appendix E.4 "LwM2M Object: Device"
+
+use crate::lwm2m::{Operations, Resource};
+
+/// Object ID
+pub const ID: u16 = 3;
+
+/// Manufacturer (String)
+pub const MANUFACTURER: u16 = 0;
+/// Model Number (String)
+pub const MODEL_NUMBER: u16 = 1;
+/// Serial Number (String)
+pub const SERIAL_NUMBER: u16 = 2;
+/// Firmware Version (String)
+pub const FIRMWARE_VERSION: u16 = 3;
+/// Reboot
+pub const REBOOT: u16 = 4;
+/// Factory Reset
+pub const FACTORY_RESET: u16 = 5;
+/// Available Power Sources (multiple Integer)
+pub const AVAILABLE_POWER_SOURCES: u16 = 6;
+/// Power Source Voltage, in mV (multiple Integer)
+pub const POWER_SOURCE_VOLTAGE: u16 = 7;
+/// Power Source Current, in mA (multiple Integer)
+pub const POWER_SOURCE_CURRENT: u16 = 8;
+/// Battery Level, in percent (Integer)
+pub const BATTERY_LEVEL: u16 = 9;
+/// Memory Free, in KB (Integer)
+pub const MEMORY_FREE: u16 = 10;
+/// Error Code (multiple Integer)
+pub const ERROR_CODE: u16 = 11;
+/// Reset Error Code
+pub const RESET_ERROR_CODE: u16 = 12;
+/// Current Time (Time)
+pub const CURRENT_TIME: u16 = 13;
+/// UTC Offset (String)
+pub const UTC_OFFSET: u16 = 14;
+/// Timezone (String)
+pub const TIMEZONE: u16 = 15;
+/// Supported Binding and Modes (String)
+pub const SUPPORTED_BINDING_AND_MODES: u16 = 16;
+/// Device Type (String)
+pub const DEVICE_TYPE: u16 = 17;
+/// Hardware Version (String)
+pub const HARDWARE_VERSION: u16 = 18;
+/// Software Version (String)
+pub const SOFTWARE_VERSION: u16 = 19;
+/// Battery Status (Integer)
+pub const BATTERY_STATUS: u16 = 20;
+/// Memory Total, in KB (Integer)
+pub const MEMORY_TOTAL: u16 = 21;
+
+/// All the resources of the Device object
+pub static RESOURCES: &[Resource] = &[
+    Resource::new(MANUFACTURER, Operations::Read),
+    Resource::new(MODEL_NUMBER, Operations::Read),
+    Resource::new(SERIAL_NUMBER, Operations::Read),
+    Resource::new(FIRMWARE_VERSION, Operations::Read),
+    Resource::new(REBOOT, Operations::Execute),
+    Resource::new(FACTORY_RESET, Operations::Execute),
+    Resource::multiple(AVAILABLE_POWER_SOURCES, Operations::Read),
+    Resource::multiple(POWER_SOURCE_VOLTAGE, Operations::Read),
+    Resource::multiple(POWER_SOURCE_CURRENT, Operations::Read),
+    Resource::new(BATTERY_LEVEL, Operations::Read),
+    Resource::new(MEMORY_FREE, Operations::Read),
+    Resource::multiple(ERROR_CODE, Operations::Read),
+    Resource::new(RESET_ERROR_CODE, Operations::Execute),
+    Resource::new(CURRENT_TIME, Operations::ReadWrite),
+    Resource::new(UTC_OFFSET, Operations::ReadWrite),
+    Resource::new(TIMEZONE, Operations::ReadWrite),
+    Resource::new(SUPPORTED_BINDING_AND_MODES, Operations::Read),
+    Resource::new(DEVICE_TYPE, Operations::Read),
+    Resource::new(HARDWARE_VERSION, Operations::Read),
+    Resource::new(SOFTWARE_VERSION, Operations::Read),
+    Resource::new(BATTERY_STATUS, Operations::Read),
+    Resource::new(MEMORY_TOTAL, Operations::Read),
+];
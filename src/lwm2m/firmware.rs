@@ -0,0 +1,76 @@
+//! Firmware Update object (ID 5)
+//!
+//! See OMA LwM2M TS 1.0.2 Appendix E.6 "LwM2M Object: Firmware Update"
+
+use crate::lwm2m::{Operations, Resource};
+
+/// Object ID
+pub const ID: u16 = 5;
+
+/// Package (Opaque)
+pub const PACKAGE: u16 = 0;
+/// Package URI (String)
+pub const PACKAGE_URI: u16 = 1;
+/// Update
+pub const UPDATE: u16 = 2;
+/// State (Integer); see the `state` module
+pub const STATE: u16 = 3;
+/// Update Result (Integer); see the `result` module
+pub const UPDATE_RESULT: u16 = 5;
+/// PkgName (String)
+pub const PKG_NAME: u16 = 6;
+/// PkgVersion (String)
+pub const PKG_VERSION: u16 = 7;
+/// Firmware Update Protocol Support (multiple Integer)
+pub const PROTOCOL_SUPPORT: u16 = 8;
+/// Firmware Update Delivery Method (Integer)
+pub const DELIVERY_METHOD: u16 = 9;
+
+/// Values of the State resource
+pub mod state {
+    /// Idle (before downloading or after a successful update)
+    pub const IDLE: i64 = 0;
+    /// Downloading
+    pub const DOWNLOADING: i64 = 1;
+    /// Downloaded
+    pub const DOWNLOADED: i64 = 2;
+    /// Updating
+    pub const UPDATING: i64 = 3;
+}
+
+/// Values of the Update Result resource
+pub mod result {
+    /// Initial value
+    pub const INITIAL: i64 = 0;
+    /// Firmware updated successfully
+    pub const SUCCESS: i64 = 1;
+    /// Not enough flash memory for the new firmware package
+    pub const NOT_ENOUGH_FLASH: i64 = 2;
+    /// Out of RAM during the downloading process
+    pub const OUT_OF_RAM: i64 = 3;
+    /// Connection lost during the downloading process
+    pub const CONNECTION_LOST: i64 = 4;
+    /// Integrity check failure for the new firmware package
+    pub const INTEGRITY_CHECK_FAILURE: i64 = 5;
+    /// Unsupported package type
+    pub const UNSUPPORTED_PACKAGE_TYPE: i64 = 6;
+    /// Invalid URI
+    pub const INVALID_URI: i64 = 7;
+    /// Firmware update failed
+    pub const UPDATE_FAILED: i64 = 8;
+    /// Unsupported protocol
+    pub const UNSUPPORTED_PROTOCOL: i64 = 9;
+}
+
+/// All the resources of the Firmware Update object
+pub static RESOURCES: &[Resource] = &[
+    Resource::new(PACKAGE, Operations::Write),
+    Resource::new(PACKAGE_URI, Operations::ReadWrite),
+    Resource::new(UPDATE, Operations::Execute),
+    Resource::new(STATE, Operations::Read),
+    Resource::new(UPDATE_RESULT, Operations::Read),
+    Resource::new(PKG_NAME, Operations::Read),
+    Resource::new(PKG_VERSION, Operations::Read),
+    Resource::multiple(PROTOCOL_SUPPORT, Operations::Read),
+    Resource::new(DELIVERY_METHOD, Operations::Read),
+];
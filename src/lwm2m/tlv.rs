@@ -0,0 +1,622 @@
+//! OMA-TLV: the `application/vnd.oma.lwm2m+tlv` content format
+//!
+//! See OMA LwM2M TS 1.0.2 Section 6.4.3 "TLV"
+//!
+//! # Example
+//!
+//! ```
+//! use jnet::lwm2m::tlv::{self, Kind, Value, Writer};
+//!
+//! let mut buf = [0; 64];
+//! let mut w = Writer::new(&mut buf);
+//! w.resource(0, Value::String("Open Mobile Alliance")).unwrap();
+//! w.multiple_resource(6, |w| {
+//!     w.resource_instance(0, Value::Integer(1))?;
+//!     w.resource_instance(1, Value::Integer(5))
+//! })
+//! .unwrap();
+//! let bytes = w.finish();
+//!
+//! let mut tlvs = tlv::parse(bytes);
+//! let manufacturer = tlvs.next().unwrap().unwrap();
+//! assert_eq!(manufacturer.kind(), Kind::Resource);
+//! assert_eq!(manufacturer.as_str(), Ok("Open Mobile Alliance"));
+//!
+//! let sources = tlvs.next().unwrap().unwrap();
+//! assert_eq!(sources.kind(), Kind::MultipleResource);
+//! assert!(sources
+//!     .children()
+//!     .map(|tlv| tlv.unwrap().as_integer().unwrap())
+//!     .eq([1, 5].iter().cloned()));
+//! assert!(tlvs.next().is_none());
+//! ```
+
+use crate::lwm2m::Error;
+
+/// Size of the largest TLV header: Type + 16-bit Identifier + 24-bit Length
+const MAX_HEADER_SIZE: usize = 6;
+
+/// Largest length that can be encoded in a TLV header
+const MAX_LENGTH: usize = (1 << 24) - 1;
+
+/* Type field */
+mod kind {
+    pub const MASK: u8 = (1 << SIZE) - 1;
+    pub const OFFSET: u8 = 6;
+    pub const SIZE: u8 = 2;
+}
+
+mod id16 {
+    pub const MASK: u8 = (1 << SIZE) - 1;
+    pub const OFFSET: u8 = 5;
+    pub const SIZE: u8 = 1;
+}
+
+mod length_type {
+    pub const MASK: u8 = (1 << SIZE) - 1;
+    pub const OFFSET: u8 = 3;
+    pub const SIZE: u8 = 2;
+}
+
+mod length {
+    pub const MASK: u8 = (1 << SIZE) - 1;
+    pub const OFFSET: u8 = 0;
+    pub const SIZE: u8 = 3;
+}
+
+/// The type of identifier of a TLV
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Kind {
+    /// Object Instance; the value is a sequence of `Resource` and `MultipleResource` TLVs
+    ObjectInstance,
+    /// Resource Instance (part of a `MultipleResource`)
+    ResourceInstance,
+    /// Multiple Resource; the value is a sequence of `ResourceInstance` TLVs
+    MultipleResource,
+    /// Resource with Value
+    Resource,
+}
+
+impl From<u8> for Kind {
+    fn from(bits: u8) -> Self {
+        match bits & kind::MASK {
+            0b00 => Kind::ObjectInstance,
+            0b01 => Kind::ResourceInstance,
+            0b10 => Kind::MultipleResource,
+            _ => Kind::Resource,
+        }
+    }
+}
+
+impl From<Kind> for u8 {
+    fn from(kind: Kind) -> u8 {
+        match kind {
+            Kind::ObjectInstance => 0b00,
+            Kind::ResourceInstance => 0b01,
+            Kind::MultipleResource => 0b10,
+            Kind::Resource => 0b11,
+        }
+    }
+}
+
+/// The value of a resource
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Value<'a> {
+    /// Integer; encoded using the smallest of 1, 2, 4 or 8 bytes
+    Integer(i64),
+    /// Float; encoded using 4 bytes if that doesn't lose precision, 8 bytes otherwise
+    Float(f64),
+    /// Boolean
+    Boolean(bool),
+    /// UTF-8 string
+    String(&'a str),
+    /// Opaque bytes
+    Opaque(&'a [u8]),
+    /// Time; seconds since the UNIX epoch
+    Time(i64),
+    /// Object Link; Object ID and Object Instance ID
+    ObjectLink(u16, u16),
+}
+
+/// Serializes TLVs into a buffer
+pub struct Writer<'a> {
+    buffer: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> Writer<'a> {
+    /// Creates a writer that serializes TLVs into `buffer`
+    pub fn new(buffer: &'a mut [u8]) -> Self {
+        Writer { buffer, len: 0 }
+    }
+
+    /// Returns the number of bytes written so far
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if nothing has been written yet
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Writes a Resource with Value TLV
+    pub fn resource(&mut self, id: u16, value: Value<'_>) -> Result<(), Error> {
+        self.value(Kind::Resource, id, value)
+    }
+
+    /// Writes a Resource Instance TLV
+    pub fn resource_instance(&mut self, id: u16, value: Value<'_>) -> Result<(), Error> {
+        self.value(Kind::ResourceInstance, id, value)
+    }
+
+    /// Writes a Multiple Resource TLV; `f` writes its Resource Instances
+    pub fn multiple_resource<F>(&mut self, id: u16, f: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut Writer<'_>) -> Result<(), Error>,
+    {
+        self.nested(Kind::MultipleResource, id, f)
+    }
+
+    /// Writes an Object Instance TLV; `f` writes its Resources
+    pub fn object_instance<F>(&mut self, id: u16, f: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut Writer<'_>) -> Result<(), Error>,
+    {
+        self.nested(Kind::ObjectInstance, id, f)
+    }
+
+    /// Returns the written part of the buffer
+    pub fn finish(self) -> &'a [u8] {
+        &self.buffer[..self.len]
+    }
+
+    /* Private */
+    fn value(&mut self, kind: Kind, id: u16, value: Value<'_>) -> Result<(), Error> {
+        let mut buf = [0; 8];
+        let bytes: &[u8] = match value {
+            Value::Integer(x) | Value::Time(x) => encode_integer(x, &mut buf),
+            Value::Float(x) => {
+                let y = x as f32;
+                if f64::from(y) == x {
+                    buf[..4].copy_from_slice(&y.to_bits().to_be_bytes());
+                    &buf[..4]
+                } else {
+                    buf.copy_from_slice(&x.to_bits().to_be_bytes());
+                    &buf[..]
+                }
+            }
+            Value::Boolean(x) => {
+                buf[0] = u8::from(x);
+                &buf[..1]
+            }
+            Value::String(s) => s.as_bytes(),
+            Value::Opaque(bytes) => bytes,
+            Value::ObjectLink(object, instance) => {
+                buf[..2].copy_from_slice(&object.to_be_bytes());
+                buf[2..4].copy_from_slice(&instance.to_be_bytes());
+                &buf[..4]
+            }
+        };
+
+        let mut buf = [0; MAX_HEADER_SIZE];
+        let header = encode_header(kind, id, bytes.len(), &mut buf)?;
+
+        let start = self.len;
+        let mid = start + header.len();
+        let end = mid + bytes.len();
+        if end > self.buffer.len() {
+            return Err(Error::BufferTooSmall);
+        }
+
+        self.buffer[start..mid].copy_from_slice(header);
+        self.buffer[mid..end].copy_from_slice(bytes);
+        self.len = end;
+
+        Ok(())
+    }
+
+    // the nested TLVs are written after space for the largest header; once their length is known
+    // they are moved next to the actual header
+    fn nested<F>(&mut self, kind: Kind, id: u16, f: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut Writer<'_>) -> Result<(), Error>,
+    {
+        let start = self.len;
+        let body = start + MAX_HEADER_SIZE;
+
+        let len = {
+            let mut w = Writer::new(self.buffer.get_mut(body..).ok_or(Error::BufferTooSmall)?);
+            f(&mut w)?;
+            w.len
+        };
+
+        let mut buf = [0; MAX_HEADER_SIZE];
+        let header = encode_header(kind, id, len, &mut buf)?;
+
+        let mid = start + header.len();
+        self.buffer.copy_within(body..body + len, mid);
+        self.buffer[start..mid].copy_from_slice(header);
+        self.len = mid + len;
+
+        Ok(())
+    }
+}
+
+/// Parses a sequence of TLVs
+///
+/// The returned iterator yields an error, and then stops, if the TLVs are malformed
+pub fn parse(bytes: &[u8]) -> Iter<'_> {
+    Iter { bytes }
+}
+
+/// Iterator over a sequence of TLVs
+#[derive(Clone)]
+pub struct Iter<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = Result<Tlv<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.bytes.is_empty() {
+            return None;
+        }
+
+        let tlv = decode(self.bytes);
+        match tlv {
+            Some((tlv, rest)) => {
+                self.bytes = rest;
+                Some(Ok(tlv))
+            }
+            None => {
+                self.bytes = &[];
+                Some(Err(Error::Malformed))
+            }
+        }
+    }
+}
+
+/// A TLV
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tlv<'a> {
+    kind: Kind,
+    id: u16,
+    value: &'a [u8],
+}
+
+impl<'a> Tlv<'a> {
+    /// Returns the type of identifier of this TLV
+    pub fn kind(&self) -> Kind {
+        self.kind
+    }
+
+    /// Returns the identifier of this TLV
+    pub fn id(&self) -> u16 {
+        self.id
+    }
+
+    /// Returns the raw value of this TLV
+    pub fn value(&self) -> &'a [u8] {
+        self.value
+    }
+
+    /// Returns an iterator over the nested TLVs of an Object Instance or a Multiple Resource
+    ///
+    /// The iterator is empty for other kinds of TLV
+    pub fn children(&self) -> Iter<'a> {
+        match self.kind {
+            Kind::ObjectInstance | Kind::MultipleResource => parse(self.value),
+            Kind::ResourceInstance | Kind::Resource => parse(&[]),
+        }
+    }
+
+    /// Interprets the value as an Integer (or Time)
+    pub fn as_integer(&self) -> Result<i64, Error> {
+        let v = self.value;
+        Ok(match v.len() {
+            1 => i64::from(v[0] as i8),
+            2 => i64::from(i16::from_be_bytes([v[0], v[1]])),
+            4 => i64::from(i32::from_be_bytes([v[0], v[1], v[2], v[3]])),
+            8 => i64::from_be_bytes([v[0], v[1], v[2], v[3], v[4], v[5], v[6], v[7]]),
+            _ => return Err(Error::Malformed),
+        })
+    }
+
+    /// Interprets the value as a Float
+    pub fn as_float(&self) -> Result<f64, Error> {
+        let v = self.value;
+        Ok(match v.len() {
+            4 => f64::from(f32::from_bits(u32::from_be_bytes([v[0], v[1], v[2], v[3]]))),
+            8 => f64::from_bits(u64::from_be_bytes([
+                v[0], v[1], v[2], v[3], v[4], v[5], v[6], v[7],
+            ])),
+            _ => return Err(Error::Malformed),
+        })
+    }
+
+    /// Interprets the value as a Boolean
+    pub fn as_bool(&self) -> Result<bool, Error> {
+        match self.value {
+            [0] => Ok(false),
+            [1] => Ok(true),
+            _ => Err(Error::Malformed),
+        }
+    }
+
+    /// Interprets the value as an UTF-8 string
+    pub fn as_str(&self) -> Result<&'a str, Error> {
+        core::str::from_utf8(self.value).map_err(|_| Error::Malformed)
+    }
+
+    /// Interprets the value as an Object Link (Object ID, Object Instance ID)
+    pub fn as_object_link(&self) -> Result<(u16, u16), Error> {
+        match *self.value {
+            [a, b, c, d] => Ok((u16::from_be_bytes([a, b]), u16::from_be_bytes([c, d]))),
+            _ => Err(Error::Malformed),
+        }
+    }
+}
+
+fn encode_header(
+    kind: Kind,
+    id: u16,
+    len: usize,
+    buf: &mut [u8; MAX_HEADER_SIZE],
+) -> Result<&[u8], Error> {
+    if len > MAX_LENGTH {
+        return Err(Error::BufferTooSmall);
+    }
+
+    let mut ty = 0;
+    set!(ty, kind, u8::from(kind));
+
+    let mut n = 1;
+    if id > 0xff {
+        set!(ty, id16, 1);
+        buf[n..n + 2].copy_from_slice(&id.to_be_bytes());
+        n += 2;
+    } else {
+        buf[n] = id as u8;
+        n += 1;
+    }
+
+    if len < 8 {
+        set!(ty, length, len as u8);
+    } else {
+        let size = if len <= 0xff {
+            1
+        } else if len <= 0xffff {
+            2
+        } else {
+            3
+        };
+
+        set!(ty, length_type, size as u8);
+        buf[n..n + size].copy_from_slice(&(len as u32).to_be_bytes()[4 - size..]);
+        n += size;
+    }
+
+    buf[0] = ty;
+    Ok(&buf[..n])
+}
+
+fn decode(bytes: &[u8]) -> Option<(Tlv<'_>, &[u8])> {
+    let ty = *bytes.first()?;
+    let mut cursor = 1;
+
+    let id = if get!(ty, id16) == 1 {
+        let id = bytes.get(cursor..cursor + 2)?;
+        cursor += 2;
+        u16::from_be_bytes([id[0], id[1]])
+    } else {
+        let id = *bytes.get(cursor)?;
+        cursor += 1;
+        u16::from(id)
+    };
+
+    let len = match get!(ty, length_type) {
+        0 => usize::from(get!(ty, length)),
+        size => {
+            let size = usize::from(size);
+            let len = bytes.get(cursor..cursor + size)?;
+            cursor += size;
+            len.iter()
+                .fold(0, |len, byte| len << 8 | usize::from(*byte))
+        }
+    };
+
+    let value = bytes.get(cursor..cursor.checked_add(len)?)?;
+
+    Some((
+        Tlv {
+            kind: Kind::from(get!(ty, kind)),
+            id,
+            value,
+        },
+        &bytes[cursor + len..],
+    ))
+}
+
+fn encode_integer(x: i64, buf: &mut [u8; 8]) -> &[u8] {
+    let size = if i64::from(x as i8) == x {
+        1
+    } else if i64::from(x as i16) == x {
+        2
+    } else if i64::from(x as i32) == x {
+        4
+    } else {
+        8
+    };
+
+    buf.copy_from_slice(&x.to_be_bytes());
+    &buf[8 - size..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Kind, Value, Writer};
+    use crate::lwm2m::Error;
+
+    // OMA LwM2M TS 1.0.2 Section 6.4.3.1 "Single Object Instance Request Example"
+    const DEVICE: &[u8] = &[
+        0xc8, 0x00, 0x14, b'O', b'p', b'e', b'n', b' ', b'M', b'o', b'b', b'i', b'l', b'e', b' ',
+        b'A', b'l', b'l', b'i', b'a', b'n', b'c', b'e', // Manufacturer
+        0xc8, 0x01, 0x16, b'L', b'i', b'g', b'h', b't', b'w', b'e', b'i', b'g', b'h', b't', b' ',
+        b'M', b'2', b'M', b' ', b'C', b'l', b'i', b'e', b'n', b't', // Model Number
+        0xc8, 0x02, 0x09, b'3', b'4', b'5', b'0', b'0', b'0', b'1', b'2',
+        b'3', // Serial Number
+        0xc3, 0x03, b'1', b'.', b'0', // Firmware Version
+        0x86, 0x06, 0x41, 0x00, 0x01, 0x41, 0x01, 0x05, // Available Power Sources
+        0x88, 0x07, 0x08, 0x42, 0x00, 0x0e, 0xd8, 0x42, 0x01, 0x13,
+        0x88, // Power Source Voltage
+        0x87, 0x08, 0x41, 0x00, 0x7d, 0x42, 0x01, 0x03, 0x84, // Power Source Current
+        0xc1, 0x09, 0x64, // Battery Level
+        0xc1, 0x0a, 0x0f, // Memory Free
+        0x83, 0x0b, 0x41, 0x00, 0x00, // Error Code
+        0xc4, 0x0d, 0x51, 0x82, 0x42, 0x8f, // Current Time
+        0xc6, 0x0e, b'+', b'0', b'2', b':', b'0', b'0', // UTC Offset
+        0xc1, 0x10, b'U', // Supported Binding and Modes
+    ];
+
+    #[test]
+    fn write() {
+        let mut buf = [0; 128];
+        let mut w = Writer::new(&mut buf);
+        w.resource(0, Value::String("Open Mobile Alliance"))
+            .unwrap();
+        w.resource(1, Value::String("Lightweight M2M Client"))
+            .unwrap();
+        w.resource(2, Value::String("345000123")).unwrap();
+        w.resource(3, Value::String("1.0")).unwrap();
+        w.multiple_resource(6, |w| {
+            w.resource_instance(0, Value::Integer(1))?;
+            w.resource_instance(1, Value::Integer(5))
+        })
+        .unwrap();
+        w.multiple_resource(7, |w| {
+            w.resource_instance(0, Value::Integer(3800))?;
+            w.resource_instance(1, Value::Integer(5000))
+        })
+        .unwrap();
+        w.multiple_resource(8, |w| {
+            w.resource_instance(0, Value::Integer(125))?;
+            w.resource_instance(1, Value::Integer(900))
+        })
+        .unwrap();
+        w.resource(9, Value::Integer(100)).unwrap();
+        w.resource(10, Value::Integer(15)).unwrap();
+        w.multiple_resource(11, |w| w.resource_instance(0, Value::Integer(0)))
+            .unwrap();
+        w.resource(13, Value::Time(0x5182_428f)).unwrap();
+        w.resource(14, Value::String("+02:00")).unwrap();
+        w.resource(16, Value::String("U")).unwrap();
+
+        assert_eq!(w.finish(), DEVICE);
+    }
+
+    #[test]
+    fn parse() {
+        let mut tlvs = super::parse(DEVICE);
+
+        let manufacturer = tlvs.next().unwrap().unwrap();
+        assert_eq!(manufacturer.kind(), Kind::Resource);
+        assert_eq!(manufacturer.id(), 0);
+        assert_eq!(manufacturer.as_str(), Ok("Open Mobile Alliance"));
+
+        let voltage = tlvs.nth(4).unwrap().unwrap();
+        assert_eq!(voltage.kind(), Kind::MultipleResource);
+        assert_eq!(voltage.id(), 7);
+        let mut instances = voltage.children();
+        let first = instances.next().unwrap().unwrap();
+        assert_eq!(first.kind(), Kind::ResourceInstance);
+        assert_eq!((first.id(), first.as_integer()), (0, Ok(3800)));
+        let second = instances.next().unwrap().unwrap();
+        assert_eq!((second.id(), second.as_integer()), (1, Ok(5000)));
+        assert!(instances.next().is_none());
+
+        let time = tlvs.nth(4).unwrap().unwrap();
+        assert_eq!(time.id(), 13);
+        assert_eq!(time.as_integer(), Ok(1_367_491_215));
+        assert_eq!(tlvs.count(), 2);
+
+        // truncated
+        let mut tlvs = super::parse(&DEVICE[..10]);
+        assert_eq!(tlvs.next(), Some(Err(Error::Malformed)));
+        assert!(tlvs.next().is_none());
+    }
+
+    #[test]
+    fn values() {
+        let mut buf = [0; 64];
+        let mut w = Writer::new(&mut buf);
+        w.resource(0, Value::Integer(-1)).unwrap();
+        w.resource(1, Value::Integer(-129)).unwrap();
+        w.resource(2, Value::Integer(1 << 40)).unwrap();
+        w.resource(3, Value::Float(1.5)).unwrap();
+        w.resource(4, Value::Float(0.1)).unwrap();
+        w.resource(5, Value::Boolean(true)).unwrap();
+        w.resource(6, Value::ObjectLink(3, 0)).unwrap();
+        w.resource(0x1234, Value::Opaque(&[0xaa; 8])).unwrap();
+        let bytes = w.finish();
+
+        assert_eq!(&bytes[..3], &[0xc1, 0, 0xff]);
+        assert_eq!(&bytes[3..7], &[0xc2, 1, 0xff, 0x7f]);
+        assert_eq!(
+            &bytes[bytes.len() - 12..bytes.len() - 8],
+            &[0xe8, 0x12, 0x34, 8]
+        );
+
+        let mut tlvs = super::parse(bytes).map(|tlv| tlv.unwrap());
+        assert_eq!(tlvs.next().unwrap().as_integer(), Ok(-1));
+        assert_eq!(tlvs.next().unwrap().as_integer(), Ok(-129));
+        assert_eq!(tlvs.next().unwrap().as_integer(), Ok(1 << 40));
+        let float = tlvs.next().unwrap();
+        assert_eq!(float.value().len(), 4);
+        assert_eq!(float.as_float(), Ok(1.5));
+        let float = tlvs.next().unwrap();
+        assert_eq!(float.value().len(), 8);
+        assert_eq!(float.as_float(), Ok(0.1));
+        assert_eq!(tlvs.next().unwrap().as_bool(), Ok(true));
+        assert_eq!(tlvs.next().unwrap().as_object_link(), Ok((3, 0)));
+        let opaque = tlvs.next().unwrap();
+        assert_eq!(opaque.id(), 0x1234);
+        assert_eq!(opaque.value(), &[0xaa; 8]);
+        assert_eq!(opaque.as_bool(), Err(Error::Malformed));
+        assert!(tlvs.next().is_none());
+    }
+
+    #[test]
+    fn object_instance() {
+        let mut buf = [0; 300];
+        let mut w = Writer::new(&mut buf);
+        w.object_instance(0, |w| {
+            w.resource(0, Value::Opaque(&[0; 7]))?;
+            w.resource(1, Value::Opaque(&[0; 255]))
+        })
+        .unwrap();
+        let bytes = w.finish();
+
+        // 2 + 7 + 3 + 255 = 267 bytes of nested TLVs
+        assert_eq!(&bytes[..4], &[0x10, 0, 0x01, 0x0b]);
+        assert_eq!(bytes.len(), 4 + 267);
+
+        let instance = super::parse(bytes).next().unwrap().unwrap();
+        assert_eq!(instance.kind(), Kind::ObjectInstance);
+        assert_eq!(instance.children().count(), 2);
+
+        // not enough space
+        let mut buf = [0; 16];
+        let mut w = Writer::new(&mut buf);
+        assert_eq!(
+            w.resource(0, Value::Opaque(&[0; 15])),
+            Err(Error::BufferTooSmall)
+        );
+        assert_eq!(
+            w.object_instance(0, |w| w.resource(0, Value::Opaque(&[0; 9]))),
+            Err(Error::BufferTooSmall)
+        );
+        assert!(w.is_empty());
+    }
+}
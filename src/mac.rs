@@ -1,6 +1,7 @@
 //! MAC: Medium Access Control
 
 use core::fmt;
+use core::str::FromStr;
 
 use hash32_derive::Hash32;
 
@@ -116,10 +117,61 @@ impl fmt::Display for Addr {
     }
 }
 
+impl FromStr for Addr {
+    type Err = ();
+
+    /// Parses an address in colon (`20:19:02:01:23:59`) or dash (`20-19-02-01-23-59`) notation
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let mut addr = [0; 6];
+        parse_hex_bytes(s, &mut addr)?;
+        Ok(Addr(addr))
+    }
+}
+
+// parses `bytes.len()` two-digit hex numbers separated by either `:` or `-`, but not both
+pub(crate) fn parse_hex_bytes(s: &str, bytes: &mut [u8]) -> Result<(), ()> {
+    let separator = if s.contains('-') { '-' } else { ':' };
+
+    let mut parts = s.split(separator);
+    for byte in bytes.iter_mut() {
+        let part = parts.next().ok_or(())?;
+        if part.len() != 2 || !part.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(());
+        }
+
+        *byte = u8::from_str_radix(part, 16).map_err(|_| ())?;
+    }
+
+    if parts.next().is_some() {
+        Err(())
+    } else {
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::Addr;
 
+    #[test]
+    fn parse() {
+        let addr = Addr([0x20, 0x19, 0x02, 0x01, 0x23, 0x5a]);
+        assert_eq!("20:19:02:01:23:5a".parse(), Ok(addr));
+        assert_eq!("20-19-02-01-23-5A".parse(), Ok(addr));
+
+        for s in &[
+            "",
+            "20:19:02:01:23",
+            "20:19:02:01:23:5a:00",
+            "20:19:02-01:23:5a",
+            "20:19:2:01:23:5a",
+            "20:19:02:01:23:5g",
+            "20:19:02:01:23:",
+        ] {
+            assert!(s.parse::<Addr>().is_err(), "{}", s);
+        }
+    }
+
     #[test]
     fn eui_64() {
         assert_eq!(
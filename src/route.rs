@@ -0,0 +1,256 @@
+//! Routing table
+//!
+//! A fixed capacity table that maps destination networks to a next hop and an interface. Lookups
+//! use longest prefix match, so more specific routes take precedence over the default route.
+//!
+//! # Example
+//!
+//! ```
+//! use jnet::{ipv4, route::RoutingTable};
+//!
+//! #[derive(Clone, Copy, Debug, PartialEq)]
+//! enum Interface {
+//!     Eth0,
+//!     Wlan0,
+//! }
+//!
+//! let mut table = RoutingTable::<ipv4::Cidr, Interface, 4>::new();
+//!
+//! // default route through the gateway at 192.168.1.1
+//! let gateway = ipv4::Addr([192, 168, 1, 1]);
+//! table
+//!     .insert("0.0.0.0/0".parse().unwrap(), Some(gateway), Interface::Eth0)
+//!     .unwrap();
+//! // directly connected networks
+//! table
+//!     .insert("192.168.1.0/24".parse().unwrap(), None, Interface::Eth0)
+//!     .unwrap();
+//! table
+//!     .insert("10.0.0.0/8".parse().unwrap(), None, Interface::Wlan0)
+//!     .unwrap();
+//!
+//! let host = ipv4::Addr([10, 1, 2, 3]);
+//! assert_eq!(table.lookup(host), Some((host, Interface::Wlan0)));
+//! assert_eq!(
+//!     table.lookup(ipv4::Addr([8, 8, 8, 8])),
+//!     Some((gateway, Interface::Eth0))
+//! );
+//! ```
+
+use crate::{ipv4, ipv6};
+
+/// A network prefix: an IPv4 CIDR block or an IPv6 prefix
+pub trait Network: Copy + PartialEq {
+    /// The address type
+    type Addr: Copy;
+
+    /// Returns the prefix length
+    fn prefix_len(&self) -> u8;
+
+    /// Is `addr` part of this network?
+    fn contains(&self, addr: Self::Addr) -> bool;
+
+    /// Returns this network with the host bits of the address cleared
+    fn network(&self) -> Self;
+}
+
+impl Network for ipv4::Cidr {
+    type Addr = ipv4::Addr;
+
+    fn prefix_len(&self) -> u8 {
+        ipv4::Cidr::prefix_len(self)
+    }
+
+    fn contains(&self, addr: ipv4::Addr) -> bool {
+        ipv4::Cidr::contains(self, addr)
+    }
+
+    fn network(&self) -> Self {
+        ipv4::Cidr::network(self)
+    }
+}
+
+impl Network for ipv6::Prefix {
+    type Addr = ipv6::Addr;
+
+    fn prefix_len(&self) -> u8 {
+        ipv6::Prefix::prefix_len(self)
+    }
+
+    fn contains(&self, addr: ipv6::Addr) -> bool {
+        ipv6::Prefix::contains(self, addr)
+    }
+
+    fn network(&self) -> Self {
+        ipv6::Prefix::network(self)
+    }
+}
+
+/// A route
+#[derive(Clone, Copy, Debug)]
+pub struct Route<P, I>
+where
+    P: Network,
+{
+    /// The destination network
+    pub destination: P,
+    /// The next hop; `None` if the destination network is directly connected
+    pub gateway: Option<P::Addr>,
+    /// The interface packets are sent through
+    pub interface: I,
+}
+
+/// Routing table that holds up to `N` routes
+///
+/// `P` is the network type (`ipv4::Cidr` or `ipv6::Prefix`) and `I` identifies an interface
+pub struct RoutingTable<P, I, const N: usize>
+where
+    P: Network,
+    I: Copy,
+{
+    routes: [Option<Route<P, I>>; N],
+}
+
+impl<P, I, const N: usize> RoutingTable<P, I, N>
+where
+    P: Network,
+    I: Copy,
+{
+    /// Creates an empty table
+    pub const fn new() -> Self {
+        RoutingTable { routes: [None; N] }
+    }
+
+    /// Adds a route to `destination`
+    ///
+    /// The host bits of `destination` are ignored. A route to the same destination is replaced.
+    /// Returns an error if the table is full.
+    pub fn insert(
+        &mut self,
+        destination: P,
+        gateway: Option<P::Addr>,
+        interface: I,
+    ) -> Result<(), ()> {
+        let destination = destination.network();
+        let route = Route {
+            destination,
+            gateway,
+            interface,
+        };
+
+        let slot = if let Some(i) = self.position(destination) {
+            &mut self.routes[i]
+        } else {
+            self.routes
+                .iter_mut()
+                .find(|slot| slot.is_none())
+                .ok_or(())?
+        };
+        *slot = Some(route);
+
+        Ok(())
+    }
+
+    /// Removes the route to `destination`
+    ///
+    /// Returns `true` if there was such route
+    pub fn remove(&mut self, destination: P) -> bool {
+        if let Some(i) = self.position(destination.network()) {
+            self.routes[i] = None;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Finds the most specific route to `addr`
+    ///
+    /// Returns the next hop (`addr` itself if the destination is directly connected) and the
+    /// interface the packet must be sent through
+    pub fn lookup(&self, addr: P::Addr) -> Option<(P::Addr, I)> {
+        self.iter()
+            .filter(|route| route.destination.contains(addr))
+            .max_by_key(|route| route.destination.prefix_len())
+            .map(|route| (route.gateway.unwrap_or(addr), route.interface))
+    }
+
+    /// Returns the number of routes
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    /// Returns `true` if the table has no routes
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns an iterator over the routes
+    pub fn iter(&self) -> impl Iterator<Item = &Route<P, I>> {
+        self.routes.iter().filter_map(|slot| slot.as_ref())
+    }
+
+    fn position(&self, destination: P) -> Option<usize> {
+        self.routes.iter().position(|slot| {
+            slot.map(|route| route.destination == destination)
+                .unwrap_or(false)
+        })
+    }
+}
+
+impl<P, I, const N: usize> Default for RoutingTable<P, I, N>
+where
+    P: Network,
+    I: Copy,
+{
+    fn default() -> Self {
+        RoutingTable::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ipv6, route::RoutingTable};
+
+    fn prefix(s: &str) -> ipv6::Prefix {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn longest_prefix_match() {
+        let mut table = RoutingTable::<ipv6::Prefix, u8, 3>::new();
+        assert!(table.is_empty());
+        assert_eq!(table.lookup(ipv6::Addr::LOOPBACK), None);
+
+        let router = prefix("fe80::1/128").address();
+        table.insert(prefix("::/0"), Some(router), 0).unwrap();
+        table.insert(prefix("2001:db8::/32"), None, 1).unwrap();
+        // host bits are ignored
+        table.insert(prefix("2001:db8:1::42/48"), None, 2).unwrap();
+        assert_eq!(table.len(), 3);
+
+        let host = prefix("2001:db8:1::7/128").address();
+        assert_eq!(table.lookup(host), Some((host, 2)));
+
+        let host = prefix("2001:db8:2::7/128").address();
+        assert_eq!(table.lookup(host), Some((host, 1)));
+
+        let host = prefix("2001:4860::8888/128").address();
+        assert_eq!(table.lookup(host), Some((router, 0)));
+
+        // full
+        assert!(table.insert(prefix("fd00::/8"), None, 3).is_err());
+
+        // replace
+        table
+            .insert(prefix("2001:db8::/32"), Some(router), 3)
+            .unwrap();
+        assert_eq!(table.len(), 3);
+        let host = prefix("2001:db8:2::7/128").address();
+        assert_eq!(table.lookup(host), Some((router, 3)));
+
+        assert!(table.remove(prefix("2001:db8:1::/48")));
+        assert!(!table.remove(prefix("2001:db8:1::/48")));
+        let host = prefix("2001:db8:1::7/128").address();
+        assert_eq!(table.lookup(host), Some((router, 3)));
+    }
+}
@@ -1,7 +1,32 @@
 use crate::icmp::{EchoReply, EchoRequest};
+use crate::icmpv6::{
+    DuplicateAddressConfirmation, DuplicateAddressRequest, MulticastListenerDone,
+    MulticastListenerQuery, MulticastListenerReport,
+};
+use crate::igmp::{LeaveGroup, MembershipQuery, MembershipReportV2};
 
 // [Type State] EchoReply or EchoRequest
 pub trait Echo: 'static {}
 
 impl Echo for EchoReply {}
 impl Echo for EchoRequest {}
+
+// [Type State] MLDv1 messages: they all share the same format
+pub trait Mldv1: 'static {}
+
+impl Mldv1 for MulticastListenerQuery {}
+impl Mldv1 for MulticastListenerReport {}
+impl Mldv1 for MulticastListenerDone {}
+
+// [Type State] DuplicateAddressRequest or DuplicateAddressConfirmation
+pub trait DuplicateAddress: 'static {}
+
+impl DuplicateAddress for DuplicateAddressRequest {}
+impl DuplicateAddress for DuplicateAddressConfirmation {}
+
+// [Type State] IGMPv2 messages: they all share the same format
+pub trait Igmpv2: 'static {}
+
+impl Igmpv2 for MembershipQuery {}
+impl Igmpv2 for MembershipReportV2 {}
+impl Igmpv2 for LeaveGroup {}
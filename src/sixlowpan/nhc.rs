@@ -4,12 +4,15 @@ use core::fmt;
 
 use as_slice::{AsMutSlice, AsSlice};
 use byteorder::{ByteOrder, NetworkEndian as NE};
+use cast::u16;
 use owning_slice::Truncate;
 
 use crate::{
+    checksum::Checksum,
     coap::{self, Unset},
     ipv6,
     traits::UncheckedIndex,
+    udp,
 };
 
 /* Header format */
@@ -163,45 +166,20 @@ where
     }
 
     fn compute_checksum(&self, src: ipv6::Addr, dest: ipv6::Addr) -> u16 {
-        const NEXT_HEADER: u8 = 17;
-
-        let mut sum: u32 = 0;
-
-        /* Pseudo-header */
-        for chunk in src.0.chunks_exact(2).chain(dest.0.chunks_exact(2)) {
-            sum += u32::from(NE::read_u16(chunk));
-        }
-
-        // length in pseudo-header
-        // XXX should this be just `as u16`?
-        let udp_len = self.payload().len() as u32 + 8;
-        sum += udp_len >> 16;
-        sum += udp_len & 0xffff;
-
-        sum += u32::from(NEXT_HEADER);
-
-        /* UDP packet */
-        sum += u32::from(self.get_source());
-        sum += u32::from(self.get_destination());
-
-        // length in UDP header (yes, again)
-        sum += udp_len >> 16;
-        sum += udp_len & 0xffff;
-
-        for chunk in self.payload().chunks(2) {
-            if chunk.len() == 2 {
-                sum += u32::from(NE::read_u16(chunk));
-            } else {
-                sum += u32::from(chunk[0]) << 8;
-            }
-        }
-
-        // fold carry-over
-        while sum >> 16 != 0 {
-            sum = (sum & 0xffff) + (sum >> 16);
-        }
-
-        !(sum as u16)
+        // the UDP header is compressed so its fields are added one by one
+        let udp_len = u16(self.payload().len() + usize::from(udp::HEADER_SIZE)).unwrap();
+
+        let mut sum = Checksum::ipv6_pseudo_header(
+            src,
+            dest,
+            ipv6::NextHeader::Udp.into(),
+            u32::from(udp_len),
+        );
+        sum.add_u16(self.get_source());
+        sum.add_u16(self.get_destination());
+        sum.add_u16(udp_len);
+        sum.add_bytes(self.payload());
+        sum.finish()
     }
 
     fn header_(&self) -> u8 {
@@ -5,12 +5,13 @@ use core::{fmt, u16};
 
 use as_slice::{AsMutSlice, AsSlice};
 use byteorder::{ByteOrder, NetworkEndian as NE};
-use cast::{u16, usize};
+use cast::{u16, u32, usize};
 use owning_slice::Truncate;
 
 use crate::{
+    checksum::Checksum,
     coap::{self, Unset},
-    ipv6,
+    ipv4, ipv6,
     traits::UncheckedIndex,
 };
 
@@ -92,42 +93,32 @@ where
 
     /* Miscellaneous */
     pub(crate) fn compute_checksum(&self, src: ipv6::Addr, dest: ipv6::Addr) -> u16 {
-        const NEXT_HEADER: u8 = 17;
+        let bytes = self.as_slice();
 
-        let mut sum: u32 = 0;
+        let mut sum = Checksum::ipv6_pseudo_header(
+            src,
+            dest,
+            ipv6::NextHeader::Udp.into(),
+            u32(bytes.len()).unwrap(),
+        );
+        sum.add_message(bytes, CHECKSUM.start);
+        sum.finish()
+    }
 
-        // Pseudo-header
-        for chunk in src.0.chunks_exact(2).chain(dest.0.chunks_exact(2)) {
-            sum += u32::from(NE::read_u16(chunk));
-        }
+    fn compute_ipv4_checksum(&self, src: ipv4::Addr, dest: ipv4::Addr) -> u16 {
+        let len = self.get_length();
 
-        // XXX should this be just `as u16`?
-        let len = self.as_slice().len() as u32;
-        sum += len >> 16;
-        sum += len & 0xffff;
-
-        sum += u32::from(NEXT_HEADER);
-
-        // UDP message
-        for (i, chunk) in self.as_slice().chunks(2).enumerate() {
-            if i == 3 {
-                // this is the checksum field, skip
-                continue;
-            }
-
-            if chunk.len() == 1 {
-                sum += u32::from(chunk[0]) << 8;
-            } else {
-                sum += u32::from(NE::read_u16(chunk));
-            }
-        }
+        let mut sum = Checksum::ipv4_pseudo_header(src, dest, ipv4::Protocol::Udp.into(), len);
+        // NOTE the buffer may contain padding (e.g. from the Ethernet frame) past the UDP length
+        sum.add_message(&self.as_slice()[..usize(len)], CHECKSUM.start);
+        let cksum = sum.finish();
 
-        // fold carry-over
-        while sum >> 16 != 0 {
-            sum = (sum & 0xffff) + (sum >> 16);
+        // RFC 768: an all zeros computed checksum is transmitted as all ones
+        if cksum == 0 {
+            0xffff
+        } else {
+            cksum
         }
-
-        !(sum as u16)
     }
 
     /// Verifies the 'Checksum' field
@@ -135,6 +126,15 @@ where
         self.compute_checksum(src, dest) == self.get_checksum()
     }
 
+    /// Verifies the 'Checksum' field against the IPv4 pseudo-header
+    ///
+    /// A zero 'Checksum' field means that the sender didn't compute the checksum; this method
+    /// returns `true` in that case
+    pub fn verify_ipv4_checksum(&self, src: ipv4::Addr, dest: ipv4::Addr) -> bool {
+        let cksum = self.get_checksum();
+        cksum == 0 || self.compute_ipv4_checksum(src, dest) == cksum
+    }
+
     /* Private */
     fn as_slice(&self) -> &[u8] {
         self.buffer.as_slice()
@@ -192,6 +192,12 @@ where
         self.set_checksum(cksum)
     }
 
+    /// Recomputes and updates the 'Checksum' field using the IPv4 pseudo-header
+    pub fn update_ipv4_checksum(&mut self, src: ipv4::Addr, dest: ipv4::Addr) {
+        let cksum = self.compute_ipv4_checksum(src, dest);
+        self.set_checksum(cksum)
+    }
+
     /* Private */
     fn as_mut_slice(&mut self) -> &mut [u8] {
         self.buffer.as_mut_slice()
@@ -343,6 +349,43 @@ mod tests {
         assert_eq!(eth.as_bytes(), &BYTES[..]);
     }
 
+    #[test]
+    fn ipv4_checksum() {
+        const CHECKSUM: [u8; 2] = [55, 192];
+
+        let mut array: [u8; SIZE] = [0; SIZE];
+        rand::thread_rng().fill_bytes(&mut array);
+
+        let mut ip = ipv4::Packet::new(&mut array[..]);
+        ip.set_destination(IP_DST);
+        ip.set_source(IP_SRC);
+        ip.udp_with_checksum(|udp| {
+            udp.set_source(0);
+            udp.set_destination(UDP_DST);
+            udp.set_payload(MESSAGE);
+        });
+        let ip = ip.update_checksum();
+
+        let udp = udp::Packet::parse(ip.payload()).unwrap();
+        assert_eq!(&udp.as_bytes()[6..8], &CHECKSUM);
+        assert!(udp.verify_ipv4_checksum(IP_SRC, IP_DST));
+        assert!(!udp.verify_ipv4_checksum(IP_SRC, ipv4::Addr([10, 0, 0, 1])));
+
+        // no checksum
+        let eth = ether::Frame::parse(&BYTES[..]).unwrap();
+        let ip = ipv4::Packet::parse(eth.payload()).unwrap();
+        let udp = udp::Packet::parse(ip.payload()).unwrap();
+        assert!(udp.verify_ipv4_checksum(IP_SRC, IP_DST));
+
+        let mut bytes = *BYTES;
+        bytes[40..42].copy_from_slice(&CHECKSUM);
+        bytes[42] ^= 1;
+        let eth = ether::Frame::parse(&bytes[..]).unwrap();
+        let ip = ipv4::Packet::parse(eth.payload()).unwrap();
+        let udp = udp::Packet::parse(ip.payload()).unwrap();
+        assert!(!udp.verify_ipv4_checksum(IP_SRC, IP_DST));
+    }
+
     #[test]
     fn new() {
         const SZ: u16 = 128;
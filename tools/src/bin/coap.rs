@@ -13,19 +13,15 @@ use std::{
 
 use clap::{App, Arg};
 use exitfailure::ExitFailure;
-use failure::{bail, Error, ResultExt};
-use jnet::coap;
-use rand::{
-    distributions::{Distribution, Uniform},
-    Rng,
-};
+use failure::{bail, format_err, Error, ResultExt};
+use jnet::coap::{self, block, link, no_response::NoResponse, reliability::Event};
+use rand::Rng;
 use url::{Host, Url};
 
-/* Transmission parameters */
-const ACK_RANDOM_FACTOR: f64 = 1.5;
-const ACK_TIMEOUT: u16 = 2_000; // ms
-const DEFAULT_LEISURE: u8 = 5; // s
-const MAX_RETRANSMIT: u8 = 4;
+/* Block-wise transfers */
+// 1024-byte blocks; the server may pick a smaller size
+const BLOCK_SZX: u8 = block::MAX_SZX;
+const MAX_BODY_SIZE: usize = 64 * 1024;
 
 fn main() -> Result<(), ExitFailure> {
     run().map_err(|e| e.into())
@@ -49,6 +45,22 @@ fn run() -> Result<(), Error> {
                 .takes_value(true)
                 .value_name("IFACE"),
         )
+        .arg(
+            Arg::with_name("filter")
+                .help("only show the links that match this filter (link-format responses)")
+                .required(false)
+                .short("f")
+                .takes_value(true)
+                .value_name("NAME=PATTERN"),
+        )
+        .arg(
+            Arg::with_name("no-response")
+                .help("ask the server to not send responses of these classes (e.g. '2,4')")
+                .required(false)
+                .short("n")
+                .takes_value(true)
+                .value_name("CLASSES"),
+        )
         .arg(
             Arg::with_name("method")
                 .help("one of DELETE, GET, POST or PUT")
@@ -76,6 +88,33 @@ fn run() -> Result<(), Error> {
         _ => panic!(),
     };
 
+    let filter = match matches.value_of("filter") {
+        Some(filter) => {
+            let mut parts = filter.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some(name), Some(pattern)) => Some((name, pattern)),
+                _ => bail!("filter must have the form NAME=PATTERN"),
+            }
+        }
+        None => None,
+    };
+
+    let no_response = match matches.value_of("no-response") {
+        Some(classes) => {
+            let mut value = NoResponse::NONE;
+            for class in classes.split(',') {
+                value |= match class {
+                    "2" => NoResponse::SUCCESS,
+                    "4" => NoResponse::CLIENT_ERROR,
+                    "5" => NoResponse::SERVER_ERROR,
+                    _ => bail!("response classes must be a list of 2, 4 and 5"),
+                };
+            }
+            Some(value)
+        }
+        None => None,
+    };
+
     let url = Url::parse(matches.value_of("url").unwrap()).context("parsing URL")?;
     if url.scheme() != "coap" {
         bail!("URL scheme must be 'coap'")
@@ -116,30 +155,33 @@ fn run() -> Result<(), Error> {
     };
 
     let is_multicast = server.ip().is_multicast();
+    let payload = matches
+        .value_of("payload")
+        .map(|s| s.as_bytes())
+        .unwrap_or(&[]);
 
     // construct outgoing message
     let mut buf = [0; 256];
-    let mut mtx = coap::Message::new(&mut buf[..], 0);
     // FIXME multicast messages must be Non-Confirmable
-    mtx.set_type(if is_multicast {
+    let ty = if is_multicast {
         coap::Type::NonConfirmable
     } else {
         coap::Type::Confirmable
-    });
+    };
     let mid = rng.gen();
-    mtx.set_code(method);
-    mtx.set_message_id(mid);
-    if let Some(segments) = url.path_segments() {
-        for segment in segments {
-            mtx.add_option(coap::OptionNumber::UriPath, segment.as_bytes());
-        }
-    }
-    let mtx = mtx.set_payload(
-        matches
-            .value_of("payload")
-            .map(|s| s.as_bytes())
-            .unwrap_or(&[]),
-    );
+    // large representations are fetched using block-wise transfers
+    let block2 = if method == coap::Method::Get && !is_multicast {
+        Some(block::Block::new(0, false, BLOCK_SZX))
+    } else {
+        None
+    };
+    let req = Request {
+        ty,
+        method,
+        url: &url,
+        no_response,
+    };
+    let mtx = req.build(&mut buf, mid, block2, payload)?;
 
     let stderr = io::stderr();
     let mut stderr = stderr.lock();
@@ -153,7 +195,7 @@ fn run() -> Result<(), Error> {
         client.send_to(mtx.as_bytes(), server).unwrap();
 
         // we report all responses received during the leisure period
-        let end = Instant::now() + Duration::from_secs(u64::from(DEFAULT_LEISURE));
+        let end = Instant::now() + Duration::from_millis(u64::from(coap::DEFAULT_LEISURE));
 
         loop {
             let now = Instant::now();
@@ -178,14 +220,7 @@ fn run() -> Result<(), Error> {
             if let Ok(mrx) = coap::Message::parse(&rx_buf[..n]) {
                 if mrx.get_type() == coap::Type::NonConfirmable && mrx.get_message_id() == mid {
                     writeln!(stderr, "<- {:?} (from {})", mrx, addr).ok();
-                    let payload = mrx.payload();
-                    if !payload.is_empty() {
-                        if let Ok(s) = str::from_utf8(payload) {
-                            writeln!(stdout, "{}", s).ok();
-                        } else {
-                            writeln!(stdout, "{:?}", payload).ok();
-                        }
-                    }
+                    print(&mut stdout, mrx.content_format(), mrx.payload(), filter);
                 } else {
                     bail!("received unrelated response");
                 }
@@ -196,51 +231,186 @@ fn run() -> Result<(), Error> {
     } else {
         // if unicast, connect to the server
         client.connect(server)?;
-        client.send(mtx.as_bytes()).unwrap();
 
-        let between = Uniform::new(1.0, ACK_RANDOM_FACTOR);
-        let mut timeout =
-            Duration::from_millis((between.sample(&mut rng) * ACK_TIMEOUT as f64) as u64);
+        let mut body = vec![0; MAX_BODY_SIZE];
+        let mut reassembler = block::Reassembler::new(&mut body[..], BLOCK_SZX);
+        let (mut mtx, mut mid) = (mtx, mid);
+        loop {
+            let n = transmit(&client, mtx.as_bytes(), mid, &mut rng, &mut rx_buf)?;
 
-        for _ in 0..MAX_RETRANSMIT {
-            client.set_read_timeout(Some(timeout))?;
+            let mrx = coap::Message::parse(&rx_buf[..n])
+                .map_err(|_| failure::err_msg("parsing incoming CoAP message"))?;
+            writeln!(stderr, "<- {:?}", mrx).ok();
 
-            let n = match client.recv(&mut rx_buf) {
-                Ok(n) => n,
-                Err(e) => {
-                    if e.kind() == io::ErrorKind::TimedOut || e.kind() == io::ErrorKind::WouldBlock
-                    {
-                        // try again
-                        timeout *= 2;
+            if block2.is_none() || !mrx.get_code().is_response() || mrx.get_code().class() != 2 {
+                print(&mut stdout, mrx.content_format(), mrx.payload(), filter);
+                return Ok(());
+            }
 
-                        continue;
-                    } else {
-                        return Err(e.into());
-                    }
+            match reassembler.response(&mrx) {
+                Ok(true) => {
+                    print(
+                        &mut stdout,
+                        mrx.content_format(),
+                        reassembler.payload(),
+                        filter,
+                    );
+                    return Ok(());
                 }
-            };
+                Ok(false) => {
+                    // request the next block
+                    mid = mid.wrapping_add(1);
+                    mtx = req.build(&mut buf, mid, Some(reassembler.request()), &[])?;
+                    writeln!(stderr, "-> {:?}", mtx).ok();
+                }
+                Err(e) => bail!("block-wise transfer failed: {:?}", e),
+            }
+        }
+    }
+}
 
-            if let Ok(mrx) = coap::Message::parse(&rx_buf[..n]) {
-                if mrx.get_type() == coap::Type::Acknowledgement && mrx.get_message_id() == mid {
-                    writeln!(stderr, "<- {:?}", mrx).ok();
-                    let payload = mrx.payload();
-                    if !payload.is_empty() {
-                        if let Ok(s) = str::from_utf8(payload) {
-                            writeln!(stdout, "{}", s).ok();
-                        } else {
-                            writeln!(stdout, "{:?}", payload).ok();
-                        }
-                    }
+/// The parts of a request that don't change between blocks
+struct Request<'u> {
+    ty: coap::Type,
+    method: coap::Method,
+    url: &'u Url,
+    no_response: Option<NoResponse>,
+}
 
-                    return Ok(());
+impl Request<'_> {
+    /// Builds a request
+    fn build<'a>(
+        &self,
+        buf: &'a mut [u8],
+        mid: u16,
+        block2: Option<block::Block>,
+        payload: &[u8],
+    ) -> Result<coap::Message<&'a mut [u8]>, Error> {
+        let mut mtx = coap::Message::new(buf, 0);
+        mtx.set_type(self.ty);
+        mtx.set_code(self.method);
+        mtx.set_message_id(mid);
+        if let Some(segments) = self.url.path_segments() {
+            for segment in segments {
+                mtx.add_uri_path(segment)
+                    .map_err(|e| format_err!("adding Uri-Path: {:?}", e))?;
+            }
+        }
+        if let Some(block2) = block2 {
+            mtx.add_block2(block2)
+                .map_err(|e| format_err!("adding Block2: {:?}", e))?;
+        }
+        if let Some(no_response) = self.no_response {
+            mtx.add_no_response(no_response)
+                .map_err(|e| format_err!("adding No-Response: {:?}", e))?;
+        }
+        Ok(mtx.set_payload(payload))
+    }
+}
+
+/// Sends a Confirmable message and waits for its acknowledgement
+///
+/// Returns the size of the acknowledgement
+fn transmit(
+    client: &UdpSocket,
+    mtx: &[u8],
+    mid: u16,
+    rng: &mut impl Rng,
+    rx_buf: &mut [u8],
+) -> Result<usize, Error> {
+    let start = Instant::now();
+    let now = || start.elapsed().as_millis() as u32;
+
+    let mut transmitter = coap::Transmitter::<1>::new();
+    client.send(mtx)?;
+    transmitter
+        .send(mid, now(), rng.gen())
+        .map_err(|_| failure::err_msg("tracking outgoing CoAP message"))?;
+
+    loop {
+        while let Some(event) = transmitter.poll(now()) {
+            match event {
+                Event::Retransmit(_) => {
+                    client.send(mtx)?;
+                }
+                Event::Timeout(_) => bail!("timed out"),
+                _ => {}
+            }
+        }
+
+        let deadline = transmitter.next_deadline(now()).unwrap_or(0);
+        let timeout = deadline.saturating_sub(now()).max(1);
+        client.set_read_timeout(Some(Duration::from_millis(u64::from(timeout))))?;
+
+        let n = match client.recv(rx_buf) {
+            Ok(n) => n,
+            Err(e) => {
+                if e.kind() == io::ErrorKind::TimedOut || e.kind() == io::ErrorKind::WouldBlock {
+                    // retransmit or give up
+                    continue;
                 } else {
-                    bail!("received unrelated response");
+                    return Err(e.into());
                 }
-            } else {
-                bail!("parsing incoming CoAP message")
             }
+        };
+
+        if let Ok(mrx) = coap::Message::parse(&rx_buf[..n]) {
+            match transmitter.received(&mrx) {
+                Some(Event::Acknowledged(_)) => return Ok(n),
+                Some(Event::Reset(_)) => bail!("the server rejected the request"),
+                _ => bail!("received unrelated response"),
+            }
+        } else {
+            bail!("parsing incoming CoAP message")
         }
+    }
+}
+
+/// Prints a payload; link-format documents are printed one link per line
+fn print(
+    stdout: &mut impl Write,
+    content_format: Option<coap::ContentFormat>,
+    payload: &[u8],
+    filter: Option<(&str, &str)>,
+) {
+    if payload.is_empty() {
+        return;
+    }
 
-        bail!("timed out")
+    if let Ok(s) = str::from_utf8(payload) {
+        if content_format == Some(coap::ContentFormat::ApplicationLinkFormat) {
+            print_links(stdout, s, filter);
+        } else {
+            writeln!(stdout, "{}", s).ok();
+        }
+    } else {
+        writeln!(stdout, "{:?}", payload).ok();
+    }
+}
+
+fn print_links(stdout: &mut impl Write, document: &str, filter: Option<(&str, &str)>) {
+    for link in link::parse(document) {
+        let link = match link {
+            Ok(link) => link,
+            Err(()) => {
+                writeln!(stdout, "(malformed link-format document)").ok();
+                return;
+            }
+        };
+
+        if let Some((name, pattern)) = filter {
+            if !link.matches(name, pattern) {
+                continue;
+            }
+        }
+
+        writeln!(stdout, "<{}>", link.target()).ok();
+        for attr in link.attributes() {
+            if let Some(value) = attr.value() {
+                writeln!(stdout, "    {}: {}", attr.name(), value).ok();
+            } else {
+                writeln!(stdout, "    {}", attr.name()).ok();
+            }
+        }
     }
 }
@@ -0,0 +1,150 @@
+#![recursion_limit = "128"]
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, GenericParam, LitByteStr};
+
+#[proc_macro_derive(uSerialize)]
+pub fn serialize(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let error = quote!(compile_error!(
+        "`#[derive(uSerialize)]` can only be used on `struct`s with named fields"
+    ))
+    .into();
+
+    match input.data {
+        Data::Struct(s) => {
+            let ident = input.ident;
+            let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+            let fields = match s.fields {
+                Fields::Named(fields) => fields.named,
+                _ => return error,
+            };
+
+            if fields.is_empty() {
+                return error;
+            }
+
+            let nfields = fields.len() as u64;
+            let mut exprs = vec![];
+            for field in fields {
+                let ident = field.ident.expect("unreachable");
+                let lit = LitByteStr::new(ident.to_string().as_bytes(), ident.span());
+                let ty = &field.ty;
+
+                exprs.push(quote!(
+                    ucbor::ser::field_name(#lit, cursor)?;
+                    <#ty as ucbor::Serialize>::serialize(&self.#ident, cursor)?;
+                ));
+            }
+
+            quote!(
+                impl #impl_generics ucbor::Serialize for #ident #ty_generics #where_clause {
+                    #[deny(unused_must_use)]
+                    fn serialize(&self, cursor: &mut ucbor::ser::Cursor) -> Result<(), ()> {
+                        ucbor::ser::map_header(#nfields, cursor)?;
+                        #(#exprs)*
+                        Ok(())
+                    }
+                }
+            )
+            .into()
+        }
+
+        _ => error,
+    }
+}
+
+#[proc_macro_derive(uDeserialize)]
+pub fn deserialize(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let error = quote!(compile_error!(
+        "`#[derive(uDeserialize)]` can only be used on `struct`s with named fields and at most one \
+         lifetime parameter"
+    ))
+    .into();
+
+    // strings are borrowed from the input so the struct's lifetime, if any, is the input lifetime
+    let mut lifetime = None;
+    for param in &input.generics.params {
+        match param {
+            GenericParam::Lifetime(def) if lifetime.is_none() => {
+                lifetime = Some(def.lifetime.clone())
+            }
+            _ => return error,
+        }
+    }
+
+    match input.data {
+        Data::Struct(s) => {
+            let ident = input.ident;
+
+            let fields = match s.fields {
+                Fields::Named(fields) => fields.named,
+                _ => return error,
+            };
+
+            if fields.is_empty() {
+                return error;
+            }
+
+            let nfields = fields.len();
+            let mut field_names = vec![];
+            let mut field_exprs = vec![];
+            let mut branches = vec![];
+            for field in fields {
+                let ident = field.ident.expect("unreachable");
+                let lit = LitByteStr::new(ident.to_string().as_bytes(), ident.span());
+                let ty = field.ty;
+                field_exprs.push(quote!(#ident: #ident.ok_or(())?));
+                field_names.push(ident.clone());
+
+                branches.push(quote!(
+                    cursor.matches_text_string(#lit)? {
+                        if #ident.is_some() {
+                            return Err(());
+                        }
+
+                        #ident = Some(<#ty as ucbor::Deserialize>::deserialize(cursor)?);
+                    }
+                ))
+            }
+
+            let (impl_generics, de, ty_generics) = match lifetime {
+                Some(lt) => (quote!(<#lt>), quote!(#lt), quote!(<#lt>)),
+                None => (quote!(<'de>), quote!('de), quote!()),
+            };
+
+            quote!(
+                impl #impl_generics ucbor::Deserialize<#de> for #ident #ty_generics {
+                    #[deny(unused_must_use)]
+                    fn deserialize(cursor: &mut ucbor::de::Cursor<#de>) -> Result<Self, ()> {
+                        const FIELDS: u64 = #nfields as u64;
+
+                        #(let mut #field_names = None;)*
+
+                        if cursor.parse_map()? != FIELDS {
+                            return Err(());
+                        }
+
+                        for _ in 0..FIELDS {
+                            #(if #branches else)* {
+                                return Err(());
+                            }
+                        }
+
+                        Ok(#ident {
+                            #(#field_exprs,)*
+                        })
+                    }
+                }
+            )
+            .into()
+        }
+
+        _ => error,
+    }
+}
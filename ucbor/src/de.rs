@@ -0,0 +1,283 @@
+//! Deserialization
+
+use core::{convert::TryFrom, str};
+
+use crate::{major, traits::SliceExt, Bytes, FALSE, TRUE};
+
+/// Deserializes `T` from the given `bytes`
+pub fn from_bytes<'de, T>(bytes: &'de [u8]) -> Result<T, ()>
+where
+    T: Deserialize<'de>,
+{
+    let mut cursor = Cursor::new(bytes);
+    let x = T::deserialize(&mut cursor)?;
+    cursor.finish()?;
+    Ok(x)
+}
+
+/// Types that can be deserialized from CBOR
+///
+/// The `'de` lifetime is the lifetime of the input; strings are borrowed from it
+pub trait Deserialize<'de>: Sized {
+    // IMPLEMENTATION DETAIL
+    #[doc(hidden)]
+    fn deserialize(cursor: &mut Cursor<'de>) -> Result<Self, ()>;
+}
+
+impl<'de> Deserialize<'de> for bool {
+    fn deserialize(cursor: &mut Cursor<'de>) -> Result<Self, ()> {
+        match cursor.parse_header()? {
+            (major::SIMPLE, arg) if arg == u64::from(FALSE) => Ok(false),
+            (major::SIMPLE, arg) if arg == u64::from(TRUE) => Ok(true),
+            _ => Err(()),
+        }
+    }
+}
+
+macro_rules! unsigned {
+    ($($uN:ty),+) => {
+        $(
+            impl<'de> Deserialize<'de> for $uN {
+                fn deserialize(cursor: &mut Cursor<'de>) -> Result<Self, ()> {
+                    match cursor.parse_header()? {
+                        (major::UNSIGNED, arg) => <$uN>::try_from(arg).map_err(|_| ()),
+                        _ => Err(()),
+                    }
+                }
+            }
+        )+
+    }
+}
+
+unsigned!(u8, u16, u32, u64, usize);
+
+macro_rules! signed {
+    ($($iN:ty),+) => {
+        $(
+            impl<'de> Deserialize<'de> for $iN {
+                fn deserialize(cursor: &mut Cursor<'de>) -> Result<Self, ()> {
+                    match cursor.parse_header()? {
+                        (major::UNSIGNED, arg) => <$iN>::try_from(arg).map_err(|_| ()),
+                        // NOTE(!) -1 - x == !x in two's complement
+                        (major::NEGATIVE, arg) => <$iN>::try_from(arg).map(|x| !x).map_err(|_| ()),
+                        _ => Err(()),
+                    }
+                }
+            }
+        )+
+    }
+}
+
+signed!(i8, i16, i32, i64, isize);
+
+impl<'de> Deserialize<'de> for &'de str {
+    fn deserialize(cursor: &mut Cursor<'de>) -> Result<Self, ()> {
+        let bytes = cursor.parse_string(major::TEXT)?;
+        str::from_utf8(bytes).map_err(|_| ())
+    }
+}
+
+impl<'de> Deserialize<'de> for Bytes<'de> {
+    fn deserialize(cursor: &mut Cursor<'de>) -> Result<Self, ()> {
+        cursor.parse_string(major::BYTES).map(Bytes)
+    }
+}
+
+impl<'de, T, const N: usize> Deserialize<'de> for [T; N]
+where
+    T: Copy + Default + Deserialize<'de>,
+{
+    fn deserialize(cursor: &mut Cursor<'de>) -> Result<Self, ()> {
+        match cursor.parse_header()? {
+            (major::ARRAY, arg) if arg == N as u64 => {}
+            _ => return Err(()),
+        }
+
+        let mut out = [T::default(); N];
+        for elem in out.iter_mut() {
+            *elem = T::deserialize(cursor)?;
+        }
+
+        Ok(out)
+    }
+}
+
+// IMPLEMENTATION DETAIL
+#[doc(hidden)]
+pub struct Cursor<'de> {
+    bytes: &'de [u8],
+    index: usize,
+}
+
+impl<'de> Cursor<'de> {
+    fn new(bytes: &'de [u8]) -> Self {
+        Cursor { bytes, index: 0 }
+    }
+
+    fn finish(self) -> Result<(), ()> {
+        if self.index == self.bytes.len() {
+            Ok(())
+        } else {
+            Err(())
+        }
+    }
+
+    // IMPLEMENTATION DETAIL
+    // returns the number of entries in the map
+    #[doc(hidden)]
+    pub fn parse_map(&mut self) -> Result<u64, ()> {
+        match self.parse_header()? {
+            (major::MAP, len) => Ok(len),
+            _ => Err(()),
+        }
+    }
+
+    // IMPLEMENTATION DETAIL
+    #[doc(hidden)]
+    pub fn matches_text_string(&mut self, ident: &[u8]) -> Result<bool, ()> {
+        let original = self.index;
+
+        if self.parse_string(major::TEXT)? == ident {
+            Ok(true)
+        } else {
+            self.index = original;
+            Ok(false)
+        }
+    }
+
+    // See RFC8949 Section 3 "Specification of the CBOR Encoding"
+    // NOTE indefinite lengths are not supported
+    fn parse_header(&mut self) -> Result<(u8, u64), ()> {
+        let initial = self.take(1)?[0];
+        let major = initial >> 5;
+        let info = initial & 0b1_1111;
+
+        let arg = match info {
+            0..=23 => u64::from(info),
+            24 => u64::from(self.take(1)?[0]),
+            25 => {
+                let mut buf = [0; 2];
+                buf.copy_from_slice(self.take(2)?);
+                u64::from(u16::from_be_bytes(buf))
+            }
+            26 => {
+                let mut buf = [0; 4];
+                buf.copy_from_slice(self.take(4)?);
+                u64::from(u32::from_be_bytes(buf))
+            }
+            27 => {
+                let mut buf = [0; 8];
+                buf.copy_from_slice(self.take(8)?);
+                u64::from_be_bytes(buf)
+            }
+            _ => return Err(()),
+        };
+
+        Ok((major, arg))
+    }
+
+    fn parse_string(&mut self, expected: u8) -> Result<&'de [u8], ()> {
+        match self.parse_header()? {
+            (major, len) if major == expected => self.take(usize::try_from(len).map_err(|_| ())?),
+            _ => Err(()),
+        }
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'de [u8], ()> {
+        let index = self.index;
+
+        invariant!(dbg!(self.index) <= dbg!(self.bytes.len()));
+        if self.bytes.len() - index < n {
+            return Err(());
+        }
+
+        self.index += n;
+        if n == 0 {
+            Ok(&[])
+        } else {
+            Ok(unsafe { self.bytes.slice(index, n) })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Bytes;
+
+    #[test]
+    fn boolean() {
+        assert_eq!(super::from_bytes::<bool>(&[0xf4]).unwrap(), false);
+        assert_eq!(super::from_bytes::<bool>(&[0xf5]).unwrap(), true);
+
+        // null
+        assert!(super::from_bytes::<bool>(&[0xf6]).is_err());
+    }
+
+    #[test]
+    fn u8() {
+        assert_eq!(super::from_bytes::<u8>(&[0x00]).unwrap(), 0);
+        assert_eq!(super::from_bytes::<u8>(&[0x17]).unwrap(), 23);
+        assert_eq!(super::from_bytes::<u8>(&[0x18, 0x18]).unwrap(), 24);
+        assert_eq!(super::from_bytes::<u8>(&[0x18, 0xff]).unwrap(), 255);
+        // non-preferred encoding
+        assert_eq!(super::from_bytes::<u8>(&[0x19, 0x00, 0x01]).unwrap(), 1);
+
+        assert!(super::from_bytes::<u8>(&[0x19, 0x01, 0x00]).is_err());
+        assert!(super::from_bytes::<u8>(&[0x20]).is_err());
+        // truncated
+        assert!(super::from_bytes::<u8>(&[0x18]).is_err());
+        // trailing data
+        assert!(super::from_bytes::<u8>(&[0x00, 0x00]).is_err());
+        // indefinite length
+        assert!(super::from_bytes::<u8>(&[0x1f]).is_err());
+    }
+
+    #[test]
+    fn i8() {
+        assert_eq!(super::from_bytes::<i8>(&[0x0a]).unwrap(), 10);
+        assert_eq!(super::from_bytes::<i8>(&[0x20]).unwrap(), -1);
+        assert_eq!(super::from_bytes::<i8>(&[0x38, 0x63]).unwrap(), -100);
+        assert_eq!(super::from_bytes::<i8>(&[0x18, 0x7f]).unwrap(), 127);
+        assert_eq!(super::from_bytes::<i8>(&[0x38, 0x7f]).unwrap(), -128);
+
+        assert!(super::from_bytes::<i8>(&[0x18, 0x80]).is_err());
+        assert!(super::from_bytes::<i8>(&[0x38, 0x80]).is_err());
+        assert_eq!(
+            super::from_bytes::<i64>(&[0x3b, 0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff])
+                .unwrap(),
+            i64::MIN
+        );
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(super::from_bytes::<&str>(&[0x60]).unwrap(), "");
+        assert_eq!(super::from_bytes::<&str>(b"\x64IETF").unwrap(), "IETF");
+        assert_eq!(
+            super::from_bytes::<&str>(&[0x62, 0xc3, 0xbc]).unwrap(),
+            "\u{00fc}"
+        );
+        assert_eq!(
+            super::from_bytes::<Bytes<'_>>(&[0x44, 0x01, 0x02, 0x03, 0x04]).unwrap(),
+            Bytes(&[1, 2, 3, 4])
+        );
+
+        // invalid UTF-8
+        assert!(super::from_bytes::<&str>(&[0x61, 0xff]).is_err());
+        // byte string is not a text string
+        assert!(super::from_bytes::<&str>(&[0x41, b'a']).is_err());
+        // truncated
+        assert!(super::from_bytes::<&str>(&[0x62, b'a']).is_err());
+    }
+
+    #[test]
+    fn seq() {
+        assert_eq!(
+            super::from_bytes::<[u8; 3]>(&[0x83, 0x01, 0x02, 0x03]).unwrap(),
+            [1, 2, 3]
+        );
+
+        assert!(super::from_bytes::<[u8; 2]>(&[0x83, 0x01, 0x02, 0x03]).is_err());
+        assert!(super::from_bytes::<[u8; 3]>(&[0x83, 0x01, 0x02]).is_err());
+    }
+}
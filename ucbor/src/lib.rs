@@ -0,0 +1,66 @@
+//! `uCBOR`: CBOR (de)serialization for memory constrained devices
+//!
+//! Structs that derive `uSerialize` / `uDeserialize` are encoded as CBOR maps keyed by their field
+//! names (text strings). Only definite-length items are produced and accepted; floating point
+//! numbers can be serialized but not deserialized, and tags are not supported.
+//!
+//! # References
+//!
+//! - [RFC8949 Concise Binary Object Representation (CBOR)][rfc8949]
+//!
+//! [rfc8949]: https://tools.ietf.org/html/rfc8949
+
+#![deny(missing_docs)]
+#![deny(rust_2018_compatibility)]
+#![deny(rust_2018_idioms)]
+#![deny(warnings)]
+#![no_std]
+
+#[cfg(test)]
+#[macro_use]
+extern crate std;
+
+pub use ucbor_macros::{uDeserialize, uSerialize};
+
+#[macro_use]
+mod macros;
+
+#[doc(hidden)]
+pub mod de;
+#[doc(hidden)]
+pub mod ser;
+
+mod traits;
+
+#[doc(inline)]
+pub use de::{from_bytes, Deserialize};
+#[doc(inline)]
+pub use ser::{write, Serialize};
+
+/// A CBOR byte string (major type 2)
+///
+/// `[u8]` is (de)serialized as an array of integers; wrap it in `Bytes` to get a byte string
+/// instead.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Bytes<'a>(pub &'a [u8]);
+
+/// A CBOR map (major type 5) made of the given key-value pairs
+///
+/// This can only be serialized; to deserialize a map derive `uDeserialize` on a struct
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Map<'a, K, V>(pub &'a [(K, V)]);
+
+// See RFC8949 Section 3.1 "Major Types"
+mod major {
+    pub const UNSIGNED: u8 = 0;
+    pub const NEGATIVE: u8 = 1;
+    pub const BYTES: u8 = 2;
+    pub const TEXT: u8 = 3;
+    pub const ARRAY: u8 = 4;
+    pub const MAP: u8 = 5;
+    pub const SIMPLE: u8 = 7;
+}
+
+// See RFC8949 Section 3.3 "Floating-Point Numbers and Values with No Content"
+const FALSE: u8 = 20;
+const TRUE: u8 = 21;
@@ -0,0 +1,342 @@
+//! Serialization
+
+use core::slice;
+
+use crate::{major, traits::SliceExt, Bytes, Map, FALSE, TRUE};
+
+/// Serializes the `value` into the given `buffer`
+pub fn write<'a, T>(value: &T, buffer: &'a mut [u8]) -> Result<&'a [u8], ()>
+where
+    T: Serialize + ?Sized,
+{
+    let mut cursor = Cursor::new(buffer);
+    value.serialize(&mut cursor)?;
+    Ok(cursor.finish())
+}
+
+// IMPLEMENTATION DETAIL
+// fast path: these are known to be valid UTF-8
+#[doc(hidden)]
+pub fn field_name(ident: &[u8], cursor: &mut Cursor<'_>) -> Result<(), ()> {
+    cursor.push_header(major::TEXT, ident.len() as u64)?;
+    cursor.push(ident)
+}
+
+// IMPLEMENTATION DETAIL
+#[doc(hidden)]
+pub fn map_header(len: u64, cursor: &mut Cursor<'_>) -> Result<(), ()> {
+    cursor.push_header(major::MAP, len)
+}
+
+/// Types that can be serialized into CBOR
+pub trait Serialize {
+    // IMPLEMENTATION DETAIL
+    #[doc(hidden)]
+    fn serialize(&self, cursor: &mut Cursor<'_>) -> Result<(), ()>;
+}
+
+impl Serialize for bool {
+    fn serialize(&self, cursor: &mut Cursor<'_>) -> Result<(), ()> {
+        cursor.push_header(major::SIMPLE, u64::from(if *self { TRUE } else { FALSE }))
+    }
+}
+
+macro_rules! unsigned {
+    ($($uN:ty),+) => {
+        $(
+            impl Serialize for $uN {
+                fn serialize(&self, cursor: &mut Cursor<'_>) -> Result<(), ()> {
+                    cursor.push_header(major::UNSIGNED, *self as u64)
+                }
+            }
+        )+
+    }
+}
+
+unsigned!(u8, u16, u32, u64);
+
+macro_rules! signed {
+    ($(($iN:ty, $uN:ty),)+) => {
+        $(
+            impl Serialize for $iN {
+                fn serialize(&self, cursor: &mut Cursor<'_>) -> Result<(), ()> {
+                    if *self < 0 {
+                        // NOTE(!) -1 - x == !x in two's complement
+                        cursor.push_header(major::NEGATIVE, !*self as $uN as u64)
+                    } else {
+                        cursor.push_header(major::UNSIGNED, *self as $uN as u64)
+                    }
+                }
+            }
+        )+
+    }
+}
+
+signed! {
+    (i8, u8),
+    (i16, u16),
+    (i32, u32),
+    (i64, u64),
+}
+
+// See RFC8949 Section 3.3 "Floating-Point Numbers and Values with No Content"
+impl Serialize for f32 {
+    fn serialize(&self, cursor: &mut Cursor<'_>) -> Result<(), ()> {
+        cursor.push_byte(major::SIMPLE << 5 | 26)?;
+        cursor.push(&self.to_bits().to_be_bytes())
+    }
+}
+
+impl Serialize for f64 {
+    fn serialize(&self, cursor: &mut Cursor<'_>) -> Result<(), ()> {
+        cursor.push_byte(major::SIMPLE << 5 | 27)?;
+        cursor.push(&self.to_bits().to_be_bytes())
+    }
+}
+
+impl Serialize for str {
+    fn serialize(&self, cursor: &mut Cursor<'_>) -> Result<(), ()> {
+        cursor.push_header(major::TEXT, self.len() as u64)?;
+        cursor.push(self.as_bytes())
+    }
+}
+
+impl<'a> Serialize for Bytes<'a> {
+    fn serialize(&self, cursor: &mut Cursor<'_>) -> Result<(), ()> {
+        cursor.push_header(major::BYTES, self.0.len() as u64)?;
+        cursor.push(self.0)
+    }
+}
+
+impl<T> Serialize for [T]
+where
+    T: Serialize,
+{
+    fn serialize(&self, cursor: &mut Cursor<'_>) -> Result<(), ()> {
+        cursor.push_header(major::ARRAY, self.len() as u64)?;
+
+        for elem in self {
+            elem.serialize(cursor)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<T, const N: usize> Serialize for [T; N]
+where
+    T: Serialize,
+{
+    fn serialize(&self, cursor: &mut Cursor<'_>) -> Result<(), ()> {
+        <[T]>::serialize(self, cursor)
+    }
+}
+
+impl<'a, K, V> Serialize for Map<'a, K, V>
+where
+    K: Serialize,
+    V: Serialize,
+{
+    fn serialize(&self, cursor: &mut Cursor<'_>) -> Result<(), ()> {
+        cursor.push_header(major::MAP, self.0.len() as u64)?;
+
+        for (key, value) in self.0 {
+            key.serialize(cursor)?;
+            value.serialize(cursor)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<T> Serialize for &T
+where
+    T: Serialize + ?Sized,
+{
+    fn serialize(&self, cursor: &mut Cursor<'_>) -> Result<(), ()> {
+        T::serialize(*self, cursor)
+    }
+}
+
+// IMPLEMENTATION DETAIL
+#[doc(hidden)]
+pub struct Cursor<'a> {
+    buffer: &'a mut [u8],
+    index: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(buffer: &'a mut [u8]) -> Self {
+        Cursor { buffer, index: 0 }
+    }
+
+    unsafe fn bump(&mut self, n: usize) {
+        self.index += n;
+
+        invariant!(dbg!(self.index) <= dbg!(self.buffer.len()));
+    }
+
+    // IMPLEMENTATION DETAIL
+    // See RFC8949 Section 3 "Specification of the CBOR Encoding"; this always uses the shortest
+    // form of the argument (RFC8949 Section 4.2.1 "Core Deterministic Encoding Requirements")
+    #[doc(hidden)]
+    pub fn push_header(&mut self, major: u8, argument: u64) -> Result<(), ()> {
+        let major = major << 5;
+
+        if argument < 24 {
+            self.push_byte(major | argument as u8)
+        } else if argument <= u64::from(u8::MAX) {
+            self.push(&[major | 24, argument as u8])
+        } else if argument <= u64::from(u16::MAX) {
+            self.push_byte(major | 25)?;
+            self.push(&(argument as u16).to_be_bytes())
+        } else if argument <= u64::from(u32::MAX) {
+            self.push_byte(major | 26)?;
+            self.push(&(argument as u32).to_be_bytes())
+        } else {
+            self.push_byte(major | 27)?;
+            self.push(&argument.to_be_bytes())
+        }
+    }
+
+    // IMPLEMENTATION DETAIL
+    #[doc(hidden)]
+    pub fn push_byte(&mut self, byte: u8) -> Result<(), ()> {
+        let index = self.index;
+
+        *self.buffer.get_mut(index).ok_or(())? = byte;
+        unsafe { self.bump(1) }
+
+        Ok(())
+    }
+
+    // IMPLEMENTATION DETAIL
+    #[doc(hidden)]
+    pub fn push(&mut self, slice: &[u8]) -> Result<(), ()> {
+        let index = self.index;
+        let len = slice.len();
+
+        if len > self.buffer.len() - index {
+            return Err(());
+        }
+
+        if len != 0 {
+            unsafe {
+                self.buffer.slice_mut(index, len).copy_from_slice(slice);
+                self.bump(len);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn finish(self) -> &'a [u8] {
+        unsafe { slice::from_raw_parts(self.buffer.as_ptr(), self.index) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Bytes, Map};
+
+    // Test vectors from RFC8949 Appendix A "Examples of Encoded CBOR Data Items"
+    #[test]
+    fn boolean() {
+        assert_eq!(super::write(&false, &mut [0; 1]).unwrap(), [0xf4]);
+        assert_eq!(super::write(&true, &mut [0; 1]).unwrap(), [0xf5]);
+    }
+
+    #[test]
+    fn unsigned() {
+        assert_eq!(super::write(&0u8, &mut [0; 1]).unwrap(), [0x00]);
+        assert_eq!(super::write(&23u8, &mut [0; 1]).unwrap(), [0x17]);
+        assert_eq!(super::write(&24u8, &mut [0; 2]).unwrap(), [0x18, 0x18]);
+        assert_eq!(super::write(&100u8, &mut [0; 2]).unwrap(), [0x18, 0x64]);
+        assert_eq!(
+            super::write(&1000u16, &mut [0; 3]).unwrap(),
+            [0x19, 0x03, 0xe8]
+        );
+        assert_eq!(
+            super::write(&1_000_000u32, &mut [0; 5]).unwrap(),
+            [0x1a, 0x00, 0x0f, 0x42, 0x40]
+        );
+        assert_eq!(
+            super::write(&u64::MAX, &mut [0; 9]).unwrap(),
+            [0x1b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]
+        );
+
+        // shortest form regardless of the type
+        assert_eq!(super::write(&10u64, &mut [0; 1]).unwrap(), [0x0a]);
+    }
+
+    #[test]
+    fn signed() {
+        assert_eq!(super::write(&10i8, &mut [0; 1]).unwrap(), [0x0a]);
+        assert_eq!(super::write(&-1i8, &mut [0; 1]).unwrap(), [0x20]);
+        assert_eq!(super::write(&-10i8, &mut [0; 1]).unwrap(), [0x29]);
+        assert_eq!(super::write(&-100i8, &mut [0; 2]).unwrap(), [0x38, 0x63]);
+        assert_eq!(super::write(&-128i8, &mut [0; 2]).unwrap(), [0x38, 0x7f]);
+        assert_eq!(
+            super::write(&-1000i16, &mut [0; 3]).unwrap(),
+            [0x39, 0x03, 0xe7]
+        );
+        assert_eq!(
+            super::write(&i64::MIN, &mut [0; 9]).unwrap(),
+            [0x3b, 0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]
+        );
+    }
+
+    #[test]
+    fn float() {
+        assert_eq!(
+            super::write(&100000.0f32, &mut [0; 5]).unwrap(),
+            [0xfa, 0x47, 0xc3, 0x50, 0x00]
+        );
+        assert_eq!(
+            super::write(&1.1f64, &mut [0; 9]).unwrap(),
+            [0xfb, 0x3f, 0xf1, 0x99, 0x99, 0x99, 0x99, 0x99, 0x9a]
+        );
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(super::write("", &mut [0; 1]).unwrap(), [0x60]);
+        assert_eq!(super::write("IETF", &mut [0; 5]).unwrap(), b"\x64IETF");
+        assert_eq!(
+            super::write("\u{00fc}", &mut [0; 3]).unwrap(),
+            [0x62, 0xc3, 0xbc]
+        );
+        assert_eq!(
+            super::write(&Bytes(&[1, 2, 3, 4]), &mut [0; 5]).unwrap(),
+            [0x44, 0x01, 0x02, 0x03, 0x04]
+        );
+    }
+
+    #[test]
+    fn seq() {
+        assert_eq!(super::write(&[0u8; 0], &mut [0; 1]).unwrap(), [0x80]);
+        assert_eq!(
+            super::write(&[1u8, 2, 3], &mut [0; 4]).unwrap(),
+            [0x83, 0x01, 0x02, 0x03]
+        );
+
+        let mut buf = [0; 32];
+        let bytes = super::write(&[1u8; 25], &mut buf).unwrap();
+        assert_eq!(bytes[..2], [0x98, 0x19]);
+        assert_eq!(bytes.len(), 27);
+    }
+
+    #[test]
+    fn map() {
+        assert_eq!(
+            super::write(&Map(&[(1u8, 2u8), (3, 4)]), &mut [0; 5]).unwrap(),
+            [0xa2, 0x01, 0x02, 0x03, 0x04]
+        );
+    }
+
+    #[test]
+    fn overflow() {
+        assert!(super::write(&1000u16, &mut [0; 2]).is_err());
+        assert!(super::write("IETF", &mut [0; 4]).is_err());
+    }
+}
@@ -0,0 +1,69 @@
+use ucbor::{uDeserialize, Bytes};
+
+#[test]
+fn one_field() {
+    #[derive(uDeserialize, Debug, PartialEq)]
+    struct Led {
+        led: bool,
+    }
+
+    assert_eq!(
+        ucbor::from_bytes::<Led>(b"\xa1\x63led\xf5").unwrap(),
+        Led { led: true }
+    );
+
+    assert_eq!(
+        ucbor::from_bytes::<Led>(b"\xa1\x63led\xf4").unwrap(),
+        Led { led: false }
+    );
+
+    // missing field
+    assert!(ucbor::from_bytes::<Led>(b"\xa0").is_err());
+    // unknown field
+    assert!(ucbor::from_bytes::<Led>(b"\xa1\x63lex\xf5").is_err());
+}
+
+#[test]
+fn two_fields() {
+    #[derive(uDeserialize, Debug, PartialEq)]
+    struct Pair {
+        x: u8,
+        y: i16,
+    }
+
+    assert_eq!(
+        ucbor::from_bytes::<Pair>(b"\xa2\x61x\x00\x61y\x39\x01\xf3").unwrap(),
+        Pair { x: 0, y: -500 }
+    );
+
+    // reverse order
+    assert_eq!(
+        ucbor::from_bytes::<Pair>(b"\xa2\x61y\x00\x61x\x01").unwrap(),
+        Pair { y: 0, x: 1 }
+    );
+
+    // duplicate field
+    assert!(ucbor::from_bytes::<Pair>(b"\xa2\x61x\x00\x61x\x01").is_err());
+}
+
+#[test]
+fn borrowed() {
+    #[derive(uDeserialize, Debug, PartialEq)]
+    struct Sensor<'a> {
+        name: &'a str,
+        raw: Bytes<'a>,
+        values: [u8; 2],
+    }
+
+    assert_eq!(
+        ucbor::from_bytes::<Sensor<'_>>(
+            b"\xa3\x64name\x64temp\x63raw\x42\xde\xad\x66values\x82\x01\x02"
+        )
+        .unwrap(),
+        Sensor {
+            name: "temp",
+            raw: Bytes(&[0xde, 0xad]),
+            values: [1, 2],
+        }
+    );
+}
@@ -0,0 +1,51 @@
+use ucbor::{uSerialize, Bytes};
+
+#[test]
+fn one_field() {
+    #[derive(uSerialize)]
+    struct Led {
+        led: bool,
+    }
+
+    assert_eq!(
+        ucbor::write(&Led { led: true }, &mut [0; 16]).unwrap(),
+        b"\xa1\x63led\xf5"
+    );
+}
+
+#[test]
+fn two_fields() {
+    #[derive(uSerialize)]
+    struct Pair {
+        x: u8,
+        y: i16,
+    }
+
+    assert_eq!(
+        ucbor::write(&Pair { x: 0, y: -500 }, &mut [0; 16]).unwrap(),
+        b"\xa2\x61x\x00\x61y\x39\x01\xf3"
+    );
+}
+
+#[test]
+fn borrowed() {
+    #[derive(uSerialize)]
+    struct Sensor<'a> {
+        name: &'a str,
+        raw: Bytes<'a>,
+        values: [u8; 2],
+    }
+
+    assert_eq!(
+        ucbor::write(
+            &Sensor {
+                name: "temp",
+                raw: Bytes(&[0xde, 0xad]),
+                values: [1, 2],
+            },
+            &mut [0; 32]
+        )
+        .unwrap(),
+        &b"\xa3\x64name\x64temp\x63raw\x42\xde\xad\x66values\x82\x01\x02"[..]
+    );
+}
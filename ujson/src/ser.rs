@@ -1,6 +1,10 @@
 //! Serialization
 
-use core::{mem::MaybeUninit, slice, str};
+use core::{
+    fmt::{self, Write as _},
+    mem::MaybeUninit,
+    slice, str,
+};
 
 use crate::traits::SliceExt;
 
@@ -114,6 +118,25 @@ signed! {
     (i64, u64, 20),
 }
 
+// NOTE JSON has no representation for NaN or the infinities
+macro_rules! float {
+    ($($fN:ty),+) => {
+        $(
+            impl Serialize for $fN {
+                fn serialize(&self, cursor: &mut Cursor<'_>) -> Result<(), ()> {
+                    if !self.is_finite() {
+                        return Err(());
+                    }
+
+                    write!(cursor, "{}", self).map_err(|_| ())
+                }
+            }
+        )+
+    }
+}
+
+float!(f32, f64);
+
 impl Serialize for str {
     fn serialize(&self, cursor: &mut Cursor<'_>) -> Result<(), ()> {
         cursor.push_byte(b'"')?;
@@ -297,6 +320,12 @@ impl<'a> Cursor<'a> {
     }
 }
 
+impl fmt::Write for Cursor<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push(s.as_bytes()).map_err(|_| fmt::Error)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -321,6 +350,18 @@ mod tests {
         assert_eq!(super::write(&-128i8, &mut [0; 4]).unwrap(), "-128");
     }
 
+    #[test]
+    fn float() {
+        assert_eq!(super::write(&0f32, &mut [0; 1]).unwrap(), "0");
+        assert_eq!(super::write(&120.1f32, &mut [0; 8]).unwrap(), "120.1");
+        assert_eq!(super::write(&-1.5f64, &mut [0; 8]).unwrap(), "-1.5");
+        assert_eq!(super::write(&1.276e9f64, &mut [0; 16]).unwrap(), "1276000000");
+
+        assert!(super::write(&f32::NAN, &mut [0; 8]).is_err());
+        assert!(super::write(&f64::INFINITY, &mut [0; 8]).is_err());
+        assert!(super::write(&120.1f32, &mut [0; 4]).is_err());
+    }
+
     #[test]
     fn seq() {
         assert_eq!(super::write(&[0u8, 1, 2], &mut [0; 8]).unwrap(), "[0,1,2]");